@@ -0,0 +1,823 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The commands printing accounts: generated, inspected and development accounts, and the accounts
+//! of modules, multisigs and anonymous proxies.
+
+use crate::{
+	crypto::{Crypto, Ecdsa, Ed25519, PublicOf, PublicT, SignatureOf, SignatureT, Sr25519},
+	error::{static_err, Error},
+	input::{
+		check_inline_password, decode_hex, get_uri, network_name, read_required_parameter, read_uris,
+	},
+	json::{
+		AccountFormOutput, AccountFormsOutput, AccountOutput, DevAccountOutput, JsonOutput,
+		ReencodedAddressOutput,
+	},
+	key::{self, pair_from_pem},
+	output::{write_atomically, Output, OutputType, Secrets},
+	qr::{address_payload, secret_payload, QrCode},
+	table::{write_table, TableStyle},
+};
+use bip39::{Language, Mnemonic, MnemonicType};
+use clap::ArgMatches;
+use codec::{Decode, Encode};
+use itertools::Itertools;
+use node_primitives::{AccountId, BlockNumber};
+use node_runtime::ProxyType;
+use sp_core::{
+	crypto::{ExposeSecret, SecretString, Ss58AddressFormat, Ss58Codec, DEV_PHRASE},
+	blake2_256, Pair,
+};
+use sp_runtime::{traits::AccountIdConversion, ModuleId};
+use std::{convert::{TryFrom, TryInto}, io::{self, Write}, path::{Path, PathBuf}, str::FromStr};
+
+/// Generate an account with a new secret phrase, the `generate` command.
+pub fn run_generate<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let mut out = Output::from_matches(matches)?;
+	let mnemonic = generate_mnemonic(matches.value_of("words"))?;
+	let phrase = mnemonic.phrase();
+	if let Some(path) = matches.value_of("keystore-path") {
+		let key_type = key::read_key_type(matches)?;
+		let format = key::read_keystore_format(matches)?;
+		let suri = SecretString::new(phrase.to_owned());
+		key::insert_keystore_pair::<C>(Path::new(path), key_type, &suri, password, format)?;
+	}
+	if matches.is_present("quiet") {
+		let public_hex = matches.is_present("public-hex");
+		print_quiet::<C, _>(&mut out, phrase, password, maybe_network, public_hex)?;
+	} else {
+		let network = maybe_network;
+		let qr = QrOutput::from_matches::<C>(matches, phrase, password, network, output)?;
+		out.set_secret(secrets != Secrets::Hide);
+		C::print_from_uri(&mut out, phrase, password, maybe_network, output, secrets)?;
+		qr.write(&mut out)?;
+	}
+	out.commit()?;
+	Ok(())
+}
+
+/// Print the accounts of secret or public URIs, the `inspect` command.
+pub fn run_inspect<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let mut out = Output::from_matches(matches)?;
+	out.set_secret(secrets != Secrets::Hide && !matches.is_present("quiet"));
+	let qr = matches.is_present("qr") || matches.is_present("qr-file");
+	if qr && (matches.is_present("uri-list") || matches.is_present("vrf")) {
+		return static_err("QR codes are only printed for a single URI, without --vrf");
+	}
+	let pem_uri = matches.value_of("pem-file")
+		.map(|path| pair_from_pem::<C>(Path::new(path), password))
+		.transpose()?
+		.map(|pair| SecretString::new(format!("0x{}", hex::encode(pair.to_raw_vec()))));
+	// The password is the one of the PEM file, not of the URI of its key.
+	let password = if pem_uri.is_some() { None } else { password };
+	let get_uri = |name, matches| match &pem_uri {
+		Some(uri) => Ok(uri.clone()),
+		None => get_uri(name, matches),
+	};
+	if let Some(path) = matches.value_of("uri-list") {
+		let uris = read_uris(Path::new(path))?;
+		uris.iter().try_for_each(|uri| check_inline_password(uri, password))?;
+		C::print_from_uris(&mut out, &uris, password, maybe_network, output, secrets)?;
+	} else if let Some(label) = matches.value_of("vrf") {
+		match output {
+			OutputType::Binary => {
+				return static_err("Binary output does not include the VRF output");
+			},
+			OutputType::Table(_) => {
+				return static_err("Table output does not include the VRF output");
+			},
+			_ => {},
+		}
+		let uri = get_uri("uri", matches)?;
+		let uri = uri.expose_secret();
+		let input = matches.value_of("vrf-input").unwrap_or("");
+		let input = decode_hex(input.trim_start_matches("0x"))?;
+		let pair = C::pair_from_suri(uri, password)?;
+		let (vrf_output, vrf_proof) = C::vrf_sign(&pair, label.as_bytes(), &input)?;
+		if output.is_json() {
+			let account = C::account_from_uri(uri, password, maybe_network, secrets)
+				.ok_or(Error::Static("Invalid phrase/URI given"))?;
+			let hex = |bytes: &[u8]| Some(format!("0x{}", hex::encode(bytes)));
+			let account = AccountOutput {
+				vrf_label: Some(label.into()),
+				vrf_input: hex(&input),
+				vrf_output: hex(&vrf_output),
+				vrf_proof: hex(&vrf_proof[..]),
+				..account
+			};
+			output.write_json(&mut out, &account.to_json())?;
+		} else {
+			C::print_from_uri(&mut out, uri, password, maybe_network, output, secrets)?;
+			writeln!(
+				out,
+				"  VRF label:          {}\n  \
+				VRF input:          0x{}\n  \
+				VRF output:         0x{}\n  \
+				VRF proof:          0x{}",
+				label,
+				hex::encode(&input),
+				hex::encode(&vrf_output),
+				hex::encode(&vrf_proof[..]),
+			)?;
+		}
+	} else {
+		let uri = get_uri("uri", matches)?;
+		let uri = uri.expose_secret();
+		check_inline_password(uri, password)?;
+		if matches.is_present("quiet") {
+			let public_hex = matches.is_present("public-hex");
+			print_quiet::<C, _>(&mut out, uri, password, maybe_network, public_hex)?;
+		} else {
+			let network = maybe_network;
+			let qr = QrOutput::from_matches::<C>(matches, uri, password, network, output)?;
+			C::print_from_uri(&mut out, uri, password, maybe_network, output, secrets)?;
+			qr.write(&mut out)?;
+		}
+	}
+	out.commit()?;
+	Ok(())
+}
+
+/// Encode addresses for other networks, the `reencode-address` command.
+pub fn run_reencode_address(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> Result<(), Error> {
+	let target = maybe_network.unwrap_or_default();
+	let addresses = matches.values_of("address").expect("address is required; qed");
+	let reencoded = addresses
+		.map(|address| reencode_ss58(address, target).map(|reencoded| (address, reencoded)))
+		.collect::<Result<Vec<_>, _>>()?;
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let json = reencoded
+				.iter()
+				.map(|(address, reencoded)| ReencodedAddressOutput {
+					address: address.to_string(),
+					network_id: network_name(target),
+					ss58_address: reencoded.clone(),
+				})
+				.collect::<Vec<_>>();
+			output.write_json(&mut io::stdout(), &json.to_json())?;
+		},
+		OutputType::Table(style) => {
+			let network = network_name(target);
+			let rows = reencoded
+				.into_iter()
+				.map(|(address, reencoded)| {
+					vec![address.into(), network.clone(), reencoded]
+				})
+				.collect::<Vec<_>>();
+			let columns = ["Address", "Network", "SS58 address"];
+			write_table(&mut io::stdout(), &columns, &rows, style)?;
+		},
+		_ => for (_, reencoded) in reencoded {
+			println!("{}", reencoded);
+		},
+	}
+	Ok(())
+}
+
+/// Print the forms of an account, the `account-forms` command.
+pub fn run_account_forms(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> Result<(), Error> {
+	let account_id = matches.value_of("account-id").expect("account-id is required; qed");
+	show_account_forms(account_id, maybe_network.unwrap_or_default(), output)?;
+	Ok(())
+}
+
+/// List the development accounts, the `key dev-accounts` command.
+pub fn run_dev_accounts<C: Crypto>(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let scheme_given = ["ed25519", "secp256k1", "sr25519"]
+		.iter()
+		.any(|scheme| matches.is_present(scheme));
+	let accounts = if scheme_given {
+		dev_accounts::<C>(maybe_network)
+	} else {
+		let mut accounts = dev_accounts::<Sr25519>(maybe_network);
+		accounts.extend(dev_accounts::<Ed25519>(maybe_network));
+		accounts.extend(dev_accounts::<Ecdsa>(maybe_network));
+		accounts
+	};
+	let style = match output {
+		OutputType::Table(style) => Some(style),
+		OutputType::Text => Some(TableStyle::from_matches(matches)),
+		_ => None,
+	};
+	if output.is_json() {
+		let json = accounts
+			.iter()
+			.map(|(scheme, uri, account)| DevAccountOutput {
+				scheme: scheme.to_string(),
+				uri: uri.clone(),
+				network_id: account.network_id.clone(),
+				public_key: account.public_key.clone(),
+				account_id: account.account_id.clone(),
+				ss58_address: account.ss58_address.clone(),
+			})
+			.collect::<Vec<_>>();
+		output.write_json(&mut io::stdout(), &json.to_json())?;
+	} else if let Some(style) = style {
+		let rows = accounts
+			.into_iter()
+			.map(|(scheme, uri, account)| vec![
+				scheme.into(),
+				uri,
+				account.public_key,
+				account.account_id,
+				account.ss58_address,
+			])
+			.collect::<Vec<_>>();
+		let columns = ["Scheme", "URI", "Public key", "Account ID", "SS58 address"];
+		write_table(&mut io::stdout(), &columns, &rows, style)?;
+	} else {
+		for (_, _, account) in accounts {
+			println!("{}", account.ss58_address);
+		}
+	}
+	Ok(())
+}
+
+/// Print the account of a module ID, the `moduleid` command.
+pub fn run_moduleid<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let id = get_uri("id", matches)?;
+	let id = id.expose_secret();
+	if id.len() != 8 {
+		Err("a module id must be a string of 8 characters")?
+	}
+
+	let id_fixed_array: [u8; 8] = id.as_bytes().try_into()
+		.map_err(|_| Error::Static("Cannot convert argument to moduleid: argument should be 8-character string"))?;
+
+	let account_id: AccountId = ModuleId(id_fixed_array).into_account();
+	let v = maybe_network.unwrap_or(Ss58AddressFormat::SubstrateAccount);
+
+	let uri = account_id.to_ss58check_with_version(v);
+	C::print_from_uri(&mut io::stdout(), &uri, password, maybe_network, output, secrets)?;
+	Ok(())
+}
+
+/// Print the account of a multisig, the `key multisig` command.
+pub fn run_multisig<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let threshold = read_required_parameter::<u16>(matches, "threshold")?;
+	let signatories = matches
+		.values_of("signatory")
+		.expect("signatory is required; thus it can't be None; qed")
+		.map(parse_account_id)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let account_id = multi_account_id(signatories, threshold)?;
+	let v = maybe_network.unwrap_or(Ss58AddressFormat::SubstrateAccount);
+
+	let uri = account_id.to_ss58check_with_version(v);
+	C::print_from_uri(&mut io::stdout(), &uri, password, maybe_network, output, secrets)?;
+	Ok(())
+}
+
+/// Print the account of an anonymous proxy, the `key pure-proxy` command.
+pub fn run_pure_proxy<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let spawner = parse_account_id(
+		matches.value_of("spawner").expect("spawner is required; thus it can't be None; qed")
+	)?;
+	let proxy_type = parse_proxy_type(
+		matches.value_of("proxy-type").expect("proxy-type is required; thus it can't be None; qed")
+	)?;
+	let index = read_required_parameter::<u16>(matches, "index")?;
+	let height = read_required_parameter::<BlockNumber>(matches, "block-height")?;
+	let ext_index = read_required_parameter::<u32>(matches, "ext-index")?;
+
+	let account_id = anonymous_account_id(&spawner, proxy_type, index, height, ext_index);
+	let v = maybe_network.unwrap_or(Ss58AddressFormat::SubstrateAccount);
+
+	let uri = account_id.to_ss58check_with_version(v);
+	C::print_from_uri(&mut io::stdout(), &uri, password, maybe_network, output, secrets)?;
+	Ok(())
+}
+
+/// Print nothing but the SS58 address of `uri`, or its hex-encoded public key if `public_hex` is
+/// set, on a single line to `out`.
+fn print_quiet<C: Crypto, W: Write>(
+	out: &mut W,
+	uri: &str,
+	password: Option<&str>,
+	network: Option<Ss58AddressFormat>,
+	public_hex: bool,
+) -> Result<(), Error>
+where
+	PublicOf<C>: PublicT,
+{
+	let json = C::json_from_uri(uri, password, network, Secrets::Hide)
+		.ok_or(Error::Static("Invalid phrase/URI given"))?;
+	let field = if public_hex { "publicKey" } else { "ss58Address" };
+	let field = json[field].as_str().expect("each account has a public key and address; qed");
+	Ok(writeln!(out, "{}", field)?)
+}
+
+/// The QR code of an account selected by the [`qr_args`](crate::qr::qr_args) of a command.
+///
+/// It is prepared before the account is printed, so that nothing is printed if it can not be.
+#[derive(Default)]
+struct QrOutput {
+	/// The code to print with `--qr`.
+	text: Option<String>,
+	/// The file given by `--qr-file`, and the PNG to write to it.
+	png: Option<(PathBuf, Vec<u8>)>,
+	/// Whether the code holds the secret of the account, with `--qr-secret`.
+	secret: bool,
+}
+
+impl QrOutput {
+	/// The QR code of the account of `uri`, if `--qr` or `--qr-file` is given in `matches`.
+	fn from_matches<C: Crypto>(
+		matches: &ArgMatches,
+		uri: &str,
+		password: Option<&str>,
+		network: Option<Ss58AddressFormat>,
+		output: OutputType,
+	) -> Result<Self, Error>
+	where
+		PublicOf<C>: PublicT,
+	{
+		let path = matches.value_of("qr-file");
+		if !matches.is_present("qr") && path.is_none() {
+			if matches.is_present("qr-secret") || matches.is_present("qr-genesis-hash") {
+				return Err(Error::Static(
+					"--qr-secret and --qr-genesis-hash require --qr or --qr-file",
+				));
+			}
+			return Ok(QrOutput::default());
+		}
+		if matches.is_present("qr") && !matches!(output, OutputType::Text) {
+			return Err(Error::Static("--qr is only supported with text output; see --qr-file"));
+		}
+		let genesis_hash = match matches.value_of("qr-genesis-hash") {
+			Some(hash) => match decode_hex(hash.trim_start_matches("0x"))? {
+				hash if hash.len() == 32 => Some(format!("0x{}", hex::encode(hash))),
+				_ => return Err(Error::Static("The genesis hash has to be 32 bytes")),
+			},
+			None => None,
+		};
+		let account = C::account_from_uri(uri, password, network, Secrets::Show)
+			.ok_or(Error::Static("Invalid phrase/URI given"))?;
+		let secret = matches.is_present("qr-secret");
+		let payload = if secret {
+			let uri = account.secret_phrase.or(account.secret_key_uri)
+				.ok_or(Error::Static("--qr-secret requires a secret phrase or secret URI"))?;
+			eprintln!(
+				"WARNING: The QR code holds the secret of the account. Anyone who sees or \
+				photographs it can take the account and its funds!"
+			);
+			secret_payload(&uri, genesis_hash.as_deref())
+		} else {
+			address_payload(&account.ss58_address, genesis_hash.as_deref())
+		};
+		let code = QrCode::encode(payload.as_bytes())?;
+		let png = match path {
+			Some(path) => Some((PathBuf::from(path), code.to_png()?)),
+			None => None,
+		};
+		Ok(QrOutput {
+			text: Some(code.to_unicode()).filter(|_| matches.is_present("qr")),
+			png,
+			secret,
+		})
+	}
+
+	/// Print the code to `out`, and write the PNG file.
+	fn write(self, out: &mut Output) -> Result<(), Error> {
+		if let Some(text) = self.text {
+			if self.secret {
+				out.set_secret(true);
+			}
+			write!(out, "\n{}", text)?;
+		}
+		if let Some((path, png)) = self.png {
+			write_atomically(&path, &png, self.secret, true)?;
+		}
+		Ok(())
+	}
+}
+
+/// The names of the well-known development accounts.
+const DEV_ACCOUNT_NAMES: [&str; 6] = ["Alice", "Bob", "Charlie", "Dave", "Eve", "Ferdie"];
+
+/// The scheme, URI and account of each development account of `C`, with its `//stash` after it.
+///
+/// The accounts are derived from [`DEV_PHRASE`], like the chain specs of the node do.
+fn dev_accounts<C: Crypto>(
+	network: Option<Ss58AddressFormat>,
+) -> Vec<(&'static str, String, AccountOutput)>
+where
+	PublicOf<C>: PublicT,
+{
+	DEV_ACCOUNT_NAMES
+		.iter()
+		.flat_map(|name| vec![format!("//{}", name), format!("//{}//stash", name)])
+		.map(|uri| {
+			let suri = format!("{}{}", DEV_PHRASE, uri);
+			let account = C::account_from_uri(&suri, None, network, Secrets::Hide)
+				.expect("the development phrase and the junctions are valid; qed");
+			(C::NAME, uri, account)
+		})
+		.collect()
+}
+
+/// Creates a new randomly generated mnemonic phrase.
+pub fn generate_mnemonic(words: Option<&str>) -> Result<Mnemonic, Error> {
+	let words = match words {
+		Some(words) => {
+			let num = usize::from_str(words).map_err(|_| Error::Static("Invalid number given for --words"))?;
+			MnemonicType::for_word_count(num)
+				.map_err(|_| Error::Static("Invalid number of words given for phrase: must be 12/15/18/21/24"))?
+		},
+		None => MnemonicType::Words12,
+	};
+	Ok(Mnemonic::new(words, Language::English))
+}
+
+pub(super) fn read_account_id(matched_uri: Option<&str>) -> Result<AccountId, Error> {
+	let uri = matched_uri.expect("parameter is required; thus it can't be None; qed");
+	parse_account_id(uri)
+}
+
+/// Parse an account id given either hex-encoded or as an SS58 address of any network.
+pub(super) fn parse_account_id(uri: &str) -> Result<AccountId, Error> {
+	let data = uri.strip_prefix("0x").unwrap_or(uri);
+	if let Ok(data_vec) = hex::decode(data) {
+		AccountId::try_from(data_vec.as_slice()).map_err(|_| Error::Formatted(format!(
+			"Invalid hex length for account ID `{}`; should be 32 bytes", uri,
+		)))
+	} else {
+		AccountId::from_ss58check_with_version(uri)
+			.map(|(account_id, _)| account_id)
+			.map_err(|_| Error::Ss58Decode(uri.into()))
+	}
+}
+
+/// Re-encode the SS58 `address` of any network for the `target` network.
+///
+/// The checksum of `address` is checked, but its network is not.
+fn reencode_ss58(address: &str, target: Ss58AddressFormat) -> Result<String, Error> {
+	AccountId::from_ss58check_with_version(address)
+		.map(|(account_id, _)| account_id.to_ss58check_with_version(target))
+		.map_err(|_| Error::Ss58Decode(address.into()))
+}
+
+/// Print the SS58 address of the hex-encoded `account_id` for `network`, and for each scheme
+/// whether the account id is the public key of the account or its hash.
+///
+/// Mixing up the key types of accounts is a common mistake, and an ecdsa account has no public
+/// key that can be recovered from its account id.
+fn show_account_forms(
+	account_id_hex: &str,
+	network: Ss58AddressFormat,
+	output: OutputType,
+) -> Result<(), Error> {
+	let bytes = decode_hex(account_id_hex.trim_start_matches("0x"))?;
+	if bytes.len() != 32 {
+		return Err(Error::Formatted(format!(
+			"Invalid account id of {} bytes; expected 32 bytes",
+			bytes.len(),
+		)));
+	}
+	let mut account_id = [0; 32];
+	account_id.copy_from_slice(&bytes);
+	let account_id_hex = format!("0x{}", hex::encode(account_id));
+	let address = AccountId::from(account_id).to_ss58check_with_version(network);
+	// Whether the account id of each scheme is its public key, rather than its blake2-256 hash.
+	let schemes = [(Sr25519::NAME, true), (Ed25519::NAME, true), (Ecdsa::NAME, false)];
+	let public_key = |is_public_key| Some(account_id_hex.clone()).filter(|_| is_public_key);
+
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let json = AccountFormsOutput {
+				account_id: account_id_hex.clone(),
+				network_id: network_name(network),
+				ss58_address: address,
+				schemes: schemes
+					.iter()
+					.map(|(scheme, is_public_key)| AccountFormOutput {
+						scheme: scheme.to_string(),
+						account_id_is_public_key: *is_public_key,
+						public_key: public_key(*is_public_key),
+					})
+					.collect(),
+			};
+			output.write_json(&mut io::stdout(), &json.to_json())?;
+		},
+		OutputType::Table(style) => {
+			let rows = schemes
+				.iter()
+				.map(|(scheme, is_public_key)| vec![
+					scheme.to_string(),
+					address.clone(),
+					public_key(*is_public_key).unwrap_or_else(|| "n/a".into()),
+				])
+				.collect::<Vec<_>>();
+			let columns = ["Scheme", "SS58 address", "Public key"];
+			write_table(&mut io::stdout(), &columns, &rows, style)?;
+		},
+		_ => {
+			println!("Account ID:    {}", account_id_hex);
+			println!("Network ID:    {}", network_name(network));
+			println!("SS58 Address:  {}", address);
+			for (scheme, is_public_key) in &schemes {
+				let scheme = format!("{}:", scheme);
+				if *is_public_key {
+					println!("{:<15}the account id is the public key {}", scheme, account_id_hex);
+				} else {
+					println!(
+						"{:<15}the account id is the blake2-256 hash of the 33-byte public key, \
+							which can not be recovered from it",
+						scheme,
+					);
+				}
+			}
+		},
+	}
+	Ok(())
+}
+
+/// Derive the account id `pallet_multisig` uses for the given signatories and threshold.
+///
+/// The signatories may be given in any order, but must not contain duplicates.
+fn multi_account_id(mut signatories: Vec<AccountId>, threshold: u16) -> Result<AccountId, Error> {
+	signatories.sort();
+	if let Some((duplicate, _)) = signatories.iter().tuple_windows().find(|(a, b)| a == b) {
+		return Err(Error::Formatted(format!("Signatory {} is given more than once", duplicate)));
+	}
+	if threshold == 0 || usize::from(threshold) > signatories.len() {
+		return Err(Error::Formatted(format!(
+			"Threshold must be between 1 and the number of signatories ({})",
+			signatories.len(),
+		)));
+	}
+
+	// Must match `pallet_multisig::Module::multi_account_id`.
+	let entropy = (b"modlpy/utilisuba", &signatories, threshold).using_encoded(blake2_256);
+	Ok(AccountId::decode(&mut &entropy[..]).expect("AccountId is 32 bytes; qed"))
+}
+
+/// Parse a proxy type, given either by its `node_runtime::ProxyType` name or by its numeric index.
+///
+/// The numeric form allows proxy types of other runtimes to be used.
+fn parse_proxy_type(proxy_type: &str) -> Result<u8, Error> {
+	let proxy_type = match proxy_type.to_lowercase().replace(|c| c == '-' || c == '_', "").as_str() {
+		"any" => ProxyType::Any,
+		"nontransfer" => ProxyType::NonTransfer,
+		"governance" => ProxyType::Governance,
+		"staking" => ProxyType::Staking,
+		_ => return proxy_type.parse().map_err(|_| Error::Formatted(format!(
+			"Invalid proxy type `{}`; expecting one of any, non-transfer, governance, staking \
+			or a numeric index.",
+			proxy_type,
+		))),
+	};
+	Ok(proxy_type as u8)
+}
+
+/// Derive the account `pallet_proxy::Module::anonymous` spawns for the given parameters.
+fn anonymous_account_id(
+	spawner: &AccountId,
+	proxy_type: u8,
+	index: u16,
+	height: BlockNumber,
+	ext_index: u32,
+) -> AccountId {
+	// Must match `pallet_proxy::Module::anonymous_account`.
+	let entropy = (b"modlpy/proxy____", spawner, height, ext_index, proxy_type, index)
+		.using_encoded(blake2_256);
+	AccountId::decode(&mut &entropy[..]).expect("AccountId is 32 bytes; qed")
+}
+
+/// Parse a public key given either hex-encoded or as an SS58 address of any network.
+pub fn parse_public_key<C: Crypto>(uri: &str) -> Result<PublicOf<C>, Error> {
+	let data = uri.strip_prefix("0x").unwrap_or(uri);
+	if let Ok(data_vec) = hex::decode(data) {
+		let mut public = PublicOf::<C>::default();
+		if data_vec.len() != public.as_ref().len() {
+			return Err(Error::Formatted(format!(
+				"Invalid hex length for public key `{}`; should be {} bytes",
+				uri,
+				public.as_ref().len(),
+			)));
+		}
+		public.as_mut().copy_from_slice(&data_vec);
+		Ok(public)
+	} else {
+		PublicOf::<C>::from_ss58check_with_version(uri)
+			.map(|(public, _)| public)
+			.map_err(|_| Error::Ss58Decode(uri.into()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{hexdisplay::HexDisplay, sr25519};
+	use sp_runtime::traits::IdentifyAccount;
+
+	fn dev_account(suri: &str) -> AccountId {
+		sr25519::Pair::from_string(suri, None).unwrap().public().into_runtime().into_account()
+	}
+
+	#[test]
+	fn multi_account_id_matches_pallet() {
+		let signatories = vec![dev_account("//Charlie"), dev_account("//Alice"), dev_account("//Bob")];
+		let mut sorted = signatories.clone();
+		sorted.sort();
+
+		for threshold in 1..=3 {
+			assert_eq!(
+				multi_account_id(signatories.clone(), threshold).unwrap(),
+				node_runtime::Multisig::multi_account_id(&sorted, threshold),
+			);
+		}
+	}
+
+	#[test]
+	fn multi_account_id_rejects_invalid_input() {
+		let alice = dev_account("//Alice");
+		let bob = dev_account("//Bob");
+
+		assert!(multi_account_id(vec![alice.clone(), bob.clone(), alice.clone()], 2).is_err());
+		assert!(multi_account_id(vec![alice.clone(), bob.clone()], 0).is_err());
+		assert!(multi_account_id(vec![alice, bob], 3).is_err());
+	}
+
+	#[test]
+	fn parse_account_id_accepts_hex_and_any_network() {
+		let alice = dev_account("//Alice");
+		let hex = format!("0x{}", HexDisplay::from(&alice.as_ref()));
+
+		assert_eq!(parse_account_id(&hex).unwrap(), alice);
+		assert_eq!(parse_account_id(&hex[2..]).unwrap(), alice);
+		assert_eq!(
+			parse_account_id(&alice.to_ss58check_with_version(Ss58AddressFormat::KusamaAccount)).unwrap(),
+			alice,
+		);
+		assert!(parse_account_id("0x1234").is_err());
+		assert!(parse_account_id("not an address").is_err());
+	}
+
+	#[test]
+	fn anonymous_account_id_matches_pallet() {
+		let spawner = dev_account("//Alice");
+		let proxy_types = [
+			ProxyType::Any,
+			ProxyType::NonTransfer,
+			ProxyType::Governance,
+			ProxyType::Staking,
+		];
+
+		for proxy_type in proxy_types.iter() {
+			assert_eq!(
+				anonymous_account_id(&spawner, *proxy_type as u8, 7, 42, 1),
+				node_runtime::Proxy::anonymous_account(&spawner, proxy_type, 7, Some((42, 1))),
+			);
+		}
+	}
+
+	#[test]
+	fn parse_proxy_type_works() {
+		assert_eq!(parse_proxy_type("any").unwrap(), ProxyType::Any as u8);
+		assert_eq!(parse_proxy_type("NonTransfer").unwrap(), ProxyType::NonTransfer as u8);
+		assert_eq!(parse_proxy_type("non-transfer").unwrap(), ProxyType::NonTransfer as u8);
+		assert_eq!(parse_proxy_type("Staking").unwrap(), ProxyType::Staking as u8);
+		assert_eq!(parse_proxy_type("9").unwrap(), 9);
+		assert!(parse_proxy_type("everything").is_err());
+		assert!(parse_proxy_type("256").is_err());
+	}
+
+	#[test]
+	fn dev_accounts_match_the_well_known_addresses() {
+		let address = |accounts: &[(&str, String, AccountOutput)], uri: &str| {
+			let (_, _, account) = accounts.iter().find(|(_, u, _)| u == uri).unwrap();
+			account.ss58_address.clone()
+		};
+		let sr25519 = dev_accounts::<Sr25519>(None);
+		assert_eq!(sr25519.len(), 12);
+		assert_eq!(
+			address(&sr25519, "//Alice"),
+			"5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+		);
+		assert_eq!(address(&sr25519, "//Bob"), "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+		assert_eq!(
+			address(&sr25519, "//Alice//stash"),
+			"5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY",
+		);
+		let ed25519 = dev_accounts::<Ed25519>(None);
+		assert_eq!(
+			address(&ed25519, "//Alice"),
+			"5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu",
+		);
+		let ecdsa = dev_accounts::<Ecdsa>(None);
+		assert_eq!(
+			ecdsa[0].2.public_key,
+			"0x020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1",
+		);
+		assert!(ecdsa.iter().all(|(scheme, _, _)| *scheme == "ecdsa"));
+
+		let kusama = dev_accounts::<Sr25519>(Some(Ss58AddressFormat::KusamaAccount));
+		assert_eq!(address(&kusama, "//Alice"), "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F");
+	}
+
+	#[test]
+	fn reencode_ss58_ignores_the_source_network() {
+		let alice = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+		let substrate = alice.to_ss58check_with_version(Ss58AddressFormat::SubstrateAccount);
+		let kusama = alice.to_ss58check_with_version(Ss58AddressFormat::KusamaAccount);
+		let polkadot = alice.to_ss58check_with_version(Ss58AddressFormat::PolkadotAccount);
+
+		for address in &[&substrate, &kusama, &polkadot] {
+			assert_eq!(reencode_ss58(address, Ss58AddressFormat::KusamaAccount).unwrap(), kusama);
+			assert_eq!(reencode_ss58(address, Ss58AddressFormat::SubstrateAccount).unwrap(), substrate);
+		}
+
+		let mut corrupted = substrate.clone();
+		corrupted.pop();
+		corrupted.push(if substrate.ends_with('Y') { 'Z' } else { 'Y' });
+		assert!(matches!(
+			reencode_ss58(&corrupted, Ss58AddressFormat::KusamaAccount),
+			Err(Error::Ss58Decode(_))
+		));
+	}
+}
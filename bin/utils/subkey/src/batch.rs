@@ -0,0 +1,182 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Running the operations of a batch manifest.
+
+use crate::{
+	account::generate_mnemonic,
+	crypto::{Crypto, CryptoScheme, PublicOf, PublicT, SignatureOf, SignatureT},
+	derive::derive_suri,
+	error::Error,
+	input::parse_network,
+	output::{OutputType, Secrets},
+	with_crypto_scheme,
+};
+use clap::ArgMatches;
+use serde_json::json;
+use sp_core::crypto::Ss58AddressFormat;
+use std::{fs, io, path::Path};
+
+/// Run the operations of a manifest, the `key batch` command.
+pub fn run_batch<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let manifest = matches.value_of("manifest").expect("manifest is required; qed");
+	let show_secret = matches.is_present("show-secret");
+	let results = run_manifest(Path::new(manifest), C::NAME, password, maybe_network, show_secret)?;
+	output.write_json(&mut io::stdout(), &results)?;
+	Ok(())
+}
+
+/// Run each operation of the `key batch` manifest at `path` and return the results in order.
+///
+/// Operations without a scheme or network use `default_scheme` and `default_network`. A failed
+/// operation is reported with the reason instead of its result.
+fn run_manifest(
+	path: &Path,
+	default_scheme: &str,
+	password: Option<&str>,
+	default_network: Option<Ss58AddressFormat>,
+	show_secret: bool,
+) -> Result<serde_json::Value, Error> {
+	let manifest: serde_json::Value = serde_json::from_slice(&fs::read(path)?)
+		.map_err(|e| Error::Formatted(format!("Invalid batch manifest ({})", e)))?;
+	let entries = manifest.as_array()
+		.ok_or(Error::Static("Invalid batch manifest; expecting an array of operations"))?;
+
+	let results = entries.iter().map(|entry| {
+		let operation = entry.get("operation").cloned().unwrap_or(serde_json::Value::Null);
+		let scheme = entry.get("scheme").and_then(serde_json::Value::as_str).unwrap_or(default_scheme);
+		let result = CryptoScheme::from_str_ci(scheme).and_then(|scheme| {
+			with_crypto_scheme!(scheme, run_batch_entry::<_>(entry, password, default_network))
+		});
+		let scheme = CryptoScheme::from_str_ci(scheme).map_or(scheme, |scheme| scheme.name());
+		let mut result = result.unwrap_or_else(|e| json!({ "error": e.to_string() }));
+		let result_fields = result.as_object_mut().expect("results are objects; qed");
+		if !show_secret {
+			for field in &["secretPhrase", "entropy", "secretKeyUri", "secretSeed"] {
+				result_fields.remove(*field);
+			}
+		}
+		result_fields.insert("operation".into(), operation);
+		result_fields.insert("scheme".into(), scheme.into());
+		result
+	}).collect();
+	Ok(serde_json::Value::Array(results))
+}
+
+/// Run a single operation of a `key batch` manifest.
+fn run_batch_entry<C: Crypto>(
+	entry: &serde_json::Value,
+	password: Option<&str>,
+	default_network: Option<Ss58AddressFormat>,
+) -> Result<serde_json::Value, Error>
+where
+	PublicOf<C>: PublicT,
+{
+	let field = |name| entry.get(name).and_then(serde_json::Value::as_str);
+	let required = |name| field(name).ok_or_else(|| Error::Formatted(format!(
+		"Missing or invalid `{}`; expecting a string", name,
+	)));
+	let network = match field("network") {
+		Some(network) => Some(parse_network(network)
+			.ok_or_else(|| Error::Formatted(format!("Invalid network name `{}`", network)))?),
+		None => default_network,
+	};
+
+	let uri = match required("operation")? {
+		"generate" => {
+			let words = entry.get("words").map(|words| words.to_string());
+			generate_mnemonic(words.as_deref())?.phrase().to_string()
+		},
+		"inspect" => required("uri")?.to_string(),
+		"derive" => derive_suri(required("uri")?, required("junction")?)?,
+		operation => return Err(Error::Formatted(format!(
+			"Unknown operation `{}`; expecting one of generate, inspect or derive",
+			operation,
+		))),
+	};
+	C::json_from_uri(&uri, password, network, Secrets::Show)
+		.ok_or(Error::Static("Invalid phrase/URI given"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crypto::Sr25519;
+	use sp_core::{crypto::Ss58Codec, ed25519, sr25519, Pair};
+	use std::io::Write;
+
+	#[test]
+	fn batch_runs_each_operation() {
+		let manifest = json!([
+			{ "operation": "generate", "scheme": "ed25519", "words": 24 },
+			{ "operation": "inspect", "uri": "//Alice" },
+			{ "operation": "derive", "scheme": "ecdsa", "uri": "//Alice", "junction": "//stash" },
+			{ "operation": "inspect", "scheme": "ecdsa", "uri": "//Alice//stash" },
+			{ "operation": "derive", "uri": "//Alice", "junction": "stash" },
+			{ "operation": "sign", "uri": "//Alice" },
+			{ "operation": "inspect", "scheme": "rsa", "uri": "//Alice" },
+			{ "operation": "inspect", "scheme": "ed25519", "uri": "//Alice", "network": "kusama" },
+		]);
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		write!(file, "{}", manifest).unwrap();
+
+		let results = run_manifest(file.path(), Sr25519::NAME, None, None, false).unwrap();
+		let results = results.as_array().unwrap();
+		assert_eq!(results.len(), 8);
+
+		assert_eq!(results[0]["operation"], "generate");
+		assert_eq!(results[0]["scheme"], "ed25519");
+		assert!(results[0]["ss58Address"].is_string());
+		assert!(results[0].get("secretPhrase").is_none());
+		assert!(results[0].get("secretSeed").is_none());
+
+		let alice = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+		assert_eq!(results[1]["scheme"], "sr25519");
+		assert_eq!(results[1]["ss58Address"], alice.to_ss58check());
+		assert!(results[1].get("secretKeyUri").is_none());
+
+		assert_eq!(results[2]["operation"], "derive");
+		assert_eq!(results[2]["ss58Address"], results[3]["ss58Address"]);
+		assert_eq!(results[2]["publicKey"].as_str().unwrap().len(), 2 + 2 * 33);
+
+		assert!(results[4]["error"].as_str().unwrap().contains("junction"));
+		assert!(results[5]["error"].as_str().unwrap().contains("Unknown operation `sign`"));
+		assert!(results[6]["error"].as_str().unwrap().contains("Unknown scheme `rsa`"));
+		let ed25519_alice = ed25519::Pair::from_string("//Alice", None).unwrap().public();
+		assert_eq!(results[7]["networkId"], "kusama");
+		assert_eq!(
+			results[7]["ss58Address"],
+			ed25519_alice.to_ss58check_with_version(Ss58AddressFormat::KusamaAccount),
+		);
+
+		let results = run_manifest(file.path(), Sr25519::NAME, None, None, true).unwrap();
+		let phrase = results[0]["secretPhrase"].as_str().unwrap();
+		assert_eq!(phrase.split(' ').count(), 24);
+		assert!(results[0]["secretSeed"].is_string());
+		assert_eq!(results[1]["secretKeyUri"], "//Alice");
+	}
+}
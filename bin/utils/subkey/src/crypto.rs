@@ -0,0 +1,522 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The cryptography schemes of the commands, and the accounts of their keys.
+
+use crate::{
+	error::Error,
+	format::{inspect_uri, KeyInfo},
+	input::{check_inline_password, network_name},
+	json::{AccountOutput, JsonOutput},
+	output::{OutputType, Secrets, UriDescription},
+	table::write_table,
+};
+use codec::Encode;
+use itertools::Itertools;
+use node_primitives::Signature;
+use sp_core::{crypto::{Ss58AddressFormat, Ss58Codec}, ecdsa, ed25519, sr25519, Pair, Public};
+use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::io::Write;
+
+pub trait Crypto: Sized {
+	type Pair: Pair<Public = Self::Public>;
+	type Public: Public + Ss58Codec + AsRef<[u8]> + std::hash::Hash;
+	/// The name of the cryptography scheme.
+	const NAME: &'static str;
+	fn pair_from_suri(suri: &str, password: Option<&str>) -> Result<Self::Pair, Error> {
+		check_inline_password(suri, password)?;
+		Ok(Self::Pair::from_string(suri, password)?)
+	}
+	fn ss58_from_pair(pair: &Self::Pair) -> String where
+		<Self::Pair as Pair>::Public: PublicT,
+	{
+		pair.public().into_runtime().into_account().to_ss58check()
+	}
+	fn public_from_pair(pair: &Self::Pair) -> Self::Public {
+		pair.public()
+	}
+	/// Recover the public key that made `signature` of `message`, if the scheme allows it.
+	fn recover_public(_signature: &SignatureOf<Self>, _message: &[u8]) -> Option<Self::Public> {
+		None
+	}
+	/// Sign `input` with the VRF of `pair`, and return the VRF output and proof.
+	///
+	/// The transcript is the schnorrkel signing context `label` with `input` appended.
+	fn vrf_sign(
+		_pair: &Self::Pair,
+		_label: &[u8],
+		_input: &[u8],
+	) -> Result<([u8; 32], [u8; 64]), Error> {
+		Err(Error::UnsupportedScheme {
+			operation: "VRF",
+			verb: "is",
+			scheme: Self::NAME,
+			hint: "only for sr25519",
+		})
+	}
+	/// Whether signatures are randomized, so that other implementations sign differently.
+	const RANDOMIZED_SIGNATURES: bool = false;
+	/// Whether keys can be derived along soft junctions, and not only along hard ones.
+	const SOFT_DERIVATION: bool = false;
+	/// Sign `message` with `pair`, with the same signature each time.
+	fn sign_deterministic(pair: &Self::Pair, message: &[u8]) -> SignatureOf<Self> {
+		pair.sign(message)
+	}
+	/// The account of `uri` as printed by `print_from_uri` with JSON output, or `None` if `uri` is
+	/// invalid.
+	///
+	/// The secrets are left out as `secrets` selects.
+	fn account_from_uri(
+		uri: &str,
+		password: Option<&str>,
+		network_override: Option<Ss58AddressFormat>,
+		secrets: Secrets,
+	) -> Option<AccountOutput> where
+		<Self::Pair as Pair>::Public: PublicT,
+	{
+		let info = inspect_uri::<Self::Pair>(uri, password, network_override).ok()?;
+		// A secret key URI with a soft derivation has no seed, which is printed as `n/a`.
+		let secret_seed = match info.secret_seed {
+			None if info.secret_key_uri.is_some() => Some("n/a".into()),
+			seed => seed,
+		};
+		let mut account = AccountOutput {
+			secret_phrase: info.secret_phrase,
+			entropy: info.entropy,
+			secret_key_uri: info.secret_key_uri,
+			inline_password: info.inline_password,
+			public_key_uri: info.public_key_uri,
+			network_id: network_name(info.network),
+			secret_seed,
+			public_key: info.public_key,
+			account_id: info.account_id,
+			ss58_address: info.ss58_address,
+			..Default::default()
+		};
+		if secrets != Secrets::Show {
+			account.secret_phrase = None;
+			account.entropy = None;
+			account.secret_key_uri = None;
+		}
+		if secrets == Secrets::Hide {
+			account.secret_seed = None;
+		}
+		Some(account)
+	}
+	/// The JSON of [`Crypto::account_from_uri`].
+	fn json_from_uri(
+		uri: &str,
+		password: Option<&str>,
+		network_override: Option<Ss58AddressFormat>,
+		secrets: Secrets,
+	) -> Option<serde_json::Value> where
+		<Self::Pair as Pair>::Public: PublicT,
+	{
+		Self::account_from_uri(uri, password, network_override, secrets)
+			.map(|account| account.to_json())
+	}
+	/// Print the account of `uri` to `out`; the secrets are left out as `secrets` selects.
+	///
+	/// Fails if `uri` is invalid.
+	fn print_from_uri<W: Write>(
+		out: &mut W,
+		uri: &str,
+		password: Option<&str>,
+		network_override: Option<Ss58AddressFormat>,
+		output: OutputType,
+		secrets: Secrets,
+	) -> Result<(), Error> where
+		<Self::Pair as Pair>::Public: PublicT,
+	{
+		if output.is_json() {
+			let json = Self::json_from_uri(uri, password, network_override, secrets)
+				.ok_or(Error::Static("Invalid phrase/URI given"))?;
+			output.write_json(out, &json)?;
+			return Ok(());
+		}
+		if let OutputType::Binary = output {
+			let json = Self::json_from_uri(uri, password, network_override, secrets)
+				.ok_or(Error::Static("Invalid phrase/URI given"))?;
+			out.write_all(&UriDescription::from_json(&json).encode())?;
+			return Ok(());
+		}
+		if let OutputType::Table(_) = output {
+			let uris = [uri.into()];
+			return Self::print_from_uris(out, &uris, password, network_override, output, secrets);
+		}
+
+		let info = inspect_uri::<Self::Pair>(uri, password, network_override)
+			.map_err(|_| Error::Static("Invalid phrase/URI given"))?;
+		if let OutputType::Address = output {
+			writeln!(out, "{}", info.ss58_address)?;
+			return Ok(());
+		}
+		let secret = match secrets {
+			Secrets::Show => format!("`{}`", uri),
+			Secrets::RedactUri | Secrets::Hide => "[redacted]".into(),
+		};
+		let seed_line = |seed: Option<String>| match secrets {
+			Secrets::Show | Secrets::RedactUri => format!(
+				"\n  Secret seed:        {}",
+				seed.unwrap_or_else(|| "n/a".into()),
+			),
+			Secrets::Hide => String::new(),
+		};
+		let entropy_line = match (secrets, &info.entropy) {
+			(Secrets::Show, Some(entropy)) => format!("\n  Entropy (hex):      {}", entropy),
+			_ => String::new(),
+		};
+		let KeyInfo { network, public_key, account_id, ss58_address, .. } = &info;
+		if info.secret_phrase.is_some() {
+			writeln!(out, "Secret phrase {} is account:\n  \
+				Network ID/version: {}{}{}\n  \
+				Public key (hex):   {}\n  \
+				Account ID:         {}\n  \
+				SS58 Address:       {}",
+				secret,
+				network_name(*network),
+				entropy_line,
+				seed_line(info.secret_seed.clone()),
+				public_key,
+				account_id,
+				ss58_address,
+			)?;
+		} else if let Some(inline_password) = info.inline_password {
+			writeln!(out, "Secret Key URI {} is account:\n  \
+				Inline password:    {}\n  \
+				Network ID/version: {}{}\n  \
+				Public key (hex):   {}\n  \
+				Account ID:         {}\n  \
+				SS58 Address:       {}",
+				secret,
+				if inline_password { "yes" } else { "no" },
+				network_name(*network),
+				seed_line(info.secret_seed.clone()),
+				public_key,
+				account_id,
+				ss58_address,
+			)?;
+		} else {
+			writeln!(out, "Public Key URI `{}` is account:\n  \
+				Network ID/version: {}\n  \
+				Public key (hex):   {}\n  \
+				Account ID:         {}\n  \
+				SS58 Address:       {}",
+				uri,
+				network_name(*network),
+				public_key,
+				account_id,
+				ss58_address,
+			)?;
+		}
+		Ok(())
+	}
+	/// Print the accounts of `uris` to `out`, failing at the first invalid one.
+	fn print_from_uris<W: Write>(
+		out: &mut W,
+		uris: &[String],
+		password: Option<&str>,
+		network_override: Option<Ss58AddressFormat>,
+		output: OutputType,
+		secrets: Secrets,
+	) -> Result<(), Error> where
+		<Self::Pair as Pair>::Public: PublicT,
+	{
+		if let OutputType::Table(style) = output {
+			let rows = uris
+				.iter()
+				.map(|uri| {
+					let account = Self::account_from_uri(uri, password, network_override, secrets)
+						.ok_or(Error::Static("Invalid phrase/URI given"))?;
+					Ok(vec![account.network_id, account.ss58_address, account.public_key])
+				})
+				.collect::<Result<Vec<_>, Error>>()?;
+			write_table(out, &["Network", "SS58 address", "Public key"], &rows, style)?;
+			return Ok(());
+		}
+		for uri in uris {
+			Self::print_from_uri(out, uri, password, network_override, output, secrets)?;
+		}
+		Ok(())
+	}
+}
+
+pub struct Ed25519;
+
+impl Crypto for Ed25519 {
+	type Pair = ed25519::Pair;
+	type Public = ed25519::Public;
+	const NAME: &'static str = "ed25519";
+
+	fn pair_from_suri(suri: &str, password_override: Option<&str>) -> Result<Self::Pair, Error> {
+		check_inline_password(suri, password_override)?;
+		Ok(ed25519::Pair::from_legacy_string(suri, password_override))
+	}
+}
+
+pub struct Sr25519;
+
+impl Crypto for Sr25519 {
+	type Pair = sr25519::Pair;
+	type Public = sr25519::Public;
+	const NAME: &'static str = "sr25519";
+
+	fn vrf_sign(
+		pair: &sr25519::Pair,
+		label: &[u8],
+		input: &[u8],
+	) -> Result<([u8; 32], [u8; 64]), Error> {
+		let keypair: &schnorrkel::Keypair = pair.as_ref();
+		let (inout, proof, _) = keypair.vrf_sign(schnorrkel::signing_context(label).bytes(input));
+		Ok((inout.to_output().to_bytes(), proof.to_bytes()))
+	}
+
+	const RANDOMIZED_SIGNATURES: bool = true;
+
+	const SOFT_DERIVATION: bool = true;
+
+	/// The nonce is made from the secret key and the message as usual, but without the system
+	/// randomness that is normally mixed in.
+	fn sign_deterministic(pair: &sr25519::Pair, message: &[u8]) -> sr25519::Signature {
+		use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+		let keypair: &schnorrkel::Keypair = pair.as_ref();
+		// The signing context of `sr25519::Pair::sign`.
+		let transcript = schnorrkel::signing_context(b"substrate").bytes(message);
+		let rng = ChaCha20Rng::from_seed([0; 32]);
+		let transcript = schnorrkel::context::attach_rng(transcript, rng);
+		sr25519::Signature::from_raw(keypair.sign(transcript).to_bytes())
+	}
+}
+
+pub struct Ecdsa;
+
+impl Crypto for Ecdsa {
+	type Pair = ecdsa::Pair;
+	type Public = ecdsa::Public;
+	const NAME: &'static str = "ecdsa";
+
+	fn recover_public(signature: &ecdsa::Signature, message: &[u8]) -> Option<ecdsa::Public> {
+		signature.recover(message)
+	}
+}
+
+/// Call `$function` with the [`Crypto`] of the [`CryptoScheme`] `$scheme` as its type parameter.
+#[macro_export]
+macro_rules! with_crypto_scheme {
+	($scheme:expr, $function:ident::<_>($($arg:expr),* $(,)?)) => {
+		match $scheme {
+			$crate::crypto::CryptoScheme::Sr25519 => {
+				$function::<$crate::crypto::Sr25519>($($arg),*)
+			},
+			$crate::crypto::CryptoScheme::Ed25519 => {
+				$function::<$crate::crypto::Ed25519>($($arg),*)
+			},
+			$crate::crypto::CryptoScheme::Ecdsa => $function::<$crate::crypto::Ecdsa>($($arg),*),
+		}
+	};
+}
+
+/// A scheme of [`Crypto`], to select one at runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CryptoScheme {
+	Sr25519,
+	Ed25519,
+	Ecdsa,
+}
+
+impl CryptoScheme {
+	/// All schemes, in the order they are listed to the user.
+	pub fn all() -> &'static [CryptoScheme] {
+		&[CryptoScheme::Sr25519, CryptoScheme::Ed25519, CryptoScheme::Ecdsa]
+	}
+
+	/// The name of the scheme, like [`Crypto::NAME`].
+	pub fn name(self) -> &'static str {
+		with_crypto_scheme!(self, scheme_name::<_>())
+	}
+
+	/// The scheme named `name`, in any case.
+	pub fn from_str_ci(name: &str) -> Result<Self, Error> {
+		CryptoScheme::all()
+			.iter()
+			.copied()
+			.find(|scheme| scheme.name().eq_ignore_ascii_case(name))
+			.ok_or_else(|| Error::Formatted(format!(
+				"Unknown scheme `{}`; expecting one of {}",
+				name,
+				CryptoScheme::all().iter().map(|scheme| scheme.name()).join(", "),
+			)))
+	}
+}
+
+fn scheme_name<C: Crypto>() -> &'static str {
+	C::NAME
+}
+
+pub type SignatureOf<C> = <<C as Crypto>::Pair as Pair>::Signature;
+pub type PublicOf<C> = <<C as Crypto>::Pair as Pair>::Public;
+pub type AccountPublic = <Signature as Verify>::Signer;
+
+pub trait SignatureT: AsRef<[u8]> + AsMut<[u8]> + Default {
+	/// Converts the signature into a runtime account signature, if possible. If not possible, bombs out.
+	fn into_runtime(self) -> Signature {
+		panic!("This cryptography isn't supported for this runtime.")
+	}
+	/// Extracts the signature from a runtime account signature, if it is of this cryptography.
+	fn from_runtime(_signature: Signature) -> Option<Self> {
+		None
+	}
+}
+pub trait PublicT: Sized + AsRef<[u8]> + Ss58Codec + Into<AccountPublic> {
+	/// Converts the public key into a runtime account public key, if possible. If not possible, bombs out.
+	fn into_runtime(self) -> AccountPublic {
+		panic!("This cryptography isn't supported for this runtime.")
+	}
+}
+
+impl SignatureT for sr25519::Signature {
+	fn into_runtime(self) -> Signature { self.into() }
+	fn from_runtime(signature: Signature) -> Option<Self> {
+		match signature { Signature::Sr25519(signature) => Some(signature), _ => None }
+	}
+}
+impl SignatureT for ed25519::Signature {
+	fn into_runtime(self) -> Signature { self.into() }
+	fn from_runtime(signature: Signature) -> Option<Self> {
+		match signature { Signature::Ed25519(signature) => Some(signature), _ => None }
+	}
+}
+impl SignatureT for ecdsa::Signature {
+	fn into_runtime(self) -> Signature { self.into() }
+	fn from_runtime(signature: Signature) -> Option<Self> {
+		match signature { Signature::Ecdsa(signature) => Some(signature), _ => None }
+	}
+}
+impl PublicT for sr25519::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
+impl PublicT for ed25519::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
+impl PublicT for ecdsa::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{format::inline_password, input::decode_hex};
+	use bip39::{Language, Mnemonic};
+	use sp_core::crypto::DEV_PHRASE;
+
+	#[test]
+	fn crypto_schemes_are_all_listed() {
+		// Fails to compile when a scheme is added, as a reminder to add it to `all` too.
+		let position = |scheme| match scheme {
+			CryptoScheme::Sr25519 => 0,
+			CryptoScheme::Ed25519 => 1,
+			CryptoScheme::Ecdsa => 2,
+		};
+		for (i, scheme) in CryptoScheme::all().iter().enumerate() {
+			assert_eq!(position(*scheme), i);
+			assert_eq!(CryptoScheme::from_str_ci(scheme.name()).unwrap(), *scheme);
+		}
+		assert_eq!(CryptoScheme::all().len(), 3);
+		let names = CryptoScheme::all().iter().map(|scheme| scheme.name()).collect::<Vec<_>>();
+		assert_eq!(names, [Sr25519::NAME, Ed25519::NAME, Ecdsa::NAME]);
+	}
+
+	#[test]
+	fn crypto_schemes_are_parsed_in_any_case() {
+		assert_eq!(CryptoScheme::from_str_ci("ED25519").unwrap(), CryptoScheme::Ed25519);
+		assert_eq!(CryptoScheme::from_str_ci("Sr25519").unwrap(), CryptoScheme::Sr25519);
+		assert_eq!(
+			CryptoScheme::from_str_ci("rsa").err().unwrap().to_string(),
+			"Unknown scheme `rsa`; expecting one of sr25519, ed25519, ecdsa",
+		);
+		assert_eq!(with_crypto_scheme!(CryptoScheme::Ecdsa, scheme_name::<_>()), "ecdsa");
+	}
+
+	#[test]
+	fn inline_and_given_passwords_must_agree() {
+		let expected = Sr25519::pair_from_suri("//Alice", Some("secret")).unwrap().public();
+		let inline_only = Sr25519::pair_from_suri("//Alice///secret", None).unwrap();
+		assert_eq!(inline_only.public(), expected);
+
+		let given_only = Sr25519::pair_from_suri("//Alice", Some("secret")).unwrap();
+		assert_eq!(given_only.public(), expected);
+
+		let both_same = Sr25519::pair_from_suri("//Alice///secret", Some("secret")).unwrap();
+		assert_eq!(both_same.public(), expected);
+
+		let both_different = Sr25519::pair_from_suri("//Alice///secret", Some("other")).err();
+		assert!(both_different.unwrap().to_string().contains("after `///`"));
+		assert!(Ed25519::pair_from_suri("//Alice///secret", Some("other")).is_err());
+
+		assert_eq!(inline_password("//Alice///secret"), Some("secret"));
+		assert_eq!(inline_password("//Alice//0"), None);
+	}
+
+	#[test]
+	fn inspect_json_tells_inline_passwords() {
+		let json = Sr25519::json_from_uri("//Alice///secret", None, None, Secrets::Show).unwrap();
+		assert_eq!(json["inlinePassword"], true);
+		let json = Sr25519::json_from_uri("//Alice", Some("secret"), None, Secrets::Show).unwrap();
+		assert_eq!(json["inlinePassword"], false);
+	}
+
+	#[test]
+	fn entropy_is_only_shown_with_the_secret_phrase() {
+		let entropy = |uri: &str, secrets| {
+			let account = Sr25519::account_from_uri(uri, None, None, secrets).unwrap();
+			account.entropy
+		};
+		let dev_entropy = entropy(DEV_PHRASE, Secrets::Show).unwrap();
+		let mnemonic = Mnemonic::from_entropy(
+			&decode_hex(&dev_entropy[2..]).unwrap(),
+			Language::English,
+		).unwrap();
+		assert_eq!(mnemonic.phrase(), DEV_PHRASE);
+		assert_eq!(entropy(DEV_PHRASE, Secrets::RedactUri), None);
+		assert_eq!(entropy(DEV_PHRASE, Secrets::Hide), None);
+		assert_eq!(entropy("//Alice", Secrets::Show), None);
+
+		let mut text = Vec::new();
+		Sr25519::print_from_uri(&mut text, DEV_PHRASE, None, None, OutputType::Text, Secrets::Show)
+			.unwrap();
+		let text = String::from_utf8(text).unwrap();
+		assert!(text.contains(&format!("  Entropy (hex):      {}\n", dev_entropy)));
+	}
+
+	#[test]
+	fn vrf_output_verifies_for_sr25519_only() {
+		let pair = Sr25519::pair_from_suri("//Alice", None).unwrap();
+		let (output, proof) = Sr25519::vrf_sign(&pair, b"substrate-babe-vrf", b"input").unwrap();
+
+		let public = schnorrkel::PublicKey::from_bytes(pair.public().as_ref()).unwrap();
+		let context = schnorrkel::signing_context(b"substrate-babe-vrf");
+		let transcript = |input: &[u8]| context.bytes(input);
+		let output = schnorrkel::vrf::VRFOutput::from_bytes(&output).unwrap();
+		let proof = schnorrkel::vrf::VRFProof::from_bytes(&proof).unwrap();
+		assert!(public.vrf_verify(transcript(b"input"), &output, &proof).is_ok());
+		assert!(public.vrf_verify(transcript(b"other"), &output, &proof).is_err());
+
+		// The output does not depend on the randomness of the proof.
+		let (again, _) = Sr25519::vrf_sign(&pair, b"substrate-babe-vrf", b"input").unwrap();
+		assert_eq!(output.to_bytes(), again);
+
+		let pair = Ed25519::pair_from_suri("//Alice", None).unwrap();
+		let error = Ed25519::vrf_sign(&pair, b"label", b"").err().unwrap();
+		assert!(matches!(error, Error::UnsupportedScheme { scheme: "ed25519", .. }));
+		assert!(error.to_string().contains("only for sr25519"));
+	}
+}
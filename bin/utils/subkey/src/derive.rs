@@ -0,0 +1,632 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The derivation of accounts from secret URIs and seeds.
+
+use crate::{
+	crypto::{Crypto, PublicOf, PublicT, SignatureOf, SignatureT},
+	error::{static_err, Error, UriStage},
+	format::{format_account_id, format_public_key, inline_password},
+	input::{check_inline_password, ct_eq, decode_hex, get_uri, read_required_parameter, read_uri},
+	json::{DerivedAccountOutput, JsonOutput, SuriOutput},
+	output::{Output, OutputType, Secrets},
+	table::{write_table, TableStyle},
+};
+use bip39::{Language, Mnemonic};
+use clap::ArgMatches;
+use rayon::prelude::*;
+use sp_core::{
+	crypto::{DeriveJunction, ExposeSecret, SecretString, Ss58AddressFormat, Ss58Codec, Zeroize},
+	Pair,
+};
+use sp_runtime::traits::IdentifyAccount;
+use std::{convert::{TryFrom, TryInto}, io::{self, Write}, iter};
+
+/// Derive a child account from a secret URI, the `derive` command.
+pub fn run_derive<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let junction = matches.value_of("junction").expect("junction is required; qed");
+	let uri = get_uri("uri", matches)?;
+	let uri = uri.expose_secret();
+	derive_and_print::<C>(uri, password, junction, maybe_network, output, secrets)?;
+	Ok(())
+}
+
+/// Derive an account from a raw seed, the `derive-from-seed` command.
+pub fn run_derive_from_seed<C: Crypto>(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let junctions = matches.value_of("junctions").expect("junctions are required; qed");
+	let seed = get_uri("seed", matches)?;
+	let mut seed = decode_hex(seed.expose_secret().trim_start_matches("0x"))?;
+	let derived = derive_from_seed::<C>(&seed, junctions, maybe_network, output, secrets);
+	seed.zeroize();
+	derived?;
+	Ok(())
+}
+
+/// Check the syntax of a secret URI, the `validate-suri` command.
+pub fn run_validate_suri<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let suri = get_uri("suri", matches)?;
+	let components = validate_suri::<C>(suri.expose_secret(), password)?;
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let json = SuriOutput {
+				valid: true,
+				scheme: C::NAME.into(),
+				is_phrase: components.is_phrase,
+				junction_count: components.junction_count(),
+				hard_junctions: components.hard_junctions,
+				soft_junctions: components.soft_junctions,
+				has_password: components.has_password,
+			};
+			output.write_json(&mut io::stdout(), &json.to_json())?;
+		},
+		_ => println!(
+			"Valid {} secret URI: {}, {} hard and {} soft derivations, {}.",
+			C::NAME,
+			if components.is_phrase { "mnemonic phrase" } else { "hex-encoded seed" },
+			components.hard_junctions,
+			components.soft_junctions,
+			if components.has_password { "with password" } else { "without password" },
+		),
+	}
+	Ok(())
+}
+
+/// Derive a range of accounts from one secret, the `key derive-many` command.
+pub fn run_derive_many<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let suri = read_uri(matches.value_of("suri"))?;
+	let suri = suri.expose_secret();
+	let count = read_required_parameter::<u64>(matches, "count")?;
+	let template = matches.value_of("path-template").unwrap_or(DEFAULT_PATH_TEMPLATE);
+	let format = match (output, matches.value_of("format").map(TryInto::try_into)) {
+		(OutputType::Table(style), _) => DerivedFormat::Table(style),
+		(_, Some(Err(_))) =>
+			return static_err("Invalid format; expecting one of csv or jsonl"),
+		(_, Some(Ok(format))) => format,
+		(_, None) => DerivedFormat::Csv,
+	};
+	let mut out = Output::from_matches(matches)?;
+	out.set_secret(true);
+	write_derived::<C, _>(&mut out, suri, password, template, count, format)?;
+	out.commit()
+}
+
+/// Append the derivation `junction`, either `//name` or `/name`, to the path of `base_suri`.
+///
+/// A password given in `base_suri` is kept at the end of the derived URI.
+pub(super) fn derive_suri(base_suri: &str, junction: &str) -> Result<String, Error> {
+	let name = if let Some(name) = junction.strip_prefix("//") {
+		name
+	} else if let Some(name) = junction.strip_prefix('/') {
+		name
+	} else {
+		return Err(Error::Formatted(format!(
+			"Invalid junction `{}`; expecting `//name` for a hard or `/name` for a soft derivation",
+			junction,
+		)));
+	};
+	if name.is_empty() || name.contains('/') {
+		return Err(Error::Formatted(format!(
+			"Invalid junction `{}`; expecting a single, non-empty name after the `//` or `/`",
+			junction,
+		)));
+	}
+
+	Ok(match base_suri.find("///") {
+		Some(password_start) => format!(
+			"{}{}{}",
+			&base_suri[..password_start],
+			junction,
+			&base_suri[password_start..],
+		),
+		None => format!("{}{}", base_suri, junction),
+	})
+}
+
+/// The parts of a secret URI, as told by `validate_suri`.
+#[derive(Debug, PartialEq)]
+pub struct SuriComponents {
+	/// Whether the secret is a mnemonic phrase rather than a hex-encoded seed.
+	pub is_phrase: bool,
+	pub hard_junctions: usize,
+	pub soft_junctions: usize,
+	/// Whether a password is given, inline after `///` or separately.
+	pub has_password: bool,
+}
+
+impl SuriComponents {
+	pub fn junction_count(&self) -> usize {
+		self.hard_junctions + self.soft_junctions
+	}
+}
+
+/// Check that `suri` is a valid secret URI for `C` and describe its parts.
+///
+/// Only the syntax is checked: the phrase against the BIP39 word list, the length of a seed and
+/// whether `C` supports the junctions. No key pair is derived.
+pub fn validate_suri<C: Crypto>(
+	suri: &str,
+	password: Option<&str>,
+) -> Result<SuriComponents, Error> {
+	check_inline_password(suri, password)?;
+	let invalid = |stage| Error::InvalidUri { stage };
+
+	let phrase_and_path = suri.find("///").map_or(suri, |password_start| &suri[..password_start]);
+	let path_start = phrase_and_path.find('/').unwrap_or(phrase_and_path.len());
+	let (secret, path) = phrase_and_path.split_at(path_start);
+	let is_phrase = !secret.starts_with("0x");
+	if is_phrase {
+		if !secret.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ' ') {
+			return Err(invalid(UriStage::Format));
+		}
+		// An empty phrase is the one of the dev accounts, as for `Pair::from_string`.
+		if !secret.is_empty() && Mnemonic::validate(secret, Language::English).is_err() {
+			return Err(invalid(UriStage::Phrase));
+		}
+	} else {
+		let mut seed = hex::decode(&secret[2..]).map_err(|_| invalid(UriStage::Seed))?;
+		let seed_len = seed.len();
+		seed.zeroize();
+		if seed_len != <C::Pair as Pair>::Seed::default().as_ref().len() {
+			return Err(invalid(UriStage::Seed));
+		}
+	}
+
+	let junctions = parse_junctions(path)?;
+	let hard_junctions = junctions.iter().filter(|(_, junction)| junction.is_hard()).count();
+	let soft_junctions = junctions.len() - hard_junctions;
+	if soft_junctions > 0 && !C::SOFT_DERIVATION {
+		return Err(invalid(UriStage::Derivation));
+	}
+	Ok(SuriComponents {
+		is_phrase,
+		hard_junctions,
+		soft_junctions,
+		has_password: password.is_some() || inline_password(suri).is_some(),
+	})
+}
+
+/// Apply the derivation `junction` to `base_suri` and print the derived account.
+pub fn derive_and_print<C: Crypto>(
+	base_suri: &str,
+	password: Option<&str>,
+	junction: &str,
+	network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	PublicOf<C>: PublicT,
+{
+	check_inline_password(base_suri, password)?;
+	let uri = derive_suri(base_suri, junction)?;
+	C::print_from_uri(&mut io::stdout(), &uri, password, network, output, secrets)
+}
+
+/// Derive the pair of the raw `seed` along `junctions`, one junction at a time.
+///
+/// Fails if the seed is not of the length of the seeds of `C`, or naming the first junction that
+/// can not be applied.
+fn pair_from_seed<C: Crypto>(seed: &[u8], junctions: &str) -> Result<C::Pair, Error> {
+	let seed_len = <C::Pair as Pair>::Seed::default().as_ref().len();
+	if seed.len() != seed_len {
+		return Err(Error::Formatted(format!(
+			"Invalid {} seed of {} bytes; expected {} bytes",
+			C::NAME,
+			seed.len(),
+			seed_len,
+		)));
+	}
+	if junctions.contains("///") {
+		return Err(Error::Static("A raw seed takes no password; remove the `///` part of the \
+			junctions"));
+	}
+	let mut pair = C::Pair::from_seed_slice(seed)?;
+	for (index, (junction, derive)) in parse_junctions(junctions)?.into_iter().enumerate() {
+		pair = match pair.derive(iter::once(derive), None) {
+			Ok((derived, _)) => derived,
+			Err(_) => return Err(Error::Formatted(format!(
+				"Junction {} `{}` can not be applied; is it a soft derivation, which {} does not \
+					support?",
+				index + 1,
+				junction,
+				C::NAME,
+			))),
+		};
+	}
+	Ok(pair)
+}
+
+/// Apply the derivation `junctions` to the raw `seed` and print the derived account.
+///
+/// The account is printed as the one of the secret URI made of the hex-encoded seed and the
+/// junctions, which derives the same pair.
+pub fn derive_from_seed<C: Crypto>(
+	seed: &[u8],
+	junctions: &str,
+	network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	PublicOf<C>: PublicT,
+{
+	let pair = pair_from_seed::<C>(seed, junctions)?;
+	let uri = SecretString::new(format!("0x{}{}", hex::encode(seed), junctions));
+	debug_assert!(C::pair_from_suri(uri.expose_secret(), None)
+		.map_or(false, |from_uri| ct_eq(&from_uri.to_raw_vec(), &pair.to_raw_vec())));
+	C::print_from_uri(&mut io::stdout(), uri.expose_secret(), None, network, output, secrets)
+}
+
+/// The path template `key derive-many` uses if none is given.
+const DEFAULT_PATH_TEMPLATE: &str = "//{i}";
+
+/// How many accounts `key derive-many` derives in parallel before writing them out.
+const DERIVE_CHUNK_SIZE: u64 = 1024;
+
+/// The output format of `key derive-many`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DerivedFormat {
+	Csv,
+	JsonLines,
+	/// A table, as selected with `--output table`. Unlike the other formats, it is only written
+	/// once all accounts are derived, so that the columns can be aligned.
+	Table(TableStyle),
+}
+
+impl<'a> TryFrom<&'a str> for DerivedFormat {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<DerivedFormat, ()> {
+		match s {
+			"csv" => Ok(DerivedFormat::Csv),
+			"jsonl" => Ok(DerivedFormat::JsonLines),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Parse a derivation path like `//hard/soft` into its junctions, each as given and as derived.
+fn parse_junctions(path: &str) -> Result<Vec<(&str, DeriveJunction)>, Error> {
+	let invalid = || Error::Formatted(format!(
+		"Invalid derivation path `{}`; expecting junctions like `//hard` or `/soft`",
+		path,
+	));
+	let mut junctions = Vec::new();
+	let mut rest = path;
+	while !rest.is_empty() {
+		if !rest.starts_with('/') {
+			return Err(invalid());
+		}
+		// `DeriveJunction::from` reads a leading `/` as a hard junction.
+		let junction = &rest[1..];
+		let name_start = if junction.starts_with('/') { 1 } else { 0 };
+		let end = junction[name_start..].find('/').map_or(junction.len(), |end| name_start + end);
+		if end == name_start {
+			return Err(invalid());
+		}
+		junctions.push((&rest[..end + 1], DeriveJunction::from(&junction[..end])));
+		rest = &junction[end..];
+	}
+	Ok(junctions)
+}
+
+/// Quote `field` for CSV if it contains a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+	if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.into()
+	}
+}
+
+/// Derive `count` accounts from `suri` along `template` and write them to `writer` in `format`.
+///
+/// The accounts are derived in parallel, a chunk at a time, and written in order. A table is
+/// only written once all accounts are derived.
+fn write_derived<C: Crypto, W: Write>(
+	writer: &mut W,
+	suri: &str,
+	password: Option<&str>,
+	template: &str,
+	count: u64,
+	format: DerivedFormat,
+) -> Result<(), Error>
+where
+	PublicOf<C>: PublicT,
+{
+	if !template.contains("{i}") {
+		return Err(Error::Formatted(format!(
+			"Invalid path template `{}`; expecting `{{i}}` for the index of the account",
+			template,
+		)));
+	}
+	let base = C::pair_from_suri(suri, password)?;
+	// Fail before writing anything if the template does not make a valid path.
+	parse_junctions(&template.replace("{i}", "0"))?;
+
+	if format == DerivedFormat::Csv {
+		writeln!(writer, "index,path,public_key_hex,account_id_hex,ss58_address")?;
+	}
+	let mut table_rows = Vec::new();
+	let mut chunk_start = 0;
+	while chunk_start < count {
+		let chunk_end = count.min(chunk_start + DERIVE_CHUNK_SIZE);
+		let accounts = (chunk_start..chunk_end)
+			.into_par_iter()
+			.map(|index| {
+				let path = template.replace("{i}", &index.to_string());
+				let junctions = parse_junctions(&path)?.into_iter().map(|(_, junction)| junction);
+				let (pair, _) = base.derive(junctions, None)
+					.map_err(|_| Error::Formatted(format!("Can not derive `{}`", path)))?;
+				let public = pair.public();
+				let ss58_address = public.clone().into_runtime().into_account().to_ss58check();
+				let public_key = format_public_key(public.clone());
+				let account_id = format_account_id(public);
+				Ok((index, path, public_key, account_id, ss58_address))
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+		for (index, path, public_key, account_id, ss58_address) in accounts {
+			match format {
+				DerivedFormat::Csv => writeln!(
+					writer,
+					"{},{},{},{},{}",
+					index,
+					csv_field(&path),
+					public_key,
+					account_id,
+					ss58_address,
+				)?,
+				DerivedFormat::JsonLines => {
+					let json = DerivedAccountOutput {
+						index,
+						path,
+						public_key,
+						account_id,
+						ss58_address,
+					};
+					writeln!(writer, "{}", json.to_json())?
+				},
+				DerivedFormat::Table(_) =>
+					table_rows.push(vec![index.to_string(), path, ss58_address, public_key]),
+			}
+		}
+		chunk_start = chunk_end;
+	}
+	if let DerivedFormat::Table(style) = format {
+		let columns = ["Index", "Path", "SS58 address", "Public key"];
+		write_table(writer, &columns, &table_rows, style)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crypto::{Ecdsa, Ed25519, Sr25519};
+	use sp_core::{crypto::DEV_PHRASE, sr25519};
+
+	#[test]
+	fn validate_suri_describes_the_parts() {
+		let components = validate_suri::<Sr25519>("//Alice//stash/0", None).unwrap();
+		assert_eq!(components, SuriComponents {
+			is_phrase: true,
+			hard_junctions: 2,
+			soft_junctions: 1,
+			has_password: false,
+		});
+		assert_eq!(components.junction_count(), 3);
+
+		let uri = format!("{}//polkadot///secret", DEV_PHRASE);
+		let components = validate_suri::<Ed25519>(&uri, None).unwrap();
+		assert!(components.is_phrase && components.has_password);
+		assert_eq!(components.junction_count(), 1);
+
+		let seed = "0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a";
+		let components = validate_suri::<Sr25519>(seed, Some("secret")).unwrap();
+		assert_eq!(components, SuriComponents {
+			is_phrase: false,
+			hard_junctions: 0,
+			soft_junctions: 0,
+			has_password: true,
+		});
+
+		assert!(validate_suri::<Sr25519>("bottom drive obey lake", None).is_err());
+		assert!(validate_suri::<Ed25519>("0x1234", None).is_err());
+		assert!(validate_suri::<Sr25519>("//Alice///secret", Some("other")).is_err());
+
+		let error = validate_suri::<Ecdsa>("//Alice/soft", None).err();
+		assert!(matches!(error, Some(Error::InvalidUri { stage: UriStage::Derivation })));
+		let error = validate_suri::<Sr25519>("bottom-drive//Alice", None).err();
+		assert!(matches!(error, Some(Error::InvalidUri { stage: UriStage::Format })));
+		let error = validate_suri::<Sr25519>("0xzz", None).err();
+		assert!(matches!(error, Some(Error::InvalidUri { stage: UriStage::Seed })));
+	}
+
+	fn derive_many<CryptoType: Crypto>(
+		suri: &str,
+		template: &str,
+		count: u64,
+		format: DerivedFormat,
+	) -> Result<String, Error>
+	where
+		PublicOf<CryptoType>: PublicT,
+	{
+		let mut output = Vec::new();
+		write_derived::<CryptoType, _>(&mut output, suri, None, template, count, format)?;
+		Ok(String::from_utf8(output).unwrap())
+	}
+
+	#[test]
+	fn derive_many_matches_derived_uris() {
+		let output = derive_many::<Sr25519>(DEV_PHRASE, "//{i}", 3, DerivedFormat::Csv).unwrap();
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 4);
+		assert_eq!(lines[0], "index,path,public_key_hex,account_id_hex,ss58_address");
+		for (index, line) in lines[1..].iter().enumerate() {
+			let uri = format!("{}//{}", DEV_PHRASE, index);
+			let public = sr25519::Pair::from_string(&uri, None).unwrap().public();
+			assert_eq!(*line, format!(
+				"{},//{},{},{},{}",
+				index,
+				index,
+				format_public_key(public),
+				format_account_id(public),
+				public.to_ss58check(),
+			));
+		}
+
+		let format = DerivedFormat::JsonLines;
+		let output = derive_many::<Sr25519>(DEV_PHRASE, "//Alice{i}", 2, format).unwrap();
+		let line = output.lines().nth(1).unwrap();
+		let json: serde_json::Value = serde_json::from_str(line).unwrap();
+		assert_eq!(json["index"], 1);
+		assert_eq!(json["path"], "//Alice1");
+		assert_eq!(json["ss58Address"], "5H97Vv7XZy8J8ntfpck31TPyH457pjGUT8MUbxwPeCoKu9nF");
+		assert_eq!(
+			json["publicKey"],
+			"0xe089bc527a025153b1cd5969410592a430b3fdf18943c0159cd74ad3fab89123",
+		);
+
+		assert!(derive_many::<Sr25519>("//Alice", "/{i}", 0, format).unwrap().is_empty());
+
+		let style = TableStyle { header: true, aligned: false };
+		let format = DerivedFormat::Table(style);
+		let output = derive_many::<Sr25519>(DEV_PHRASE, "//Alice{i}", 2, format).unwrap();
+		let lines = output.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 3);
+		assert_eq!(lines[0], "Index\tPath\tSS58 address\tPublic key");
+		assert_eq!(
+			lines[2],
+			"1\t//Alice1\t5H97Vv7XZy8J8ntfpck31TPyH457pjGUT8MUbxwPeCoKu9nF\t\
+			0xe089bc527a025153b1cd5969410592a430b3fdf18943c0159cd74ad3fab89123",
+		);
+	}
+
+	#[test]
+	fn derive_many_escapes_csv_and_rejects_bad_templates() {
+		assert_eq!(csv_field("//batch//1"), "//batch//1");
+		assert_eq!(csv_field("//a,b"), "\"//a,b\"");
+		assert_eq!(csv_field("//say \"hi\""), "\"//say \"\"hi\"\"\"");
+
+		let format = DerivedFormat::Csv;
+		let output = derive_many::<Ed25519>("//Alice", "//a,b//{i}", 1, format).unwrap();
+		assert!(output.lines().nth(1).unwrap().starts_with("0,\"//a,b//0\",0x"));
+
+		for template in &["//batch", "{i}", "//{i}//", "///{i}"] {
+			let result = derive_many::<Ed25519>("//Alice", template, 1, DerivedFormat::Csv);
+			assert!(result.is_err(), "{} should be rejected", template);
+		}
+		// ed25519 supports hard derivation only.
+		assert!(derive_many::<Ed25519>("//Alice", "/{i}", 1, DerivedFormat::Csv).is_err());
+	}
+
+	#[test]
+	fn parse_junctions_matches_secret_uris() {
+		let junctions = parse_junctions("//hard/soft//7").unwrap();
+		assert_eq!(junctions, vec![
+			("//hard", DeriveJunction::from("/hard")),
+			("/soft", DeriveJunction::from("soft")),
+			("//7", DeriveJunction::from("/7")),
+		]);
+		assert!(parse_junctions("").unwrap().is_empty());
+	}
+
+	#[test]
+	fn derive_suri_appends_junction() {
+		assert_eq!(derive_suri("//Alice", "//stash").unwrap(), "//Alice//stash");
+		assert_eq!(derive_suri("//Alice", "/soft").unwrap(), "//Alice/soft");
+		assert_eq!(
+			derive_suri("//Alice///password", "//stash").unwrap(),
+			"//Alice//stash///password",
+		);
+
+		let derived = derive_suri("//Alice", "//stash").unwrap();
+		let derived = sr25519::Pair::from_string(&derived, None).unwrap();
+		let stash = sr25519::Pair::from_string("//Alice//stash", None).unwrap();
+		assert_eq!(derived.public(), stash.public());
+	}
+
+	#[test]
+	fn derive_suri_rejects_malformed_junctions() {
+		for junction in &["", "stash", "/", "//", "//a//b", "/a/b", "///password"] {
+			assert!(derive_suri("//Alice", junction).is_err(), "{} should be rejected", junction);
+		}
+	}
+
+	#[test]
+	fn pair_from_seed_matches_seed_uri() {
+		let seed = [7u8; 32];
+		for junctions in &["", "//stash", "//stash/soft", "/1//2"] {
+			let derived = pair_from_seed::<Sr25519>(&seed, junctions).unwrap();
+			let uri = format!("0x{}{}", hex::encode(&seed), junctions);
+			let from_uri = sr25519::Pair::from_string(&uri, None).unwrap();
+			assert_eq!(derived.public(), from_uri.public(), "{} should derive alike", junctions);
+		}
+	}
+
+	#[test]
+	fn pair_from_seed_rejects_bad_seeds_and_junctions() {
+		let seed = [7u8; 32];
+		fn error<P>(result: Result<P, Error>) -> String {
+			result.err().unwrap().to_string()
+		}
+
+		assert!(error(pair_from_seed::<Sr25519>(&seed[..31], "")).contains("31 bytes"));
+		assert!(error(pair_from_seed::<Ed25519>(&seed, "//a/b")).contains("Junction 2 `/b`"));
+		assert!(error(pair_from_seed::<Ecdsa>(&seed, "/a")).contains("Junction 1 `/a`"));
+		for junctions in &["stash", "//", "//a/", "///password"] {
+			let pair = pair_from_seed::<Sr25519>(&seed, junctions);
+			assert!(pair.is_err(), "{} should be rejected", junctions);
+		}
+	}
+}
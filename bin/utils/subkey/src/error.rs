@@ -0,0 +1,354 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The errors of the commands, and how a failure is reported on STDERR and in the exit code.
+
+use crate::rpc;
+use serde_json::json;
+use sp_core::crypto::SecretStringError;
+use std::{convert::{TryFrom, TryInto}, fmt};
+
+#[derive(derive_more::Display, derive_more::From)]
+pub enum Error {
+	Static(&'static str),
+	Io(std::io::Error),
+	Formatted(String),
+	RemoteSigner(rpc::RemoteSignerError),
+	InsertKey(rpc::InsertKeyError),
+	RotateKeys(rpc::RotateKeysError),
+	/// Data that should be hex-encoded is not, with the position of the first invalid character if
+	/// there is one.
+	#[display(fmt = "Invalid hex ({})", source)]
+	#[from(ignore)]
+	InvalidHex { position: Option<usize>, source: hex::FromHexError },
+	/// Data that should be base64-encoded is not.
+	#[display(fmt = "Invalid base64 ({})", _0)]
+	InvalidBase64(base64::DecodeError),
+	/// A message that is printed as text is not valid UTF-8.
+	#[display(fmt = "Message is not valid UTF-8 ({})", _0)]
+	InvalidUtf8(std::string::FromUtf8Error),
+	/// A secret URI is invalid, at the given stage of reading it.
+	#[display(fmt = "Invalid phrase or secret URI: {}", stage)]
+	#[from(ignore)]
+	InvalidUri { stage: UriStage },
+	/// The message is too large to be read into memory.
+	#[display(fmt = "Message is larger than {} bytes; use --hash to sign or verify its digest", _0)]
+	#[from(ignore)]
+	MessageTooLarge(usize),
+	/// An SS58 address can not be decoded.
+	#[display(fmt = "Invalid SS58-check address `{}`", _0)]
+	#[from(ignore)]
+	Ss58Decode(String),
+	/// Decoded data, like a signature, is not of the length expected for `scheme`.
+	#[display(fmt = "{} has an invalid length. read {} bytes, expected {} bytes for {}",
+		what, read, expected, scheme)]
+	#[from(ignore)]
+	InvalidLength { what: &'static str, read: usize, expected: usize, scheme: &'static str },
+	/// A signature is well-formed, but does not verify.
+	#[display(fmt = "Signature invalid.")]
+	#[from(ignore)]
+	InvalidSignature,
+	/// The keystore can not be opened, read or written; `action` says which.
+	#[display(fmt = "Failed to {}: {}", action, source)]
+	#[from(ignore)]
+	KeystoreIo { action: &'static str, source: sc_keystore::Error },
+	/// An operation is only supported for some schemes, and not for `scheme`.
+	#[display(fmt = "{} {} not supported for {}; {}", operation, verb, scheme, hint)]
+	#[from(ignore)]
+	UnsupportedScheme {
+		operation: &'static str,
+		verb: &'static str,
+		scheme: &'static str,
+		hint: &'static str,
+	},
+}
+
+impl From<hex::FromHexError> for Error {
+	fn from(source: hex::FromHexError) -> Self {
+		let position = match source {
+			hex::FromHexError::InvalidHexCharacter { index, .. } => Some(index),
+			_ => None,
+		};
+		Error::InvalidHex { position, source }
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(source) => Some(source),
+			Error::InvalidHex { source, .. } => Some(source),
+			Error::InvalidBase64(source) => Some(source),
+			Error::InvalidUtf8(source) => Some(source),
+			Error::KeystoreIo { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
+
+/// The stage at which reading a secret URI fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum UriStage {
+	/// The secret is neither a phrase nor a hex-encoded seed.
+	#[display(fmt = "neither a phrase nor a hex-encoded seed")]
+	Format,
+	/// The phrase is not a valid BIP39 mnemonic.
+	#[display(fmt = "the phrase is not a valid BIP39 mnemonic")]
+	Phrase,
+	/// The password does not fit the secret.
+	#[display(fmt = "the password is invalid")]
+	Password,
+	/// The seed has invalid content or length.
+	#[display(fmt = "the seed is invalid, or of the wrong length")]
+	Seed,
+	/// A derivation junction can not be applied, like a soft junction to an ed25519 key.
+	#[display(fmt = "the derivation path is invalid for this scheme")]
+	Derivation,
+}
+
+impl From<SecretStringError> for UriStage {
+	fn from(error: SecretStringError) -> Self {
+		match error {
+			SecretStringError::InvalidFormat => UriStage::Format,
+			SecretStringError::InvalidPhrase => UriStage::Phrase,
+			SecretStringError::InvalidPassword => UriStage::Password,
+			SecretStringError::InvalidSeed | SecretStringError::InvalidSeedLength => UriStage::Seed,
+			SecretStringError::InvalidPath => UriStage::Derivation,
+		}
+	}
+}
+
+impl From<SecretStringError> for Error {
+	fn from(error: SecretStringError) -> Self {
+		Error::InvalidUri { stage: error.into() }
+	}
+}
+
+impl Error {
+	/// The kind of this error, which gives the exit code of the process failing with it.
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Error::InvalidSignature => ErrorKind::VerificationFailed,
+			Error::KeystoreIo { source: sc_keystore::Error::InvalidKeyFileFormat(_), .. } =>
+				ErrorKind::Usage,
+			Error::Io(_) | Error::KeystoreIo { .. } => ErrorKind::Io,
+			Error::InvalidHex { .. }
+			| Error::InvalidBase64(_)
+			| Error::InvalidUtf8(_)
+			| Error::InvalidUri { .. }
+			| Error::Ss58Decode(_)
+			| Error::InvalidLength { .. } => ErrorKind::Parse,
+			Error::RemoteSigner(_) | Error::InsertKey(_) | Error::RotateKeys(_) => ErrorKind::Rpc,
+			Error::Static(_)
+			| Error::Formatted(_)
+			| Error::MessageTooLarge(_)
+			| Error::UnsupportedScheme { .. } => ErrorKind::Usage,
+		}
+	}
+}
+
+/// The kind of a failure, printed by `--error-format json` and giving the exit code.
+///
+/// The exit codes are stable, so that scripts can tell the kinds apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+	/// A signature is well-formed, but does not verify. Exits with 1.
+	VerificationFailed,
+	/// The arguments are invalid, or the operation can not be done with them. Exits with 2.
+	Usage,
+	/// A file or keystore can not be read or written. Exits with 3.
+	Io,
+	/// Hex, base64, SS58, a secret URI or other encoded data can not be decoded. Exits with 4.
+	Parse,
+	/// A node or remote signer can not be reached, or fails the request. Exits with 5.
+	Rpc,
+}
+
+impl ErrorKind {
+	/// The name of the kind, as printed by `--error-format json`.
+	fn name(self) -> &'static str {
+		match self {
+			ErrorKind::VerificationFailed => "verification-failed",
+			ErrorKind::Usage => "usage",
+			ErrorKind::Io => "io",
+			ErrorKind::Parse => "parse",
+			ErrorKind::Rpc => "rpc",
+		}
+	}
+
+	fn exit_code(self) -> i32 {
+		match self {
+			ErrorKind::VerificationFailed => 1,
+			ErrorKind::Usage => 2,
+			ErrorKind::Io => 3,
+			ErrorKind::Parse => 4,
+			ErrorKind::Rpc => 5,
+		}
+	}
+}
+
+/// How a failure is printed to STDERR, as selected with `--error-format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorFormat {
+	/// The message, as prose.
+	Text,
+	/// A single line of `{ "error": { "kind": ..., "message": ..., "exitCode": ... } }`.
+	Json,
+}
+
+impl<'a> TryFrom<&'a str> for ErrorFormat {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<ErrorFormat, ()> {
+		match s {
+			"text" => Ok(ErrorFormat::Text),
+			"json" => Ok(ErrorFormat::Json),
+			_ => Err(()),
+		}
+	}
+}
+
+impl ErrorFormat {
+	/// The format selected with `--error-format` in the raw command line `args`.
+	///
+	/// The arguments are searched before they are parsed, so that a command line clap rejects is
+	/// reported in the selected format too. An invalid format is reported by `execute`, and text
+	/// is used until then.
+	pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Self {
+		let args = args.into_iter().collect::<Vec<_>>();
+		let format = args.iter().enumerate().find_map(|(index, arg)| {
+			if arg == "--error-format" {
+				Some(args.get(index + 1).map_or("", |format| &format[..]))
+			} else {
+				arg.strip_prefix("--error-format=")
+			}
+		});
+		format.and_then(|format| format.try_into().ok()).unwrap_or(ErrorFormat::Text)
+	}
+
+	/// Print the failure `message` of `kind` to STDERR and exit with the exit code of `kind`.
+	///
+	/// As text, the message is printed as given.
+	pub fn exit(self, kind: ErrorKind, message: &str) -> ! {
+		match self {
+			ErrorFormat::Text => eprintln!("{}", message),
+			ErrorFormat::Json => eprintln!("{}", error_json(kind, message)),
+		}
+		std::process::exit(kind.exit_code())
+	}
+}
+
+/// The JSON printed by `--error-format json` for the failure `message` of `kind`.
+fn error_json(kind: ErrorKind, message: &str) -> serde_json::Value {
+	json!({
+		"error": {
+			"kind": kind.name(),
+			"message": message,
+			"exitCode": kind.exit_code(),
+		}
+	})
+}
+
+impl fmt::Debug for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+pub fn static_err(msg: &'static str) -> Result<(), Error> {
+	Err(Error::Static(msg))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		account::parse_account_id,
+		crypto::{Crypto, Ed25519, Sr25519},
+		derive::validate_suri,
+		input::{decode_hex, message_to_utf8, read_message_from, MessageEncoding, MAX_MESSAGE_SIZE},
+	};
+	use std::io::{self, Read};
+
+	#[test]
+	fn helpers_return_specific_errors() {
+		let error = decode_hex("0x00").err().unwrap();
+		assert!(matches!(error, Error::InvalidHex { position: Some(1), .. }));
+		assert_eq!(error.to_string(), "Invalid hex (Invalid character 'x' at position 1)");
+		assert!(std::error::Error::source(&error).is_some());
+		let error = decode_hex("abc").err().unwrap();
+		assert!(matches!(error, Error::InvalidHex { position: None, .. }));
+
+		assert_eq!(message_to_utf8(b"Blah".to_vec(), false).unwrap(), "Blah");
+		let error = message_to_utf8(b"Bl\xffh".to_vec(), false).err().unwrap();
+		assert!(matches!(error, Error::InvalidUtf8(_)));
+		assert_eq!(message_to_utf8(b"Bl\xffh".to_vec(), true).unwrap(), "Bl\u{fffd}h");
+
+		let error = Sr25519::pair_from_suri("not a phrase", None).err().unwrap();
+		assert!(matches!(error, Error::InvalidUri { stage: UriStage::Phrase }));
+		assert_eq!(
+			error.to_string(),
+			"Invalid phrase or secret URI: the phrase is not a valid BIP39 mnemonic",
+		);
+		let error = Sr25519::pair_from_suri("0x1234", None).err().unwrap();
+		assert!(matches!(error, Error::InvalidUri { stage: UriStage::Seed }));
+		let error = validate_suri::<Ed25519>("//Alice/soft", None).err();
+		assert!(matches!(error, Some(Error::InvalidUri { stage: UriStage::Derivation })));
+
+		let error = parse_account_id("5NotAnAddress").err().unwrap();
+		assert!(matches!(error, Error::Ss58Decode(_)));
+		assert_eq!(error.to_string(), "Invalid SS58-check address `5NotAnAddress`");
+
+		let message = std::io::repeat(0).take(MAX_MESSAGE_SIZE as u64);
+		let raw = MessageEncoding::Raw;
+		assert_eq!(read_message_from(message, raw).unwrap().len(), MAX_MESSAGE_SIZE);
+		let message = std::io::repeat(0).take(MAX_MESSAGE_SIZE as u64 + 1);
+		assert!(matches!(read_message_from(message, raw), Err(Error::MessageTooLarge(MAX_MESSAGE_SIZE))));
+	}
+
+	#[test]
+	fn error_format_is_read_from_raw_args() {
+		let format = |args: &[&str]| ErrorFormat::from_args(args.iter().map(|arg| arg.to_string()));
+		assert_eq!(format(&["subkey", "inspect"]), ErrorFormat::Text);
+		assert_eq!(format(&["subkey", "--error-format", "json", "inspect"]), ErrorFormat::Json);
+		assert_eq!(format(&["subkey", "inspect", "--error-format=json"]), ErrorFormat::Json);
+		assert_eq!(format(&["subkey", "--error-format", "yaml"]), ErrorFormat::Text);
+		assert_eq!(format(&["subkey", "--error-format"]), ErrorFormat::Text);
+	}
+
+	#[test]
+	fn error_kinds_give_exit_codes() {
+		let codes = |errors: Vec<Error>| {
+			errors.iter().map(|e| e.kind().exit_code()).collect::<Vec<_>>()
+		};
+		assert_eq!(
+			codes(vec![
+				Error::InvalidSignature,
+				Error::Static("usage"),
+				io::Error::from(io::ErrorKind::NotFound).into(),
+				decode_hex("zz").err().unwrap(),
+				rpc::RotateKeysError::Connection(String::new()).into(),
+			]),
+			vec![1, 2, 3, 4, 5],
+		);
+		assert_eq!(
+			error_json(ErrorKind::Parse, "Invalid hex"),
+			json!({ "error": { "kind": "parse", "message": "Invalid hex", "exitCode": 4 } }),
+		);
+	}
+}
@@ -0,0 +1,182 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The formatting of keys and accounts, and the account of a URI as `subkey inspect` prints it.
+//!
+//! Keys, seeds and account ids are always hex-encoded in lowercase, with a `0x` prefix.
+
+use bip39::{Language, Mnemonic};
+use sp_core::{
+	crypto::{AccountId32, SecretStringError, Ss58AddressFormat, Ss58Codec},
+	hexdisplay::HexDisplay,
+	Pair,
+};
+use sp_runtime::{traits::IdentifyAccount, MultiSigner};
+
+/// The account of a URI, as printed by `subkey inspect`.
+///
+/// Exactly one of `secret_phrase`, `secret_key_uri` and `public_key_uri` is set, as the URI is
+/// one of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyInfo {
+	/// The secret phrase, if the account is given by one.
+	pub secret_phrase: Option<String>,
+	/// The hex-encoded BIP39 entropy of the secret phrase, if it is an English mnemonic.
+	pub entropy: Option<String>,
+	/// The secret key URI, if the account is given by one.
+	pub secret_key_uri: Option<String>,
+	/// Whether the secret key URI includes a password after `///`.
+	pub inline_password: Option<bool>,
+	/// The public key URI, if the account is given by one.
+	pub public_key_uri: Option<String>,
+	/// The network of the SS58 address.
+	pub network: Ss58AddressFormat,
+	/// The hex-encoded secret seed; `None` for public key URIs and soft derivations.
+	pub secret_seed: Option<String>,
+	/// The hex-encoded public key.
+	pub public_key: String,
+	/// The hex-encoded account id.
+	pub account_id: String,
+	/// The SS58 address of the account on the network.
+	pub ss58_address: String,
+}
+
+/// The account of `uri`, which is a secret phrase, a secret key URI or a public key URI.
+///
+/// The password of a secret `uri` is `password`. The SS58 address is of the network
+/// `network_override`, or else of the default network for secret URIs, and of the network of the
+/// address for public key URIs.
+///
+/// ```
+/// use sp_core::sr25519;
+/// use subkey::format::inspect_uri;
+///
+/// let info = inspect_uri::<sr25519::Pair>("//Alice", None, None).unwrap();
+/// assert_eq!(info.secret_key_uri.as_deref(), Some("//Alice"));
+/// assert_eq!(info.ss58_address, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+/// assert_eq!(
+/// 	info.public_key,
+/// 	"0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+/// );
+/// assert!(inspect_uri::<sr25519::Pair>("not a uri", None, None).is_err());
+/// ```
+pub fn inspect_uri<P: Pair>(
+	uri: &str,
+	password: Option<&str>,
+	network_override: Option<Ss58AddressFormat>,
+) -> Result<KeyInfo, SecretStringError>
+where
+	P::Public: Into<MultiSigner> + Ss58Codec,
+{
+	let network = network_override.unwrap_or_default();
+	if let Ok((pair, seed)) = P::from_phrase(uri, password) {
+		let public_key = pair.public();
+		return Ok(KeyInfo {
+			secret_phrase: Some(uri.into()),
+			entropy: format_entropy(uri),
+			network,
+			secret_seed: Some(format_seed(&seed)),
+			public_key: format_public_key(public_key.clone()),
+			account_id: format_account_id(public_key.clone()),
+			ss58_address: account_id_bytes(public_key).to_ss58check_with_version(network),
+			..Default::default()
+		});
+	}
+	let error = match P::from_string_with_seed(uri, password) {
+		Ok((pair, seed)) => {
+			let public_key = pair.public();
+			return Ok(KeyInfo {
+				secret_key_uri: Some(uri.into()),
+				inline_password: Some(inline_password(uri).is_some()),
+				network,
+				secret_seed: seed.map(|seed| format_seed(&seed)),
+				public_key: format_public_key(public_key.clone()),
+				account_id: format_account_id(public_key.clone()),
+				ss58_address: account_id_bytes(public_key).to_ss58check_with_version(network),
+				..Default::default()
+			});
+		},
+		Err(error) => error,
+	};
+	let (public_key, network) = P::Public::from_string_with_version(uri).map_err(|_| error)?;
+	let network = network_override.unwrap_or(network);
+	Ok(KeyInfo {
+		public_key_uri: Some(uri.into()),
+		network,
+		public_key: format_public_key(public_key.clone()),
+		account_id: format_account_id(public_key.clone()),
+		ss58_address: public_key.to_ss58check_with_version(network),
+		..Default::default()
+	})
+}
+
+/// The password given inline in the secret URI `suri`, after `///`.
+pub fn inline_password(suri: &str) -> Option<&str> {
+	// Neither the phrase nor the derivation path may contain `///`.
+	suri.find("///").map(|start| &suri[start + 3..])
+}
+
+/// The hex-encoded `seed`.
+///
+/// ```
+/// assert_eq!(subkey::format::format_seed(&[0xab; 2]), "0xabab");
+/// ```
+pub fn format_seed<S: AsRef<[u8]>>(seed: &S) -> String {
+	format!("0x{}", HexDisplay::from(&seed.as_ref()))
+}
+
+/// The hex-encoded BIP39 entropy of the English mnemonic `phrase`, or `None` if it is not one.
+pub fn format_entropy(phrase: &str) -> Option<String> {
+	let mnemonic = Mnemonic::from_phrase(phrase, Language::English).ok()?;
+	Some(format!("0x{}", HexDisplay::from(&mnemonic.entropy())))
+}
+
+/// The hex-encoded `public_key`.
+///
+/// ```
+/// use sp_core::{ed25519, Pair};
+///
+/// let public = ed25519::Pair::from_string("//Alice", None).unwrap().public();
+/// assert_eq!(
+/// 	subkey::format::format_public_key(public),
+/// 	"0x88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee",
+/// );
+/// ```
+pub fn format_public_key<P: AsRef<[u8]>>(public_key: P) -> String {
+	format!("0x{}", HexDisplay::from(&public_key.as_ref()))
+}
+
+/// The account id of `public_key`: the public key itself, or its blake2-256 hash for ecdsa.
+pub fn account_id_bytes<P: Into<MultiSigner>>(public_key: P) -> AccountId32 {
+	public_key.into().into_account()
+}
+
+/// The hex-encoded account id of `public_key`.
+///
+/// ```
+/// use sp_core::{sr25519, Pair};
+///
+/// let public = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+/// assert_eq!(
+/// 	subkey::format::format_account_id(public),
+/// 	subkey::format::format_public_key(public),
+/// );
+/// ```
+pub fn format_account_id<P: Into<MultiSigner>>(public_key: P) -> String {
+	format!("0x{}", HexDisplay::from(&account_id_bytes(public_key).as_ref()))
+}
@@ -0,0 +1,560 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Hashing of messages that are streamed rather than read into memory at once, and of the small
+//! inputs the runtime storage hashers are applied to.
+
+use crate::{
+	error::{static_err, Error},
+	input::{decode_hex, read_message, read_message_encoding, MessageEncoding},
+	json::{JsonOutput, StorageKeyOutput, StorageKeySegmentOutput},
+	output::OutputType,
+};
+use clap::ArgMatches;
+use sp_core::hashing::{blake2_128, blake2_256, keccak_256, sha2_256, twox_128, twox_256, twox_64};
+use blake2_rfc::blake2b::Blake2b;
+use sha2::{Digest, Sha256};
+use std::{convert::TryFrom, io::{self, Read}};
+use tiny_keccak::{Hasher as _, Keccak};
+
+/// The size of the chunks a message is read in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A hash function a message can be digested with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashAlgo {
+	Blake2_256,
+	Keccak256,
+	Sha2_256,
+}
+
+impl HashAlgo {
+	pub(super) fn as_str(&self) -> &'static str {
+		match self {
+			HashAlgo::Blake2_256 => "blake2-256",
+			HashAlgo::Keccak256 => "keccak-256",
+			HashAlgo::Sha2_256 => "sha-256",
+		}
+	}
+}
+
+impl<'a> TryFrom<&'a str> for HashAlgo {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<HashAlgo, ()> {
+		match s {
+			"blake2-256" => Ok(HashAlgo::Blake2_256),
+			"keccak-256" => Ok(HashAlgo::Keccak256),
+			"sha-256" => Ok(HashAlgo::Sha2_256),
+			_ => Err(()),
+		}
+	}
+}
+
+/// A hasher the runtime applies to storage keys, see `frame_support::Hashable`, or `sha2_256`
+/// of `sp_io::hashing`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum KeyHasher {
+	Blake2_128,
+	Blake2_256,
+	Twox64,
+	Twox128,
+	Keccak256,
+	Sha2_256,
+}
+
+impl KeyHasher {
+	/// All key hashers, in the order they are listed to the user.
+	pub(super) const ALL: &'static [KeyHasher] = &[
+		KeyHasher::Blake2_128,
+		KeyHasher::Blake2_256,
+		KeyHasher::Twox64,
+		KeyHasher::Twox128,
+		KeyHasher::Keccak256,
+		KeyHasher::Sha2_256,
+	];
+
+	pub(super) fn as_str(&self) -> &'static str {
+		match self {
+			KeyHasher::Blake2_128 => "blake2_128",
+			KeyHasher::Blake2_256 => "blake2_256",
+			KeyHasher::Twox64 => "twox_64",
+			KeyHasher::Twox128 => "twox_128",
+			KeyHasher::Keccak256 => "keccak_256",
+			KeyHasher::Sha2_256 => "sha2_256",
+		}
+	}
+
+	/// The [`HashAlgo`] computing the same digest, which messages too long to be read into memory
+	/// can be streamed through.
+	pub(super) fn message_algo(&self) -> Option<HashAlgo> {
+		match self {
+			KeyHasher::Blake2_256 => Some(HashAlgo::Blake2_256),
+			KeyHasher::Keccak256 => Some(HashAlgo::Keccak256),
+			KeyHasher::Sha2_256 => Some(HashAlgo::Sha2_256),
+			_ => None,
+		}
+	}
+
+	/// Hash `data`, and append `data` itself to the digest if `concat` is set, like
+	/// `blake2_128_concat` and `twox_64_concat` do.
+	pub(super) fn hash(&self, data: &[u8], concat: bool) -> Vec<u8> {
+		let mut digest = match self {
+			KeyHasher::Blake2_128 => blake2_128(data).to_vec(),
+			KeyHasher::Blake2_256 => blake2_256(data).to_vec(),
+			KeyHasher::Twox64 => twox_64(data).to_vec(),
+			KeyHasher::Twox128 => twox_128(data).to_vec(),
+			KeyHasher::Keccak256 => keccak_256(data).to_vec(),
+			KeyHasher::Sha2_256 => sha2_256(data).to_vec(),
+		};
+		if concat {
+			digest.extend_from_slice(data);
+		}
+		digest
+	}
+}
+
+impl<'a> TryFrom<&'a str> for KeyHasher {
+	type Error = Error;
+
+	fn try_from(s: &'a str) -> Result<KeyHasher, Error> {
+		KeyHasher::ALL.iter().find(|hasher| hasher.as_str() == s).copied().ok_or_else(|| {
+			let names = KeyHasher::ALL.iter().map(KeyHasher::as_str).collect::<Vec<_>>();
+			Error::Formatted(format!(
+				"Unknown hash algorithm `{}`; expecting one of {}",
+				s,
+				names.join(", "),
+			))
+		})
+	}
+}
+
+/// A hasher of the keys of a storage map, see `frame_support::StorageHasher`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum StorageHasher {
+	Blake2_128,
+	Blake2_256,
+	Blake2_128Concat,
+	Twox128,
+	Twox256,
+	Twox64Concat,
+	Identity,
+}
+
+impl StorageHasher {
+	/// All storage hashers, in the order they are listed to the user.
+	pub(super) const ALL: &'static [StorageHasher] = &[
+		StorageHasher::Blake2_128,
+		StorageHasher::Blake2_256,
+		StorageHasher::Blake2_128Concat,
+		StorageHasher::Twox128,
+		StorageHasher::Twox256,
+		StorageHasher::Twox64Concat,
+		StorageHasher::Identity,
+	];
+
+	pub(super) fn as_str(&self) -> &'static str {
+		match self {
+			StorageHasher::Blake2_128 => "blake2_128",
+			StorageHasher::Blake2_256 => "blake2_256",
+			StorageHasher::Blake2_128Concat => "blake2_128_concat",
+			StorageHasher::Twox128 => "twox_128",
+			StorageHasher::Twox256 => "twox_256",
+			StorageHasher::Twox64Concat => "twox_64_concat",
+			StorageHasher::Identity => "identity",
+		}
+	}
+
+	pub(super) fn hash(&self, key: &[u8]) -> Vec<u8> {
+		match self {
+			StorageHasher::Blake2_128 => blake2_128(key).to_vec(),
+			StorageHasher::Blake2_256 => blake2_256(key).to_vec(),
+			StorageHasher::Blake2_128Concat => KeyHasher::Blake2_128.hash(key, true),
+			StorageHasher::Twox128 => twox_128(key).to_vec(),
+			StorageHasher::Twox256 => twox_256(key).to_vec(),
+			StorageHasher::Twox64Concat => KeyHasher::Twox64.hash(key, true),
+			StorageHasher::Identity => key.to_vec(),
+		}
+	}
+}
+
+impl<'a> TryFrom<&'a str> for StorageHasher {
+	type Error = Error;
+
+	fn try_from(s: &'a str) -> Result<StorageHasher, Error> {
+		StorageHasher::ALL.iter().find(|hasher| hasher.as_str() == s).copied().ok_or_else(|| {
+			let names = StorageHasher::ALL.iter().map(StorageHasher::as_str).collect::<Vec<_>>();
+			Error::Formatted(format!(
+				"Unknown storage hasher `{}`; expecting one of {}",
+				s,
+				names.join(", "),
+			))
+		})
+	}
+}
+
+/// A part of a storage key, see [`storage_key`].
+#[derive(Debug, PartialEq)]
+pub(super) struct StorageKeySegment {
+	/// What the segment is the hash of, e.g. `pallet` or `key 1`.
+	pub(super) label: String,
+	/// The hashed value, as given.
+	pub(super) value: Vec<u8>,
+	pub(super) hasher: StorageHasher,
+	pub(super) hash: Vec<u8>,
+}
+
+/// Compute the segments of the key of the storage `item` of `pallet`, for the map `keys` if any.
+///
+/// The storage key is the concatenation of the hashes of the segments: the `twox_128` hashes of
+/// the pallet prefix and of the item name, followed by each map key hashed with its hasher.
+pub(super) fn storage_key(
+	pallet: &str,
+	item: &str,
+	keys: &[(StorageHasher, Vec<u8>)],
+) -> Vec<StorageKeySegment> {
+	let prefix = |label: &str, name: &str| StorageKeySegment {
+		label: label.into(),
+		value: name.as_bytes().to_vec(),
+		hasher: StorageHasher::Twox128,
+		hash: StorageHasher::Twox128.hash(name.as_bytes()),
+	};
+
+	let mut segments = vec![prefix("pallet", pallet), prefix("item", item)];
+	segments.extend(keys.iter().enumerate().map(|(i, (hasher, key))| StorageKeySegment {
+		label: format!("key {}", i + 1),
+		value: key.clone(),
+		hasher: *hasher,
+		hash: hasher.hash(key),
+	}));
+	segments
+}
+
+/// The running state of a [`HashAlgo`].
+enum Hasher {
+	Blake2_256(Blake2b),
+	Keccak256(Keccak),
+	Sha2_256(Sha256),
+}
+
+impl Hasher {
+	fn new(algo: HashAlgo) -> Self {
+		match algo {
+			HashAlgo::Blake2_256 => Hasher::Blake2_256(Blake2b::new(32)),
+			HashAlgo::Keccak256 => Hasher::Keccak256(Keccak::v256()),
+			HashAlgo::Sha2_256 => Hasher::Sha2_256(Sha256::new()),
+		}
+	}
+
+	fn update(&mut self, data: &[u8]) {
+		match self {
+			Hasher::Blake2_256(hasher) => hasher.update(data),
+			Hasher::Keccak256(hasher) => hasher.update(data),
+			Hasher::Sha2_256(hasher) => hasher.input(data),
+		}
+	}
+
+	fn finalize(self) -> [u8; 32] {
+		let mut output = [0; 32];
+		match self {
+			Hasher::Blake2_256(hasher) => output.copy_from_slice(hasher.finalize().as_bytes()),
+			Hasher::Keccak256(hasher) => hasher.finalize(&mut output),
+			Hasher::Sha2_256(hasher) => output.copy_from_slice(&hasher.result()),
+		}
+		output
+	}
+}
+
+/// Read the message from `reader` chunk by chunk and return its `algo` digest.
+///
+/// The message is decoded with `encoding` before it is hashed.
+pub fn hash_message<R: Read>(
+	mut reader: R,
+	encoding: MessageEncoding,
+	algo: HashAlgo,
+) -> Result<[u8; 32], Error> {
+	let mut hasher = Hasher::new(algo);
+	let mut buffer = vec![0; CHUNK_SIZE];
+	// The number of encoded characters that decode on their own.
+	let unit = match encoding {
+		MessageEncoding::Raw => 1,
+		MessageEncoding::Hex => 2,
+		MessageEncoding::Base64 => 4,
+	};
+	// The trailing characters of the previous chunk, if it ended in the middle of a unit.
+	let mut pending = Vec::new();
+
+	loop {
+		let read = match reader.read(&mut buffer) {
+			Ok(0) => break,
+			Ok(read) => read,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e.into()),
+		};
+
+		if encoding == MessageEncoding::Raw {
+			hasher.update(&buffer[..read]);
+		} else {
+			let mut encoded = std::mem::take(&mut pending);
+			encoded.extend_from_slice(&buffer[..read]);
+			pending = encoded.split_off(encoded.len() - encoded.len() % unit);
+			hasher.update(&encoding.decode(&encoded)?);
+		}
+	}
+
+	if !pending.is_empty() {
+		// An incomplete unit is only valid for unpadded base64; otherwise this fails with the
+		// error of the encoding.
+		hasher.update(&encoding.decode(&pending)?);
+	}
+
+	Ok(hasher.finalize())
+}
+
+/// Hash a message, the `key hash` command.
+pub fn run_hash(matches: &ArgMatches) -> Result<(), Error> {
+	let hasher = KeyHasher::try_from(matches.value_of("algo").unwrap_or("blake2_256"))?;
+	let concat = matches.is_present("concat");
+	let digest = match matches.value_of("data") {
+		Some(data) if data.starts_with("0x") =>
+			hasher.hash(&decode_hex(&data[2..])?, concat),
+		Some(data) => hasher.hash(data.as_bytes(), concat),
+		None => {
+			let encoding = read_message_encoding(matches)?;
+			// Without --concat, the message needs not be read into memory at once.
+			match hasher.message_algo().filter(|_| !concat) {
+				Some(algo) => read_message(encoding, Some(algo))?,
+				None => hasher.hash(&read_message(encoding, None)?, concat),
+			}
+		},
+	};
+	println!("0x{}", hex::encode(digest));
+	Ok(())
+}
+
+/// Compute the key of a storage item, the `key storage-key` command.
+pub fn run_storage_key(matches: &ArgMatches, output: OutputType) -> Result<(), Error> {
+	let pallet = matches.value_of("pallet").expect("pallet is required; qed");
+	let item = matches.value_of("item").expect("item is required; qed");
+	let values = |name| matches.values_of(name).map(Iterator::collect).unwrap_or_else(Vec::new);
+	let (keys, hashers): (Vec<_>, Vec<_>) = (values("key"), values("hasher"));
+	if keys.len() != hashers.len() {
+		return static_err("Each --key must be given along with a --hasher");
+	}
+	let keys = keys
+		.into_iter()
+		.zip(hashers)
+		.map(|(key, hasher)| Ok((
+			StorageHasher::try_from(hasher)?,
+			decode_hex(key.trim_start_matches("0x"))?,
+		)))
+		.collect::<Result<Vec<_>, Error>>()?;
+	let segments = storage_key(pallet, item, &keys);
+	let storage_key = segments
+		.iter()
+		.flat_map(|segment| segment.hash.iter().copied())
+		.collect::<Vec<_>>();
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let segments = segments
+				.iter()
+				.map(|segment| StorageKeySegmentOutput {
+					label: segment.label.clone(),
+					value: format!("0x{}", hex::encode(&segment.value)),
+					hasher: segment.hasher.as_str().into(),
+					hash: format!("0x{}", hex::encode(&segment.hash)),
+				})
+				.collect();
+			let json = StorageKeyOutput {
+				storage_key: format!("0x{}", hex::encode(&storage_key)),
+				segments,
+			};
+			output.write_json(&mut io::stdout(), &json.to_json())?;
+		},
+		_ => {
+			println!("0x{}", hex::encode(&storage_key));
+		},
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A reader handing out the data in chunks of an odd size.
+	struct OddReader<'a>(&'a [u8]);
+
+	impl<'a> Read for OddReader<'a> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let len = self.0.len().min(buf.len()).min(3);
+			buf[..len].copy_from_slice(&self.0[..len]);
+			self.0 = &self.0[len..];
+			Ok(len)
+		}
+	}
+
+	#[test]
+	fn hash_message_matches_sp_core() {
+		let message = (0..3 * CHUNK_SIZE + 7).map(|i| i as u8).collect::<Vec<_>>();
+		let hash = |algo, data: &[u8]| match algo {
+			HashAlgo::Blake2_256 => blake2_256(data),
+			HashAlgo::Keccak256 => keccak_256(data),
+			HashAlgo::Sha2_256 => sha2_256(data),
+		};
+
+		for algo in &[HashAlgo::Blake2_256, HashAlgo::Keccak256, HashAlgo::Sha2_256] {
+			let raw = MessageEncoding::Raw;
+			assert_eq!(hash_message(&message[..], raw, *algo).unwrap(), hash(*algo, &message));
+			assert_eq!(hash_message(&[][..], raw, *algo).unwrap(), hash(*algo, &[]));
+		}
+	}
+
+	#[test]
+	fn hash_message_decodes_hex_across_chunks() {
+		let message = b"Blah Blah\n";
+		let hex_message = hex::encode(message);
+		let hash = |encoded: &[u8]| {
+			hash_message(OddReader(encoded), MessageEncoding::Hex, HashAlgo::Blake2_256)
+		};
+
+		assert_eq!(hash(hex_message.as_bytes()).unwrap(), blake2_256(message));
+		assert!(hash(b"abc").is_err());
+		assert!(hash(b"zz").is_err());
+	}
+
+	#[test]
+	fn hash_message_decodes_base64_across_chunks() {
+		let hash = |encoded: &[u8]| {
+			hash_message(OddReader(encoded), MessageEncoding::Base64, HashAlgo::Blake2_256)
+		};
+
+		for message in &[&b"Blah Blah\n"[..], b"Blah Blah", b"Blah Bla"] {
+			let base64_message = base64::encode(message);
+			assert_eq!(hash(base64_message.as_bytes()).unwrap(), blake2_256(message));
+		}
+		assert!(hash(b"QmxhQ").is_err());
+		assert!(hash(b"Qmxh!A==").is_err());
+	}
+
+	#[test]
+	fn hash_algo_names_roundtrip() {
+		for algo in &[HashAlgo::Blake2_256, HashAlgo::Keccak256, HashAlgo::Sha2_256] {
+			assert_eq!(HashAlgo::try_from(algo.as_str()), Ok(*algo));
+		}
+		assert!(HashAlgo::try_from("md5").is_err());
+	}
+
+	#[test]
+	fn key_hashers_match_sp_core() {
+		let data = b"subkey";
+		assert_eq!(KeyHasher::Blake2_128.hash(data, false), blake2_128(data));
+		assert_eq!(KeyHasher::Blake2_256.hash(data, false), blake2_256(data));
+		assert_eq!(KeyHasher::Twox64.hash(data, false), twox_64(data));
+		assert_eq!(KeyHasher::Twox128.hash(data, false), twox_128(data));
+		assert_eq!(KeyHasher::Keccak256.hash(data, false), keccak_256(data));
+		assert_eq!(KeyHasher::Sha2_256.hash(data, false), sha2_256(data));
+
+		// The prefix of the storage of `System`.
+		assert_eq!(
+			KeyHasher::Twox128.hash(b"System", false),
+			hex::decode("26aa394eea5630e07c48ae0c9558cef7").unwrap(),
+		);
+	}
+
+	#[test]
+	fn key_hashers_concat_the_input() {
+		let data = [1u8, 2, 3];
+		for hasher in KeyHasher::ALL {
+			let digest = hasher.hash(&data, true);
+			assert_eq!(digest[..digest.len() - data.len()], hasher.hash(&data, false)[..]);
+			assert_eq!(digest[digest.len() - data.len()..], data);
+		}
+	}
+
+	#[test]
+	fn key_hashers_stream_like_their_message_algo() {
+		let data = b"subkey";
+		for hasher in KeyHasher::ALL {
+			if let Some(algo) = hasher.message_algo() {
+				let digest = hash_message(&data[..], MessageEncoding::Raw, algo).unwrap();
+				assert_eq!(digest[..], hasher.hash(data, false)[..]);
+			}
+		}
+		assert_eq!(KeyHasher::Twox128.message_algo(), None);
+	}
+
+	#[test]
+	fn key_hasher_names_roundtrip() {
+		for hasher in KeyHasher::ALL {
+			assert_eq!(KeyHasher::try_from(hasher.as_str()).ok(), Some(*hasher));
+		}
+		let error = KeyHasher::try_from("md5").err().unwrap().to_string();
+		assert!(error.contains("blake2_128, blake2_256, twox_64, twox_128, keccak_256"));
+	}
+
+	fn full_key(segments: Vec<StorageKeySegment>) -> String {
+		hex::encode(segments.into_iter().flat_map(|segment| segment.hash).collect::<Vec<_>>())
+	}
+
+	#[test]
+	fn storage_key_of_plain_values() {
+		assert_eq!(
+			full_key(storage_key("System", "Number", &[])),
+			"26aa394eea5630e07c48ae0c9558cef702a5c1b19ab7a04f536c519aca4983ac",
+		);
+		assert_eq!(
+			full_key(storage_key("Timestamp", "Now", &[])),
+			"f0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb",
+		);
+	}
+
+	#[test]
+	fn storage_key_of_map_values() {
+		// `System.Account` of Alice.
+		let alice = hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+			.unwrap();
+		let keys = [(StorageHasher::Blake2_128Concat, alice)];
+		let segments = storage_key("System", "Account", &keys);
+		assert_eq!(segments[2].label, "key 1");
+		assert_eq!(
+			full_key(segments),
+			"26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9\
+			de1e86a9a8c739864cf3cc5ec2bea59f\
+			d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+		);
+
+		let keys = [
+			(StorageHasher::Twox64Concat, vec![1, 0, 0, 0]),
+			(StorageHasher::Identity, vec![2]),
+		];
+		let segments = storage_key("Pallet", "DoubleMap", &keys);
+		assert_eq!(segments.len(), 4);
+		assert_eq!(segments[2].hash, [&twox_64(&[1, 0, 0, 0])[..], &[1, 0, 0, 0]].concat());
+		assert_eq!(segments[3].label, "key 2");
+		assert_eq!(segments[3].hash, [2]);
+	}
+
+	#[test]
+	fn storage_hasher_names_roundtrip() {
+		for hasher in StorageHasher::ALL {
+			assert_eq!(StorageHasher::try_from(hasher.as_str()).ok(), Some(*hasher));
+		}
+		assert!(StorageHasher::try_from("twox_64").is_err());
+	}
+}
@@ -0,0 +1,763 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading what the commands are given: URIs, passwords, messages and the other arguments.
+
+use crate::{
+	error::{static_err, Error},
+	format::inline_password,
+	hash::{self, HashAlgo},
+};
+use clap::ArgMatches;
+use sp_core::crypto::{
+	ExposeSecret, SecretString, Ss58AddressFormat, Zeroize,
+};
+use std::{
+	collections::BTreeMap, convert::{TryFrom, TryInto}, io::{self, stdin, BufRead, Read},
+	str::FromStr, path::{Path, PathBuf}, fs, sync::Mutex,
+};
+use subtle::ConstantTimeEq;
+
+/// The encoding of a message on STDIN, or in a `verify --batch` manifest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageEncoding {
+	/// The message itself.
+	Raw,
+	/// The message bytes, hex-encoded.
+	Hex,
+	/// The message bytes, base64-encoded.
+	Base64,
+}
+
+impl MessageEncoding {
+	/// Decode `message` into the message bytes.
+	pub(super) fn decode(self, message: &[u8]) -> Result<Vec<u8>, Error> {
+		match self {
+			MessageEncoding::Raw => Ok(message.to_vec()),
+			MessageEncoding::Hex => decode_hex(message),
+			MessageEncoding::Base64 => decode_base64(message),
+		}
+	}
+}
+
+impl<'a> TryFrom<&'a str> for MessageEncoding {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<MessageEncoding, ()> {
+		match s {
+			"hex" => Ok(MessageEncoding::Hex),
+			"base64" => Ok(MessageEncoding::Base64),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Get `URI` from CLI or prompt the user.
+///
+/// `URI` is extracted from `matches` by using `match_name`. See [`read_uri`] for how it is read.
+pub(super) fn get_uri(match_name: &str, matches: &ArgMatches) -> Result<SecretString, Error> {
+	read_uri(matches.value_of(match_name))
+}
+
+/// Read the URIs of the file at `path`, one per line.
+///
+/// Surrounding whitespace is trimmed, blank lines and lines starting with `#` are skipped.
+pub(super) fn read_uris(path: &Path) -> Result<Vec<String>, Error> {
+	Ok(fs::read_to_string(path)?
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(Into::into)
+		.collect())
+}
+
+/// Fail if the secret URI `suri` contains a password, and a different `password` is given too.
+///
+/// The given password silently takes precedence, so the resulting key would not be the one of the
+/// inline password.
+pub(super) fn check_inline_password(suri: &str, password: Option<&str>) -> Result<(), Error> {
+	match (inline_password(suri), password) {
+		(Some(inline), Some(password)) if !ct_eq(inline.as_bytes(), password.as_bytes()) => {
+			static_err("The secret URI contains a password after `///`, and a different password \
+				was given with --password, --password-filename, --password-env or \
+				--password-interactive. Give the password only once.")
+		},
+		_ => Ok(()),
+	}
+}
+
+/// Read the `URI` given as CLI argument or prompt the user.
+///
+/// If the `URI` given as CLI argument is a regular file, the file content is taken as `URI`, see
+/// [`sanitize_file_uri`], with a warning if other users can read the file. Other special files,
+/// like FIFOs or devices, are rejected as reading them may block forever.
+/// If the `URI` is `-`, it is read from STDIN, or prompted for if STDIN is a terminal. If no `URI`
+/// is given to the CLI, the user is prompted for it.
+///
+/// The `URI` is returned as a `SecretString`, so that it is zeroized when dropped, and so is the
+/// buffer the file is read into.
+pub(super) fn read_uri(uri: Option<&str>) -> Result<SecretString, Error> {
+	let uri = if let Some(uri) = uri {
+		let file = PathBuf::from(uri);
+		if uri == "-" && atty::is(atty::Stream::Stdin) {
+			prompt_secret("URI: ")?
+		} else if uri == "-" {
+			read_uri_from(io::stdin().lock())?
+		} else if file.is_file() {
+			if is_readable_by_others(&file) {
+				eprintln!(
+					"Warning: `{}` is readable by other users than its owner; restrict it with \
+						`chmod 600`",
+					uri,
+				);
+			}
+			sanitize_file_uri(read_uri_from(fs::File::open(file)?)?)
+		} else if is_special_file(&file) {
+			return Err(Error::Formatted(format!(
+				"`{}` is not a regular file; refusing to read the URI from it", uri,
+			)));
+		} else if uri.contains(|c| c == '\n' || c == '\r') {
+			return Err(Error::Static("Invalid URI; a URI can not contain line breaks"));
+		} else {
+			uri.into()
+		}
+	} else {
+		prompt_secret("URI: ")?
+	};
+
+	Ok(SecretString::new(uri))
+}
+
+/// Prompt for a secret on the terminal with `prompt`, without echoing it.
+///
+/// Without a terminal, as in containers and CI, the secret is read from a line of STDIN
+/// instead.
+pub fn prompt_secret(prompt: &str) -> io::Result<String> {
+	let read_from_tty = |prompt: &str| rpassword::read_password_from_tty(Some(prompt));
+	prompt_secret_with(prompt, has_tty(), read_from_tty, stdin().lock())
+}
+
+/// Prompt for a secret like [`prompt_secret`], with `read_from_tty` if `has_tty` is set, and
+/// from a line of `stdin` otherwise.
+pub fn prompt_secret_with<T, R>(
+	prompt: &str,
+	has_tty: bool,
+	read_from_tty: T,
+	mut stdin: R,
+) -> io::Result<String>
+where
+	T: FnOnce(&str) -> io::Result<String>,
+	R: BufRead,
+{
+	if has_tty {
+		return read_from_tty(prompt);
+	}
+
+	let name = prompt.trim_end().trim_end_matches(':');
+	eprintln!(
+		"Note: there is no terminal to prompt for `{}` on; reading it from STDIN, which can not \
+			be kept from echoing it",
+		name,
+	);
+	let mut line = String::new();
+	let read = stdin.read_line(&mut line);
+	let secret = line.trim_end_matches(|c| c == '\n' || c == '\r').to_owned();
+	line.zeroize();
+	match read? {
+		0 => Err(io::Error::new(
+			io::ErrorKind::UnexpectedEof,
+			format!("There is no terminal to prompt for `{}` on, and STDIN is empty", name),
+		)),
+		_ => Ok(secret),
+	}
+}
+
+/// Whether the process has a controlling terminal to prompt on.
+#[cfg(unix)]
+fn has_tty() -> bool {
+	fs::OpenOptions::new().read(true).write(true).open("/dev/tty").is_ok()
+}
+
+/// Whether the process has a terminal to prompt on.
+#[cfg(not(unix))]
+fn has_tty() -> bool {
+	atty::is(atty::Stream::Stdin)
+}
+
+/// Read a URI from all of `reader`, without the line break it ends with.
+pub(super) fn read_uri_from<R: Read>(mut reader: R) -> io::Result<String> {
+	let mut content = String::new();
+	let read = reader.read_to_string(&mut content);
+	let uri = content.trim_end().to_owned();
+	content.zeroize();
+	read.map(|_| uri)
+}
+
+/// Strip what editors and configuration management add around a URI written to a file: a UTF-8
+/// byte order mark, carriage returns, and matching single or double quotes around it.
+fn sanitize_file_uri(mut content: String) -> String {
+	let mut uri = content.trim_start_matches('\u{feff}').replace('\r', "");
+	content.zeroize();
+	let quoted = |quote| uri.len() >= 2 && uri.starts_with(quote) && uri.ends_with(quote);
+	if quoted('"') || quoted('\'') {
+		let unquoted = uri[1..uri.len() - 1].to_owned();
+		uri.zeroize();
+		unquoted
+	} else {
+		uri
+	}
+}
+
+/// Returns `true` if the group or other users can read the file at `path`.
+#[cfg(unix)]
+fn is_readable_by_others(path: &Path) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+	fs::metadata(path).map(|metadata| metadata.permissions().mode() & 0o044 != 0).unwrap_or(false)
+}
+
+/// Returns `true` if the group or other users can read the file at `path`.
+#[cfg(not(unix))]
+fn is_readable_by_others(_path: &Path) -> bool {
+	false
+}
+
+/// Returns `true` if `path` exists, but is neither a regular file nor a directory.
+#[cfg(unix)]
+fn is_special_file(path: &Path) -> bool {
+	use std::os::unix::fs::FileTypeExt;
+
+	fs::metadata(path)
+		.map(|metadata| {
+			let file_type = metadata.file_type();
+			file_type.is_fifo()
+				|| file_type.is_char_device()
+				|| file_type.is_block_device()
+				|| file_type.is_socket()
+		})
+		.unwrap_or(false)
+}
+
+/// Returns `true` if `path` exists, but is neither a regular file nor a directory.
+#[cfg(not(unix))]
+fn is_special_file(path: &Path) -> bool {
+	path.exists() && !path.is_file() && !path.is_dir()
+}
+
+/// How often the password is prompted for until its confirmation matches.
+const PASSWORD_ATTEMPTS: usize = 3;
+
+/// Read the key password with `read_from_tty`, which is given the prompt to show.
+///
+/// If `confirm` is set, the password has to be entered twice, and is prompted for again if the
+/// two entries differ.
+pub fn read_password<F>(mut read_from_tty: F, confirm: bool) -> Result<String, Error>
+where
+	F: FnMut(&str) -> std::io::Result<String>,
+{
+	if !confirm {
+		return Ok(read_from_tty("Key password: ")?);
+	}
+
+	for _ in 0..PASSWORD_ATTEMPTS {
+		let mut password = read_from_tty("Key password: ")?;
+		let mut confirmation = read_from_tty("Repeat key password: ")?;
+		let is_confirmed = ct_eq(password.as_bytes(), confirmation.as_bytes());
+		confirmation.zeroize();
+		if is_confirmed {
+			return Ok(password);
+		}
+		password.zeroize();
+		eprintln!("Passwords do not match, please try again.");
+	}
+	Err(Error::Static("Passwords do not match"))
+}
+
+/// Read a password from the file at `path`, without the line breaks it ends with.
+///
+/// The contents are zeroized when they are dropped, including when they are not valid UTF-8.
+pub(super) fn read_password_file(path: &Path) -> Result<SecretString, Error> {
+	let contents = String::from_utf8(fs::read(path)?).map_err(|e| {
+		e.into_bytes().zeroize();
+		Error::Formatted(format!("The password file `{}` is not valid UTF-8", path.display()))
+	})?;
+	let contents = SecretString::new(contents);
+	let password = contents.expose_secret().trim_end_matches(|c| c == '\n' || c == '\r');
+	Ok(SecretString::new(password.to_owned()))
+}
+
+/// Resolve the key password from, in order of precedence, `--password`, `--password-filename`,
+/// `--password-env` and `--password-interactive`; clap rejects more than one of them.
+///
+/// The interactive password is read with `read_from_tty`, and confirmed when it is set by
+/// `generate`, `insert` or `key export-json`. Environment variables are looked up with `read_env`.
+pub fn read_password_source<F, E>(
+	matches: &ArgMatches,
+	read_from_tty: F,
+	read_env: E,
+) -> Result<Option<SecretString>, Error>
+where
+	F: FnMut(&str) -> std::io::Result<String>,
+	E: FnOnce(&str) -> Option<String>,
+{
+	let password = if let Some(password) = matches.value_of("password") {
+		password.to_owned()
+	} else if let Some(path) = matches.value_of("password-filename") {
+		return read_password_file(Path::new(path)).map(Some);
+	} else if let Some(var) = matches.value_of("password-env") {
+		read_env(var).ok_or_else(|| {
+			Error::Formatted(format!("The environment variable `{}` is not set", var))
+		})?
+	} else if matches.is_present("password-interactive") {
+		// A mistyped password would silently generate a different key, so have it confirmed.
+		let confirm = match matches.subcommand() {
+			("generate", _) | ("insert", _) => true,
+			("key", Some(matches)) => matches.subcommand_name() == Some("export-json"),
+			_ => false,
+		};
+		read_password(read_from_tty, confirm).map_err(|e| match e {
+			Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => Error::Static(
+				"--password-interactive needs a terminal, or the password on STDIN; in scripts, \
+					give it with --password-filename or --password-env instead",
+			),
+			e => e,
+		})?
+	} else {
+		return Ok(None);
+	};
+	Ok(Some(SecretString::new(password)))
+}
+
+/// The argon2id parameters `--password-kdf` stretches the password with.
+///
+/// Only the memory cost, the number of passes and the salt can be configured; argon2id is always
+/// run with a single lane to derive 32 bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KdfParams {
+	pub memory_kib: u32,
+	pub passes: u32,
+	pub salt: String,
+}
+
+impl Default for KdfParams {
+	fn default() -> Self {
+		KdfParams {
+			memory_kib: 64 * 1024,
+			passes: 3,
+			salt: "subkey-password-kdf".into(),
+		}
+	}
+}
+
+pub fn read_kdf_params(matches: &ArgMatches) -> Result<KdfParams, Error> {
+	let default = KdfParams::default();
+	Ok(KdfParams {
+		memory_kib: read_optional_parameter(matches, "kdf-memory")?.unwrap_or(default.memory_kib),
+		passes: read_optional_parameter(matches, "kdf-passes")?.unwrap_or(default.passes),
+		salt: matches.value_of("kdf-salt").map(Into::into).unwrap_or(default.salt),
+	})
+}
+
+/// Stretch `password` through argon2id and return the derived bytes hex-encoded, without `0x`.
+pub fn stretch_password(password: &str, params: &KdfParams) -> Result<String, Error> {
+	let config = argon2::Config {
+		variant: argon2::Variant::Argon2id,
+		version: argon2::Version::Version13,
+		mem_cost: params.memory_kib,
+		time_cost: params.passes,
+		lanes: 1,
+		thread_mode: argon2::ThreadMode::Sequential,
+		hash_length: 32,
+		..Default::default()
+	};
+	argon2::hash_raw(password.as_bytes(), params.salt.as_bytes(), &config)
+		.map(hex::encode)
+		.map_err(|e| Error::Formatted(format!("Invalid --password-kdf parameters ({})", e)))
+}
+
+lazy_static::lazy_static! {
+	/// The names of the custom networks registered with [`register_custom_network`], by prefix.
+	static ref CUSTOM_NETWORKS: Mutex<BTreeMap<u8, String>> = Mutex::new(BTreeMap::new());
+}
+
+/// Register a custom network by `name`, so that subkey prints `Ss58AddressFormat::Custom(prefix)`
+/// as `name`, and parses `name` as that format.
+///
+/// Registering the same name for the same prefix again does nothing. A prefix can not be
+/// registered under another name, nor can a known network be renamed. Only prefixes up to 63 can
+/// be registered, since larger ones need two byte addresses.
+pub fn register_custom_network(name: &str, prefix: u16) -> Result<Ss58AddressFormat, Error> {
+	if prefix > 63 {
+		return Err(Error::Formatted(format!(
+			"SS58 prefix {} is too large; custom networks take prefixes from 0 to 63",
+			prefix,
+		)));
+	}
+	let prefix = prefix as u8;
+	if let Some(known) = Ss58AddressFormat::all().iter().find(|f| u8::from(**f) == prefix) {
+		return Err(Error::Formatted(format!(
+			"SS58 prefix {} is already the one of {}",
+			prefix,
+			String::from(*known),
+		)));
+	}
+	let mut networks = CUSTOM_NETWORKS.lock().expect("the lock is never poisoned; qed");
+	match networks.get(&prefix) {
+		Some(registered) if registered == name => return Ok(Ss58AddressFormat::Custom(prefix)),
+		Some(registered) => return Err(Error::Formatted(format!(
+			"SS58 prefix {} is already registered as {}",
+			prefix,
+			registered,
+		))),
+		None => {},
+	}
+	let is_known_name = Ss58AddressFormat::all().iter().any(|f| String::from(*f) == name);
+	if is_known_name || networks.values().any(|n| n == name) || name.parse::<u8>().is_ok() {
+		return Err(Error::Formatted(format!("The network name `{}` is already in use", name)));
+	}
+	networks.insert(prefix, name.into());
+	Ok(Ss58AddressFormat::Custom(prefix))
+}
+
+/// The name of `network`, or the one it is registered by if it is a custom network.
+pub fn network_name(network: Ss58AddressFormat) -> String {
+	let networks = CUSTOM_NETWORKS.lock().expect("the lock is never poisoned; qed");
+	match network {
+		Ss58AddressFormat::Custom(prefix) => networks.get(&prefix).cloned(),
+		_ => None,
+	}.unwrap_or_else(|| network.into())
+}
+
+/// Parse the network `name`, which may be one registered by [`register_custom_network`].
+pub fn parse_network(name: &str) -> Option<Ss58AddressFormat> {
+	let networks = CUSTOM_NETWORKS.lock().expect("the lock is never poisoned; qed");
+	match networks.iter().find(|(_, registered)| *registered == name) {
+		Some((prefix, _)) => Some(Ss58AddressFormat::Custom(*prefix)),
+		None => name.try_into().ok(),
+	}
+}
+
+/// Register the custom network given as `<name>:<prefix>`.
+pub fn register_network(network: &str) -> Result<Ss58AddressFormat, Error> {
+	let mut parts = network.rsplitn(2, ':');
+	let (prefix, name) = match (parts.next().map(str::parse::<u16>), parts.next()) {
+		(Some(Ok(prefix)), Some(name)) if !name.is_empty() => (prefix, name),
+		_ => return Err(Error::Formatted(format!(
+			"Invalid custom network `{}`; expecting <name>:<prefix>, e.g. mychain:50",
+			network,
+		))),
+	};
+	register_custom_network(name, prefix)
+}
+
+pub(super) fn decode_hex<T: AsRef<[u8]>>(message: T) -> Result<Vec<u8>, Error> {
+	Ok(hex::decode(message)?)
+}
+
+pub fn encode_base64<T: AsRef<[u8]>>(data: T) -> String {
+	base64::encode(data)
+}
+
+pub fn decode_base64<T: AsRef<[u8]>>(data: T) -> Result<Vec<u8>, Error> {
+	base64::decode(data).map_err(Error::InvalidBase64)
+}
+
+/// Compare two byte slices for equality without leaking the position of the first mismatch.
+///
+/// Use this whenever comparing secret-derived bytes such as seeds or signatures. Only the
+/// content comparison is constant-time, the lengths are compared normally.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+/// The largest message that is read into memory. Larger messages can be signed by their digest.
+pub(super) const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+pub(super) fn read_message_from_stdin(encoding: MessageEncoding) -> Result<Vec<u8>, Error> {
+	read_message_from(stdin().lock(), encoding)
+}
+
+pub fn read_message_from<R: Read>(reader: R, encoding: MessageEncoding) -> Result<Vec<u8>, Error> {
+	let mut message = vec![];
+	reader
+		.take(MAX_MESSAGE_SIZE as u64 + 1)
+		.read_to_end(&mut message)?;
+	if message.len() > MAX_MESSAGE_SIZE {
+		return Err(Error::MessageTooLarge(MAX_MESSAGE_SIZE));
+	}
+	if encoding != MessageEncoding::Raw {
+		message = encoding.decode(&message)?;
+	}
+	Ok(message)
+}
+
+/// Read the message to sign or verify from STDIN.
+///
+/// If `hash` is given, the message is streamed through it and its digest returned instead.
+pub(super) fn read_message(
+	encoding: MessageEncoding,
+	hash: Option<HashAlgo>,
+) -> Result<Vec<u8>, Error> {
+	match hash {
+		Some(hash) => Ok(hash::hash_message(stdin().lock(), encoding, hash)?.to_vec()),
+		None => read_message_from_stdin(encoding),
+	}
+}
+
+/// Read the message from STDIN like [`read_message`], as text for display.
+///
+/// A message that is not valid UTF-8 is an error, unless `lossy` is set and the invalid bytes
+/// are replaced by U+FFFD.
+pub(super) fn read_message_utf8(encoding: MessageEncoding, lossy: bool) -> Result<String, Error> {
+	message_to_utf8(read_message_from_stdin(encoding)?, lossy)
+}
+
+pub(super) fn message_to_utf8(message: Vec<u8>, lossy: bool) -> Result<String, Error> {
+	if lossy {
+		Ok(String::from_utf8_lossy(&message).into_owned())
+	} else {
+		Ok(String::from_utf8(message)?)
+	}
+}
+
+/// The encoding of the message selected by `--encoding` or `--hex`.
+pub fn read_message_encoding(matches: &ArgMatches) -> Result<MessageEncoding, Error> {
+	match matches.value_of("encoding").map(TryInto::try_into) {
+		Some(Err(_)) => Err(Error::Static("Invalid encoding. One of hex, base64.")),
+		Some(Ok(encoding)) => Ok(encoding),
+		None if matches.is_present("hex") => Ok(MessageEncoding::Hex),
+		None => Ok(MessageEncoding::Raw),
+	}
+}
+
+pub fn read_hash_algo(matches: &ArgMatches) -> Result<Option<HashAlgo>, Error> {
+	match matches.value_of("hash").map(TryInto::try_into) {
+		Some(Err(_)) => Err(Error::Static("Invalid hash. One of blake2-256, keccak-256, sha-256.")),
+		Some(Ok(hash)) => Ok(Some(hash)),
+		None => Ok(None),
+	}
+}
+
+pub(super) fn read_required_parameter<T: FromStr>(
+	matches: &ArgMatches,
+	name: &str,
+) -> Result<T, Error> where
+	<T as FromStr>::Err: std::fmt::Debug,
+{
+	let str_value = matches
+		.value_of(name)
+		.expect("parameter is required; thus it can't be None; qed");
+	str::parse::<T>(str_value).map_err(|_|
+		Error::Formatted(format!("Invalid `{}' parameter; expecting an integer.", name))
+	)
+}
+
+pub(super) fn read_optional_parameter<T: FromStr>(
+	matches: &ArgMatches,
+	name: &str,
+) -> Result<Option<T>, Error> where
+	<T as FromStr>::Err: std::fmt::Debug,
+{
+	match matches.value_of(name) {
+		Some(_) => read_required_parameter(matches, name).map(Some),
+		None => Ok(None),
+	}
+}
+
+/// Ask whether to go on with `prompt`, failing with `refusal` unless the answer is yes.
+pub(super) fn confirm<F>(
+	prompt: &str,
+	refusal: &'static str,
+	mut read_answer: F,
+) -> Result<(), Error>
+where
+	F: FnMut(&str) -> std::io::Result<String>,
+{
+	match read_answer(prompt)?.trim().to_lowercase().as_str() {
+		"y" | "yes" => Ok(()),
+		_ => Err(Error::Static(refusal)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::crypto::ExposeSecret;
+	use std::io::Write;
+
+	#[test]
+	fn prompt_secret_reads_a_line_of_stdin_without_a_tty() {
+		let no_tty = |_: &str| -> io::Result<String> { panic!("there is no terminal") };
+		let stdin = &b"secret\r\nmessage\n"[..];
+		assert_eq!(prompt_secret_with("URI: ", false, no_tty, stdin).unwrap(), "secret");
+		let stdin = &b"secret"[..];
+		assert_eq!(prompt_secret_with("URI: ", false, no_tty, stdin).unwrap(), "secret");
+		let error = prompt_secret_with("Key password: ", false, no_tty, &b""[..]).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+		assert!(error.to_string().contains("`Key password`"));
+
+		let tty = |prompt: &str| Ok(format!("typed at {}", prompt));
+		let stdin = &b"piped\n"[..];
+		assert_eq!(prompt_secret_with("URI: ", true, tty, stdin).unwrap(), "typed at URI: ");
+	}
+
+	#[test]
+	fn register_network_names_custom_prefixes() {
+		let format = register_network("subkey-testnet:60").unwrap();
+		assert_eq!(format, Ss58AddressFormat::Custom(60));
+		assert_eq!(network_name(format), "subkey-testnet");
+		assert_eq!(parse_network("subkey-testnet"), Some(format));
+		assert_eq!(network_name(Ss58AddressFormat::Custom(61)), "61");
+		assert_eq!(network_name(Ss58AddressFormat::KusamaAccount), "kusama");
+		assert_eq!(parse_network("other-testnet"), None);
+
+		assert!(register_network("subkey-testnet:60").is_ok());
+		let error = register_network("other-testnet:60").err().unwrap().to_string();
+		assert!(error.contains("already registered as subkey-testnet"));
+		assert!(register_network("kusama-two:2").is_err());
+		assert!(register_network("subkey-testnet").is_err());
+		assert!(register_network(":61").is_err());
+		assert!(register_network("subkey-testnet:65536").is_err());
+		let error = register_network("other-testnet:64").err().unwrap().to_string();
+		assert!(error.contains("SS58 prefix 64 is too large"));
+		let error = register_custom_network("other-testnet", 16383).err().unwrap().to_string();
+		assert!(error.contains("SS58 prefix 16383 is too large"));
+		let error = register_custom_network("kusama", 62).err().unwrap().to_string();
+		assert!(error.contains("`kusama` is already in use"));
+	}
+
+	#[test]
+	fn actions_have_to_be_confirmed() {
+		let confirmed = |answer: &str| {
+			let answer = answer.to_string();
+			confirm("Sign? ", "Signing was not confirmed", |_| Ok(answer.clone()))
+		};
+		assert!(confirmed("y\n").is_ok());
+		assert!(confirmed("YES\n").is_ok());
+		assert!(confirmed("\n").is_err());
+		assert_eq!(confirmed("no\n").err().unwrap().to_string(), "Signing was not confirmed");
+	}
+
+	#[test]
+	fn stretch_password_is_reproducible() {
+		let params = KdfParams { memory_kib: 64, passes: 1, salt: "subkey-test-salt".into() };
+		let stretched = stretch_password("password", &params).unwrap();
+		assert_eq!(stretched, stretch_password("password", &params).unwrap());
+		assert_eq!(stretched.len(), 64);
+
+		assert_ne!(stretched, stretch_password("Password", &params).unwrap());
+		let other_salt = KdfParams { salt: "subkey-other-salt".into(), ..params };
+		assert_ne!(stretched, stretch_password("password", &other_salt).unwrap());
+		let more_passes = KdfParams { passes: 2, ..params.clone() };
+		assert_ne!(stretched, stretch_password("password", &more_passes).unwrap());
+
+		let short_salt = KdfParams { salt: "salt".into(), ..params };
+		assert!(stretch_password("password", &short_salt).is_err());
+	}
+
+	#[test]
+	fn ct_eq_works() {
+		assert!(ct_eq(b"", b""));
+		assert!(ct_eq(&[1, 2, 3], &[1, 2, 3]));
+		assert!(!ct_eq(&[1, 2, 3], &[1, 2, 4]));
+		assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+	}
+
+	#[test]
+	fn read_uris_skips_blanks_and_comments() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "# validators\n//Alice\n\n  5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY  \n\t\n# end").unwrap();
+
+		assert_eq!(
+			read_uris(file.path()).unwrap(),
+			vec!["//Alice", "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"],
+		);
+	}
+
+	#[test]
+	fn read_uris_of_empty_file_is_empty() {
+		let file = tempfile::NamedTempFile::new().unwrap();
+		assert!(read_uris(file.path()).unwrap().is_empty());
+	}
+
+	#[test]
+	fn read_uri_reads_regular_files() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "//Alice").unwrap();
+
+		assert_eq!(read_uri(file.path().to_str()).unwrap().expose_secret(), "//Alice");
+	}
+
+	#[test]
+	fn file_uris_are_sanitized() {
+		let sanitized = |content: &str| sanitize_file_uri(content.into());
+		assert_eq!(sanitized("\u{feff}//Alice"), "//Alice");
+		assert_eq!(sanitized("//Alice\r"), "//Alice");
+		assert_eq!(sanitized("\"//Alice///pass\""), "//Alice///pass");
+		assert_eq!(sanitized("'//Alice'"), "//Alice");
+		assert_eq!(sanitized("\u{feff}\"//Alice\"\r"), "//Alice");
+		// Only matching quotes around all of the URI are stripped.
+		assert_eq!(sanitized("\"//Alice'"), "\"//Alice'");
+		assert_eq!(sanitized("//Alice\"s key\""), "//Alice\"s key\"");
+		assert_eq!(sanitized("\""), "\"");
+		let uri = "bottom drive obey lake curtain smoke basket hold race lonely fit walk//1/soft#x";
+		assert_eq!(sanitized(uri), uri);
+
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		write!(file, "\u{feff}\"//Alice/soft\"\r\n").unwrap();
+		assert_eq!(read_uri(file.path().to_str()).unwrap().expose_secret(), "//Alice/soft");
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn files_readable_by_others_are_detected() {
+		use std::os::unix::fs::PermissionsExt;
+		let file = tempfile::NamedTempFile::new().unwrap();
+		file.as_file().set_permissions(fs::Permissions::from_mode(0o600)).unwrap();
+		assert!(!is_readable_by_others(file.path()));
+		file.as_file().set_permissions(fs::Permissions::from_mode(0o644)).unwrap();
+		assert!(is_readable_by_others(file.path()));
+		file.as_file().set_permissions(fs::Permissions::from_mode(0o640)).unwrap();
+		assert!(is_readable_by_others(file.path()));
+	}
+
+	#[test]
+	fn read_uri_accepts_literal_uris() {
+		let uri = read_uri(Some("//Alice///password")).unwrap();
+		assert_eq!(uri.expose_secret(), "//Alice///password");
+		assert!(read_uri(Some("//Alice\n//Bob")).is_err());
+		assert!(read_uri(Some("//Alice\r")).is_err());
+	}
+
+	#[test]
+	fn read_uri_keeps_the_uri_secret() {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "//Alice").unwrap();
+
+		// Debug output is redacted, so the URI can not end up in logs or error messages.
+		for uri in &[file.path().to_str(), Some("//Alice")] {
+			let uri: SecretString = read_uri(*uri).unwrap();
+			assert!(!format!("{:?}", uri).contains("Alice"));
+		}
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn read_uri_rejects_special_files() {
+		assert!(read_uri(Some("/dev/null")).is_err());
+	}
+}
@@ -0,0 +1,669 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The JSON output of the key commands, and the JSON Schema describing it.
+//!
+//! Each output is a struct declared with [`json_output!`], which derives both the JSON and its
+//! schema from the fields, so that the two can not get out of sync.
+
+use crate::{error::Error, output::OutputType};
+use clap::ArgMatches;
+use itertools::Itertools;
+use serde_json::{json, Map, Value};
+use std::io;
+
+/// The version of the JSON output, printed as `formatVersion`.
+///
+/// Bump it whenever a field of an output is added, removed or changes its meaning.
+pub(super) const FORMAT_VERSION: u64 = 2;
+
+/// The URI of the JSON Schema dialect of [`JsonOutput::schema`].
+const SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// A field of a [`json_output!`] struct.
+pub(super) trait JsonField {
+	/// Whether the field is always present.
+	const REQUIRED: bool = true;
+
+	/// The value of the field, or `None` if it is left out.
+	fn value(&self) -> Option<Value>;
+
+	/// The schema of the values of the field.
+	fn value_schema() -> Value;
+}
+
+impl JsonField for String {
+	fn value(&self) -> Option<Value> {
+		Some(self.as_str().into())
+	}
+
+	fn value_schema() -> Value {
+		json!({ "type": "string" })
+	}
+}
+
+impl JsonField for bool {
+	fn value(&self) -> Option<Value> {
+		Some((*self).into())
+	}
+
+	fn value_schema() -> Value {
+		json!({ "type": "boolean" })
+	}
+}
+
+impl JsonField for u64 {
+	fn value(&self) -> Option<Value> {
+		Some((*self).into())
+	}
+
+	fn value_schema() -> Value {
+		json!({ "type": "integer", "minimum": 0 })
+	}
+}
+
+impl JsonField for usize {
+	fn value(&self) -> Option<Value> {
+		Some((*self).into())
+	}
+
+	fn value_schema() -> Value {
+		json!({ "type": "integer", "minimum": 0 })
+	}
+}
+
+/// Fields that are `None` are left out of the output.
+impl<T: JsonField> JsonField for Option<T> {
+	const REQUIRED: bool = false;
+
+	fn value(&self) -> Option<Value> {
+		self.as_ref().and_then(T::value)
+	}
+
+	fn value_schema() -> Value {
+		T::value_schema()
+	}
+}
+
+/// Lists are JSON arrays.
+impl<T: JsonField> JsonField for Vec<T> {
+	fn value(&self) -> Option<Value> {
+		Some(self.iter().map(|item| item.value().unwrap_or(Value::Null)).collect())
+	}
+
+	fn value_schema() -> Value {
+		json!({ "type": "array", "items": T::value_schema() })
+	}
+}
+
+/// The JSON output of a command.
+pub(super) trait JsonOutput {
+	/// The output, including its `formatVersion`.
+	fn to_json(&self) -> Value;
+
+	/// The JSON Schema of the output.
+	fn schema() -> Value;
+}
+
+/// Commands that print a list print each item with its `formatVersion`.
+impl<T: JsonOutput> JsonOutput for Vec<T> {
+	fn to_json(&self) -> Value {
+		self.iter().map(T::to_json).collect()
+	}
+
+	fn schema() -> Value {
+		let mut items = T::schema();
+		let dialect = items.as_object_mut().and_then(|items| items.remove("$schema"));
+		json!({ "$schema": dialect, "type": "array", "items": items })
+	}
+}
+
+/// Add the `formatVersion` to the `schema` of the fields of an output.
+fn versioned_schema(title: &str, mut schema: Value) -> Value {
+	schema["properties"]["formatVersion"] = json!({
+		"description": "The version of the output format.",
+		"const": FORMAT_VERSION,
+	});
+	let mut required = vec![Value::from("formatVersion")];
+	required.extend(schema["required"].as_array().cloned().unwrap_or_default());
+	schema["required"] = required.into();
+	schema["$schema"] = SCHEMA_DIALECT.into();
+	schema["title"] = title.into();
+	schema
+}
+
+/// Declare the struct of the JSON output of a command, and implement [`JsonOutput`] for it.
+///
+/// Each field is given with its JSON name after `as`, and has to be documented; the doc comment
+/// is its description in the schema. The struct is also a [`JsonField`], without `formatVersion`,
+/// so that outputs can be nested.
+macro_rules! json_output {
+	(
+		$(#[doc = $struct_doc:literal])+
+		$vis:vis struct $name:ident {
+			$(
+				$(#[doc = $doc:literal])+
+				pub(super) $field:ident: $ty:ty as $json_name:literal,
+			)*
+		}
+	) => {
+		$(#[doc = $struct_doc])+
+		#[derive(Debug, Default)]
+		$vis struct $name {
+			$(
+				$(#[doc = $doc])+
+				pub(super) $field: $ty,
+			)*
+		}
+
+		impl JsonField for $name {
+			fn value(&self) -> Option<Value> {
+				let mut fields = Map::new();
+				$(
+					if let Some(value) = JsonField::value(&self.$field) {
+						fields.insert($json_name.into(), value);
+					}
+				)*
+				Some(fields.into())
+			}
+
+			fn value_schema() -> Value {
+				let mut properties = Map::new();
+				let mut required: Vec<&str> = Vec::new();
+				$(
+					let mut schema = <$ty as JsonField>::value_schema();
+					schema["description"] = concat!($($doc),+).trim().into();
+					properties.insert($json_name.into(), schema);
+					if <$ty as JsonField>::REQUIRED {
+						required.push($json_name);
+					}
+				)*
+				json!({
+					"description": concat!($($struct_doc),+).trim(),
+					"type": "object",
+					"properties": properties,
+					"required": required,
+					"additionalProperties": false,
+				})
+			}
+		}
+
+		impl JsonOutput for $name {
+			fn to_json(&self) -> Value {
+				let mut json = JsonField::value(self).expect("objects are always present; qed");
+				json["formatVersion"] = FORMAT_VERSION.into();
+				json
+			}
+
+			fn schema() -> Value {
+				versioned_schema(stringify!($name), <Self as JsonField>::value_schema())
+			}
+		}
+	};
+}
+
+json_output! {
+	/// An account, as printed by generate, inspect, derive and vanity.
+	pub struct AccountOutput {
+		/// The secret phrase, if the account is given by one and secrets are shown.
+		pub(super) secret_phrase: Option<String> as "secretPhrase",
+		/// The hex-encoded BIP39 entropy of the secret phrase, if it is shown.
+		pub(super) entropy: Option<String> as "entropy",
+		/// The secret key URI, if the account is given by one and secrets are shown.
+		pub(super) secret_key_uri: Option<String> as "secretKeyUri",
+		/// Whether the secret key URI includes a password after `///`.
+		pub(super) inline_password: Option<bool> as "inlinePassword",
+		/// The public key URI, if the account is given by one.
+		pub(super) public_key_uri: Option<String> as "publicKeyUri",
+		/// The name of the network of the SS58 address.
+		pub(super) network_id: String as "networkId",
+		/// The hex-encoded secret seed, or `n/a` for soft derivations, unless hidden.
+		pub(super) secret_seed: Option<String> as "secretSeed",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The SS58 address of the account on the network.
+		pub(super) ss58_address: String as "ss58Address",
+		/// The VRF transcript label, with `inspect --vrf`.
+		pub(super) vrf_label: Option<String> as "vrfLabel",
+		/// The hex-encoded VRF transcript input, with `inspect --vrf`.
+		pub(super) vrf_input: Option<String> as "vrfInput",
+		/// The hex-encoded VRF output, with `inspect --vrf`.
+		pub(super) vrf_output: Option<String> as "vrfOutput",
+		/// The hex-encoded VRF proof, with `inspect --vrf`.
+		pub(super) vrf_proof: Option<String> as "vrfProof",
+	}
+}
+
+json_output! {
+	/// The parts of a secret URI, as printed by validate-suri.
+	pub(super) struct SuriOutput {
+		/// Always true; invalid secret URIs are an error.
+		pub(super) valid: bool as "valid",
+		/// The cryptography the secret URI is valid for.
+		pub(super) scheme: String as "scheme",
+		/// Whether the secret is a mnemonic phrase rather than a hex-encoded seed.
+		pub(super) is_phrase: bool as "isPhrase",
+		/// The number of derivation junctions.
+		pub(super) junction_count: usize as "junctionCount",
+		/// The number of hard derivation junctions.
+		pub(super) hard_junctions: usize as "hardJunctions",
+		/// The number of soft derivation junctions.
+		pub(super) soft_junctions: usize as "softJunctions",
+		/// Whether a password is given, inline or separately.
+		pub(super) has_password: bool as "hasPassword",
+	}
+}
+
+json_output! {
+	/// A signature, as printed by sign.
+	pub(super) struct SignatureOutput {
+		/// The signature, encoded as given by `signatureFormat`.
+		pub(super) signature: String as "signature",
+		/// The encoding of the signature.
+		pub(super) signature_format: String as "signatureFormat",
+		/// The algorithm of the digest that is signed, if not the message itself.
+		pub(super) hash: Option<String> as "hash",
+		/// The hex-encoded public key of the signer.
+		pub(super) public_key: String as "publicKey",
+	}
+}
+
+json_output! {
+	/// A detached signature file, as printed by sign-file.
+	pub(super) struct SignatureFileOutput {
+		/// The signed file.
+		pub(super) file: String as "file",
+		/// The file the signature is written to.
+		pub(super) signature_file: String as "signatureFile",
+		/// The encoding of the signature.
+		pub(super) signature_format: String as "signatureFormat",
+		/// The algorithm of the digest that is signed, if not the file itself.
+		pub(super) hash: Option<String> as "hash",
+		/// The hex-encoded public key of the signer.
+		pub(super) public_key: String as "publicKey",
+	}
+}
+
+json_output! {
+	/// The result of verifying a signature, as printed by verify without `--batch`.
+	pub(super) struct VerificationOutput {
+		/// Whether the signature is valid.
+		pub(super) valid: bool as "valid",
+		/// The cryptography of the key, unless `--scheme auto` detected none.
+		pub(super) scheme: Option<String> as "scheme",
+		/// The hex-encoded public key, if known.
+		pub(super) public_key: Option<String> as "publicKey",
+		/// The SS58 address of the signer, unless `--scheme auto` detected no scheme.
+		pub(super) ss58_address: Option<String> as "ss58Address",
+	}
+}
+
+json_output! {
+	/// A node key, as printed by generate-node-key.
+	pub(super) struct NodeKeyOutput {
+		/// The hex-encoded ed25519 secret key, unless it is written to `--file`.
+		pub(super) secret: Option<String> as "secret",
+		/// The hex-encoded ed25519 public key.
+		pub(super) public_key: String as "publicKey",
+		/// The base58-encoded libp2p peer ID of the node.
+		pub(super) peer_id: String as "peerId",
+	}
+}
+
+json_output! {
+	/// The peer ID of a node key, as printed by inspect-node-key.
+	pub(super) struct PeerIdOutput {
+		/// The hex-encoded ed25519 public key.
+		pub(super) public_key: String as "publicKey",
+		/// The base58-encoded libp2p peer ID of the node.
+		pub(super) peer_id: String as "peerId",
+		/// The multihash codec of the peer ID, `identity` for ed25519 keys.
+		pub(super) multihash_codec: String as "multihashCodec",
+		/// The length of the multihash digest, the protobuf-encoded public key, in bytes.
+		pub(super) multihash_length: usize as "multihashLength",
+	}
+}
+
+json_output! {
+	/// A derived account, as printed on each line by `key derive-many --format jsonl`.
+	pub(super) struct DerivedAccountOutput {
+		/// The index the path is made with.
+		pub(super) index: u64 as "index",
+		/// The derivation path.
+		pub(super) path: String as "path",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The SS58 address of the account.
+		pub(super) ss58_address: String as "ss58Address",
+	}
+}
+
+json_output! {
+	/// A signature of a test vector.
+	pub(super) struct TestSignatureOutput {
+		/// The hex-encoded message.
+		pub(super) message: String as "message",
+		/// The hex-encoded signature of the message.
+		pub(super) signature: String as "signature",
+	}
+}
+
+json_output! {
+	/// A test vector, the account of a secret URI and its signatures.
+	pub(super) struct TestVectorOutput {
+		/// The secret URI, the seed with the index as hard junction.
+		pub(super) suri: String as "suri",
+		/// The hex-encoded secret seed of the account.
+		pub(super) seed: Option<String> as "seed",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The SS58 address of the account.
+		pub(super) ss58_address: String as "ss58Address",
+		/// The signatures of the test messages.
+		pub(super) signatures: Vec<TestSignatureOutput> as "signatures",
+	}
+}
+
+json_output! {
+	/// The test vectors of a scheme, as printed by `key test-vectors`.
+	pub(super) struct TestVectorsOutput {
+		/// The cryptography of the accounts.
+		pub(super) scheme: String as "scheme",
+		/// The hex-encoded seed the secret URIs are derived from.
+		pub(super) seed: String as "seed",
+		/// The name of the network of the SS58 addresses.
+		pub(super) network_id: String as "networkId",
+		/// Whether other implementations make the same signatures.
+		pub(super) signatures: String as "signatures",
+		/// The test vectors.
+		pub(super) vectors: Vec<TestVectorOutput> as "vectors",
+	}
+}
+
+json_output! {
+	/// The result of verifying an entry of a batch manifest.
+	pub(super) struct BatchEntryOutput {
+		/// The index of the entry in the manifest.
+		pub(super) index: usize as "index",
+		/// Whether the signature is valid.
+		pub(super) valid: bool as "valid",
+		/// Why the entry is malformed, if it is.
+		pub(super) error: Option<String> as "error",
+	}
+}
+
+json_output! {
+	/// The number of entries of a batch manifest, by result.
+	pub(super) struct BatchSummaryOutput {
+		/// The number of entries.
+		pub(super) total: usize as "total",
+		/// The number of valid signatures.
+		pub(super) valid: usize as "valid",
+		/// The number of invalid signatures.
+		pub(super) invalid: usize as "invalid",
+		/// The number of malformed entries.
+		pub(super) malformed: usize as "malformed",
+	}
+}
+
+json_output! {
+	/// The report of verifying a batch manifest, as printed by `verify --batch`.
+	pub(super) struct BatchVerificationOutput {
+		/// The result of each entry, in the order of the manifest.
+		pub(super) entries: Vec<BatchEntryOutput> as "entries",
+		/// The number of entries by result.
+		pub(super) summary: BatchSummaryOutput as "summary",
+	}
+}
+
+json_output! {
+	/// A key type, as listed by `key key-types`.
+	pub(super) struct KeyTypeOutput {
+		/// The four character key type.
+		pub(super) key_type: String as "keyType",
+		/// The name of the module using the key type.
+		pub(super) name: String as "name",
+		/// The cryptography of the keys, if it is fixed.
+		pub(super) scheme: Option<String> as "scheme",
+		/// What the keys are used for.
+		pub(super) description: String as "description",
+	}
+}
+
+json_output! {
+	/// A key of a keystore, as listed by `key list`.
+	pub(super) struct KeystoreKeyOutput {
+		/// The four character key type.
+		pub(super) key_type: String as "keyType",
+		/// The cryptography of the key, if the key type has a fixed one.
+		pub(super) scheme: Option<String> as "scheme",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The SS58 address of the key.
+		pub(super) ss58_address: String as "ss58Address",
+	}
+}
+
+json_output! {
+	/// Session keys, as printed by `key decode-session-keys` and `key rotate-session-keys`.
+	pub(super) struct SessionKeysOutput {
+		/// The hex-encoded concatenated session keys.
+		pub(super) session_keys: String as "sessionKeys",
+		/// The public key of each module, in the order of the session keys.
+		pub(super) keys: Vec<KeystoreKeyOutput> as "keys",
+	}
+}
+
+json_output! {
+	/// An account of a polkadot-js JSON file, as printed by `key import-json`.
+	pub(super) struct ImportedAccountOutput {
+		/// The cryptography of the account.
+		pub(super) scheme: String as "scheme",
+		/// The name of the account, if the file has one.
+		pub(super) name: Option<String> as "name",
+		/// The hex-encoded genesis hash the account is meant for, if the file has one.
+		pub(super) genesis_hash: Option<String> as "genesisHash",
+		/// The hex-encoded secret key, unless hidden.
+		pub(super) secret_key: Option<String> as "secretKey",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The SS58 address of the account.
+		pub(super) ss58_address: String as "ss58Address",
+	}
+}
+
+json_output! {
+	/// An address encoded for another network, as printed by reencode-address.
+	pub(super) struct ReencodedAddressOutput {
+		/// The address, as given.
+		pub(super) address: String as "address",
+		/// The name of the network it is encoded for.
+		pub(super) network_id: String as "networkId",
+		/// The SS58 address on that network.
+		pub(super) ss58_address: String as "ss58Address",
+	}
+}
+
+json_output! {
+	/// A development account, as listed by `key dev-accounts`.
+	pub(super) struct DevAccountOutput {
+		/// The cryptography of the account.
+		pub(super) scheme: String as "scheme",
+		/// The secret key URI of the account.
+		pub(super) uri: String as "uri",
+		/// The name of the network of the SS58 address.
+		pub(super) network_id: String as "networkId",
+		/// The hex-encoded public key.
+		pub(super) public_key: String as "publicKey",
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The SS58 address of the account on the network.
+		pub(super) ss58_address: String as "ss58Address",
+	}
+}
+
+json_output! {
+	/// How an account id relates to the public key of a scheme.
+	pub(super) struct AccountFormOutput {
+		/// The cryptography.
+		pub(super) scheme: String as "scheme",
+		/// Whether the account id is the public key, rather than its hash.
+		pub(super) account_id_is_public_key: bool as "accountIdIsPublicKey",
+		/// The hex-encoded public key, if it is the account id.
+		pub(super) public_key: Option<String> as "publicKey",
+	}
+}
+
+json_output! {
+	/// The forms of an account, as printed by account-forms.
+	pub(super) struct AccountFormsOutput {
+		/// The hex-encoded account id.
+		pub(super) account_id: String as "accountId",
+		/// The name of the network of the SS58 address.
+		pub(super) network_id: String as "networkId",
+		/// The SS58 address of the account on the network.
+		pub(super) ss58_address: String as "ss58Address",
+		/// The form of the account for each scheme.
+		pub(super) schemes: Vec<AccountFormOutput> as "schemes",
+	}
+}
+
+json_output! {
+	/// A segment of a storage key.
+	pub(super) struct StorageKeySegmentOutput {
+		/// What the segment is the hash of, e.g. `pallet` or `key 1`.
+		pub(super) label: String as "label",
+		/// The hex-encoded hashed value.
+		pub(super) value: String as "value",
+		/// The hasher of the segment.
+		pub(super) hasher: String as "hasher",
+		/// The hex-encoded hash.
+		pub(super) hash: String as "hash",
+	}
+}
+
+json_output! {
+	/// The key of a storage item, as printed by `key storage-key`.
+	pub(super) struct StorageKeyOutput {
+		/// The hex-encoded storage key.
+		pub(super) storage_key: String as "storageKey",
+		/// The segments the storage key is made of.
+		pub(super) segments: Vec<StorageKeySegmentOutput> as "segments",
+	}
+}
+
+/// The commands with a versioned JSON output, and the schema of the output of each.
+pub(super) fn schemas() -> Vec<(&'static str, Value)> {
+	vec![
+		("generate", AccountOutput::schema()),
+		("inspect", AccountOutput::schema()),
+		("derive", AccountOutput::schema()),
+		("vanity", AccountOutput::schema()),
+		("validate-suri", SuriOutput::schema()),
+		("reencode-address", Vec::<ReencodedAddressOutput>::schema()),
+		("account-forms", AccountFormsOutput::schema()),
+		("sign", SignatureOutput::schema()),
+		("sign-file", SignatureFileOutput::schema()),
+		("verify", VerificationOutput::schema()),
+		("verify --batch", BatchVerificationOutput::schema()),
+		("generate-node-key", NodeKeyOutput::schema()),
+		("inspect-node-key", PeerIdOutput::schema()),
+		("key list", Vec::<KeystoreKeyOutput>::schema()),
+		("key key-types", Vec::<KeyTypeOutput>::schema()),
+		("key test-vectors", TestVectorsOutput::schema()),
+		("key dev-accounts", Vec::<DevAccountOutput>::schema()),
+		("key import-json", ImportedAccountOutput::schema()),
+		("key rotate-session-keys", SessionKeysOutput::schema()),
+		("key decode-session-keys", SessionKeysOutput::schema()),
+		("key derive-many", DerivedAccountOutput::schema()),
+		("key storage-key", StorageKeyOutput::schema()),
+		("key multisig", AccountOutput::schema()),
+		("key pure-proxy", AccountOutput::schema()),
+	]
+}
+
+/// Print the JSON schema of an output, the `key schema` command.
+pub fn run_schema(matches: &ArgMatches, output: OutputType) -> Result<(), Error> {
+	let schemas = schemas();
+	let json = match matches.value_of("command") {
+		Some(command) => {
+			let names = schemas.iter().map(|(name, _)| *name).join(", ");
+			schemas
+				.into_iter()
+				.find(|(name, _)| *name == command)
+				.map(|(_, schema)| schema)
+				.ok_or_else(|| Error::Formatted(format!(
+					"No JSON output schema for `{}`; expecting one of {}",
+					command,
+					names,
+				)))?
+		},
+		None => schemas
+			.into_iter()
+			.map(|(name, schema)| (name.to_string(), schema))
+			.collect::<serde_json::Map<_, _>>()
+			.into(),
+	};
+	output.write_json(&mut io::stdout(), &json)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	json_output! {
+		/// An output for testing.
+		pub(super) struct TestOutput {
+			/// A required field.
+			pub(super) name: String as "name",
+			/// An optional field, documented on
+			/// two lines.
+			pub(super) count: Option<usize> as "count",
+		}
+	}
+
+	#[test]
+	fn output_and_schema_are_derived_from_the_fields() {
+		let output = TestOutput { name: "test".into(), count: None };
+		assert_eq!(output.to_json(), json!({ "formatVersion": FORMAT_VERSION, "name": "test" }));
+		let output = TestOutput { count: Some(2), ..output };
+		assert_eq!(output.to_json()["count"], 2);
+
+		let schema = TestOutput::schema();
+		assert_eq!(schema["required"], json!(["formatVersion", "name"]));
+		assert_eq!(schema["properties"]["formatVersion"]["const"], FORMAT_VERSION);
+		assert_eq!(
+			schema["properties"]["count"],
+			json!({
+				"type": "integer",
+				"minimum": 0,
+				"description": "An optional field, documented on two lines.",
+			}),
+		);
+		assert_eq!(schema["additionalProperties"], false);
+	}
+}
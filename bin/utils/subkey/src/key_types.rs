@@ -0,0 +1,362 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The well-known key types, and the validation of key types given on the command line.
+
+use crate::{crypto::CryptoScheme, error::Error};
+use clap::Arg;
+use itertools::Itertools;
+use sp_core::crypto::{key_types, KeyTypeId};
+use std::convert::TryFrom;
+
+/// A key type used by the modules of Substrate or Polkadot.
+pub(super) struct KnownKeyType {
+	pub(super) id: KeyTypeId,
+	/// The name of the module, for suggestions and listings.
+	pub(super) name: &'static str,
+	/// The scheme of the keys of the module, if it is fixed.
+	pub(super) scheme: Option<&'static str>,
+	pub(super) description: &'static str,
+}
+
+/// The key types listed by `key key-types`, and accepted without `--force-key-type`.
+pub(super) const KNOWN_KEY_TYPES: &[KnownKeyType] = &[
+	KnownKeyType {
+		id: key_types::BABE,
+		name: "babe",
+		scheme: Some("sr25519"),
+		description: "BABE block production",
+	},
+	KnownKeyType {
+		id: key_types::GRANDPA,
+		name: "grandpa",
+		scheme: Some("ed25519"),
+		description: "GRANDPA finality",
+	},
+	KnownKeyType {
+		id: key_types::IM_ONLINE,
+		name: "im_online",
+		scheme: Some("sr25519"),
+		description: "ImOnline heartbeats",
+	},
+	KnownKeyType {
+		id: key_types::AUTHORITY_DISCOVERY,
+		name: "authority_discovery",
+		scheme: Some("sr25519"),
+		description: "Authority discovery",
+	},
+	KnownKeyType {
+		id: key_types::AURA,
+		name: "aura",
+		scheme: Some("sr25519"),
+		description: "Aura block production",
+	},
+	KnownKeyType {
+		id: KeyTypeId(*b"para"),
+		name: "parachain_validator",
+		scheme: Some("sr25519"),
+		description: "Polkadot parachain validation",
+	},
+	KnownKeyType {
+		id: KeyTypeId(*b"asgn"),
+		name: "assignment",
+		scheme: Some("sr25519"),
+		description: "Polkadot approval assignments",
+	},
+	KnownKeyType {
+		id: key_types::ACCOUNT,
+		name: "account",
+		scheme: None,
+		description: "Controlling an account",
+	},
+	KnownKeyType {
+		id: key_types::STAKING,
+		name: "staking",
+		scheme: None,
+		description: "Staking",
+	},
+	KnownKeyType {
+		id: key_types::REPORTING,
+		name: "reporting",
+		scheme: None,
+		description: "Equivocation reporting",
+	},
+];
+
+/// The most suggestions printed for a mistyped key type.
+const MAX_SUGGESTIONS: usize = 3;
+
+impl KnownKeyType {
+	/// The key type id as a string.
+	pub(super) fn id_str(&self) -> &str {
+		std::str::from_utf8(&self.id.0).expect("the known key types are ASCII; qed")
+	}
+}
+
+/// The known key type `id`, if any.
+pub(super) fn known_key_type(id: KeyTypeId) -> Option<&'static KnownKeyType> {
+	KNOWN_KEY_TYPES.iter().find(|known| known.id == id)
+}
+
+/// Parse `key_type` into a key type id.
+///
+/// Anything but a known key type is refused with the closest known ones, unless `force` is given
+/// and `key_type` has four characters.
+pub(super) fn parse_key_type(key_type: &str, force: bool) -> Result<KeyTypeId, Error> {
+	let id = KeyTypeId::try_from(key_type).ok();
+	if let Some(id) = id {
+		if force || known_key_type(id).is_some() {
+			return Ok(id);
+		}
+	}
+
+	let problem = match id {
+		Some(_) => format!("Unknown key type `{}`", key_type),
+		None => format!("Invalid key type `{}`; expecting four characters", key_type),
+	};
+	let suggestions = suggestions(key_type)
+		.iter()
+		.map(|known| format!("{} ({})", known.id_str(), known.description))
+		.collect::<Vec<_>>();
+	let hint = match suggestions.len() {
+		0 => "see `subkey key key-types` for the known key types".to_string(),
+		_ => format!("did you mean {}?", suggestions.join(" or ")),
+	};
+	let force_hint = match id {
+		Some(_) => " Use --force-key-type to use it anyway.",
+		None => "",
+	};
+	Err(Error::Formatted(format!("{}; {}{}", problem, hint, force_hint)))
+}
+
+/// The session keys of the node runtime, in the order of its `SessionKeys`.
+pub(super) const NODE_SESSION_KEYS: &str = "gran:ed25519,babe:sr25519,imon:sr25519,audi:sr25519";
+
+/// The options selecting the layout of session keys, shared by the commands printing them.
+pub fn session_key_layout_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+	vec![
+		Arg::from_usage("[layout] --layout <layout> 'The key type and scheme of each session key, \
+				in their order, like `gran:ed25519,babe:sr25519`, optionally followed by the \
+				length of the key, like `gran:ed25519:32`. The scheme can be left out for the \
+				well-known key types. Default is the order of the node runtime, \
+				`gran:ed25519,babe:sr25519,imon:sr25519,audi:sr25519`.'"),
+		Arg::from_usage("--force-key-type 'Use the key types of the layout even if they are none \
+				of the well-known ones listed by `key key-types`'")
+			.requires("layout"),
+	]
+}
+
+/// The key type and scheme of each session key, in their order in the session keys.
+pub(super) type SessionKeyLayout = Vec<(KeyTypeId, &'static str)>;
+
+/// The length of the public keys of `scheme`: 33 bytes for ecdsa, 32 for the others.
+fn public_key_length(scheme: &str) -> usize {
+	if scheme == "ecdsa" { 33 } else { 32 }
+}
+
+/// Parse a layout of session keys, given as `<key type>:<scheme>[:<length>]` separated by commas.
+///
+/// The scheme can be left out for the known key types with a fixed scheme. The length, if given,
+/// has to be the one of the public keys of the scheme. The key types are parsed like
+/// [`parse_key_type`] does, with `force`.
+pub(super) fn parse_session_key_layout(
+	layout: &str,
+	force: bool,
+) -> Result<SessionKeyLayout, Error> {
+	layout
+		.split(',')
+		.map(|entry| {
+			let mut parts = entry.trim().splitn(3, ':');
+			let key_type = parse_key_type(parts.next().unwrap_or_default(), force)?;
+			let scheme = match parts.next() {
+				Some(scheme) => CryptoScheme::from_str_ci(scheme)
+					.map(CryptoScheme::name)
+					.map_err(|_| Error::Formatted(format!(
+						"Invalid scheme `{}` in the layout; expecting one of {}",
+						scheme,
+						CryptoScheme::all().iter().map(|scheme| scheme.name()).join(", "),
+					)))?,
+				None => known_key_type(key_type).and_then(|known| known.scheme).ok_or_else(|| {
+					Error::Formatted(format!(
+						"The scheme of the key type `{}` has to be given in the layout, e.g. \
+						`{}:sr25519`",
+						entry.trim(),
+						entry.trim(),
+					))
+				})?,
+			};
+			if let Some(length) = parts.next() {
+				if length.parse() != Ok(public_key_length(scheme)) {
+					return Err(Error::Formatted(format!(
+						"Invalid length `{}` of `{}` in the layout; {} public keys are {} bytes \
+						long",
+						length,
+						entry.trim(),
+						scheme,
+						public_key_length(scheme),
+					)));
+				}
+			}
+			Ok((key_type, scheme))
+		})
+		.collect()
+}
+
+/// Split the concatenated public `keys` of a set of session keys along `layout`.
+pub(super) fn split_session_keys(
+	keys: &[u8],
+	layout: &[(KeyTypeId, &'static str)],
+) -> Result<Vec<Vec<u8>>, Error> {
+	let expected = layout.iter().map(|(_, scheme)| public_key_length(scheme)).sum::<usize>();
+	if keys.len() != expected {
+		return Err(Error::Formatted(format!(
+			"The session keys are {} bytes long, but the layout expects {} bytes",
+			keys.len(),
+			expected,
+		)));
+	}
+	let mut rest = keys;
+	Ok(layout.iter().map(|(_, scheme)| {
+		let (key, tail) = rest.split_at(public_key_length(scheme));
+		rest = tail;
+		key.to_vec()
+	}).collect())
+}
+
+/// The known key types closest to `key_type`, by prefix and then by edit distance.
+fn suggestions(key_type: &str) -> Vec<&'static KnownKeyType> {
+	let input = normalize(key_type);
+	let mut candidates = KNOWN_KEY_TYPES
+		.iter()
+		.filter_map(|known| {
+			let id = known.id_str();
+			let name = normalize(known.name);
+			if input.starts_with(id) || name.starts_with(&input) || input.starts_with(&name) {
+				return Some((0, known));
+			}
+			let distance = edit_distance(&input, id).min(edit_distance(&input, &name));
+			if distance <= 2 { Some((distance, known)) } else { None }
+		})
+		.collect::<Vec<_>>();
+	candidates.sort_by_key(|(distance, _)| *distance);
+	candidates.into_iter().map(|(_, known)| known).take(MAX_SUGGESTIONS).collect()
+}
+
+/// `name` in lower case, without separators.
+fn normalize(name: &str) -> String {
+	name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut previous = (0..=b.len()).collect::<Vec<_>>();
+	for (i, a) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		for (j, b) in b.iter().enumerate() {
+			let substitution = previous[j] + if a == *b { 0 } else { 1 };
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+	previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn known_key_types_are_accepted() {
+		for known in KNOWN_KEY_TYPES {
+			assert_eq!(parse_key_type(known.id_str(), false).unwrap(), known.id);
+		}
+		let ids = KNOWN_KEY_TYPES.iter().map(|known| known.id);
+		let ids = ids.collect::<std::collections::HashSet<_>>();
+		assert_eq!(ids.len(), KNOWN_KEY_TYPES.len());
+	}
+
+	#[test]
+	fn mistyped_key_types_are_refused_with_suggestions() {
+		let error = |key_type| parse_key_type(key_type, false).err().unwrap().to_string();
+		assert_eq!(
+			error("grandpa"),
+			"Invalid key type `grandpa`; expecting four characters; did you mean gran (GRANDPA \
+			finality)?",
+		);
+		assert_eq!(
+			error("grna"),
+			"Unknown key type `grna`; did you mean gran (GRANDPA finality)? Use --force-key-type \
+			to use it anyway.",
+		);
+		assert!(error("ImOnline").contains("did you mean imon"));
+		assert!(error("authority-discovery").contains("did you mean audi"));
+		assert!(error("zzzz").contains("see `subkey key key-types`"));
+	}
+
+	#[test]
+	fn unknown_key_types_are_only_accepted_with_force() {
+		assert_eq!(parse_key_type("zzzz", true).unwrap(), KeyTypeId(*b"zzzz"));
+		assert!(parse_key_type("grandpa", true).is_err());
+		assert!(parse_key_type("", true).is_err());
+	}
+
+	#[test]
+	fn session_keys_are_split_along_the_layout() {
+		let layout = parse_session_key_layout(NODE_SESSION_KEYS, false).unwrap();
+		assert_eq!(layout, vec![
+			(key_types::GRANDPA, "ed25519"),
+			(key_types::BABE, "sr25519"),
+			(key_types::IM_ONLINE, "sr25519"),
+			(key_types::AUTHORITY_DISCOVERY, "sr25519"),
+		]);
+		let keys = (0..128).collect::<Vec<u8>>();
+		let split = split_session_keys(&keys, &layout).unwrap();
+		assert_eq!(split.len(), 4);
+		assert_eq!(split[1], (32..64).collect::<Vec<u8>>());
+		assert!(split_session_keys(&keys[..127], &layout).is_err());
+
+		let layout = parse_session_key_layout("babe, zzzz:ecdsa", true).unwrap();
+		assert_eq!(layout, vec![(key_types::BABE, "sr25519"), (KeyTypeId(*b"zzzz"), "ecdsa")]);
+		let split = split_session_keys(&keys[..65], &layout).unwrap();
+		assert_eq!(split[1], (32..65).collect::<Vec<u8>>());
+	}
+
+	#[test]
+	fn session_key_layouts_are_checked() {
+		let error = |layout| parse_session_key_layout(layout, true).err().unwrap().to_string();
+		assert!(error("babe:rsa").starts_with("Invalid scheme `rsa`"));
+		assert_eq!(
+			error("gran:ed25519:32,babe:sr25519:33"),
+			"Invalid length `33` of `babe:sr25519:33` in the layout; sr25519 public keys are 32 \
+			bytes long",
+		);
+		assert!(parse_session_key_layout("gran:ed25519:32,acco:ecdsa:33", false).is_ok());
+		assert!(error("zzzz").contains("e.g. `zzzz:sr25519`"));
+		assert!(error("grandpa:ed25519").starts_with("Invalid key type `grandpa`"));
+		assert!(parse_session_key_layout("zzzz:ecdsa", false).is_err());
+	}
+
+	#[test]
+	fn edit_distance_works() {
+		assert_eq!(edit_distance("gran", "gran"), 0);
+		assert_eq!(edit_distance("gran", "grna"), 2);
+		assert_eq!(edit_distance("babe", "bab"), 1);
+		assert_eq!(edit_distance("", "aura"), 4);
+	}
+}
@@ -0,0 +1,280 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading the keys of a node keystore from the names of its files, removing them, and rewriting
+//! them for a new password.
+//!
+//! The keystore holds a file per key, named by the hex-encoded key type followed by the
+//! hex-encoded public key, and holding the secret key URI, as a JSON string or encrypted with the
+//! keystore password. The files are only read to check that they are key files, and never printed.
+
+use crate::{
+	crypto::{Crypto, CryptoScheme},
+	error::Error,
+	format::inline_password,
+	key_types,
+	with_crypto_scheme,
+};
+use node_primitives::AccountId;
+use sp_core::{
+	blake2_256,
+	crypto::{ExposeSecret, KeyTypeId, SecretString, Ss58Codec, Zeroize, DEV_PHRASE},
+	Pair,
+};
+use std::{fs, io::Write, path::Path};
+
+/// A key of a node keystore.
+pub(super) struct KeystoreKey {
+	pub(super) key_type: KeyTypeId,
+	pub(super) public: Vec<u8>,
+}
+
+impl KeystoreKey {
+	/// Parse the name of a keystore file.
+	fn from_file_name(name: &str) -> Result<Self, String> {
+		let bytes = hex::decode(name).map_err(|_| "the name is not hex-encoded".to_string())?;
+		if bytes.len() <= 4 {
+			return Err("the name has no public key".into());
+		}
+		let (key_type, public) = bytes.split_at(4);
+		let key = KeystoreKey {
+			key_type: KeyTypeId([key_type[0], key_type[1], key_type[2], key_type[3]]),
+			public: public.to_vec(),
+		};
+		let expected = match key.scheme() {
+			Some("ecdsa") => 33,
+			Some(_) => 32,
+			None if key.public.len() == 33 => 33,
+			None => 32,
+		};
+		if key.public.len() != expected {
+			return Err(format!(
+				"the public key has {} bytes, expecting {}",
+				key.public.len(),
+				expected,
+			));
+		}
+		Ok(key)
+	}
+
+	/// The key type as a string, with any bytes that are not ASCII replaced.
+	pub(super) fn key_type_str(&self) -> String {
+		String::from_utf8_lossy(&self.key_type.0).into_owned()
+	}
+
+	/// The scheme of the key, if it is fixed by the key type or only ecdsa has keys of its length.
+	///
+	/// The keys of sr25519 and ed25519 have the same length, but also the same SS58 address.
+	pub(super) fn scheme(&self) -> Option<&'static str> {
+		match key_types::known_key_type(self.key_type).and_then(|known| known.scheme) {
+			Some(scheme) => Some(scheme),
+			None if self.public.len() == 33 => Some("ecdsa"),
+			None => None,
+		}
+	}
+
+	/// The account id of the key: the public key itself, or its blake2-256 hash for ecdsa.
+	pub(super) fn account_id(&self) -> AccountId {
+		let account_id = match self.public.len() {
+			32 => {
+				let mut account_id = [0u8; 32];
+				account_id.copy_from_slice(&self.public);
+				account_id
+			},
+			_ => blake2_256(&self.public),
+		};
+		AccountId::from(account_id)
+	}
+
+	/// The SS58 address of the account of the key, on the default network.
+	pub(super) fn ss58_address(&self) -> String {
+		self.account_id().to_ss58check()
+	}
+
+	/// The name of the file of the key in the keystore.
+	pub(super) fn file_name(&self) -> String {
+		format!("{}{}", hex::encode(self.key_type.0), hex::encode(&self.public))
+	}
+}
+
+/// Remove the key file at `path`, first overwriting its contents with zeros if `shred` is set.
+///
+/// Shredding only keeps the secret from being read back on file systems that overwrite files in
+/// place, which copy-on-write ones do not.
+pub(super) fn remove_key_file(path: &Path, shred: bool) -> Result<(), Error> {
+	if shred {
+		let mut file = fs::OpenOptions::new().write(true).open(path)?;
+		let len = file.metadata()?.len();
+		file.write_all(&vec![0; len as usize])?;
+		file.sync_all()?;
+	}
+	Ok(fs::remove_file(path)?)
+}
+
+/// The contents of the key file of `key` at `path`, read with `old_password`, encrypted with
+/// the keystore password `new_password`, and whether its phrase was replaced.
+///
+/// The keystore password is also the password of the secret URIs in the files, so a phrase is
+/// replaced by the seed it makes with `old_password`, and the new URI is checked to make the key
+/// of the file with `new_password`. Plain files are encrypted too: they would still be loaded with
+/// any password otherwise.
+pub(super) fn rotate_key_file(
+	path: &Path,
+	key: &KeystoreKey,
+	old_password: Option<&str>,
+	new_password: &str,
+) -> Result<(Vec<u8>, bool), Error> {
+	let mut contents = fs::read(path)?;
+	let suri = sc_keystore::decode_key_file(&contents, old_password)
+		.map(SecretString::new)
+		.map_err(|source| Error::KeystoreIo { action: "read key file", source });
+	contents.zeroize();
+	let suri = suri?;
+
+	let rotated = CryptoScheme::all().iter().find_map(|scheme| with_crypto_scheme!(
+		*scheme,
+		password_free_suri::<_>(suri.expose_secret(), old_password, new_password, &key.public)
+	));
+	let rotated = rotated.ok_or(Error::Static(
+		"the key file does not hold the key of its name with the old password",
+	))?;
+	let format = sc_keystore::KeyFileFormat::encrypted();
+	let contents = sc_keystore::encode_key_file(rotated.expose_secret(), format, Some(new_password))
+		.map_err(|source| Error::KeystoreIo { action: "write key file", source })?;
+	Ok((contents, !suri.expose_secret().starts_with("0x")))
+}
+
+/// A secret URI of the key of `suri` with `old_password` that makes the same key with
+/// `new_password`, if that key is the one of `public`.
+///
+/// A phrase is replaced by the hex-encoded seed it makes with `old_password`, which no password
+/// applies to; the derivation path is kept.
+fn password_free_suri<C: Crypto>(
+	suri: &str,
+	old_password: Option<&str>,
+	new_password: &str,
+	public: &[u8],
+) -> Option<SecretString> {
+	let pair = C::Pair::from_string(suri, old_password).ok()?;
+	if pair.public().as_ref() != public {
+		return None;
+	}
+	let phrase_and_path = suri.find("///").map_or(suri, |password_start| &suri[..password_start]);
+	let path_start = phrase_and_path.find('/').unwrap_or(phrase_and_path.len());
+	let (phrase, path) = phrase_and_path.split_at(path_start);
+	let rotated = if phrase.starts_with("0x") {
+		SecretString::new(phrase_and_path.into())
+	} else {
+		// Like `Pair::from_string`, which reads an empty phrase as the one of the dev accounts.
+		let phrase = if phrase.is_empty() { DEV_PHRASE } else { phrase };
+		let password = old_password.or_else(|| inline_password(suri));
+		let (_, mut seed) = C::Pair::from_phrase(phrase, password).ok()?;
+		let rotated = SecretString::new(format!("0x{}{}", hex::encode(seed.as_ref()), path));
+		seed.as_mut().zeroize();
+		rotated
+	};
+	let rotated_pair = C::Pair::from_string(rotated.expose_secret(), Some(new_password)).ok()?;
+	Some(rotated).filter(|_| rotated_pair.public() == pair.public())
+}
+
+/// The keys of the node keystore at `path`, by their file names, and a warning for each entry
+/// that is no key or whose file is corrupt.
+pub(super) fn list_keys(path: &Path) -> Result<(Vec<KeystoreKey>, Vec<String>), Error> {
+	if !path.is_dir() {
+		return Err(Error::Formatted(format!("Keystore `{}` is not a directory", path.display())));
+	}
+	let mut entries = fs::read_dir(path)?
+		.map(|entry| entry.map(|entry| entry.path()))
+		.collect::<Result<Vec<_>, _>>()?;
+	entries.sort();
+
+	let mut keys = Vec::new();
+	let mut warnings = Vec::new();
+	for entry in entries {
+		let name = entry.file_name().map_or_else(String::new, |name| name.to_string_lossy().into());
+		let key = match entry.file_name().and_then(|name| name.to_str()) {
+			_ if !entry.is_file() => Err("it is not a file".into()),
+			Some(name) => KeystoreKey::from_file_name(name)
+				.and_then(|key| check_key_file(&entry).map(|_| key)),
+			None => Err("the name is not valid UTF-8".into()),
+		};
+		match key {
+			Ok(key) => keys.push(key),
+			Err(problem) => warnings.push(format!("Skipping `{}`: {}", name, problem)),
+		}
+	}
+	Ok((keys, warnings))
+}
+
+/// Check that the file at `path` is a key file, without keeping its secret.
+fn check_key_file(path: &Path) -> Result<(), String> {
+	let mut contents = fs::read(path).map_err(|e| format!("the file can not be read ({})", e))?;
+	let is_key_file = sc_keystore::is_key_file(&contents);
+	contents.zeroize();
+	if is_key_file {
+		Ok(())
+	} else {
+		Err("the file does not hold a secret key URI".into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn file_names_are_parsed_into_keys() {
+		let alice = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+		let key = KeystoreKey::from_file_name(&format!("{}{}", hex::encode(b"gran"), alice)).unwrap();
+		assert_eq!(key.file_name(), format!("{}{}", hex::encode(b"gran"), alice));
+		assert_eq!(key.key_type_str(), "gran");
+		assert_eq!(key.scheme(), Some("ed25519"));
+		assert_eq!(key.ss58_address(), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+
+		let key = KeystoreKey::from_file_name(&format!("{}{}", hex::encode(b"acco"), alice)).unwrap();
+		assert_eq!(key.scheme(), None);
+		let ecdsa = format!("{}02{}", hex::encode(b"acco"), alice);
+		assert_eq!(KeystoreKey::from_file_name(&ecdsa).unwrap().scheme(), Some("ecdsa"));
+
+		let error = |name: &str| KeystoreKey::from_file_name(name).err().unwrap();
+		assert_eq!(error("notes.txt"), "the name is not hex-encoded");
+		assert_eq!(error(&hex::encode(b"gran")), "the name has no public key");
+		assert_eq!(
+			error(&format!("{}02{}", hex::encode(b"gran"), alice)),
+			"the public key has 33 bytes, expecting 32",
+		);
+	}
+
+	#[test]
+	fn rotated_uris_make_the_same_key_with_the_new_password() {
+		use crate::crypto::{Ecdsa, Ed25519, Sr25519};
+
+		fn rotates<C: Crypto>(suri: &str) {
+			let public = C::Pair::from_string(suri, Some("old")).unwrap().public();
+			let rotated = password_free_suri::<C>(suri, Some("old"), "new", public.as_ref()).unwrap();
+			assert!(rotated.expose_secret().starts_with("0x"));
+			let pair = C::Pair::from_string(rotated.expose_secret(), Some("new")).unwrap();
+			assert!(pair.public() == public);
+			let other = C::Pair::from_string(suri, Some("new")).unwrap().public();
+			assert!(password_free_suri::<C>(suri, Some("old"), "new", other.as_ref()).is_none());
+		}
+		rotates::<Sr25519>("//Alice//stash/0");
+		rotates::<Ed25519>(&format!("{}//polkadot", DEV_PHRASE));
+		rotates::<Ecdsa>("//Alice");
+	}
+}
@@ -0,0 +1,73 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The key formatting of subkey, for tools that print keys exactly as its commands do.
+//!
+//! The `subkey` binary formats its keys and accounts with [`format`], so the two can not get out
+//! of sync. The other modules implement the commands of the binary, and are not a stable API.
+
+#![cfg_attr(feature = "bench", feature(test))]
+#[cfg(feature = "bench")]
+extern crate test;
+
+pub mod format;
+
+#[doc(hidden)]
+pub mod account;
+#[doc(hidden)]
+pub mod batch;
+#[doc(hidden)]
+pub mod crypto;
+#[doc(hidden)]
+pub mod derive;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod hash;
+#[doc(hidden)]
+pub mod input;
+#[doc(hidden)]
+pub mod json;
+#[doc(hidden)]
+pub mod key;
+#[doc(hidden)]
+pub mod key_types;
+#[doc(hidden)]
+pub mod keystore;
+#[doc(hidden)]
+pub mod node_key;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod pem;
+#[doc(hidden)]
+pub mod polkadot_js;
+#[doc(hidden)]
+pub mod qr;
+#[doc(hidden)]
+pub mod rpc;
+#[doc(hidden)]
+pub mod sign;
+#[doc(hidden)]
+pub mod table;
+#[doc(hidden)]
+pub mod transaction;
+#[doc(hidden)]
+pub mod vanity;
+#[doc(hidden)]
+pub mod ws;
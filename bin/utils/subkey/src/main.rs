@@ -16,219 +16,27 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-#![cfg_attr(feature = "bench", feature(test))]
-#[cfg(feature = "bench")]
-extern crate test;
-
-use bip39::{Language, Mnemonic, MnemonicType};
-use clap::{App, ArgMatches, SubCommand};
-use codec::{Decode, Encode};
-use hex_literal::hex;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use itertools::Itertools;
-use libp2p::identity::{ed25519 as libp2p_ed25519, PublicKey};
-use node_primitives::{Balance, Hash, Index, AccountId, Signature};
-use node_runtime::{BalancesCall, Call, Runtime, SignedPayload, UncheckedExtrinsic, VERSION};
-use serde_json::json;
-use sp_core::{
-	crypto::{set_default_ss58_version, Ss58AddressFormat, Ss58Codec},
-	ed25519, sr25519, ecdsa, Pair, Public, H256, hexdisplay::HexDisplay,
+use sp_core::crypto::{set_default_ss58_version, ExposeSecret, SecretString, Ss58AddressFormat};
+use std::convert::{TryFrom, TryInto};
+use subkey::{
+	account, batch,
+	crypto::{Crypto, CryptoScheme, PublicOf, PublicT, SignatureOf, SignatureT},
+	derive,
+	error::{static_err, Error, ErrorFormat, ErrorKind},
+	hash,
+	input::{
+		parse_network, prompt_secret, read_kdf_params, read_password_source, register_network,
+		stretch_password, KdfParams,
+	},
+	json, key, key_types, node_key,
+	output::{output_file_args, OutputType, Secrets},
+	qr::qr_args,
+	sign,
+	table::TableStyle,
+	transaction, vanity, with_crypto_scheme,
 };
-use sp_runtime::{traits::{AccountIdConversion, IdentifyAccount, Verify}, generic::Era, ModuleId};
-use std::{
-	convert::{TryInto, TryFrom}, io::{stdin, Read}, str::FromStr, path::PathBuf, fs, fmt,
-};
-
-mod rpc;
-mod vanity;
-
-enum OutputType {
-	Json,
-	Text,
-}
-
-impl<'a> TryFrom<&'a str> for OutputType {
-	type Error = ();
-
-	fn try_from(s: &'a str) -> Result<OutputType, ()> {
-		match s {
-			"json" => Ok(OutputType::Json),
-			"text" => Ok(OutputType::Text),
-			_ => Err(()),
-		}
-	}
-
-}
-
-trait Crypto: Sized {
-	type Pair: Pair<Public = Self::Public>;
-	type Public: Public + Ss58Codec + AsRef<[u8]> + std::hash::Hash;
-	fn pair_from_suri(suri: &str, password: Option<&str>) -> Self::Pair {
-		Self::Pair::from_string(suri, password).expect("Invalid phrase")
-	}
-	fn ss58_from_pair(pair: &Self::Pair) -> String where
-		<Self::Pair as Pair>::Public: PublicT,
-	{
-		pair.public().into_runtime().into_account().to_ss58check()
-	}
-	fn public_from_pair(pair: &Self::Pair) -> Self::Public {
-		pair.public()
-	}
-	fn print_from_uri(
-		uri: &str,
-		password: Option<&str>,
-		network_override: Option<Ss58AddressFormat>,
-		output: OutputType,
-	) where
-		<Self::Pair as Pair>::Public: PublicT,
-	{
-		let v = network_override.unwrap_or_default();
-		if let Ok((pair, seed)) = Self::Pair::from_phrase(uri, password) {
-			let public_key = Self::public_from_pair(&pair);
-
-			match output {
-				OutputType::Json => {
-					let json = json!({
-						"secretPhrase": uri,
-						"networkId": String::from(v),
-						"secretSeed": format_seed::<Self>(seed),
-						"publicKey": format_public_key::<Self>(public_key.clone()),
-						"accountId": format_account_id::<Self>(public_key),
-						"ss58Address": Self::ss58_from_pair(&pair),
-					});
-					println!("{}", serde_json::to_string_pretty(&json).expect("Json pretty print failed"));
-				},
-				OutputType::Text => {
-					println!("Secret phrase `{}` is account:\n  \
-						Network ID/version: {}\n  \
-						Secret seed:        {}\n  \
-						Public key (hex):   {}\n  \
-						Account ID:         {}\n  \
-						SS58 Address:       {}",
-						uri,
-						String::from(v),
-						format_seed::<Self>(seed),
-						format_public_key::<Self>(public_key.clone()),
-						format_account_id::<Self>(public_key),
-						Self::ss58_from_pair(&pair),
-					);
-				},
-			}
-		} else if let Ok((pair, seed)) = Self::Pair::from_string_with_seed(uri, password) {
-			let public_key = Self::public_from_pair(&pair);
-
-			match output {
-				OutputType::Json => {
-					let json = json!({
-						"secretKeyUri": uri,
-						"networkId": String::from(v),
-						"secretSeed": if let Some(seed) = seed { format_seed::<Self>(seed) } else { "n/a".into() },
-						"publicKey": format_public_key::<Self>(public_key.clone()),
-						"accountId": format_account_id::<Self>(public_key),
-						"ss58Address": Self::ss58_from_pair(&pair),
-					});
-					println!("{}", serde_json::to_string_pretty(&json).expect("Json pretty print failed"));
-				},
-				OutputType::Text => {
-					println!("Secret Key URI `{}` is account:\n  \
-						Network ID/version: {}\n  \
-						Secret seed:        {}\n  \
-						Public key (hex):   {}\n  \
-						Account ID:         {}\n  \
-						SS58 Address:       {}",
-						uri,
-						String::from(v),
-						if let Some(seed) = seed { format_seed::<Self>(seed) } else { "n/a".into() },
-						format_public_key::<Self>(public_key.clone()),
-						format_account_id::<Self>(public_key),
-						Self::ss58_from_pair(&pair),
-					);
-				},
-			}
-		} else if let Ok((public_key, v)) =
-			<Self::Pair as Pair>::Public::from_string_with_version(uri)
-		{
-			let v = network_override.unwrap_or(v);
-
-			match output {
-				OutputType::Json => {
-					let json = json!({
-						"publicKeyUri": uri,
-						"networkId": String::from(v),
-						"publicKey": format_public_key::<Self>(public_key.clone()),
-						"accountId": format_account_id::<Self>(public_key.clone()),
-						"ss58Address": public_key.to_ss58check_with_version(v),
-					});
-					println!("{}", serde_json::to_string_pretty(&json).expect("Json pretty print failed"));
-				},
-				OutputType::Text => {
-					println!("Public Key URI `{}` is account:\n  \
-						Network ID/version: {}\n  \
-						Public key (hex):   {}\n  \
-						Account ID:         {}\n  \
-						SS58 Address:       {}",
-						uri,
-						String::from(v),
-						format_public_key::<Self>(public_key.clone()),
-						format_account_id::<Self>(public_key.clone()),
-						public_key.to_ss58check_with_version(v),
-					);
-				},
-			}
-		} else {
-			eprintln!("Invalid phrase/URI given");
-		}
-	}
-}
-
-struct Ed25519;
-
-impl Crypto for Ed25519 {
-	type Pair = ed25519::Pair;
-	type Public = ed25519::Public;
-
-	fn pair_from_suri(suri: &str, password_override: Option<&str>) -> Self::Pair {
-		ed25519::Pair::from_legacy_string(suri, password_override)
-	}
-}
-
-struct Sr25519;
-
-impl Crypto for Sr25519 {
-	type Pair = sr25519::Pair;
-	type Public = sr25519::Public;
-}
-
-struct Ecdsa;
-
-impl Crypto for Ecdsa {
-	type Pair = ecdsa::Pair;
-	type Public = ecdsa::Public;
-}
-
-type SignatureOf<C> = <<C as Crypto>::Pair as Pair>::Signature;
-type PublicOf<C> = <<C as Crypto>::Pair as Pair>::Public;
-type SeedOf<C> = <<C as Crypto>::Pair as Pair>::Seed;
-type AccountPublic = <Signature as Verify>::Signer;
-
-trait SignatureT: AsRef<[u8]> + AsMut<[u8]> + Default {
-	/// Converts the signature into a runtime account signature, if possible. If not possible, bombs out.
-	fn into_runtime(self) -> Signature {
-		panic!("This cryptography isn't supported for this runtime.")
-	}
-}
-trait PublicT: Sized + AsRef<[u8]> + Ss58Codec {
-	/// Converts the public key into a runtime account public key, if possible. If not possible, bombs out.
-	fn into_runtime(self) -> AccountPublic {
-		panic!("This cryptography isn't supported for this runtime.")
-	}
-}
-
-impl SignatureT for sr25519::Signature { fn into_runtime(self) -> Signature { self.into() } }
-impl SignatureT for ed25519::Signature { fn into_runtime(self) -> Signature { self.into() } }
-impl SignatureT for ecdsa::Signature { fn into_runtime(self) -> Signature { self.into() } }
-impl PublicT for sr25519::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
-impl PublicT for ed25519::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
-impl PublicT for ecdsa::Public { fn into_runtime(self) -> AccountPublic { self.into() } }
 
 fn get_usage() -> String {
 	let networks = Ss58AddressFormat::all().iter().cloned().map(String::from).join("/");
@@ -238,10 +46,38 @@ fn get_usage() -> String {
 		-k, --secp256k1 'Use SECP256k1/ECDSA/BIP39 cryptography'
 		-s, --sr25519 'Use Schnorr/Ristretto x25519/BIP39 cryptography'
 		[network] -n, --network <network> 'Specify a network. One of {}. Default is {}'
-		[password] -p, --password <password> 'The password for the key'
-		--password-interactive 'You will be prompted for the password for the key.'
-		[output] -o, --output <output> 'Specify an output format. One of text, json. Default is text.'
-	", networks, default_network)
+		[custom-network] --custom-network <name:prefix> 'Name the network with this numeric SS58 \
+			prefix, from 0 to 63, so that it can be selected with --network and is printed by name.'
+		--password-kdf 'Stretch the password entered with --password-interactive through argon2id \
+			and use the hex-encoded result as the password. This changes the resulting keys!'
+		[kdf-memory] --kdf-memory <kdf-memory> 'The argon2id memory cost in KiB for \
+			--password-kdf. Default is {}.'
+		[kdf-passes] --kdf-passes <kdf-passes> 'The argon2id number of passes for --password-kdf. \
+			Default is {}.'
+		[kdf-salt] --kdf-salt <kdf-salt> 'The argon2id salt for --password-kdf, at least 8 bytes. \
+			Default is `{}`.'
+		[output] -o, --output <output> 'Specify an output format. One of text, json, json-compact \
+			(JSON on a single line), address (only the SS58 address), binary (the SCALE-encoded \
+			account, only for generate, inspect and derive), table (a row per account, for \
+			inspect, key derive-many, reencode-address and key dev-accounts). Default is text.'
+		[error-format] --error-format <error-format> 'Print a failure to STDERR as text, or as \
+			json: a single line with its kind, message and exit code. The exit code is 1 if a \
+			signature does not verify, 2 for invalid arguments, 3 if a file or keystore can not \
+			be read or written, 4 if data can not be decoded and 5 if a node fails. Default is text.'
+		--force-binary 'Write binary output even if STDOUT is a terminal'
+		-y, --yes 'Print secrets as text even if STDOUT is not a terminal'
+		--no-header 'Leave out the header row of --output table'
+		--no-color 'Print --output table as plain rows, separated by tabs, even on a terminal. \
+			This is the default if NO_COLOR is set.'
+		--redact-secret 'Do not echo the secret phrase or secret key URI back in the output. \
+			The secret seed is still printed.'
+	",
+		networks,
+		default_network,
+		KdfParams::default().memory_kib,
+		KdfParams::default().passes,
+		KdfParams::default().salt,
+	)
 }
 
 fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
@@ -249,45 +85,482 @@ fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
 		.author("Parity Team <admin@parity.io>")
 		.about("Utility for generating and restoring with Substrate keys")
 		.version(env!("CARGO_PKG_VERSION"))
+		.global_setting(AppSettings::StrictUtf8)
 		.args_from_usage(usage)
+		.arg(Arg::from_usage("--show-secret 'Print the secret phrase or secret key URI and the \
+				secret seed. This is the default, except for inspect.'")
+			.conflicts_with("hide-secret")
+		)
+		.arg(Arg::from_usage("--hide-secret 'Leave the secret phrase or secret key URI and the \
+				secret seed out of the output. This is the default for inspect.'")
+			.conflicts_with("show-secret")
+		)
+		.arg(Arg::from_usage("[password] -p, --password <password> 'The password for the key'")
+			.conflicts_with_all(&["password-filename", "password-env", "password-interactive"])
+		)
+		.arg(Arg::from_usage("[password-filename] --password-filename <path> 'Read the password \
+				for the key from this file. A trailing newline is not part of the password.'")
+			.conflicts_with_all(&["password", "password-env", "password-interactive"])
+		)
+		.arg(Arg::from_usage("[password-env] --password-env <var> 'Read the password for the key \
+				from this environment variable.'")
+			.conflicts_with_all(&["password", "password-filename", "password-interactive"])
+		)
+		.arg(Arg::from_usage("--password-interactive 'You will be prompted for the password for \
+				the key.'")
+			.conflicts_with_all(&["password", "password-filename", "password-env"])
+		)
 		.subcommands(vec![
 			SubCommand::with_name("generate")
 				.about("Generate a random account")
 				.args_from_usage("[words] -w, --words <words> \
 						'The number of words in the phrase to generate. One of 12 \
 						(default), 15, 18, 21 and 24.'
-				"),
+				")
+				.arg(Arg::from_usage("-q, --quiet 'Print nothing but the SS58 address of the \
+						account for the selected network, on a single line.'")
+				)
+				.arg(Arg::from_usage("--public-hex 'With --quiet, print the hex-encoded public key \
+						instead of the SS58 address.'")
+					.requires("quiet")
+				)
+				.arg(Arg::from_usage("[keystore-path] --keystore-path <keystore-path> 'Also insert \
+						the generated key into the node keystore in this directory'")
+					.requires("key-type")
+				)
+				.arg(Arg::from_usage("[key-type] --key-type <key-type> 'The key type to insert the \
+						key with, examples: \"gran\", or \"imon\"'")
+					.requires("keystore-path")
+				)
+				.arg(Arg::from_usage("--force-key-type 'Use the key type even if it is none of the \
+						well-known ones listed by `key key-types`'")
+					.requires("key-type")
+				)
+				.arg(Arg::from_usage("[keystore-format] --keystore-format <keystore-format> 'The \
+						format of the keystore file. One of plain (the secret phrase), or encrypted \
+						(the secret phrase encrypted with the password). Default is encrypted if a \
+						password is given, plain otherwise.'")
+					.requires("keystore-path")
+				)
+				.args(&output_file_args())
+				.args(&qr_args()),
 			SubCommand::with_name("generate-node-key")
 				.about("Generate a random node libp2p key, save it to file and print its peer ID")
-				.args_from_usage("[file] 'Name of file to save secret key to'"),
+				.args_from_usage("
+					[file] --file <file> 'Write the secret key to this file, only readable by its \
+						owner, and print only the public key and peer ID'
+					--force 'Replace the file given by --file if it exists'
+					-q, --quiet 'Print nothing but the peer ID'
+				")
+				.arg(Arg::from_usage("[legacy-file] 'Same as --file'")
+					.hidden(true)
+					.conflicts_with("file")
+				),
 			SubCommand::with_name("inspect")
 				.about("Gets a public key and a SS58 address from the provided Secret URI")
 				.args_from_usage("[uri] 'A Key URI to be inspected. May be a secret seed, \
 						secret URI (with derivation paths and password), SS58 or public URI. \
 						If the value is a file, the file content is used as URI. \
 						If not given, you will be prompted for the URI.'
+				")
+				.arg(Arg::from_usage("[uri-list] --uri-list <uri-list> 'Inspect each URI of this \
+						file, one per line. Blank lines and lines starting with # are skipped.'")
+					.conflicts_with("uri")
+				)
+				.arg(Arg::from_usage("[pem-file] --pem-file <pem-file> 'Inspect the private key \
+						of this PEM file, as written by openssl, instead of a URI. Only for ed25519 \
+						and ecdsa. The password decrypts the file, if it is encrypted.'")
+					.conflicts_with_all(&["uri", "uri-list"])
+				)
+				.arg(Arg::from_usage("[vrf] --vrf <label> 'Also print the VRF output and proof \
+						of the secret URI for a transcript with this label, like BABE computes \
+						them. Only for sr25519.'")
+					.conflicts_with("uri-list")
+				)
+				.arg(Arg::from_usage("[vrf-input] --vrf-input <vrf-input> 'The hex-encoded input \
+						appended to the VRF transcript. Default is empty.'")
+					.requires("vrf")
+				)
+				.arg(Arg::from_usage("-q, --quiet 'Print nothing but the SS58 address of the \
+						account for the selected network, on a single line.'")
+					.conflicts_with_all(&["uri-list", "vrf"])
+				)
+				.arg(Arg::from_usage("--public-hex 'With --quiet, print the hex-encoded public key \
+						instead of the SS58 address.'")
+					.requires("quiet")
+				)
+				.args(&output_file_args())
+				.args(&qr_args()),
+			SubCommand::with_name("derive")
+				.about("Apply a derivation junction to a URI and print the derived account")
+				.args_from_usage("
+					<uri> 'The Key URI to derive from. May be a secret URI, SS58 or public URI. \
+						If the value is a file, the file content is used as URI.'
+					<junction> 'The junction to apply: `//name` for a hard or `/name` for a soft \
+						derivation.'
+				"),
+			SubCommand::with_name("derive-from-seed")
+				.about("Apply derivation junctions to a raw seed and print the derived account")
+				.args_from_usage("
+					<seed> 'The hex-encoded raw seed of the scheme, 32 bytes for each of them. \
+						If the value is a file, the file content is used as seed. If the value is \
+						`-`, the seed is read from STDIN.'
+					<junctions> 'The junctions to apply, like `//hard/soft`: `//name` for a hard \
+						and `/name` for a soft derivation.'
+				"),
+			SubCommand::with_name("validate-suri")
+				.about("Check that a secret URI is well-formed and describe its parts, without \
+						printing the account")
+				.args_from_usage("
+					[suri] 'The secret URI to validate. If the value is a file, the file content \
+						is used as URI. If not given, you will be prompted for the URI.'
+				"),
+			SubCommand::with_name("reencode-address")
+				.about("Re-encode SS58 addresses of any network for the network given by --network, \
+						or the default network")
+				.args_from_usage("
+					<address>... 'The SS58 addresses to re-encode.'
 				"),
+			SubCommand::with_name("account-forms")
+				.about("Print the SS58 address of an account id for the network given by \
+						--network, and how each scheme derives account ids from public keys")
+				.args_from_usage("
+					<account-id> 'The hex-encoded 32-byte account id'
+				"),
+			SubCommand::with_name("key")
+				.about("Manage the keys of a node keystore")
+				.setting(AppSettings::SubcommandRequiredElseHelp)
+				.subcommand(SubCommand::with_name("list")
+					.about("List the keys of a node keystore, by key type, with their scheme if \
+							known, public key and SS58 address. Secrets are never printed.")
+					.args_from_usage("
+						<keystore-path> --keystore-path <keystore-path> 'The directory of the \
+							node keystore'
+					")
+				)
+				.subcommand(SubCommand::with_name("schema")
+					.about("Print the JSON Schema of the JSON output of the key commands")
+					.args_from_usage("
+						[command] 'Print the schema of the output of this command only, as \
+							listed without it, e.g. `inspect` or `key list`.'
+					")
+				)
+				.subcommand(SubCommand::with_name("test-vectors")
+					.about("Print reproducible keys and signatures as JSON, for testing other \
+							implementations against")
+					.args_from_usage("
+						--count <count> 'The number of keys to derive.'
+						[seed] --seed <seed> 'The hex-encoded 32 byte seed the keys are derived \
+							from, along `//0`, `//1` and so on. Default is 0x000102...1f.'
+					")
+				)
+				.subcommand(SubCommand::with_name("dev-accounts")
+					.about("List the well-known development accounts, like //Alice and \
+							//Alice//stash, of the scheme given by -e, -k or -s, or of all schemes")
+				)
+				.subcommand(SubCommand::with_name("key-types")
+					.about("List the well-known key types of the keystore, like gran and babe, and \
+							the scheme of their keys")
+				)
+				.subcommand(SubCommand::with_name("remove")
+					.about("Remove a key, or all keys of a type, from a node keystore. The files \
+							to remove are printed, and removed once confirmed.")
+					.args_from_usage("
+						<keystore-path> --keystore-path <keystore-path> 'The directory of the \
+							node keystore'
+						<key-type> --key-type <key-type> 'The key type of the keys to remove, \
+							examples: \"gran\", or \"imon\"'
+						--force-key-type 'Use the key type even if it is none of the well-known \
+							ones listed by `key key-types`'
+						--shred 'Overwrite the files with zeros before removing them'
+						-y, --yes 'Remove the keys without asking, for automation.'
+					")
+					.arg(Arg::from_usage("[public] --public <public> 'The public key of the key to \
+							remove, hex-encoded, or its SS58 address'")
+						.required_unless("all")
+					)
+					.arg(Arg::from_usage("--all 'Remove every key of the key type'")
+						.conflicts_with("public")
+					)
+				)
+				.subcommand(SubCommand::with_name("rotate-password")
+					.about("Rewrite the key files of a node keystore for a new password. The old \
+							password is read from --old-password-filename or prompted for, and the \
+							new one is prompted for twice.")
+					.args_from_usage("
+						<keystore-path> --keystore-path <keystore-path> 'The directory of the \
+							node keystore'
+						[old-password-filename] --old-password-filename <path> 'File that \
+							contains the old password; empty if the keystore has none'
+						[backup-dir] --backup-dir <backup-dir> 'Copy each key file into this \
+							directory before rewriting it. Files holding a secret phrase are only \
+							rewritten with a backup.'
+						--strict 'Rewrite no key file if any can not be rotated, instead of \
+							skipping those'
+					")
+				)
+				.subcommand(SubCommand::with_name("export-json")
+					.about("Export a key as an encrypted JSON file of polkadot-js, which its \
+							extension and apps import. The file is encrypted with the password, \
+							which is required.")
+					.args_from_usage("
+						[suri] --suri <suri> 'The secret key URI. If the value is a file, the \
+							file content is used as URI. If not given, you will be prompted for \
+							the URI. The password is the one of the file, so a password of the URI \
+							has to be given after `///`.'
+						[name] --name <name> 'The name of the account in the file'
+						[genesis-hash] --genesis-hash <genesis-hash> 'The hex-encoded genesis hash \
+							of the chain the account is for'
+					")
+					.args(&output_file_args())
+				)
+				.subcommand(SubCommand::with_name("import-json")
+					.about("Decrypt an encrypted JSON file of polkadot-js with the password, and \
+							print its key, or insert it into a node keystore. Secrets are only \
+							printed with --show-secret.")
+					.args_from_usage("<file> 'The JSON file'")
+					.arg(Arg::from_usage("[keystore-path] --keystore-path <keystore-path> 'Insert \
+							the key into the node keystore in this directory instead of printing \
+							it. sr25519 keys can not be inserted, since polkadot-js only exports \
+							their expanded secret key, which is no secret URI.'")
+						.requires("key-type")
+					)
+					.arg(Arg::from_usage("[key-type] --key-type <key-type> 'The key type to insert \
+							the key with, examples: \"gran\", or \"imon\"'")
+						.requires("keystore-path")
+					)
+					.arg(Arg::from_usage("--force-key-type 'Use the key type even if it is none of \
+							the well-known ones listed by `key key-types`'")
+						.requires("key-type")
+					)
+					.arg(Arg::from_usage("[keystore-format] --keystore-format <keystore-format> \
+							'The format of the keystore file. One of plain (the hex-encoded secret \
+							key), or encrypted (the secret key encrypted with the password of the \
+							JSON file). Default is plain.'")
+						.requires("keystore-path")
+					)
+				)
+				.subcommand(SubCommand::with_name("rotate-session-keys")
+					.about("Have a node generate new session keys with author_rotateKeys, and \
+							print them as the hex-encoded concatenation that is set on chain, and \
+							split into their public keys")
+					.args_from_usage("
+						[node-url] --node-url <node-url> 'Node JSON-RPC endpoint, over http://, \
+							ws:// or wss://, default \"http://localhost:9933\"'
+					")
+					.args(&key_types::session_key_layout_args())
+				)
+				.subcommand(SubCommand::with_name("decode-session-keys")
+					.about("Split hex-encoded session keys, as set on chain or returned by \
+							author_rotateKeys, into their public keys")
+					.args_from_usage("<session-keys> 'The hex-encoded session keys'")
+					.args(&key_types::session_key_layout_args())
+				)
+				.subcommand(SubCommand::with_name("convert-node-key")
+					.about("Convert a node key between the raw secret key nodes read from \
+							--node-key-file, its hex encoding and the protobuf keypair of libp2p")
+					.args_from_usage("
+						--input <input> 'The file to read the node key from'
+						--from <from> 'The format of the input. One of raw, hex or protobuf.'
+						--to <to> 'The format of the output. One of raw, hex or protobuf.'
+					")
+					.args(&output_file_args())
+				)
+				.subcommand(SubCommand::with_name("batch")
+					.about("Run the generate, inspect and derive operations listed in a JSON \
+							manifest and print their results as a JSON array, in the same order")
+					.args_from_usage("
+						<manifest> 'The JSON file with an array of operations: objects with \
+							`operation` (generate, inspect or derive) and optionally `scheme` and \
+							`network`. generate takes optional `words`, inspect a `uri`, and derive a \
+							`uri` and a `junction`.'
+						--show-secret 'Include the secret phrases, URIs and seeds in the results.'
+					")
+				)
+				.subcommand(SubCommand::with_name("derive-many")
+					.about("Derive a number of accounts from a secret URI along a path template \
+							and print them as CSV or JSON lines")
+					.args_from_usage("
+						[suri] --suri <suri> 'The secret URI to derive from. \
+							If the value is a file, the file content is used as URI. \
+							If not given, you will be prompted for the URI.'
+						--count <count> 'The number of accounts to derive.'
+						[path-template] --path-template <path-template> 'The derivation path of \
+							each account, where `{i}` is replaced by its index. Default is \
+							`//{i}`.'
+						[format] --format <format> 'One of csv or jsonl (JSON lines). Default is \
+							csv.'
+					")
+					.args(&output_file_args())
+				)
+				.subcommand(SubCommand::with_name("hash")
+					.about("Hash data with one of the hashers the runtime applies to storage keys \
+							and messages")
+					.args_from_usage("
+						[algo] --algo <algo> 'The hasher. One of blake2_128, blake2_256, twox_64, \
+							twox_128, keccak_256 or sha2_256. Default is blake2_256.'
+						--concat 'Append the data to its digest, like blake2_128_concat and \
+							twox_64_concat do'
+						-h, --hex 'The data on STDIN is hex-encoded'
+						[data] 'The data to hash. Hex-encoded if it starts with 0x, the string \
+							itself otherwise. If not given, it is read from STDIN.'
+					")
+				)
+				.subcommand(SubCommand::with_name("storage-key")
+					.about("Compute the storage key of a storage value or map entry, e.g. for \
+							`state_getStorage`")
+					.args_from_usage("
+						--pallet <pallet> 'The storage prefix of the pallet, usually its name, \
+							e.g. System'
+						--item <item> 'The name of the storage item, e.g. Account'
+					")
+					.arg(Arg::from_usage("[key] --key <key>... 'A hex-encoded, SCALE-encoded map \
+							key. Repeat once per key of a double map, each with its --hasher.'")
+						.number_of_values(1)
+						.requires("hasher")
+					)
+					.arg(Arg::from_usage("[hasher] --hasher <hasher>... 'The hasher of the map \
+							key given at the same position. One of blake2_128, blake2_256, \
+							blake2_128_concat, twox_128, twox_256, twox_64_concat or identity.'")
+						.number_of_values(1)
+						.requires("key")
+					)
+				)
+				.subcommand(SubCommand::with_name("multisig")
+					.about("Inspect the pallet-multisig account of the given signatories and \
+							threshold")
+					.args_from_usage("
+						-t, --threshold <threshold> 'The number of approvals required to dispatch \
+							a call'
+					")
+					.arg(Arg::from_usage("--signatory <signatory>... 'A signatory account, SS58 \
+							(any network) or hex-encoded. Repeat once per signatory.'")
+						.number_of_values(1)
+					)
+				)
+				.subcommand(SubCommand::with_name("pure-proxy")
+					.about("Inspect the account a pallet-proxy `anonymous` call spawns")
+					.args_from_usage("
+						--spawner <spawner> 'The account calling `anonymous`, SS58 (any network) \
+							or hex-encoded'
+						--proxy-type <proxy-type> 'The proxy type, by name (any, non-transfer, \
+							governance, staking) or by its numeric index'
+						--index <index> 'The disambiguation index passed to `anonymous`'
+						--block-height <block-height> 'The number of the block the call was \
+							included in'
+						--ext-index <ext-index> 'The index of the extrinsic within that block'
+					")
+				),
 			SubCommand::with_name("inspect-node-key")
-				.about("Print the peer ID corresponding to the node key in the given file")
-				.args_from_usage("[file] 'Name of file to read the secret key from'"),
+				.about("Print the peer ID and public key of a node key, read from a file, the \
+						command line or STDIN")
+				.args_from_usage("
+					[file] 'Name of file to read the raw secret key from. If neither it nor --hex \
+						is given, the raw secret key is read from STDIN.'
+					-q, --quiet 'Print nothing but the peer ID'
+				")
+				.arg(Arg::from_usage("[hex] --hex <secret> 'The hex-encoded secret key'")
+					.conflicts_with("file")
+				),
 			SubCommand::with_name("sign")
 				.about("Sign a message, provided on STDIN, with a given (secret) key")
 				.args_from_usage("
 					-h, --hex 'The message on STDIN is hex-encoded data'
-					<suri> 'The secret key URI. \
+					--wrap-bytes 'Sign the message wrapped in `<Bytes>`...`</Bytes>`, like polkadot-js does'
+					[signature-format] --signature-format <signature-format> 'The encoding of the \
+						signature. One of hex, base64, multisig-hex (a SCALE-encoded MultiSignature). \
+						Default is hex.'
+				")
+				.arg(Arg::from_usage("[encoding] --encoding <encoding> 'The encoding of the message \
+						on STDIN. One of hex or base64; --hex is the same as --encoding hex.'")
+					.conflicts_with("hex")
+				)
+				.arg(Arg::from_usage("<suri> 'The secret key URI. \
 						If the value is a file, the file content is used as URI. \
-						If not given, you will be prompted for the URI.'
+						If not given, you will be prompted for the URI.'")
+					.required_unless_one(&["keystore-path", "remote-signer"])
+					.conflicts_with("address")
+				)
+				.arg(Arg::from_usage("[keystore-path] --keystore-path <keystore-path> 'Sign with a key \
+						of the node keystore in this directory instead of a secret key URI. If the \
+						keystore is password-protected and no password is given, you will be prompted \
+						for it.'")
+					.conflicts_with("suri")
+					.requires_all(&["key-type", "address"])
+				)
+				.arg(Arg::from_usage("[key-type] --key-type <key-type> 'The four-character type of the \
+						keystore key, e.g. gran or babe.'")
+					.requires("keystore-path")
+				)
+				.arg(Arg::from_usage("--force-key-type 'Use the key type even if it is none of the \
+						well-known ones listed by `key key-types`'")
+					.requires("key-type")
+				)
+				.arg(Arg::from_usage("[address] --address <address> 'The SS58 address or hex-encoded \
+						public key of the keystore or remote signer key.'")
+				)
+				.arg(Arg::from_usage("[remote-signer] --remote-signer <url> 'Have the signer daemon \
+						at this JSON-RPC URL sign instead of signing with a secret key URI. The \
+						returned signature is verified before it is printed.'")
+					.conflicts_with("keystore-path")
+					.requires("address")
+				)
+				.arg(Arg::from_usage("[hash] --hash <hash> 'Sign the digest of the message, hashed \
+						with one of blake2-256, keccak-256 or sha-256, instead of the message itself. The \
+						message is streamed, so it may be arbitrarily large. Note that ecdsa additionally \
+						hashes the digest with blake2-256, as it does with any message.'")
+					.conflicts_with("wrap-bytes")
+				)
+				.args(&output_file_args()),
+			SubCommand::with_name("sign-file")
+				.about("Sign a file with a given (secret) key, and write the detached signature \
+						next to it")
+				.args_from_usage("
+					<file> 'The file to sign'
+					[suri] 'The secret key URI. If the value is a file, the file content is used \
+						as URI. If not given, you will be prompted for the URI.'
+					[signature-file] --signature-file <signature-file> 'Write the signature to \
+						this file instead of to the signed file with `.sig` appended'
+					[signature-format] --signature-format <signature-format> 'The encoding of the \
+						signature. One of hex, base64, multisig-hex (a SCALE-encoded MultiSignature). \
+						Default is hex.'
+					[hash] --hash <hash> 'Sign the digest of the file, hashed with one of \
+						blake2-256, keccak-256 or sha-256, instead of its content. The file is \
+						streamed, so it may be arbitrarily large.'
 				"),
 			SubCommand::with_name("sign-transaction")
-				.about("Sign transaction from encoded Call. Returns a signed and encoded \
-						UncheckedMortalCompactExtrinsic as hex.")
+				.about("Sign transaction from encoded Call, entirely offline. Returns a signed and encoded \
+						UncheckedExtrinsic as hex, ready for `author_submitExtrinsic`.")
 				.args_from_usage("
 					-c, --call <call> 'The call, hex-encoded.'
 					-n, --nonce <nonce> 'The nonce.'
-					-p, --password <password> 'The password for the key.'
-					-h, --prior-block-hash <prior-block-hash> 'The prior block hash, hex-encoded.'
-					-s, --suri <suri> 'The secret key URI.'
-				"),
+					[password] -p, --password <password> 'The password for the key.'
+					[prior-block-hash] -h, --prior-block-hash <prior-block-hash> 'The hash of the \
+						block given by --block-number, hex-encoded. Required for --era.'
+					[block-number] --block-number <block-number> 'The number of the block the mortal \
+						era starts at. Required for --era.'
+					[suri] -s, --suri <suri> 'The secret key URI. \
+						If the value is a file, the file content is used as URI. \
+						If not given, you will be prompted for the URI.'
+					[genesis] -g, --genesis-hash <genesis> 'The genesis hash or a recognized chain \
+						identifier (elm, alex). Default is alex.'
+					[spec-version] --spec-version <spec-version> 'The spec version of the runtime. \
+						Default is the one of the node runtime subkey is built with.'
+					[transaction-version] --transaction-version <transaction-version> 'The \
+						transaction version of the runtime. Default is the one of the node runtime \
+						subkey is built with.'
+					[tip] --tip <tip> 'The tip for the block author. Default is 0.'
+					--show-payload 'Print the signed payload as hex and a breakdown of its signed \
+						extensions to stderr before signing.'
+					--confirm 'Show the payload like --show-payload and ask for confirmation \
+						before signing.'
+					-y, --yes 'Confirm signing without asking, for automation.'
+				")
+				.arg(Arg::from_usage("[era] --era <era> 'Make the transaction mortal, valid for this \
+						many blocks from --block-number on. Rounded up to a power of two. Default is an \
+						immortal transaction.'")
+					.requires_all(&["prior-block-hash", "block-number"])
+				),
 			SubCommand::with_name("transfer")
 				.about("Author and sign a Node pallet_balances::Transfer transaction with a given (secret) key")
 				.args_from_usage("
@@ -303,110 +576,187 @@ fn get_app<'a, 'b>(usage: &'a str) -> App<'a, 'b> {
 				.args_from_usage("
 					-n, --number <number> 'Number of keys to generate'
 					<pattern> 'Desired pattern'
-				"),
+					--prefix 'Only accept an address starting with the pattern, which includes the \
+						leading characters of the network, like 5 for substrate. Ctrl-C stops the \
+						search.'
+				")
+				.arg(Arg::from_usage("[max-attempts] --max-attempts <max-attempts> 'Give up the \
+						search for a prefix after this many keys'")
+					.requires("prefix")
+				)
+				.args(&output_file_args()),
 			SubCommand::with_name("verify")
 				.about("Verify a signature for a message, provided on STDIN, with a given \
 						(public or secret) key")
+				.arg(Arg::from_usage("<sig> 'Signature, encoded as given by --signature-format.'")
+					.required_unless_one(&["sig-file", "batch"])
+				)
 				.args_from_usage("
 					-h, --hex 'The message on STDIN is hex-encoded data'
-					<sig> 'Signature, hex-encoded.'
-					<uri> 'The public or secret key URI. \
+					--wrap-bytes 'The message was signed wrapped in `<Bytes>`...`</Bytes>`, like \
+						polkadot-js does'
+					[signature-format] --signature-format <signature-format> 'The encoding of the \
+						signature. One of hex, base64, multisig-hex (a SCALE-encoded MultiSignature). \
+						Default is hex.'
+					[uri] 'The public key URI: an SS58 address of any network or a hex-encoded \
+						public key. `@<path>` reads either from the file at <path>. \
 						If the value is a file, the file content is used as URI. \
 						If not given, you will be prompted for the URI.'
-				"),
+				")
+				.arg(Arg::from_usage("[encoding] --encoding <encoding> 'The encoding of the message \
+						on STDIN. One of hex or base64; --hex is the same as --encoding hex.'")
+					.conflicts_with("hex")
+				)
+				.arg(Arg::from_usage("[sig-file] --sig-file <sig-file> 'Read the signature from this \
+						file instead, either raw or encoded as given by --signature-format. The URI is \
+						then the only positional argument.'")
+					.conflicts_with("uri")
+				)
+				.arg(Arg::from_usage("--auto-wrap-bytes 'Accept the signature for either the message \
+						or the message wrapped in `<Bytes>`...`</Bytes>`'")
+					.conflicts_with("wrap-bytes")
+				)
+				.arg(Arg::from_usage("--recover 'If the URI is an account id that is not the public \
+						key itself, as for ecdsa, recover the public key from the signature and \
+						check that it is the one of the account.'"))
+				.arg(Arg::from_usage("[scheme] --scheme <scheme> 'The cryptography of the \
+						signature, one of sr25519, ed25519, ecdsa or auto, instead of the one \
+						selected by -e, -k or -s. auto tries ed25519, sr25519 and ecdsa in turn, as \
+						far as the lengths of the signature and public key fit them, reporting the \
+						one the signature is valid for. The detection is best-effort.'"))
+				.arg(Arg::from_usage("-q, --quiet 'Print nothing; only the exit code tells whether the \
+						signature is valid (0), invalid (1) or could not be checked (2)'"))
+				.arg(Arg::from_usage("--print-message 'Print the message instead of the result if the \
+						signature is valid, for scripts that only use signed input. The message, after \
+						decoding, has to be UTF-8.'")
+					.conflicts_with_all(&["quiet", "hash", "batch"])
+				)
+				.arg(Arg::from_usage("[hash] --hash <hash> 'Verify the digest of the message, hashed \
+						with one of blake2-256, keccak-256 or sha-256, instead of the message itself. The \
+						message is streamed, so it may be arbitrarily large. Note that ecdsa additionally \
+						hashes the digest with blake2-256, as it does with any message.'")
+					.conflicts_with_all(&["wrap-bytes", "auto-wrap-bytes"])
+				)
+				.arg(Arg::from_usage("[batch] --batch <batch> 'Verify each entry of this JSON file, an \
+						array of objects with `message`, `signature`, `address` and optionally `scheme`, \
+						and print a JSON report. The messages are encoded as given by --encoding. The \
+						scheme defaults to the selected cryptography.'")
+					.conflicts_with_all(&["sig", "sig-file", "uri", "auto-wrap-bytes", "hash"])
+				)
+				.arg(Arg::from_usage("--allow-failures 'Exit with 0 even if entries of --batch are \
+						invalid or malformed'")
+					.requires("batch")
+				)
+				.args(&output_file_args()),
 			SubCommand::with_name("insert")
 				.about("Insert a key to the keystore of a node")
 				.args_from_usage("
 					<suri> 'The secret key URI. \
 						If the value is a file, the file content is used as URI. \
-						If not given, you will be prompted for the URI.'
+						If the value is `-`, the URI is read from STDIN, or prompted for if STDIN \
+						is a terminal.'
 					<key-type> 'Key type, examples: \"gran\", or \"imon\" '
-					[node-url] 'Node JSON-RPC endpoint, default \"http:://localhost:9933\"'
-				"),
+					[node-url] 'Node JSON-RPC endpoint, over http://, ws:// or wss://, default \
+						\"http://localhost:9933\"'
+				")
+				.arg(Arg::from_usage("[keystore-path] --keystore-path <keystore-path> 'Insert the \
+						key into the node keystore in this directory, instead of into the keystore \
+						of a running node. The keystore is encrypted with the password, if given.'")
+					.conflicts_with("node-url")
+				)
+				.arg(Arg::from_usage("[keystore-format] --keystore-format <keystore-format> 'The \
+						format of the keystore file. One of plain (the secret URI as it is given), \
+						or encrypted (the secret URI encrypted with the password). Default is \
+						encrypted if a password is given, plain otherwise.'")
+					.requires("keystore-path")
+				)
+				.arg(Arg::from_usage("--no-verify 'Do not check with author_hasKey that the node \
+						has the key after inserting it'")
+					.conflicts_with("keystore-path")
+				)
+				.arg(Arg::from_usage("--force-key-type 'Use the key type even if it is none of the \
+						well-known ones listed by `key key-types`'")),
 			SubCommand::with_name("moduleid")
 				.about("Inspect a module ID address")
 				.args_from_usage("
 					<id> 'The module ID used to derive the account'
-				")
+				"),
 		])
 }
 
-fn main() -> Result<(), Error> {
+fn main() {
 	let usage = get_usage();
-	let matches = get_app(&usage).get_matches();
-
-	if matches.is_present("ed25519") {
-		return execute::<Ed25519>(matches);
-	}
-	if matches.is_present("secp256k1") {
-		return execute::<Ecdsa>(matches)
-	}
-	return execute::<Sr25519>(matches)
-}
-
-/// Get `URI` from CLI or prompt the user.
-///
-/// `URI` is extracted from `matches` by using `match_name`.
-///
-/// If the `URI` given as CLI argument is a file, the file content is taken as `URI`.
-/// If no `URI` is given to the CLI, the user is prompted for it.
-fn get_uri(match_name: &str, matches: &ArgMatches) -> Result<String, Error> {
-	let uri = if let Some(uri) = matches.value_of(match_name) {
-		let file = PathBuf::from(uri);
-		if file.is_file() {
-			fs::read_to_string(uri)?
-				.trim_end()
-				.into()
-		} else {
-			uri.into()
+	// `std::env::args` panics on arguments that are not valid UTF-8, which clap reports instead.
+	let args = std::env::args_os().map(|arg| arg.to_string_lossy().into_owned());
+	let error_format = ErrorFormat::from_args(args);
+	let matches = get_app(&usage).get_matches_safe().unwrap_or_else(|e| {
+		if e.use_stderr() {
+			let message = match error_format {
+				ErrorFormat::Text => &e.message[..],
+				ErrorFormat::Json => e.message.trim_start_matches("error: ").trim_end(),
+			};
+			error_format.exit(ErrorKind::Usage, message);
 		}
+		println!("{}", e.message);
+		std::process::exit(0);
+	});
+
+	let scheme = if matches.is_present("ed25519") {
+		CryptoScheme::Ed25519
+	} else if matches.is_present("secp256k1") {
+		CryptoScheme::Ecdsa
 	} else {
-		rpassword::read_password_from_tty(Some("URI: "))?
+		CryptoScheme::Sr25519
 	};
-
-	Ok(uri)
-}
-
-#[derive(derive_more::Display, derive_more::From)]
-enum Error {
-	Static(&'static str),
-	Io(std::io::Error),
-	Formatted(String),
-}
-
-impl fmt::Debug for Error {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		fmt::Display::fmt(self, f)
+	let result = with_crypto_scheme!(scheme, execute::<_>(matches));
+	if let Err(e) = result {
+		let message = match error_format {
+			ErrorFormat::Text => format!("Error: {}", e),
+			ErrorFormat::Json => e.to_string(),
+		};
+		error_format.exit(e.kind(), &message);
 	}
 }
 
-fn static_err(msg: &'static str) -> Result<(), Error> {
-	Err(Error::Static(msg))
-}
-
 fn execute<C: Crypto>(matches: ArgMatches) -> Result<(), Error>
 where
 	SignatureOf<C>: SignatureT,
 	PublicOf<C>: PublicT,
 {
-	let password_interactive = matches.is_present("password-interactive");
-	let password = matches.value_of("password");
-
-	let password = if password.is_some() && password_interactive {
-		return static_err("`--password` given and `--password-interactive` selected!");
-	} else if password_interactive {
-		Some(
-			rpassword::read_password_from_tty(Some("Key password: "))?
-		)
-	} else {
-		password.map(Into::into)
+	let password = read_password_source(
+		&matches,
+		prompt_secret,
+		|var| std::env::var(var).ok(),
+	)?;
+
+	let password = match password {
+		Some(password) if matches.is_present("password-interactive")
+			&& matches.is_present("password-kdf") =>
+		{
+			let params = read_kdf_params(&matches)?;
+			eprintln!(
+				"Note: the password is stretched with argon2id (memory {} KiB, {} passes, salt `{}`); \
+				the resulting keys differ from those of the password itself.",
+				params.memory_kib,
+				params.passes,
+				params.salt,
+			);
+			Some(SecretString::new(stretch_password(password.expose_secret(), &params)?))
+		},
+		_ if matches.is_present("password-kdf") => {
+			return static_err("`--password-kdf` requires `--password-interactive`!");
+		},
+		password => password,
 	};
-	let password = password.as_ref().map(String::as_str);
+	let password = password.as_ref().map(|password| password.expose_secret().as_str());
+
+	if let Some(network) = matches.value_of("custom-network") {
+		register_network(network)?;
+	}
 
 	let maybe_network: Option<Ss58AddressFormat> = match matches.value_of("network").map(|network| {
-		network
-			.try_into()
-			.map_err(|_| Error::Static("Invalid network name. See --help for available networks."))
+		parse_network(network)
+			.ok_or(Error::Static("Invalid network name. See --help for available networks."))
 	}) {
 		Some(Err(e)) => return Err(e),
 		Some(Ok(v)) => Some(v),
@@ -422,408 +772,739 @@ where
 		Some(Ok(v)) => v,
 		None => OutputType::Text,
 	 };
+	if let Some(Err(_)) = matches.value_of("error-format").map(ErrorFormat::try_from) {
+		return Err(Error::Static("Invalid error format name; expecting text or json."));
+	}
+	let to_file = match matches.subcommand() {
+		("key", Some(key_matches)) => key_matches.subcommand().1,
+		(_, matches) => matches,
+	}.map_or(false, |matches| matches.is_present("output-file"));
+	let output = match output {
+		OutputType::Table(_) => {
+			let style = TableStyle::from_matches(&matches);
+			OutputType::Table(TableStyle { aligned: style.aligned && !to_file, ..style })
+		},
+		output => output,
+	};
+	if let OutputType::Binary = output {
+		match matches.subcommand_name() {
+			Some("generate") | Some("inspect") | Some("derive") => {},
+			_ => return static_err("Binary output is only supported by generate, inspect and \
+				derive"),
+		}
+		if !to_file && atty::is(atty::Stream::Stdout) && !matches.is_present("force-binary") {
+			return static_err("Refusing to write binary output to a terminal; see --force-binary");
+		}
+	}
+	let secrets = Secrets::from_matches(&matches);
+	// Text output redirected to a file or pipe ends up in logs; the other outputs are for machines.
+	let prints_secrets = match matches.subcommand() {
+		("generate", Some(matches)) | ("inspect", Some(matches)) => !matches.is_present("quiet"),
+		("generate-node-key", Some(matches)) => !matches.is_present("quiet")
+			&& !matches.is_present("file") && !matches.is_present("legacy-file"),
+		("derive", _) | ("derive-from-seed", _) | ("vanity", _) => true,
+		("key", Some(matches)) => match matches.subcommand() {
+			("import-json", Some(matches)) => !matches.is_present("keystore-path"),
+			("convert-node-key", Some(matches)) => !matches.is_present("output-file"),
+			_ => false,
+		},
+		_ => false,
+	};
+	if prints_secrets && secrets != Secrets::Hide && matches!(output, OutputType::Text) && !to_file
+		&& !atty::is(atty::Stream::Stdout) && !matches.is_present("yes")
+	{
+		return static_err("Refusing to print secrets to STDOUT, which is not a terminal; see --yes \
+			or --hide-secret");
+	}
 
 	match matches.subcommand() {
 		("generate", Some(matches)) => {
-			let mnemonic = generate_mnemonic(matches)?;
-			C::print_from_uri(mnemonic.phrase(), password, maybe_network, output);
-		}
+			account::run_generate::<C>(matches, password, maybe_network, output, secrets)?
+		},
 		("generate-node-key", Some(matches)) => {
-			let file = matches.value_of("file").ok_or(Error::Static("Output file name is required"))?;
+			node_key::run_generate_node_key(matches, output, secrets)?
+		},
+		("inspect", Some(matches)) => {
+			account::run_inspect::<C>(matches, password, maybe_network, output, secrets)?
+		},
+		("derive", Some(matches)) => {
+			derive::run_derive::<C>(matches, password, maybe_network, output, secrets)?
+		},
+		("derive-from-seed", Some(matches)) => {
+			derive::run_derive_from_seed::<C>(matches, maybe_network, output, secrets)?
+		},
+		("validate-suri", Some(matches)) => {
+			derive::run_validate_suri::<C>(matches, password, output)?
+		},
+		("reencode-address", Some(matches)) => {
+			account::run_reencode_address(matches, maybe_network, output)?
+		},
+		("account-forms", Some(matches)) => {
+			account::run_account_forms(matches, maybe_network, output)?
+		},
+		("key", Some(key_matches)) => match key_matches.subcommand() {
+			("schema", Some(schema_matches)) => json::run_schema(schema_matches, output)?,
+			("test-vectors", Some(vectors_matches)) => {
+				sign::run_test_vectors::<C>(vectors_matches, maybe_network, output)?
+			},
+			("dev-accounts", Some(_)) => {
+				account::run_dev_accounts::<C>(&matches, maybe_network, output)?
+			},
+			("key-types", Some(_)) => key::run_key_types(&matches, output)?,
+			("remove", Some(remove_matches)) => key::run_remove(remove_matches)?,
+			("list", Some(list_matches)) => {
+				key::run_list(list_matches, output, TableStyle::from_matches(&matches))?
+			},
+			("rotate-password", Some(rotate_matches)) => key::run_rotate_password(rotate_matches)?,
+			("export-json", Some(export_matches)) => {
+				key::run_export_json::<C>(export_matches, password, output)?
+			},
+			("import-json", Some(import_matches)) => {
+				key::run_import_json::<C>(import_matches, password, output, secrets)?
+			},
+			("rotate-session-keys", Some(rotate_matches)) => {
+				let style = TableStyle::from_matches(&matches);
+				key::run_rotate_session_keys(rotate_matches, output, style)?
+			},
+			("decode-session-keys", Some(decode_matches)) => {
+				let style = TableStyle::from_matches(&matches);
+				key::run_decode_session_keys(decode_matches, output, style)?
+			},
+			("convert-node-key", Some(convert_matches)) => {
+				node_key::run_convert_node_key(convert_matches)?
+			},
+			("derive-many", Some(derive_matches)) => {
+				derive::run_derive_many::<C>(derive_matches, password, output)?
+			},
+			("hash", Some(hash_matches)) => hash::run_hash(hash_matches)?,
+			("storage-key", Some(storage_matches)) => {
+				hash::run_storage_key(storage_matches, output)?
+			},
+			("batch", Some(batch_matches)) => {
+				batch::run_batch::<C>(batch_matches, password, maybe_network, output)?
+			},
+			("multisig", Some(multisig_matches)) => account::run_multisig::<C>(
+				multisig_matches,
+				password,
+				maybe_network,
+				output,
+				secrets,
+			)?,
+			("pure-proxy", Some(proxy_matches)) => account::run_pure_proxy::<C>(
+				proxy_matches,
+				password,
+				maybe_network,
+				output,
+				secrets,
+			)?,
+			_ => unreachable!("a subcommand is required; qed"),
+		},
+		("inspect-node-key", Some(matches)) => node_key::run_inspect_node_key(matches, output)?,
+		("sign", Some(matches)) => sign::run_sign::<C>(matches, password, output)?,
+		("verify", Some(matches)) => sign::run_verify::<C>(matches, output)?,
+		("vanity", Some(matches)) => {
+			vanity::run_vanity::<C>(matches, maybe_network, output, secrets)?
+		},
+		("transfer", Some(matches)) => transaction::run_transfer::<C>(matches, password)?,
+		("sign-file", Some(matches)) => sign::run_sign_file::<C>(matches, password, output)?,
+		("sign-transaction", Some(matches)) => {
+			transaction::run_sign_transaction::<C>(matches, password)?
+		},
+		("insert", Some(matches)) => key::run_insert::<C>(matches, password)?,
+		("moduleid", Some(matches)) => {
+			account::run_moduleid::<C>(matches, password, maybe_network, output, secrets)?
+		},
+		_ => print_usage(&matches),
+	}
+
+	Ok(())
+}
 
-			let keypair = libp2p_ed25519::Keypair::generate();
-			let secret = keypair.secret();
-			let peer_id = PublicKey::Ed25519(keypair.public()).into_peer_id();
+fn print_usage(matches: &ArgMatches) {
+	println!("{}", matches.usage());
+}
 
-			fs::write(file, secret.as_ref())?;
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use node_primitives::{AccountId, Balance, Index};
+	use node_runtime::{BalancesCall, Call, Runtime, SignedPayload, UncheckedExtrinsic, VERSION};
+	use sp_core::{blake2_256, crypto::{KeyTypeId, Ss58Codec}, ed25519, Pair, H256};
+	use sp_runtime::{generic::Era, traits::{IdentifyAccount, Verify}};
+	use std::io::{self, Write};
+	use subkey::{
+		account::{generate_mnemonic, parse_public_key},
+		crypto::{AccountPublic, Ecdsa, Ed25519, Sr25519},
+		format::{format_public_key, format_seed},
+		hash::HashAlgo,
+		input::{
+			decode_base64, encode_base64, prompt_secret_with, read_hash_algo, read_message_encoding,
+			read_message_from, read_password, MessageEncoding,
+		},
+		key::{read_key_type, read_keystore_pair},
+		sign::{
+			do_sign, do_verify, do_verify_any_wrapping, read_pair, read_signature_format,
+			wrap_bytes, MessageWrapping, SignatureFormat,
+		},
+		transaction::{create_extrinsic, decode_call, read_extrinsic_params, read_genesis_hash},
+	};
 
-			println!("{}", peer_id);
-		}
-		("inspect", Some(matches)) => {
-			C::print_from_uri(&get_uri("uri", &matches)?, password, maybe_network, output);
-		}
-		("inspect-node-key", Some(matches)) => {
-			let file = matches.value_of("file").ok_or(Error::Static("Input file name is required"))?;
+	fn test_generate_sign_verify<CryptoType: Crypto>()
+	where
+		SignatureOf<CryptoType>: SignatureT,
+		PublicOf<CryptoType>: PublicT,
+	{
+		let usage = get_usage();
+		let app = get_app(&usage);
+		let password = None;
 
-			let mut file_content = fs::read(file)?;
-			let secret = libp2p_ed25519::SecretKey::from_bytes(&mut file_content)
-				.map_err(|_| Error::Static("Bad node key file"))?;
-			let keypair = libp2p_ed25519::Keypair::from(secret);
-			let peer_id = PublicKey::Ed25519(keypair.public()).into_peer_id();
+		// Generate public key and seed.
+		let arg_vec = vec!["subkey", "generate"];
 
-			println!("{}", peer_id);
-		}
-		("sign", Some(matches)) => {
-			let suri = get_uri("suri", &matches)?;
-			let should_decode = matches.is_present("hex");
+		let matches = app.clone().get_matches_from(arg_vec);
+		let matches = matches.subcommand().1.unwrap();
+		let mnemonic = generate_mnemonic(matches.value_of("words")).expect("generate failed");
+
+		let (pair, seed) =
+			<<CryptoType as Crypto>::Pair as Pair>::from_phrase(mnemonic.phrase(), password)
+				.unwrap();
+		let public_key = CryptoType::public_from_pair(&pair);
+		let public_key = format_public_key(public_key);
+		let seed = format_seed(&seed);
+		let message = "Blah Blah\n".as_bytes().to_vec();
+
+		let pair = read_pair::<CryptoType>(Some(&seed), password).expect("reading pair failed");
+		let signature = do_sign::<CryptoType>(&pair, &message, SignatureFormat::Hex);
+
+		// Verify the previous signature.
+		let arg_vec = vec!["subkey", "verify", &signature[..], &public_key[..]];
+
+		let matches = get_app(&usage).get_matches_from(arg_vec);
+		let matches = matches.subcommand().1.unwrap();
+
+		assert!(do_verify::<CryptoType>(matches, &public_key, message).expect("verify failed"));
+	}
+
+	#[test]
+	fn generate_sign_verify_should_work_for_ed25519() {
+		test_generate_sign_verify::<Ed25519>();
+	}
+
+	#[test]
+	fn generate_sign_verify_should_work_for_sr25519() {
+		test_generate_sign_verify::<Sr25519>();
+	}
+
+	fn test_signature_format_roundtrip<CryptoType: Crypto>()
+	where
+		SignatureOf<CryptoType>: SignatureT,
+		PublicOf<CryptoType>: PublicT,
+	{
+		let usage = get_usage();
+		let pair = CryptoType::pair_from_suri("//Alice", None).unwrap();
+		let public_key = format_public_key(pair.public());
+		let message = b"Blah Blah\n".to_vec();
+
+		for format in &["hex", "base64", "multisig-hex"] {
+			let format_value = SignatureFormat::try_from(*format).unwrap();
+			let signature = do_sign::<CryptoType>(&pair, &message, format_value);
 
-			let message = read_message_from_stdin(should_decode)?;
-			let signature = do_sign::<C>(&suri, message, password)?;
-			println!("{}", signature);
+			let arg_vec = vec!["subkey", "verify", "--signature-format", format, &signature, &public_key];
+			let matches = get_app(&usage).get_matches_from(arg_vec);
+			let matches = matches.subcommand().1.unwrap();
+
+			assert_eq!(read_signature_format(matches).unwrap(), format_value);
+			assert!(do_verify::<CryptoType>(matches, &public_key, message.clone()).expect("verify failed"));
 		}
-		("verify", Some(matches)) => {
-			let uri = get_uri("uri", &matches)?;
-			let should_decode = matches.is_present("hex");
-
-			let message = read_message_from_stdin(should_decode)?;
-			let is_valid_signature = do_verify::<C>(matches, &uri, message)?;
-			if is_valid_signature {
-				println!("Signature verifies correctly.");
-			} else {
-				return static_err("Signature invalid.");
+	}
+
+	#[test]
+	fn message_encodings_roundtrip() {
+		let usage = get_usage();
+		let pair = Sr25519::pair_from_suri("//Alice", None).unwrap();
+		let public_key = format_public_key(pair.public());
+		let message = b"Blah Blah\n".to_vec();
+		let encoding = |args: &[&str]| {
+			let matches = get_app(&usage).get_matches_from(args);
+			read_message_encoding(matches.subcommand().1.unwrap())
+		};
+
+		assert_eq!(decode_base64(encode_base64(&message)).unwrap(), message);
+		assert_eq!(encoding(&["subkey", "sign", "//Alice"]).unwrap(), MessageEncoding::Raw);
+		let hex_encoding = encoding(&["subkey", "sign", "--hex", "//Alice"]).unwrap();
+		assert_eq!(hex_encoding, MessageEncoding::Hex);
+		assert!(encoding(&["subkey", "sign", "--encoding", "base58", "//Alice"]).is_err());
+
+		let encoded = [("hex", hex::encode(&message)), ("base64", encode_base64(&message))];
+		for (name, encoded_message) in &encoded {
+			let signing = encoding(&["subkey", "sign", "--encoding", name, "//Alice"]).unwrap();
+			let signed = read_message_from(encoded_message.as_bytes(), signing).unwrap();
+			assert_eq!(signed, message);
+
+			for format in &["hex", "base64"] {
+				let format_value = SignatureFormat::try_from(*format).unwrap();
+				let signature = do_sign::<Sr25519>(&pair, &signed, format_value);
+				let arg_vec = vec![
+					"subkey", "verify", "--encoding", name, "--signature-format", format, &signature,
+					&public_key,
+				];
+				let matches = get_app(&usage).get_matches_from(arg_vec);
+				let matches = matches.subcommand().1.unwrap();
+				let verifying = read_message_encoding(matches).unwrap();
+				let verified = read_message_from(encoded_message.as_bytes(), verifying).unwrap();
+				assert!(do_verify::<Sr25519>(matches, &public_key, verified).expect("verify failed"));
 			}
 		}
-		("vanity", Some(matches)) => {
-			let desired: String = matches
-				.value_of("pattern")
-				.map(str::to_string)
-				.unwrap_or_default();
-			let result = vanity::generate_key::<C>(&desired)?;
-			let formated_seed = format_seed::<C>(result.seed);
-			C::print_from_uri(&formated_seed, None, maybe_network, output);
-		}
-		("transfer", Some(matches)) => {
-			let signer = read_pair::<C>(matches.value_of("from"), password)?;
-			let index = read_required_parameter::<Index>(matches, "index")?;
-			let genesis_hash = read_genesis_hash(matches)?;
 
-			let to: AccountId = read_account_id(matches.value_of("to"));
-			let amount = read_required_parameter::<Balance>(matches, "amount")?;
-			let function = Call::Balances(BalancesCall::transfer(to.into(), amount));
+		let error = read_message_from(&b"not base64!"[..], MessageEncoding::Base64).err().unwrap();
+		assert!(matches!(error, Error::InvalidBase64(_)));
+	}
 
-			let extrinsic = create_extrinsic::<C>(function, index, signer, genesis_hash);
+	#[test]
+	fn signature_formats_roundtrip_for_ed25519() {
+		test_signature_format_roundtrip::<Ed25519>();
+	}
 
-			print_extrinsic(extrinsic);
-		}
-		("sign-transaction", Some(matches)) => {
-			let signer = read_pair::<C>(matches.value_of("suri"), password)?;
-			let index = read_required_parameter::<Index>(matches, "nonce")?;
-			let genesis_hash = read_genesis_hash(matches)?;
-
-			let call = matches.value_of("call").expect("call is required; qed");
-			let function: Call = hex::decode(&call)
-				.ok()
-				.and_then(|x| Decode::decode(&mut &x[..]).ok())
-				.unwrap();
+	#[test]
+	fn signature_formats_roundtrip_for_sr25519() {
+		test_signature_format_roundtrip::<Sr25519>();
+	}
 
-			let extrinsic = create_extrinsic::<C>(function, index, signer, genesis_hash);
+	#[test]
+	fn signature_formats_roundtrip_for_ecdsa() {
+		test_signature_format_roundtrip::<Ecdsa>();
+	}
 
-			print_extrinsic(extrinsic);
-		}
-		("insert", Some(matches)) => {
-			let suri = get_uri("suri", &matches)?;
-			let pair = read_pair::<C>(Some(&suri), password)?;
-			let node_url = matches.value_of("node-url").unwrap_or("http://localhost:9933");
-			let key_type = matches.value_of("key-type").ok_or(Error::Static("Key type id is required"))?;
-
-			// Just checking
-			let _key_type_id = sp_core::crypto::KeyTypeId::try_from(key_type)
-				.map_err(|_| Error::Static("Cannot convert argument to keytype: argument should be 4-character string"))?;
-
-			let rpc = rpc::RpcClient::new(node_url.to_string());
-
-			rpc.insert_key(
-				key_type.to_string(),
-				suri,
-				sp_core::Bytes(pair.public().as_ref().to_vec()),
-			);
-		}
-		("moduleid", Some(matches)) => {
-			let id = get_uri("id", &matches)?;
-			if id.len() != 8 {
-				Err("a module id must be a string of 8 characters")?
+	fn test_verify_account_id<CryptoType: Crypto>(recover: bool)
+	where
+		SignatureOf<CryptoType>: SignatureT,
+		PublicOf<CryptoType>: PublicT,
+	{
+		let usage = get_usage();
+		let pair = CryptoType::pair_from_suri("//Alice", None).unwrap();
+		let account_id = pair.public().into_runtime().into_account();
+		let message = b"Blah Blah\n".to_vec();
+		let signature = do_sign::<CryptoType>(&pair, &message, SignatureFormat::Hex);
+
+		for uri in &[account_id.to_ss58check(), format!("0x{}", hex::encode(&account_id))] {
+			let mut arg_vec = vec!["subkey", "verify", &signature[..], &uri[..]];
+			if recover {
+				arg_vec.push("--recover");
 			}
+			let matches = get_app(&usage).get_matches_from(arg_vec);
+			let matches = matches.subcommand().1.unwrap();
+			assert!(do_verify::<CryptoType>(matches, uri, message.clone()).unwrap());
+			assert!(!do_verify::<CryptoType>(matches, uri, b"other".to_vec()).unwrap());
+
+			let bob = CryptoType::pair_from_suri("//Bob", None).unwrap();
+			let bob = bob.public().into_runtime().into_account().to_ss58check();
+			assert!(!do_verify::<CryptoType>(matches, &bob, message.clone()).unwrap());
+		}
+	}
+
+	#[test]
+	fn verify_against_account_id_works_for_ed25519() {
+		test_verify_account_id::<Ed25519>(false);
+	}
+
+	#[test]
+	fn verify_against_account_id_works_for_sr25519() {
+		test_verify_account_id::<Sr25519>(false);
+	}
+
+	#[test]
+	fn verify_against_account_id_recovers_ecdsa_public_key() {
+		test_verify_account_id::<Ecdsa>(true);
+	}
+
+	#[test]
+	fn verify_against_ecdsa_account_id_requires_recovery() {
+		let usage = get_usage();
+		let pair = Ecdsa::pair_from_suri("//Alice", None).unwrap();
+		let account_id = pair.public().into_runtime().into_account().to_ss58check();
+		let signature = do_sign::<Ecdsa>(&pair, b"message", SignatureFormat::Hex);
+
+		let arg_vec = vec!["subkey", "verify", &signature[..], &account_id[..]];
+		let matches = get_app(&usage).get_matches_from(arg_vec);
+		let matches = matches.subcommand().1.unwrap();
+		let error = do_verify::<Ecdsa>(matches, &account_id, b"message".to_vec()).err().unwrap();
+		assert!(error.to_string().contains("--recover"));
+	}
 
-			let id_fixed_array: [u8; 8] = id.as_bytes().try_into()
-				.map_err(|_| Error::Static("Cannot convert argument to moduleid: argument should be 8-character string"))?;
+	#[test]
+	fn verify_detects_bytes_wrapping() {
+		let usage = get_usage();
+		let pair = Sr25519::pair_from_suri("//Alice", None).unwrap();
+		let public_key = format_public_key(pair.public());
+		let message = b"hello".to_vec();
+
+		let raw = do_sign::<Sr25519>(&pair, &message, SignatureFormat::Hex);
+		let wrapped = do_sign::<Sr25519>(&pair, &wrap_bytes(&message), SignatureFormat::Hex);
 
-			let account_id: AccountId = ModuleId(id_fixed_array).into_account();
-			let v = maybe_network.unwrap_or(Ss58AddressFormat::SubstrateAccount);
+		for (signature, expected) in &[(raw, MessageWrapping::None), (wrapped, MessageWrapping::Bytes)] {
+			let arg_vec = vec!["subkey", "verify", "--auto-wrap-bytes", signature, &public_key];
+			let matches = get_app(&usage).get_matches_from(arg_vec);
+			let matches = matches.subcommand().1.unwrap();
 
-			C::print_from_uri(&account_id.to_ss58check_with_version(v), password, maybe_network, output);
+			assert_eq!(
+				do_verify_any_wrapping::<Sr25519>(matches, &public_key, message.clone()).unwrap().as_ref(),
+				Some(expected),
+			);
+			assert_eq!(
+				do_verify_any_wrapping::<Sr25519>(matches, &public_key, b"other".to_vec()).unwrap(),
+				None,
+			);
 		}
-		_ => print_usage(&matches),
 	}
 
-	Ok(())
-}
+	#[test]
+	fn wrapped_ed25519_signature_is_stable() {
+		// The signature of `<Bytes>hello</Bytes>` by the ed25519 `//Alice`, as made by subkey. It
+		// guards against changes of the wrapping, but is not checked against other implementations.
+		let fixture = "5889ce090cf779bcb36e7c2716755ad44daacad91adca9a7d4c389210c2204469c32ef49926c8a\
+			07089a5c5200419396e4e6983679ddccfbcf5a4bf1c9eee106";
+		let usage = get_usage();
+		let pair = Ed25519::pair_from_suri("//Alice", None).unwrap();
+		let public_key = format_public_key(pair.public());
 
-/// Creates a new randomly generated mnemonic phrase.
-fn generate_mnemonic(matches: &ArgMatches) -> Result<Mnemonic, Error> {
-	let words = match matches.value_of("words") {
-		Some(words) => {
-			let num = usize::from_str(words).map_err(|_| Error::Static("Invalid number given for --words"))?;
-			MnemonicType::for_word_count(num)
-				.map_err(|_| Error::Static("Invalid number of words given for phrase: must be 12/15/18/21/24"))?
-		},
-		None => MnemonicType::Words12,
-	};
-	Ok(Mnemonic::new(words, Language::English))
-}
+		assert_eq!(do_sign::<Ed25519>(&pair, &wrap_bytes(b"hello"), SignatureFormat::Hex), fixture);
 
-fn do_sign<C: Crypto>(suri: &str, message: Vec<u8>, password: Option<&str>) -> Result<String, Error>
-where
-	SignatureOf<C>: SignatureT,
-	PublicOf<C>: PublicT,
-{
-	let pair = read_pair::<C>(Some(suri), password)?;
-	let signature = pair.sign(&message);
-	Ok(format_signature::<C>(&signature))
-}
+		let arg_vec = vec!["subkey", "verify", "--wrap-bytes", fixture, &public_key];
+		let matches = get_app(&usage).get_matches_from(arg_vec);
+		let matches = matches.subcommand().1.unwrap();
 
-fn do_verify<C: Crypto>(matches: &ArgMatches, uri: &str, message: Vec<u8>) -> Result<bool, Error>
-where
-	SignatureOf<C>: SignatureT,
-	PublicOf<C>: PublicT,
-{
+		assert!(do_verify::<Ed25519>(matches, &public_key, wrap_bytes(b"hello")).unwrap());
+		assert!(!do_verify::<Ed25519>(matches, &public_key, b"hello".to_vec()).unwrap());
+	}
 
-	let signature = read_signature::<C>(matches)?;
-	let pubkey = read_public_key::<C>(Some(uri));
-	Ok(<<C as Crypto>::Pair as Pair>::verify(&signature, &message, &pubkey))
-}
+	fn test_sign_verify_digest<CryptoType: Crypto>()
+	where
+		SignatureOf<CryptoType>: SignatureT,
+		PublicOf<CryptoType>: PublicT,
+	{
+		let usage = get_usage();
+		let pair = CryptoType::pair_from_suri("//Alice", None).unwrap();
+		let public_key = format_public_key(pair.public());
+		let message = vec![42u8; 200_000];
+
+		for algo in &["blake2-256", "keccak-256", "sha-256"] {
+			let hash = HashAlgo::try_from(*algo).unwrap();
+			let digest = hash::hash_message(&message[..], MessageEncoding::Raw, hash).unwrap();
+			let signature = do_sign::<CryptoType>(&pair, &digest, SignatureFormat::Hex);
+
+			let arg_vec = vec!["subkey", "verify", "--hash", algo, &signature, &public_key];
+			let matches = get_app(&usage).get_matches_from(arg_vec);
+			let matches = matches.subcommand().1.unwrap();
+
+			assert_eq!(read_hash_algo(matches).unwrap(), Some(hash));
+			assert!(do_verify::<CryptoType>(matches, &public_key, digest.to_vec()).unwrap());
+			assert!(!do_verify::<CryptoType>(matches, &public_key, message.clone()).unwrap());
+		}
+	}
 
-fn decode_hex<T: AsRef<[u8]>>(message: T) -> Result<Vec<u8>, Error> {
-	hex::decode(message).map_err(|e| Error::Formatted(format!("Invalid hex ({})", e)))
-}
+	#[test]
+	fn sign_verify_digest_works_for_ed25519() {
+		test_sign_verify_digest::<Ed25519>();
+	}
 
-fn read_message_from_stdin(should_decode: bool) -> Result<Vec<u8>, Error> {
-	let mut message = vec![];
-	stdin()
-		.lock()
-		.read_to_end(&mut message)?;
-	if should_decode {
-		message = decode_hex(&message)?;
+	#[test]
+	fn sign_verify_digest_works_for_sr25519() {
+		test_sign_verify_digest::<Sr25519>();
 	}
-	Ok(message)
-}
 
-fn read_required_parameter<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T, Error> where
-	<T as FromStr>::Err: std::fmt::Debug,
-{
-	let str_value = matches
-		.value_of(name)
-		.expect("parameter is required; thus it can't be None; qed");
-	str::parse::<T>(str_value).map_err(|_|
-		Error::Formatted(format!("Invalid `{}' parameter; expecting an integer.", name))
-	)
-}
+	#[test]
+	fn sign_verify_digest_works_for_ecdsa() {
+		test_sign_verify_digest::<Ecdsa>();
+	}
 
-fn read_genesis_hash(matches: &ArgMatches) -> Result<H256, Error> {
-	let genesis_hash: Hash = match matches.value_of("genesis").unwrap_or("alex") {
-		"elm" => hex!["10c08714a10c7da78f40a60f6f732cf0dba97acfb5e2035445b032386157d5c3"].into(),
-		"alex" => hex!["dcd1346701ca8396496e52aa2785b1748deb6db09551b72159dcb3e08991025b"].into(),
-		h => Decode::decode(&mut &decode_hex(h)?[..])
-			.expect("Invalid genesis hash or unrecognized chain identifier"),
-	};
-	println!(
-		"Using a genesis hash of {}",
-		HexDisplay::from(&genesis_hash.as_ref())
-	);
-	Ok(genesis_hash)
-}
+	/// A TTY handing out the given entries, recording the prompts shown.
+	fn tty<'a>(
+		entries: &'a [&str],
+		prompts: &'a mut Vec<String>,
+	) -> impl FnMut(&str) -> std::io::Result<String> + 'a {
+		let mut entries = entries.iter();
+		move |prompt| {
+			prompts.push(prompt.into());
+			entries.next()
+				.map(|entry| entry.to_string())
+				.ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into())
+		}
+	}
 
-fn read_signature<C: Crypto>(matches: &ArgMatches) -> Result<SignatureOf<C>, Error>
-where
-	SignatureOf<C>: SignatureT,
-	PublicOf<C>: PublicT,
-{
-	let sig_data = matches
-		.value_of("sig")
-		.expect("signature parameter is required; thus it can't be None; qed");
-	let mut signature = <<C as Crypto>::Pair as Pair>::Signature::default();
-	let sig_data = decode_hex(sig_data)?;
-	if sig_data.len() != signature.as_ref().len() {
-		return Err(Error::Formatted(format!(
-			"signature has an invalid length. read {} bytes, expected {} bytes",
-			sig_data.len(),
-			signature.as_ref().len(),
-		)));
-	}
-	signature.as_mut().copy_from_slice(&sig_data);
-	Ok(signature)
-}
+	#[test]
+	fn read_password_prompts_once_without_confirmation() {
+		let mut prompts = Vec::new();
+		assert_eq!(read_password(tty(&["secret", "other"], &mut prompts), false).unwrap(), "secret");
+		assert_eq!(prompts, vec!["Key password: "]);
+	}
 
-fn read_public_key<C: Crypto>(matched_uri: Option<&str>) -> PublicOf<C>
-where
-	PublicOf<C>: PublicT,
-{
-	let uri = matched_uri.expect("parameter is required; thus it can't be None; qed");
-	let uri = if uri.starts_with("0x") {
-		&uri[2..]
-	} else {
-		uri
-	};
-	if let Ok(pubkey_vec) = hex::decode(uri) {
-		<C as Crypto>::Public::from_slice(pubkey_vec.as_slice())
-	} else {
-		<C as Crypto>::Public::from_string(uri)
-			.ok()
-			.expect("Invalid URI; expecting either a secret URI or a public URI.")
+	#[test]
+	fn read_password_reprompts_until_confirmed() {
+		let mut prompts = Vec::new();
+		let entries = ["secret", "secert", "secret", "secret"];
+		assert_eq!(read_password(tty(&entries, &mut prompts), true).unwrap(), "secret");
+		assert_eq!(prompts.len(), 4);
+
+		let entries = ["a", "b", "c", "d", "e", "f", "secret", "secret"];
+		assert!(read_password(tty(&entries, &mut Vec::new()), true).is_err());
 	}
-}
 
-fn read_account_id(matched_uri: Option<&str>) -> AccountId {
-	let uri = matched_uri.expect("parameter is required; thus it can't be None; qed");
-	let uri = if uri.starts_with("0x") {
-		&uri[2..]
-	} else {
-		uri
-	};
-	if let Ok(data_vec) = hex::decode(uri) {
-		AccountId::try_from(data_vec.as_slice())
-			.expect("Invalid hex length for account ID; should be 32 bytes")
-	} else {
-		AccountId::from_ss58check(uri).ok()
-			.expect("Invalid SS58-check address given for account ID.")
+	#[test]
+	fn password_interactive_without_input_is_explained() {
+		let usage = get_usage();
+		let matches = get_app(&usage)
+			.get_matches_from(vec!["subkey", "--password-interactive", "inspect", "//Alice"]);
+		let no_tty = |_: &str| -> io::Result<String> { unreachable!("there is no terminal") };
+		let no_input = |prompt: &str| prompt_secret_with(prompt, false, no_tty, &b""[..]);
+		let error = read_password_source(&matches, no_input, |_| None).err().unwrap();
+		assert!(error.to_string().contains("--password-filename or --password-env"));
 	}
-}
 
-fn read_pair<C: Crypto>(
-	matched_suri: Option<&str>,
-	password: Option<&str>,
-) -> Result<<C as Crypto>::Pair, Error> where
-	SignatureOf<C>: SignatureT,
-	PublicOf<C>: PublicT,
-{
-	let suri = matched_suri.ok_or(Error::Static("parameter is required; thus it can't be None; qed"))?;
-	Ok(C::pair_from_suri(suri, password))
-}
+	/// The password `read_password_source` resolves for the global arguments `args`.
+	fn password_from(args: &[&str], entries: &[&str], env: Option<&str>) -> Option<String> {
+		let usage = get_usage();
+		let arg_vec = ["subkey"].iter().chain(args).chain(&["generate"]).collect::<Vec<_>>();
+		let matches = get_app(&usage).get_matches_from(arg_vec);
+		read_password_source(&matches, tty(entries, &mut Vec::new()), |_| env.map(Into::into))
+			.unwrap()
+			.map(|password| password.expose_secret().clone())
+	}
 
-fn format_signature<C: Crypto>(signature: &SignatureOf<C>) -> String {
-	format!("{}", HexDisplay::from(&signature.as_ref()))
-}
+	#[test]
+	fn read_password_source_reads_each_source() {
+		assert_eq!(password_from(&[], &[], None), None);
+		assert_eq!(password_from(&["--password", "secret"], &[], None).unwrap(), "secret");
+		assert_eq!(
+			password_from(&["--password-env", "SUBKEY_PASSWORD"], &[], Some("secret")).unwrap(),
+			"secret",
+		);
+		assert_eq!(
+			password_from(&["--password-interactive"], &["secret", "secret"], None).unwrap(),
+			"secret",
+		);
+
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		writeln!(file, "secret ").unwrap();
+		let path = file.path().to_str().unwrap();
+		assert_eq!(password_from(&["--password-filename", path], &[], None).unwrap(), "secret ");
+	}
 
-fn format_seed<C: Crypto>(seed: SeedOf<C>) -> String {
-	format!("0x{}", HexDisplay::from(&seed.as_ref()))
-}
+	#[test]
+	fn read_password_source_prefers_explicit_sources() {
+		// Neither the environment nor the TTY are consulted when the password is given.
+		assert_eq!(
+			password_from(&["--password", "secret"], &["other", "other"], Some("other")).unwrap(),
+			"secret",
+		);
 
-fn format_public_key<C: Crypto>(public_key: PublicOf<C>) -> String {
-	format!("0x{}", HexDisplay::from(&public_key.as_ref()))
-}
+		let usage = get_usage();
+		let matches = get_app(&usage)
+			.get_matches_from(vec!["subkey", "--password-env", "SUBKEY_UNSET", "generate"]);
+		let error = read_password_source(&matches, tty(&[], &mut Vec::new()), |_| None).err();
+		assert!(error.unwrap().to_string().contains("SUBKEY_UNSET"));
+	}
 
-fn format_account_id<C: Crypto>(public_key: PublicOf<C>) -> String where
-	PublicOf<C>: PublicT,
-{
-	format!("0x{}", HexDisplay::from(&public_key.into_runtime().into_account().as_ref()))
-}
+	#[test]
+	fn password_sources_conflict() {
+		let usage = get_usage();
+		let conflicting = [
+			&["--password", "a", "--password-interactive"][..],
+			&["--password", "a", "--password-env", "B"],
+			&["--password-filename", "a", "--password-env", "B"],
+			&["--password-filename", "a", "--password-interactive"],
+		];
+		for args in &conflicting {
+			let arg_vec = ["subkey"].iter().chain(*args).chain(&["generate"]).collect::<Vec<_>>();
+			assert!(get_app(&usage).get_matches_from_safe(arg_vec).is_err());
+		}
+	}
 
-fn create_extrinsic<C: Crypto>(
-	function: Call,
-	index: Index,
-	signer: C::Pair,
-	genesis_hash: H256,
-) -> UncheckedExtrinsic where
-	PublicOf<C>: PublicT,
-	SignatureOf<C>: SignatureT,
-{
-	let extra = |i: Index, f: Balance| {
-		(
+	/// The extrinsic signed for `function` with `runtime::SignedPayload` directly.
+	fn expected_extrinsic(
+		function: Call,
+		pair: &ed25519::Pair,
+		nonce: Index,
+		tip: Balance,
+		genesis_hash: H256,
+		era: Era,
+		era_hash: H256,
+	) -> UncheckedExtrinsic {
+		let extra = (
 			frame_system::CheckSpecVersion::<Runtime>::new(),
 			frame_system::CheckTxVersion::<Runtime>::new(),
 			frame_system::CheckGenesis::<Runtime>::new(),
-			frame_system::CheckEra::<Runtime>::from(Era::Immortal),
-			frame_system::CheckNonce::<Runtime>::from(i),
+			frame_system::CheckEra::<Runtime>::from(era),
+			frame_system::CheckNonce::<Runtime>::from(nonce),
 			frame_system::CheckWeight::<Runtime>::new(),
-			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(f),
-		)
-	};
-	let raw_payload = SignedPayload::from_raw(
-		function,
-		extra(index, 0),
-		(
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(tip),
+		);
+		let additional = (
 			VERSION.spec_version,
 			VERSION.transaction_version,
 			genesis_hash,
-			genesis_hash,
+			era_hash,
 			(),
 			(),
 			(),
-		),
-	);
-	let signature = raw_payload.using_encoded(|payload| signer.sign(payload)).into_runtime();
-	let signer = signer.public().into_runtime();
-	let (function, extra, _) = raw_payload.deconstruct();
-
-	UncheckedExtrinsic::new_signed(
-		function,
-		signer.into_account().into(),
-		signature,
-		extra,
-	)
-}
+		);
+		let payload = SignedPayload::from_raw(function, extra, additional);
+		let signature = payload.using_encoded(|payload| pair.sign(payload));
+		let (function, extra, _) = payload.deconstruct();
+		UncheckedExtrinsic::new_signed(
+			function,
+			AccountPublic::from(pair.public()).into_account().into(),
+			signature.into(),
+			extra,
+		)
+	}
 
-fn print_extrinsic(extrinsic: UncheckedExtrinsic) {
-	println!("0x{}", HexDisplay::from(&extrinsic.encode()));
-}
+	fn sign_transaction(call: &Call, extra_args: &[&str]) -> UncheckedExtrinsic {
+		let usage = get_usage();
+		let call = hex::encode(call.encode());
+		let mut arg_vec = vec!["subkey", "sign-transaction", "--call", &call, "--nonce", "7", "--suri", "//Alice"];
+		arg_vec.extend_from_slice(extra_args);
+		let matches = get_app(&usage).get_matches_from(arg_vec);
+		let matches = matches.subcommand().1.unwrap();
 
-fn print_usage(matches: &ArgMatches) {
-	println!("{}", matches.usage());
-}
+		let signer = Ed25519::pair_from_suri(matches.value_of("suri").unwrap(), None).unwrap();
+		let function = decode_call(matches.value_of("call").unwrap()).unwrap();
+		create_extrinsic::<Ed25519>(function, signer, read_extrinsic_params(matches).unwrap())
+	}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	#[test]
+	fn sign_transaction_matches_signed_payload() {
+		let pair = Ed25519::pair_from_suri("//Alice", None).unwrap();
+		let genesis_hash = H256::repeat_byte(0x22);
+		let block_hash = H256::repeat_byte(0x33);
+		let call = Call::Balances(BalancesCall::transfer(AccountId::from([1; 32]).into(), 1_000));
+		let genesis = format!("{:?}", genesis_hash);
+		let genesis = &genesis[2..];
+
+		let immortal = sign_transaction(&call, &["-g", genesis]);
+		let expected = expected_extrinsic(call.clone(), &pair, 7, 0, genesis_hash, Era::Immortal, genesis_hash);
+		assert_eq!(immortal.encode(), expected.encode());
+
+		let block_hash_arg = format!("{:?}", block_hash);
+		let mortal = sign_transaction(
+			&call,
+			&["-g", genesis, "--era", "64", "--block-number", "128", "-h", &block_hash_arg, "--tip", "5"],
+		);
+		let expected = expected_extrinsic(call, &pair, 7, 5, genesis_hash, Era::mortal(64, 128), block_hash);
+		assert_eq!(mortal.encode(), expected.encode());
+		assert_ne!(mortal.encode(), immortal.encode());
+	}
 
-	fn test_generate_sign_verify<CryptoType: Crypto>()
+	#[test]
+	fn sign_transaction_signs_hash_of_long_payload() {
+		let pair = Ed25519::pair_from_suri("//Alice", None).unwrap();
+		let call = Call::System(frame_system::Call::remark(vec![0; 300]));
+		let extrinsic = sign_transaction(&call, &[]);
+
+		let genesis_hash = read_genesis_hash(&get_app(&get_usage()).get_matches_from(vec!["subkey"])).unwrap();
+		let expected = expected_extrinsic(call, &pair, 7, 0, genesis_hash, Era::Immortal, genesis_hash);
+		assert_eq!(extrinsic.encode(), expected.encode());
+
+		let (_, signature, extra) = extrinsic.signature.unwrap();
+		let payload = (extrinsic.function, extra, (
+			VERSION.spec_version, VERSION.transaction_version, genesis_hash, genesis_hash, (), (), (),
+		)).encode();
+		assert!(payload.len() > 256);
+		assert!(signature.verify(&blake2_256(&payload)[..], &AccountPublic::from(pair.public()).into_account()));
+	}
+
+	#[test]
+	fn sign_transaction_rejects_invalid_genesis_hash() {
+		let usage = get_usage();
+		for genesis in &["1234", "not-a-chain"] {
+			let matches = get_app(&usage).get_matches_from(vec![
+				"subkey", "sign-transaction", "--call", "00", "--nonce", "7", "--suri", "//Alice", "-g", genesis,
+			]);
+			let matches = matches.subcommand_matches("sign-transaction").unwrap();
+			assert!(read_genesis_hash(matches).is_err());
+		}
+	}
+
+	#[test]
+	fn kdf_params_are_read_from_arguments() {
+		let usage = get_usage();
+		let matches = get_app(&usage).get_matches_from(vec!["subkey", "generate"]);
+		assert_eq!(read_kdf_params(&matches).unwrap(), KdfParams::default());
+		assert_eq!(
+			KdfParams::default(),
+			KdfParams { memory_kib: 65536, passes: 3, salt: "subkey-password-kdf".into() },
+		);
+
+		let matches = get_app(&usage).get_matches_from(vec![
+			"subkey", "--kdf-memory", "128", "--kdf-passes", "5", "--kdf-salt", "my own salt", "generate",
+		]);
+		assert_eq!(
+			read_kdf_params(&matches).unwrap(),
+			KdfParams { memory_kib: 128, passes: 5, salt: "my own salt".into() },
+		);
+	}
+
+	fn test_sign_with_keystore_key<CryptoType: Crypto>()
 	where
 		SignatureOf<CryptoType>: SignatureT,
 		PublicOf<CryptoType>: PublicT,
 	{
 		let usage = get_usage();
-		let app = get_app(&usage);
-		let password = None;
-
-		// Generate public key and seed.
-		let arg_vec = vec!["subkey", "generate"];
-
-		let matches = app.clone().get_matches_from(arg_vec);
+		let dir = tempfile::tempdir().unwrap();
+		let key_type = KeyTypeId(*b"gran");
+		let keystore = sc_keystore::Store::open(dir.path(), None).unwrap();
+		let pair = keystore.read().insert_by_type::<CryptoType::Pair>(key_type, "//Alice").unwrap();
+		let address = pair.public().to_ss58check();
+
+		let arg_vec = vec![
+			"subkey", "sign", "--keystore-path", dir.path().to_str().unwrap(),
+			"--key-type", "gran", "--address", &address,
+		];
+		let matches = get_app(&usage).get_matches_from(arg_vec);
 		let matches = matches.subcommand().1.unwrap();
-		let mnemonic = generate_mnemonic(matches).expect("generate failed");
-
-		let (pair, seed) =
-			<<CryptoType as Crypto>::Pair as Pair>::from_phrase(mnemonic.phrase(), password)
-				.unwrap();
-		let public_key = CryptoType::public_from_pair(&pair);
-		let public_key = format_public_key::<CryptoType>(public_key);
-		let seed = format_seed::<CryptoType>(seed);
-		let message = "Blah Blah\n".as_bytes().to_vec();
+		assert_eq!(read_key_type(matches).unwrap(), key_type);
+		let public = parse_public_key::<CryptoType>(matches.value_of("address").unwrap()).unwrap();
 
-		let signature = do_sign::<CryptoType>(&seed, message.clone(), password).expect("signing failed");
+		let signer = read_keystore_pair::<CryptoType>(dir.path(), key_type, &public, None).unwrap();
+		let message = b"Blah Blah\n".to_vec();
+		let signature = do_sign::<CryptoType>(&signer, &message, SignatureFormat::Hex);
+		let public_key = format_public_key(public);
 
-		// Verify the previous signature.
 		let arg_vec = vec!["subkey", "verify", &signature[..], &public_key[..]];
-
 		let matches = get_app(&usage).get_matches_from(arg_vec);
 		let matches = matches.subcommand().1.unwrap();
+		assert!(do_verify::<CryptoType>(matches, &public_key, message).unwrap());
+	}
 
-		assert!(do_verify::<CryptoType>(matches, &public_key, message).expect("verify failed"));
+	#[test]
+	fn sign_with_keystore_key_works_for_ed25519() {
+		test_sign_with_keystore_key::<Ed25519>();
 	}
 
 	#[test]
-	fn generate_sign_verify_should_work_for_ed25519() {
-		test_generate_sign_verify::<Ed25519>();
+	fn sign_with_keystore_key_works_for_sr25519() {
+		test_sign_with_keystore_key::<Sr25519>();
 	}
 
 	#[test]
-	fn generate_sign_verify_should_work_for_sr25519() {
-		test_generate_sign_verify::<Sr25519>();
+	fn sign_with_keystore_key_works_for_ecdsa() {
+		test_sign_with_keystore_key::<Ecdsa>();
 	}
 
 	#[test]
-	fn should_work() {
-		let s = "0123456789012345678901234567890123456789012345678901234567890123";
-
-		let d1: Hash = hex::decode(s)
-			.ok()
-			.and_then(|x| Decode::decode(&mut &x[..]).ok())
-			.unwrap();
-
-		let d2: Hash = {
-			let mut gh: [u8; 32] = Default::default();
-			gh.copy_from_slice(hex::decode(s).unwrap().as_ref());
-			Hash::from(gh)
+	fn unknown_key_types_require_force() {
+		let usage = get_usage();
+		let key_type = |args: &[&str]| {
+			let matches = get_app(&usage).get_matches_from(args);
+			read_key_type(matches.subcommand().1.unwrap())
 		};
 
-		assert_eq!(d1, d2);
+		let known = key_type(&["subkey", "insert", "//Alice", "gran"]);
+		assert_eq!(known.unwrap(), KeyTypeId(*b"gran"));
+		let error = key_type(&["subkey", "insert", "//Alice", "grandpa"]).err().unwrap();
+		assert!(error.to_string().contains("did you mean gran"));
+		assert!(key_type(&["subkey", "insert", "//Alice", "abcd"]).is_err());
+		let forced = key_type(&["subkey", "insert", "//Alice", "abcd", "--force-key-type"]);
+		assert_eq!(forced.unwrap(), KeyTypeId(*b"abcd"));
 	}
 }
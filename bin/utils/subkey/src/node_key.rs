@@ -0,0 +1,364 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Node keys in the formats they are stored in by Substrate and by other libp2p tooling.
+//!
+//! Substrate nodes read the raw 32-byte ed25519 secret key from `--node-key-file`. Other libp2p
+//! implementations store the `PrivateKey` protobuf message of libp2p, which for ed25519 holds the
+//! secret key followed by the public key.
+
+use crate::{
+	error::{static_err, Error},
+	input::{decode_hex, read_message_from_stdin, MessageEncoding},
+	json::{JsonOutput, NodeKeyOutput, PeerIdOutput},
+	output::{write_atomically, Output, OutputType, Secrets},
+};
+use clap::ArgMatches;
+use libp2p::{identity::{ed25519, PublicKey}, PeerId};
+use std::{convert::{TryFrom, TryInto}, fs, io::{self, Write}, path::Path};
+
+/// The `KeyType` of the libp2p protobuf messages for ed25519 keys.
+const KEY_TYPE_ED25519: u64 = 1;
+
+/// A format of a node key file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum NodeKeyFormat {
+	/// The raw secret key, as read by `--node-key-file`.
+	Raw,
+	/// The hex-encoded secret key.
+	Hex,
+	/// The libp2p `PrivateKey` protobuf message.
+	Protobuf,
+}
+
+impl<'a> TryFrom<&'a str> for NodeKeyFormat {
+	type Error = Error;
+
+	fn try_from(s: &'a str) -> Result<NodeKeyFormat, Error> {
+		match s {
+			"raw" => Ok(NodeKeyFormat::Raw),
+			"hex" => Ok(NodeKeyFormat::Hex),
+			"protobuf" => Ok(NodeKeyFormat::Protobuf),
+			_ => Err(Error::Formatted(format!(
+				"Unknown node key format `{}`; expecting one of raw, hex, protobuf",
+				s,
+			))),
+		}
+	}
+}
+
+/// Decode the node key `data` of `format`.
+pub(super) fn decode_node_key(
+	data: &[u8],
+	format: NodeKeyFormat,
+) -> Result<ed25519::Keypair, Error> {
+	let mut secret = match format {
+		NodeKeyFormat::Raw => data.to_vec(),
+		NodeKeyFormat::Hex => {
+			let data = std::str::from_utf8(data)
+				.map_err(|_| Error::Static("Invalid hex-encoded node key"))?;
+			hex::decode(data.trim().trim_start_matches("0x"))?
+		},
+		NodeKeyFormat::Protobuf => return decode_protobuf(data),
+	};
+	secret_keypair(&mut secret)
+}
+
+/// Encode `keypair` in `format`.
+pub(super) fn encode_node_key(keypair: &ed25519::Keypair, format: NodeKeyFormat) -> Vec<u8> {
+	let secret = keypair.secret();
+	match format {
+		NodeKeyFormat::Raw => secret.as_ref().to_vec(),
+		NodeKeyFormat::Hex => hex::encode(secret.as_ref()).into_bytes(),
+		NodeKeyFormat::Protobuf => {
+			let keypair = keypair.encode();
+			let mut encoded = vec![0x08, KEY_TYPE_ED25519 as u8, 0x12, keypair.len() as u8];
+			encoded.extend_from_slice(&keypair);
+			encoded
+		},
+	}
+}
+
+/// The peer ID of the node with `keypair`.
+pub(super) fn peer_id(keypair: &ed25519::Keypair) -> PeerId {
+	PublicKey::Ed25519(keypair.public()).into_peer_id()
+}
+
+fn secret_keypair(secret: &mut [u8]) -> Result<ed25519::Keypair, Error> {
+	if secret.len() != 32 {
+		return Err(Error::Formatted(format!(
+			"Invalid node key of {} bytes; expected 32 bytes",
+			secret.len(),
+		)));
+	}
+	ed25519::SecretKey::from_bytes(secret)
+		.map(ed25519::Keypair::from)
+		.map_err(|_| Error::Static("Invalid node key"))
+}
+
+/// Decode a `PrivateKey` protobuf message with an ed25519 key.
+///
+/// The key is the secret key followed by the public key, like go-libp2p writes it, or only the
+/// secret key. The public key has to be the one of the secret key.
+fn decode_protobuf(mut data: &[u8]) -> Result<ed25519::Keypair, Error> {
+	let invalid = || Error::Static("Invalid protobuf-encoded node key");
+	let (mut key_type, mut key) = (None, None);
+	while !data.is_empty() {
+		match read_varint(&mut data).ok_or_else(invalid)? {
+			// Field 1, `Type`, a varint.
+			0x08 => key_type = Some(read_varint(&mut data).ok_or_else(invalid)?),
+			// Field 2, `Data`, length-delimited.
+			0x12 => {
+				let len = read_varint(&mut data).ok_or_else(invalid)? as usize;
+				if len > data.len() {
+					return Err(invalid());
+				}
+				key = Some(&data[..len]);
+				data = &data[len..];
+			},
+			_ => return Err(invalid()),
+		}
+	}
+
+	let key = match (key_type.ok_or_else(invalid)?, key.ok_or_else(invalid)?) {
+		(KEY_TYPE_ED25519, key) => key,
+		(key_type, _) => {
+			let name = match key_type {
+				0 => "an RSA",
+				2 => "a secp256k1",
+				3 => "an ECDSA",
+				_ => "an unknown",
+			};
+			return Err(Error::Formatted(format!(
+				"The protobuf-encoded node key is {} key; Substrate nodes only use ed25519 node \
+					keys",
+				name,
+			)));
+		},
+	};
+	match key.len() {
+		32 | 64 => {
+			let mut secret = key[..32].to_vec();
+			let keypair = secret_keypair(&mut secret)?;
+			if key.len() == 64 && keypair.public().encode()[..] != key[32..] {
+				return Err(Error::Static("The public key of the protobuf-encoded node key is not \
+					the one of its secret key"));
+			}
+			Ok(keypair)
+		},
+		len => Err(Error::Formatted(format!(
+			"Invalid protobuf-encoded ed25519 key of {} bytes; expected 64 bytes",
+			len,
+		))),
+	}
+}
+
+/// Read a protobuf varint from the start of `data`, and advance `data` past it.
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+	let mut value = 0u64;
+	for (i, byte) in data.iter().enumerate().take(10) {
+		value |= u64::from(byte & 0x7f) << (7 * i);
+		if byte & 0x80 == 0 {
+			*data = &data[i + 1..];
+			return Some(value);
+		}
+	}
+	None
+}
+
+/// Generate a node key, the `generate-node-key` command.
+pub fn run_generate_node_key(
+	matches: &ArgMatches,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error> {
+	let file = matches.value_of("file").or_else(|| matches.value_of("legacy-file"));
+	let quiet = matches.is_present("quiet");
+	if file.is_none() && (quiet || secrets == Secrets::Hide) {
+		return static_err("Refusing to generate a node key that is neither written to \
+			--file nor printed");
+	}
+
+	let keypair = ed25519::Keypair::generate();
+	let secret = keypair.secret();
+	let public = keypair.public();
+	let peer_id = PublicKey::Ed25519(public.clone()).into_peer_id();
+
+	if let Some(file) = file {
+		let force = matches.is_present("force");
+		write_atomically(Path::new(file), secret.as_ref(), true, force)?;
+	}
+	// The secret is only printed if it is not written to a file.
+	let secret = match file {
+		None => Some(format!("0x{}", hex::encode(secret.as_ref()))),
+		Some(_) => None,
+	};
+	let public = format!("0x{}", hex::encode(public.encode()));
+	if quiet {
+		println!("{}", peer_id);
+	} else if output.is_json() {
+		let peer_id = peer_id.to_base58();
+		let json = NodeKeyOutput { secret, public_key: public, peer_id };
+		output.write_json(&mut io::stdout(), &json.to_json())?;
+	} else {
+		if let Some(secret) = secret {
+			println!("Secret key:  {}", secret);
+		}
+		println!("Public key:  {}", public);
+		println!("Peer ID:     {}", peer_id);
+	}
+	Ok(())
+}
+
+/// Convert a node key file, the `key convert-node-key` command.
+pub fn run_convert_node_key(matches: &ArgMatches) -> Result<(), Error> {
+	let format = |name| matches
+		.value_of(name)
+		.expect("from and to are required; qed")
+		.try_into();
+	let (from, to): (NodeKeyFormat, NodeKeyFormat) = (format("from")?, format("to")?);
+	let input = matches.value_of("input").expect("input is required; qed");
+	let mut out = Output::from_matches(matches)?;
+	let to_file = matches.is_present("output-file");
+	if to != NodeKeyFormat::Hex && !to_file && atty::is(atty::Stream::Stdout) {
+		return static_err("Refusing to write a binary node key to a terminal; see \
+			--output-file");
+	}
+
+	let keypair = decode_node_key(&fs::read(input)?, from)?;
+	let mut converted = encode_node_key(&keypair, to);
+	let expected = peer_id(&keypair);
+	if peer_id(&decode_node_key(&converted, to)?) != expected {
+		return static_err("The converted node key has another peer ID");
+	}
+	if to == NodeKeyFormat::Hex {
+		converted.push(b'\n');
+	}
+	out.set_secret(true);
+	out.write_all(&converted)?;
+	out.commit()?;
+	if to_file {
+		println!("Peer ID:     {}", expected);
+	}
+	Ok(())
+}
+
+/// Print the peer ID of a node key, the `inspect-node-key` command.
+pub fn run_inspect_node_key(matches: &ArgMatches, output: OutputType) -> Result<(), Error> {
+	let mut secret = match (matches.value_of("file"), matches.value_of("hex")) {
+		(Some(file), _) => fs::read(file)?,
+		(None, Some(secret)) => decode_hex(secret.trim_start_matches("0x"))?,
+		(None, None) => read_message_from_stdin(MessageEncoding::Raw)?,
+	};
+	if secret.len() != 32 {
+		return Err(Error::Formatted(format!(
+			"Invalid node key of {} bytes; expected 32 bytes",
+			secret.len(),
+		)));
+	}
+	let secret = ed25519::SecretKey::from_bytes(&mut secret)
+		.map_err(|_| Error::Static("Invalid node key"))?;
+	let public = ed25519::Keypair::from(secret).public();
+	let public_hex = format!("0x{}", hex::encode(public.encode()));
+	let peer_id = PublicKey::Ed25519(public).into_peer_id();
+	// The code and length are single-byte varints for the multihashes of peer IDs.
+	let (code, length) = (peer_id.as_bytes()[0], peer_id.as_bytes()[1]);
+
+	if matches.is_present("quiet") {
+		println!("{}", peer_id);
+	} else if output.is_json() {
+		let json = PeerIdOutput {
+			public_key: public_hex,
+			peer_id: peer_id.to_base58(),
+			multihash_codec: multihash_codec_name(code).into(),
+			multihash_length: length.into(),
+		};
+		output.write_json(&mut io::stdout(), &json.to_json())?;
+	} else {
+		println!("Peer ID:     {}", peer_id);
+		println!("Public key:  {}", public_hex);
+		println!(
+			"Multihash:   {} (0x{:02x}), {} bytes",
+			multihash_codec_name(code),
+			code,
+			length,
+		);
+	}
+	Ok(())
+}
+
+/// The name of the multihash codec `code`, one of those libp2p derives peer IDs with.
+fn multihash_codec_name(code: u8) -> &'static str {
+	match code {
+		0x00 => "identity",
+		0x12 => "sha2-256",
+		_ => "unknown",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The protobuf message of the key in `tests/fixtures/node-key.pb`.
+	const PROTOBUF: &str = concat!(
+		"08011240",
+		"0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+		"79b5562e8fe654f94078b112e8a98ba7901f853ae695bed7e0e3910bad049664",
+	);
+
+	#[test]
+	fn node_keys_convert_between_formats() {
+		let secret = (1..=32).collect::<Vec<u8>>();
+		let keypair = decode_node_key(&secret, NodeKeyFormat::Raw).unwrap();
+		let protobuf = encode_node_key(&keypair, NodeKeyFormat::Protobuf);
+		assert_eq!(hex::encode(&protobuf), PROTOBUF);
+		assert_eq!(
+			peer_id(&decode_node_key(&protobuf, NodeKeyFormat::Protobuf).unwrap()),
+			peer_id(&keypair),
+		);
+
+		let encoded = encode_node_key(&keypair, NodeKeyFormat::Hex);
+		assert_eq!(encoded, hex::encode(&secret).into_bytes());
+		let decoded = decode_node_key(b"0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b\
+			1c1d1e1f20\n", NodeKeyFormat::Hex).unwrap();
+		assert_eq!(encode_node_key(&decoded, NodeKeyFormat::Raw), secret);
+	}
+
+	#[test]
+	fn protobuf_node_keys_are_checked() {
+		let error = |protobuf: &str| {
+			let data = hex::decode(protobuf).unwrap();
+			decode_node_key(&data, NodeKeyFormat::Protobuf).err().unwrap().to_string()
+		};
+		// An RSA key, with a length of two bytes.
+		assert!(error(&format!("0800128001{}", "00".repeat(128))).contains("is an RSA key"));
+		assert!(error("0802120102").contains("is a secp256k1 key"));
+		let mut other_public = PROTOBUF.to_string();
+		other_public.replace_range(PROTOBUF.len() - 2.., "00");
+		assert!(error(&other_public).contains("is not the one of its secret key"));
+		assert!(error(&PROTOBUF[..PROTOBUF.len() - 2]).starts_with("Invalid protobuf"));
+		assert!(error("0801122001").starts_with("Invalid protobuf"));
+		assert!(error("080112020102").contains("of 2 bytes; expected 64 bytes"));
+		assert!(error("1a00").starts_with("Invalid protobuf"));
+
+		// Only the secret key.
+		let protobuf = hex::decode(&format!("08011220{}", &PROTOBUF[8..72])).unwrap();
+		assert!(decode_node_key(&protobuf, NodeKeyFormat::Protobuf).is_ok());
+	}
+}
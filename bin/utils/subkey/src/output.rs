@@ -0,0 +1,422 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The output formats of the commands, and writing the output of a command to STDOUT, or
+//! atomically to the file given by `--output-file`.
+
+use crate::{error::Error, input::decode_hex, table::TableStyle};
+use clap::{Arg, ArgMatches};
+use codec::{Decode, Encode};
+use std::{convert::TryFrom, io::{self, Write}, path::{Path, PathBuf}};
+use tempfile::NamedTempFile;
+
+#[derive(Clone, Copy)]
+pub enum OutputType {
+	Json,
+	/// JSON on a single line, e.g. for log aggregation.
+	JsonCompact,
+	Text,
+	/// Only the SS58 address, for use in scripts.
+	Address,
+	/// The SCALE-encoded [`UriDescription`], as raw bytes.
+	Binary,
+	/// A table with a row per account, for results with many accounts.
+	Table(TableStyle),
+}
+
+impl<'a> TryFrom<&'a str> for OutputType {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<OutputType, ()> {
+		match s {
+			"json" => Ok(OutputType::Json),
+			"json-compact" => Ok(OutputType::JsonCompact),
+			"text" => Ok(OutputType::Text),
+			"address" => Ok(OutputType::Address),
+			"binary" => Ok(OutputType::Binary),
+			// The style is selected by `execute`.
+			"table" => Ok(OutputType::Table(TableStyle { header: true, aligned: false })),
+			_ => Err(()),
+		}
+	}
+
+}
+
+impl OutputType {
+	/// Whether the output is JSON, pretty-printed or compact.
+	pub(super) fn is_json(self) -> bool {
+		matches!(self, OutputType::Json | OutputType::JsonCompact)
+	}
+
+	/// Write `json` to `out` on a single line for [`OutputType::JsonCompact`], and pretty-printed
+	/// otherwise, followed by a newline.
+	pub(super) fn write_json<W: Write>(
+		self,
+		out: &mut W,
+		json: &serde_json::Value,
+	) -> io::Result<()> {
+		let json = match self {
+			OutputType::JsonCompact => serde_json::to_string(json),
+			_ => serde_json::to_string_pretty(json),
+		};
+		writeln!(out, "{}", json.expect("Json print failed"))
+	}
+}
+
+/// Which secrets of an account are printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Secrets {
+	/// The secret phrase or URI and the secret seed.
+	Show,
+	/// Only the secret seed, see `--redact-secret`.
+	RedactUri,
+	/// None, see `--hide-secret`.
+	Hide,
+}
+
+impl Secrets {
+	/// The secrets selected on the command line. `inspect` and `key import-json` hide them unless
+	/// `--show-secret` is given, the other commands show them unless `--hide-secret` is given.
+	pub fn from_matches(matches: &ArgMatches) -> Self {
+		let hide_by_default = match matches.subcommand() {
+			("inspect", _) => true,
+			("key", Some(matches)) => matches.subcommand_name() == Some("import-json"),
+			_ => false,
+		};
+		let hide = matches.is_present("hide-secret")
+			|| (hide_by_default && !matches.is_present("show-secret"));
+		if hide {
+			Secrets::Hide
+		} else if matches.is_present("redact-secret") {
+			Secrets::RedactUri
+		} else {
+			Secrets::Show
+		}
+	}
+}
+
+/// The account of a URI, as written with `--output binary`.
+///
+/// It holds the same values as the JSON output. Exactly one of `secret_phrase`, `secret_key_uri`
+/// and `public_key_uri` is set, unless the secret is redacted.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub(super) struct UriDescription {
+	secret_phrase: Option<String>,
+	secret_key_uri: Option<String>,
+	public_key_uri: Option<String>,
+	network_id: String,
+	secret_seed: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	account_id: Vec<u8>,
+	ss58_address: String,
+}
+
+impl UriDescription {
+	/// Build the description from the output of `Crypto::json_from_uri`.
+	pub(super) fn from_json(json: &serde_json::Value) -> Self {
+		let string = |field| json[field].as_str().map(String::from);
+		let bytes = |field| string(field)
+			.filter(|value| value != "n/a")
+			.map(|value| decode_hex(value.trim_start_matches("0x")).expect("hex is printed; qed"));
+		UriDescription {
+			secret_phrase: string("secretPhrase"),
+			secret_key_uri: string("secretKeyUri"),
+			public_key_uri: string("publicKeyUri"),
+			network_id: string("networkId").unwrap_or_default(),
+			secret_seed: bytes("secretSeed"),
+			public_key: bytes("publicKey").unwrap_or_default(),
+			account_id: bytes("accountId").unwrap_or_default(),
+			ss58_address: string("ss58Address").unwrap_or_default(),
+		}
+	}
+}
+
+/// The options selecting the output file, shared by the commands printing an account, a signature
+/// or a verification result.
+pub fn output_file_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+	vec![
+		Arg::from_usage("[output-file] --output-file <output-file> 'Write the output to this file \
+				instead of printing it. The file is only written once the command succeeded, and \
+				is replaced at once, never partially.'"),
+		Arg::from_usage("--force 'Replace the file given by --output-file if it exists'")
+			.requires("output-file"),
+	]
+}
+
+/// Where the output of a command goes.
+///
+/// Output for a file is written to a temporary file next to it, which only replaces the file in
+/// [`Output::commit`], so that a command failing halfway leaves no partial file behind.
+pub(super) enum Output {
+	Stdout(io::Stdout),
+	File {
+		path: PathBuf,
+		/// Whether an existing file is replaced.
+		force: bool,
+		/// Whether the output includes secrets, and the file is only readable by its owner.
+		secret: bool,
+		file: io::BufWriter<NamedTempFile>,
+	},
+}
+
+impl Output {
+	/// The output selected by the [`output_file_args`] in `matches`.
+	///
+	/// Fails if the output file exists, unless `--force` is given, or if no temporary file can be
+	/// made next to it, so that no work is done for output that can not be written.
+	pub(super) fn from_matches(matches: &ArgMatches) -> Result<Self, Error> {
+		let path = match matches.value_of("output-file") {
+			Some(path) => PathBuf::from(path),
+			None => return Ok(Output::Stdout(io::stdout())),
+		};
+		let force = matches.is_present("force");
+		if path.exists() && !force {
+			return Err(Error::Formatted(format!(
+				"Output file `{}` exists; use --force to replace it",
+				path.display(),
+			)));
+		}
+		let file = io::BufWriter::new(NamedTempFile::new_in(parent_dir(&path))?);
+		Ok(Output::File { path, force, secret: false, file })
+	}
+
+	/// Mark the output as including secrets, or not.
+	pub(super) fn set_secret(&mut self, includes_secrets: bool) {
+		if let Output::File { secret, .. } = self {
+			*secret = includes_secrets;
+		}
+	}
+
+	/// Replace the output file, if any, with the temporary file the output is written to.
+	pub(super) fn commit(self) -> Result<(), Error> {
+		match self {
+			Output::Stdout(mut stdout) => Ok(stdout.flush()?),
+			Output::File { path, force, secret, file } => {
+				let file = file.into_inner().map_err(|e| e.into_error())?;
+				persist(file, &path, secret, force)
+			},
+		}
+	}
+}
+
+impl Write for Output {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self {
+			Output::Stdout(stdout) => stdout.write(buf),
+			Output::File { file, .. } => file.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			Output::Stdout(stdout) => stdout.flush(),
+			Output::File { file, .. } => file.flush(),
+		}
+	}
+}
+
+/// Write `contents` to a temporary file in the directory of `path` and rename it to `path`.
+///
+/// The file is only readable by its owner if `secret` is set. An existing file at `path` is
+/// replaced if `force` is set, and an error otherwise. On failure, the temporary file is removed.
+pub(super) fn write_atomically(
+	path: &Path,
+	contents: &[u8],
+	secret: bool,
+	force: bool,
+) -> Result<(), Error> {
+	let mut file = NamedTempFile::new_in(parent_dir(path))?;
+	file.write_all(contents)?;
+	persist(file, path, secret, force)
+}
+
+/// The directory of `path`, in which its temporary file is made.
+fn parent_dir(path: &Path) -> &Path {
+	match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new("."),
+	}
+}
+
+/// Sync the temporary `file` and rename it to `path`, as described by [`write_atomically`].
+fn persist(file: NamedTempFile, path: &Path, secret: bool, force: bool) -> Result<(), Error> {
+	file.as_file().sync_all()?;
+	set_permissions(&file, secret)?;
+
+	let persisted = if force { file.persist(path) } else { file.persist_noclobber(path) };
+	persisted.map(drop).map_err(|e| match e.error.kind() {
+		io::ErrorKind::AlreadyExists => Error::Formatted(format!(
+			"Output file `{}` exists; use --force to replace it",
+			path.display(),
+		)),
+		_ => e.error.into(),
+	})
+}
+
+/// Make `file` readable by its owner only if `secret` is set, and by everyone otherwise.
+#[cfg(unix)]
+fn set_permissions(file: &NamedTempFile, secret: bool) -> io::Result<()> {
+	use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+	let mode = if secret { 0o600 } else { 0o644 };
+	file.as_file().set_permissions(Permissions::from_mode(mode))
+}
+
+/// Make `file` readable by its owner only if `secret` is set, and by everyone otherwise.
+#[cfg(not(unix))]
+fn set_permissions(_file: &NamedTempFile, _secret: bool) -> io::Result<()> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crypto::{Crypto, Sr25519};
+	use sp_core::{crypto::DEV_PHRASE, Pair, Public};
+	use std::fs;
+
+	fn output(path: &Path, force: bool) -> Result<Output, Error> {
+		let path = path.to_str().unwrap();
+		let mut args = vec!["test", "--output-file", path];
+		if force {
+			args.push("--force");
+		}
+		let matches = clap::App::new("test").args(&output_file_args()).get_matches_from(args);
+		Output::from_matches(&matches)
+	}
+
+	#[test]
+	fn output_is_only_written_on_commit() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("account.json");
+
+		let mut out = output(&path, false).unwrap();
+		writeln!(out, "partial").unwrap();
+		out.flush().unwrap();
+		// The output goes to a temporary file, rather than to memory.
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+		drop(out);
+		assert!(!path.exists());
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+		let mut out = output(&path, false).unwrap();
+		writeln!(out, "account").unwrap();
+		out.commit().unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "account\n");
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn existing_file_is_only_replaced_with_force() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("account.json");
+		fs::write(&path, "old").unwrap();
+
+		assert!(output(&path, false).is_err());
+		// The file may also appear while the command runs.
+		assert!(write_atomically(&path, b"new", false, false).is_err());
+		assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+
+		let mut out = output(&path, true).unwrap();
+		write!(out, "new").unwrap();
+		out.commit().unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn failed_write_leaves_no_file_behind() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("missing").join("account.json");
+		assert!(write_atomically(&path, b"account", false, true).is_err());
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+		// Renaming the file onto a directory fails after the temporary file is written.
+		let path = dir.path().join("directory");
+		fs::create_dir(&path).unwrap();
+		assert!(write_atomically(&path, b"account", false, true).is_err());
+		assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn secrets_are_only_readable_by_the_owner() {
+		use std::os::unix::fs::PermissionsExt;
+		let dir = tempfile::tempdir().unwrap();
+		let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+		let path = dir.path().join("secret.json");
+		let mut out = output(&path, false).unwrap();
+		out.set_secret(true);
+		out.commit().unwrap();
+		assert_eq!(mode(&path), 0o600);
+
+		let path = dir.path().join("public.json");
+		write_atomically(&path, b"account", false, false).unwrap();
+		assert_eq!(mode(&path), 0o644);
+		write_atomically(&path, b"account", true, true).unwrap();
+		assert_eq!(mode(&path), 0o600);
+	}
+
+	#[test]
+	fn compact_json_is_a_single_line() {
+		let json = Sr25519::json_from_uri("//Alice", None, None, Secrets::Show).unwrap();
+		let mut compact = Vec::new();
+		OutputType::JsonCompact.write_json(&mut compact, &json).unwrap();
+		let compact = String::from_utf8(compact).unwrap();
+		assert_eq!(compact.find('\n'), Some(compact.len() - 1));
+
+		let mut pretty = Vec::new();
+		OutputType::Json.write_json(&mut pretty, &json).unwrap();
+		assert!(String::from_utf8(pretty.clone()).unwrap().lines().count() > 1);
+		let parse = |bytes: &[u8]| serde_json::from_slice::<serde_json::Value>(bytes).unwrap();
+		assert_eq!(parse(compact.as_bytes()), parse(&pretty));
+	}
+
+	fn decode_uri_description(mut bytes: &[u8]) -> UriDescription {
+		let description = UriDescription::decode(&mut bytes).unwrap();
+		assert!(bytes.is_empty());
+		description
+	}
+
+	#[test]
+	fn uri_description_roundtrips_through_binary() {
+		let uri = format!("{}//Alice", DEV_PHRASE);
+		let json = Sr25519::json_from_uri(&uri, None, None, Secrets::Show).unwrap();
+		let description = UriDescription::from_json(&json);
+		assert_eq!(decode_uri_description(&description.encode()), description);
+
+		let pair = Sr25519::pair_from_suri(&uri, None).unwrap();
+		assert_eq!(description.secret_key_uri.as_deref(), Some(&uri[..]));
+		assert_eq!(description.secret_phrase, None);
+		assert_eq!(description.public_key, pair.public().to_raw_vec());
+		assert_eq!(description.account_id, pair.public().to_raw_vec());
+		assert_eq!(description.ss58_address, "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+		assert_eq!(description.secret_seed.map(|seed| seed.len()), Some(32));
+
+		let json = Sr25519::json_from_uri(DEV_PHRASE, None, None, Secrets::RedactUri).unwrap();
+		let description = decode_uri_description(&UriDescription::from_json(&json).encode());
+		assert_eq!(description.secret_phrase, None);
+		assert_eq!(description.secret_seed.map(|seed| seed.len()), Some(32));
+
+		let address = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+		let json = Sr25519::json_from_uri(address, None, None, Secrets::Show).unwrap();
+		let description = decode_uri_description(&UriDescription::from_json(&json).encode());
+		assert_eq!(description.public_key_uri.as_deref(), Some(address));
+		assert_eq!(description.secret_seed, None);
+	}
+}
@@ -0,0 +1,275 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The encrypted JSON key files of polkadot-js, as exported and imported by its extension and apps.
+//!
+//! Only version 3 of the format is read and written: the key is encoded like a PKCS#8 key, and
+//! encrypted with xsalsa20-poly1305 (the secretbox of NaCl) under a key derived from the password
+//! with scrypt.
+
+use crate::error::Error;
+use scrypt::ScryptParams;
+use serde_json::{json, Map, Value};
+use sp_core::crypto::Zeroize;
+use std::convert::TryInto;
+use xsalsa20poly1305::{
+	aead::{generic_array::GenericArray, Aead, NewAead},
+	Nonce, XSalsa20Poly1305,
+};
+
+/// The bytes before the secret key.
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+/// The bytes between the secret and the public key.
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+
+/// The scrypt cost; polkadot-js refuses files with other parameters.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_N: u32 = 1 << SCRYPT_LOG_N;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_R: u32 = 8;
+
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+const TAG_LENGTH: usize = 16;
+
+/// The schemes polkadot-js names the way [`Crypto::NAME`](crate::crypto::Crypto::NAME) does.
+const SCHEMES: [&str; 3] = ["sr25519", "ed25519", "ecdsa"];
+
+/// A key pair in the encoding of polkadot-js.
+pub(super) struct KeyPair {
+	/// The scheme of the key, like [`Crypto::NAME`](crate::crypto::Crypto::NAME).
+	pub(super) scheme: &'static str,
+	/// The secret key: the schnorrkel secret key in its ed25519 form for sr25519, the seed followed
+	/// by the public key for ed25519, and the secret scalar for ecdsa.
+	secret: Vec<u8>,
+	/// The public key.
+	pub(super) public: Vec<u8>,
+}
+
+impl Drop for KeyPair {
+	fn drop(&mut self) {
+		self.secret.zeroize();
+	}
+}
+
+impl KeyPair {
+	/// The pair of the secret `seed` as subkey encodes it, with `public`.
+	///
+	/// The seed is the secret key of an sr25519 pair, and the secret seed of the other schemes.
+	pub(super) fn from_seed(
+		scheme: &'static str,
+		seed: &[u8],
+		public: &[u8],
+	) -> Result<Self, Error> {
+		let secret = match scheme {
+			"sr25519" => schnorrkel::SecretKey::from_bytes(seed)
+				.map_err(|_| Error::Static("Invalid sr25519 secret key"))?
+				.to_ed25519_bytes()
+				.to_vec(),
+			"ed25519" => [seed, public].concat(),
+			_ => seed.to_vec(),
+		};
+		Ok(KeyPair { scheme, secret, public: public.to_vec() })
+	}
+
+	/// The secret seed of the pair as subkey encodes it, the inverse of [`KeyPair::from_seed`].
+	pub(super) fn seed(&self) -> Result<Vec<u8>, Error> {
+		match self.scheme {
+			"sr25519" => schnorrkel::SecretKey::from_ed25519_bytes(&self.secret)
+				.map(|secret| secret.to_bytes().to_vec())
+				.map_err(|_| Error::Static("Invalid sr25519 secret key in the JSON file")),
+			"ed25519" => Ok(self.secret[..32].to_vec()),
+			_ => Ok(self.secret.clone()),
+		}
+	}
+
+	/// The PKCS#8-like encoding of polkadot-js.
+	fn encode(&self) -> Vec<u8> {
+		[&PKCS8_HEADER[..], &self.secret, &PKCS8_DIVIDER, &self.public].concat()
+	}
+
+	/// Decode the encoding of `scheme`; ecdsa secret keys are 32 bytes long, the others 64.
+	fn decode(scheme: &'static str, encoded: &[u8]) -> Option<Self> {
+		let secret_length = if scheme == "ecdsa" { 32 } else { 64 };
+		let public_length = if scheme == "ecdsa" { 33 } else { 32 };
+		if encoded.len() != PKCS8_HEADER.len() + secret_length + PKCS8_DIVIDER.len() + public_length
+			|| !encoded.starts_with(&PKCS8_HEADER)
+		{
+			return None;
+		}
+		let (secret, rest) = encoded[PKCS8_HEADER.len()..].split_at(secret_length);
+		let (divider, public) = rest.split_at(PKCS8_DIVIDER.len());
+		if divider != PKCS8_DIVIDER {
+			return None;
+		}
+		Some(KeyPair { scheme, secret: secret.to_vec(), public: public.to_vec() })
+	}
+}
+
+/// Encrypt `pair` with `password` into the JSON of polkadot-js, with `address` and the `meta` data
+/// the file is labelled with.
+pub(super) fn encrypt(
+	pair: &KeyPair,
+	address: &str,
+	meta: Map<String, Value>,
+	password: &str,
+) -> Value {
+	let salt: [u8; SALT_LENGTH] = rand::random();
+	let nonce: [u8; NONCE_LENGTH] = rand::random();
+	let mut key = derive_key(password, &salt);
+	let mut encoded = pair.encode();
+	let sealed = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..32]))
+		.encrypt(Nonce::from_slice(&nonce), &encoded[..])
+		.expect("encoded keys are far shorter than the limit of xsalsa20-poly1305; qed");
+	key.zeroize();
+	encoded.zeroize();
+
+	let params = [SCRYPT_N, SCRYPT_P, SCRYPT_R].iter().flat_map(|p| p.to_le_bytes().to_vec());
+	let encrypted = salt.iter().copied().chain(params).chain(nonce.iter().copied()).chain(sealed);
+	json!({
+		"encoded": base64::encode(&encrypted.collect::<Vec<_>>()),
+		"encoding": {
+			"content": ["pkcs8", pair.scheme],
+			"type": ["scrypt", "xsalsa20-poly1305"],
+			"version": "3",
+		},
+		"address": address,
+		"meta": meta,
+	})
+}
+
+/// Decrypt the key pair of the polkadot-js JSON `file` with `password`.
+pub(super) fn decrypt(file: &Value, password: &str) -> Result<KeyPair, Error> {
+	let encoding = &file["encoding"];
+	if encoding["version"] != "3" {
+		return Err(Error::Formatted(format!(
+			"Unsupported JSON file version {}; only version 3 is supported",
+			encoding["version"],
+		)));
+	}
+	if encoding["type"] != json!(["scrypt", "xsalsa20-poly1305"]) {
+		return Err(Error::Formatted(format!(
+			"Unsupported JSON file encryption {}; only scrypt with xsalsa20-poly1305 is supported",
+			encoding["type"],
+		)));
+	}
+	let scheme = match (encoding["content"][0].as_str(), encoding["content"][1].as_str()) {
+		(Some("pkcs8"), Some(scheme)) => SCHEMES.iter().copied().find(|name| *name == scheme),
+		_ => None,
+	}.ok_or_else(|| Error::Formatted(format!(
+		"Unsupported JSON file content {}; expecting pkcs8 of sr25519, ed25519 or ecdsa",
+		encoding["content"],
+	)))?;
+	let encrypted = file["encoded"].as_str()
+		.ok_or(Error::Static("The JSON file has no encoded key"))
+		.and_then(|encoded| Ok(base64::decode(encoded)?))?;
+
+	if encrypted.len() < SALT_LENGTH + 12 + NONCE_LENGTH + TAG_LENGTH {
+		return Err(Error::Static("The encoded key of the JSON file is too short"));
+	}
+	let (salt, rest) = encrypted.split_at(SALT_LENGTH);
+	let (params, rest) = rest.split_at(12);
+	let (nonce, sealed) = rest.split_at(NONCE_LENGTH);
+	let param = |i: usize| {
+		u32::from_le_bytes(params[4 * i..4 * i + 4].try_into().expect("4 bytes"))
+	};
+	if (param(0), param(1), param(2)) != (SCRYPT_N, SCRYPT_P, SCRYPT_R) {
+		return Err(Error::Static("Unsupported scrypt parameters in the JSON file"));
+	}
+
+	let mut key = derive_key(password, salt);
+	let encoded = XSalsa20Poly1305::new(GenericArray::from_slice(&key[..32]))
+		.decrypt(Nonce::from_slice(nonce), sealed);
+	key.zeroize();
+	let mut encoded = encoded.map_err(|_| Error::Static("Invalid password for the JSON file"))?;
+	let pair = KeyPair::decode(scheme, &encoded);
+	encoded.zeroize();
+	pair.ok_or_else(|| Error::Formatted(format!("Invalid {} key in the JSON file", scheme)))
+}
+
+/// The 64 bytes polkadot-js derives from `password` and `salt` with scrypt, of which the first 32
+/// are the key of xsalsa20-poly1305.
+fn derive_key(password: &str, salt: &[u8]) -> [u8; 64] {
+	let params = ScryptParams::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)
+		.expect("the scrypt parameters of polkadot-js are valid; qed");
+	let mut key = [0; 64];
+	scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+		.expect("64 bytes are a valid output length of scrypt; qed");
+	key
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+
+	const ALICE_ED25519_SEED: [u8; 32] =
+		hex!("abf8e5bdbe30c65656c0a3cbd181ff8a56294a69dfedd27982aace4a76909115");
+	const ALICE_ED25519_PUBLIC: [u8; 32] =
+		hex!("88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee");
+
+	#[test]
+	fn encrypted_files_are_decrypted() {
+		let pair = KeyPair::from_seed("ed25519", &ALICE_ED25519_SEED, &ALICE_ED25519_PUBLIC)
+			.unwrap();
+		let mut meta = Map::new();
+		meta.insert("name".into(), "Alice".into());
+		let address = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu";
+		let mut file = encrypt(&pair, address, meta, "correct horse");
+		assert_eq!(file["address"], address);
+		assert_eq!(file["meta"]["name"], "Alice");
+		let decrypted = decrypt(&file, "correct horse").unwrap();
+		assert_eq!(decrypted.scheme, "ed25519");
+		assert_eq!(decrypted.seed().unwrap(), ALICE_ED25519_SEED.to_vec());
+		assert_eq!(decrypted.public, ALICE_ED25519_PUBLIC.to_vec());
+
+		let error = decrypt(&file, "wrong horse").err().unwrap();
+		assert_eq!(error.to_string(), "Invalid password for the JSON file");
+
+		file["encoding"]["version"] = "2".into();
+		assert!(decrypt(&file, "correct horse").is_err());
+	}
+
+	#[test]
+	fn tampered_files_are_refused() {
+		let pair = KeyPair::from_seed("ed25519", &ALICE_ED25519_SEED, &ALICE_ED25519_PUBLIC)
+			.unwrap();
+		let address = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu";
+		let mut file = encrypt(&pair, address, Map::new(), "correct horse");
+		let mut encrypted = base64::decode(file["encoded"].as_str().unwrap()).unwrap();
+		let last = encrypted.len() - 1;
+		encrypted[last] ^= 1;
+		file["encoded"] = base64::encode(&encrypted).into();
+		assert!(decrypt(&file, "correct horse").is_err());
+	}
+
+	#[test]
+	fn sr25519_secret_keys_are_converted() {
+		let secret = schnorrkel::MiniSecretKey::from_bytes(&[1; 32])
+			.unwrap()
+			.expand(schnorrkel::ExpansionMode::Ed25519);
+		let seed = secret.to_bytes();
+		let public = secret.to_public().to_bytes();
+		let pair = KeyPair::from_seed("sr25519", &seed, &public).unwrap();
+		assert_ne!(pair.secret, seed.to_vec());
+		let encoded = pair.encode();
+		let decoded = KeyPair::decode("sr25519", &encoded).unwrap();
+		assert_eq!(decoded.seed().unwrap(), seed.to_vec());
+		assert!(KeyPair::decode("ecdsa", &encoded).is_none());
+	}
+}
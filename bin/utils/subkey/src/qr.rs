@@ -0,0 +1,252 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Encoding of accounts as QR codes, for `--qr` and `--qr-file`.
+//!
+//! The codes have error correction level M and are made by the `qrcode` crate; this module only
+//! draws them.
+
+use crate::error::Error;
+use clap::Arg;
+use qrcode::{Color, EcLevel};
+
+/// The prefix of the payload of an address, as scanned by the polkadot-js apps.
+const ADDRESS_PREFIX: &str = "substrate";
+/// The prefix of the payload of a secret, as scanned by the polkadot-js apps.
+const SECRET_PREFIX: &str = "secret";
+
+/// The light modules around a code, so that it is told apart from its surroundings.
+const QUIET_ZONE: usize = 4;
+/// The pixels of each side of a module in a PNG.
+#[cfg(feature = "qr-png")]
+const PNG_SCALE: usize = 8;
+
+/// The options selecting the QR code of an account, shared by generate and inspect.
+pub fn qr_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+	vec![
+		Arg::from_usage("--qr 'Also print the SS58 address as a QR code, for wallet apps to scan. \
+				It is drawn for terminals with a dark background.'")
+			.conflicts_with("quiet"),
+		Arg::from_usage("[qr-file] --qr-file <qr-file> 'Write the QR code of the SS58 address to \
+				this PNG file. Requires subkey to be built with the qr-png feature.'"),
+		Arg::from_usage("--qr-secret 'Encode the secret phrase or secret URI in the QR code \
+				instead of the address. Anyone who sees the code can take the account!'"),
+		Arg::from_usage("[qr-genesis-hash] --qr-genesis-hash <qr-genesis-hash> 'The hex-encoded \
+				genesis hash of the chain to add the account to, included in the QR code.'"),
+	]
+}
+
+/// The payload of the QR code of `address` on the chain with `genesis_hash`, as scanned by the
+/// polkadot-js apps.
+pub(super) fn address_payload(address: &str, genesis_hash: Option<&str>) -> String {
+	payload(ADDRESS_PREFIX, address, genesis_hash)
+}
+
+/// The payload of the QR code of the secret phrase or secret URI `secret` on the chain with
+/// `genesis_hash`, as scanned by the polkadot-js apps.
+pub(super) fn secret_payload(secret: &str, genesis_hash: Option<&str>) -> String {
+	payload(SECRET_PREFIX, secret, genesis_hash)
+}
+
+fn payload(prefix: &str, value: &str, genesis_hash: Option<&str>) -> String {
+	match genesis_hash {
+		Some(genesis_hash) => format!("{}:{}:{}", prefix, value, genesis_hash),
+		None => format!("{}:{}", prefix, value),
+	}
+}
+
+/// A QR code.
+#[derive(Debug)]
+pub(super) struct QrCode {
+	/// The number of modules of each side.
+	size: usize,
+	/// Whether each module is dark, row by row.
+	modules: Vec<bool>,
+}
+
+impl QrCode {
+	/// Encode `data` in the smallest QR code that holds it.
+	pub(super) fn encode(data: &[u8]) -> Result<Self, Error> {
+		let code = qrcode::QrCode::with_error_correction_level(data, EcLevel::M)
+			.map_err(|e| Error::Formatted(format!(
+				"{} bytes do not fit in a QR code ({})",
+				data.len(),
+				e,
+			)))?;
+		let modules = code.to_colors().into_iter().map(|color| color == Color::Dark).collect();
+		Ok(QrCode { size: code.width(), modules })
+	}
+
+	/// Whether the module at column `x` and row `y` of the code with its quiet zone is dark.
+	fn is_dark(&self, x: usize, y: usize) -> bool {
+		let inside = |z: usize| z.checked_sub(QUIET_ZONE).filter(|&z| z < self.size);
+		match (inside(x), inside(y)) {
+			(Some(x), Some(y)) => self.modules[y * self.size + x],
+			_ => false,
+		}
+	}
+
+	/// Render the code with Unicode blocks, two rows of modules per line.
+	///
+	/// The light modules are the blocks, so that the code shows on a dark background.
+	pub(super) fn to_unicode(&self) -> String {
+		let width = self.size + 2 * QUIET_ZONE;
+		let mut text = String::new();
+		for y in (0..width).step_by(2) {
+			for x in 0..width {
+				let top = self.is_dark(x, y);
+				// Below the last row there is nothing to draw.
+				let bottom = y + 1 == width || self.is_dark(x, y + 1);
+				text.push(match (top, bottom) {
+					(false, false) => '█',
+					(false, true) => '▀',
+					(true, false) => '▄',
+					(true, true) => ' ',
+				});
+			}
+			text.push('\n');
+		}
+		text
+	}
+
+	/// Render the code as a grayscale PNG.
+	#[cfg(feature = "qr-png")]
+	pub(super) fn to_png(&self) -> Result<Vec<u8>, Error> {
+		use flate2::{write::ZlibEncoder, Compression};
+		use std::io::Write;
+
+		let width = (self.size + 2 * QUIET_ZONE) * PNG_SCALE;
+		let mut pixels = Vec::with_capacity((width + 1) * width);
+		for y in 0..width {
+			// No filter.
+			pixels.push(0);
+			for x in 0..width {
+				let dark = self.is_dark(x / PNG_SCALE, y / PNG_SCALE);
+				pixels.push(if dark { 0 } else { 255 });
+			}
+		}
+		let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+		encoder.write_all(&pixels).expect("writing to a vector never fails; qed");
+		let pixels = encoder.finish().expect("writing to a vector never fails; qed");
+
+		let mut header = Vec::with_capacity(13);
+		header.extend_from_slice(&(width as u32).to_be_bytes());
+		header.extend_from_slice(&(width as u32).to_be_bytes());
+		// A bit depth of 8, grayscale, and the only compression, filter method and no interlace.
+		header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+		let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+		write_png_chunk(&mut png, b"IHDR", &header);
+		write_png_chunk(&mut png, b"IDAT", &pixels);
+		write_png_chunk(&mut png, b"IEND", &[]);
+		Ok(png)
+	}
+
+	/// Render the code as a PNG; this build of subkey can not.
+	#[cfg(not(feature = "qr-png"))]
+	pub(super) fn to_png(&self) -> Result<Vec<u8>, Error> {
+		Err(Error::Static("Writing PNG files requires subkey to be built with the qr-png feature"))
+	}
+}
+
+#[cfg(feature = "qr-png")]
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+	let mut crc = crc32fast::Hasher::new();
+	crc.update(kind);
+	crc.update(data);
+	png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+	png.extend_from_slice(kind);
+	png.extend_from_slice(data);
+	png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Read the data back from `code` with an independent decoder.
+	fn decode(code: &QrCode) -> String {
+		// The decoder looks for the finder patterns, which are too small at a pixel per module.
+		let scale = 4;
+		let width = (code.size + 2 * QUIET_ZONE) * scale;
+		let mut image = rqrr::PreparedImage::prepare_from_greyscale(width, width, |x, y| {
+			if code.is_dark(x / scale, y / scale) { 0 } else { 255 }
+		});
+		let grids = image.detect_grids();
+		assert_eq!(grids.len(), 1);
+		let (_, content) = grids[0].decode().unwrap();
+		content
+	}
+
+	#[test]
+	fn payload_decodes_back_to_the_address() {
+		let address = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+		let genesis_hash = "0x91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3";
+		for genesis_hash in &[None, Some(genesis_hash)] {
+			let payload = address_payload(address, *genesis_hash);
+			let code = QrCode::encode(payload.as_bytes()).unwrap();
+			assert_eq!(decode(&code), payload);
+		}
+		assert_eq!(address_payload(address, None), format!("substrate:{}", address));
+
+		let phrase = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+		let payload = secret_payload(&[phrase, phrase].join(" "), Some(genesis_hash));
+		assert!(payload.starts_with("secret:bottom drive"));
+		assert_eq!(decode(&QrCode::encode(payload.as_bytes()).unwrap()), payload);
+
+		assert!(QrCode::encode(&vec![b'a'; 4096]).is_err());
+	}
+
+	#[test]
+	fn unicode_has_two_rows_per_line() {
+		let code = QrCode::encode(b"substrate").unwrap();
+		assert_eq!(code.size, 21);
+		let text = code.to_unicode();
+		let lines = text.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), (21 + 2 * QUIET_ZONE + 1) / 2);
+		assert!(lines.iter().all(|line| line.chars().count() == 21 + 2 * QUIET_ZONE));
+		// The quiet zone is light, and the finder in the top left corner is dark.
+		assert!(lines[0].chars().all(|c| c == '█'));
+		assert_eq!(lines[2].chars().nth(QUIET_ZONE), Some(' '));
+	}
+
+	#[cfg(feature = "qr-png")]
+	#[test]
+	fn png_has_a_pixel_block_per_module() {
+		use flate2::read::ZlibDecoder;
+		use std::io::Read;
+
+		let code = QrCode::encode(b"substrate").unwrap();
+		let png = code.to_png().unwrap();
+		assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+		let width = (code.size + 2 * QUIET_ZONE) * PNG_SCALE;
+		assert_eq!(png[16..20], (width as u32).to_be_bytes());
+
+		let idat = png.windows(4).position(|window| window == b"IDAT").unwrap();
+		let len = u32::from_be_bytes([png[idat - 4], png[idat - 3], png[idat - 2], png[idat - 1]]);
+		let mut pixels = Vec::new();
+		ZlibDecoder::new(&png[idat + 4..idat + 4 + len as usize]).read_to_end(&mut pixels).unwrap();
+		assert_eq!(pixels.len(), (width + 1) * width);
+		for y in 0..width {
+			for x in 0..width {
+				let dark = code.is_dark(x / PNG_SCALE, y / PNG_SCALE);
+				assert_eq!(pixels[y * (width + 1) + 1 + x] == 0, dark);
+			}
+		}
+	}
+}
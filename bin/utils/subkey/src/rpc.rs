@@ -18,34 +18,404 @@
 
 //! Helper to run commands against current node RPC
 
-use futures::Future;
-use hyper::rt;
+use futures::{future, Future};
 use node_primitives::Hash;
 use sc_rpc::author::AuthorClient;
-use jsonrpc_core_client::transports::http;
+use jsonrpc_core::{ErrorCode, Params, Value};
+use jsonrpc_core_client::{transports::http, RawClient, RpcError};
 use sp_core::Bytes;
+use crate::ws::{CallError, WsClient};
+use std::time::Duration;
+use tokio::{runtime::Runtime, timer::Timeout};
+
+/// The JSON-RPC method of a remote signer that signs a message.
+pub const SIGNER_SIGN_METHOD: &str = "signer_sign";
+
+/// Failure of a request to a remote signer.
+#[derive(Debug, derive_more::Display)]
+pub enum RemoteSignerError {
+	/// The remote signer could not be reached.
+	#[display(fmt = "Failed to connect to remote signer: {}", _0)]
+	Connection(String),
+	/// The remote signer did not respond in time.
+	#[display(fmt = "Remote signer did not respond within {:?}", _0)]
+	Timeout(Duration),
+	/// The remote signer refused to sign.
+	#[display(fmt = "Remote signer returned an error: {}", _0)]
+	Rpc(String),
+	/// The response of the remote signer is not a signature.
+	#[display(fmt = "Invalid response from remote signer: {}", _0)]
+	InvalidResponse(String),
+	/// The returned signature is not a signature of the message by the requested key.
+	#[display(fmt = "Signature returned by remote signer does not match the public key and message")]
+	SignatureMismatch,
+}
+
+/// Failure of inserting a key into the keystore of a node.
+#[derive(Debug, derive_more::Display)]
+pub enum InsertKeyError {
+	/// The node could not be reached.
+	#[display(fmt = "Failed to connect to node: {}", _0)]
+	Connection(String),
+	/// The node refused a request, e.g. because unsafe RPC methods are disabled.
+	#[display(fmt = "Node returned an error: {}", _0)]
+	Rpc(String),
+	/// The response of the node is not what the method returns.
+	#[display(fmt = "Invalid response from node: {}", _0)]
+	InvalidResponse(String),
+	/// The node accepted the key, but does not report it as present afterwards.
+	#[display(fmt = "Node does not have the `{}` key after inserting it; check the key type", _0)]
+	NotPresent(String),
+}
+
+impl From<RpcError> for InsertKeyError {
+	fn from(e: RpcError) -> Self {
+		match e {
+			RpcError::JsonRpcError(e) => InsertKeyError::Rpc(e.message),
+			e => InsertKeyError::Connection(e.to_string()),
+		}
+	}
+}
+
+impl From<CallError> for InsertKeyError {
+	fn from(e: CallError) -> Self {
+		match e {
+			CallError::Connection(e) => InsertKeyError::Connection(e),
+			CallError::Rpc(e) => InsertKeyError::Rpc(e.message),
+			CallError::InvalidResponse(e) => InsertKeyError::InvalidResponse(e),
+		}
+	}
+}
+
+/// Failure of rotating the session keys of a node.
+#[derive(Debug, derive_more::Display)]
+pub enum RotateKeysError {
+	/// The node could not be reached.
+	#[display(fmt = "Failed to connect to node: {}", _0)]
+	Connection(String),
+	/// The node does not serve `author_rotateKeys`, which is an unsafe RPC method.
+	#[display(fmt = "Node does not serve author_rotateKeys, which is an unsafe RPC method: the \
+		node only serves it with `--rpc-methods Unsafe`, or by default if its RPC only listens on \
+		localhost")]
+	UnsafeRpc,
+	/// The node refused the request.
+	#[display(fmt = "Node returned an error: {}", _0)]
+	Rpc(String),
+	/// The response of the node is not what the method returns.
+	#[display(fmt = "Invalid response from node: {}", _0)]
+	InvalidResponse(String),
+}
+
+impl From<jsonrpc_core::Error> for RotateKeysError {
+	fn from(e: jsonrpc_core::Error) -> Self {
+		match e.code {
+			ErrorCode::MethodNotFound => RotateKeysError::UnsafeRpc,
+			_ => RotateKeysError::Rpc(e.message),
+		}
+	}
+}
+
+impl From<RpcError> for RotateKeysError {
+	fn from(e: RpcError) -> Self {
+		match e {
+			RpcError::JsonRpcError(e) => e.into(),
+			e @ RpcError::ParseError(..) => RotateKeysError::InvalidResponse(e.to_string()),
+			e => RotateKeysError::Connection(e.to_string()),
+		}
+	}
+}
+
+impl From<CallError> for RotateKeysError {
+	fn from(e: CallError) -> Self {
+		match e {
+			CallError::Connection(e) => RotateKeysError::Connection(e),
+			CallError::Rpc(e) => e.into(),
+			CallError::InvalidResponse(e) => RotateKeysError::InvalidResponse(e),
+		}
+	}
+}
 
 pub struct RpcClient { url: String }
 
 impl RpcClient {
 	pub fn new(url: String) -> Self { Self { url } }
 
+	/// Insert the key `suri` with `public` into the keystore of the node, with `author_insertKey`.
+	///
+	/// With `verify`, `author_hasKey` is asked afterwards whether the node has the key, since a
+	/// key inserted under an unexpected key type is otherwise accepted silently.
 	pub fn insert_key(
 		&self,
 		key_type: String,
 		suri: String,
 		public: Bytes,
-	) {
-		let url = self.url.clone();
+		verify: bool,
+	) -> Result<(), InsertKeyError> {
+		if self.url.starts_with("ws://") || self.url.starts_with("wss://") {
+			return async_std::task::block_on(self.insert_key_ws(key_type, suri, public, verify));
+		}
 
-		rt::run(
-			http::connect(&url)
-				.and_then(|client: AuthorClient<Hash, Hash>| {
-					client.insert_key(key_type, suri, public).map(|_| ())
-				})
-				.map_err(|e| {
-					eprintln!("Error inserting key: {:?}", e);
+		let missing = InsertKeyError::NotPresent(key_type.clone());
+		let has_key_type = key_type.clone();
+		let request = http::connect(&self.url)
+			.and_then(move |client: AuthorClient<Hash, Hash>| {
+				client.insert_key(key_type, suri, public.clone()).and_then(move |_| {
+					if verify {
+						future::Either::A(client.has_key(public, has_key_type))
+					} else {
+						future::Either::B(future::ok(true))
+					}
 				})
+			});
+		let mut runtime = Runtime::new()
+			.map_err(|e| InsertKeyError::Connection(e.to_string()))?;
+		if runtime.block_on(request)? {
+			Ok(())
+		} else {
+			Err(missing)
+		}
+	}
+
+	/// [`RpcClient::insert_key`] over a websocket connection.
+	async fn insert_key_ws(
+		&self,
+		key_type: String,
+		suri: String,
+		public: Bytes,
+		verify: bool,
+	) -> Result<(), InsertKeyError> {
+		let mut client = WsClient::connect(&self.url).await?;
+		let public = serde_json::to_value(public).expect("bytes are always serializable; qed");
+		client.call("author_insertKey", vec![key_type.clone().into(), suri.into(), public.clone()])
+			.await?;
+		if !verify {
+			return Ok(());
+		}
+		match client.call("author_hasKey", vec![public, key_type.clone().into()]).await? {
+			Value::Bool(true) => Ok(()),
+			Value::Bool(false) => Err(InsertKeyError::NotPresent(key_type)),
+			other =>
+				Err(InsertKeyError::InvalidResponse(format!("expected a boolean, got {}", other))),
+		}
+	}
+
+	/// Have the node generate new session keys with `author_rotateKeys`, and return their
+	/// concatenated public keys.
+	pub fn rotate_keys(&self) -> Result<Bytes, RotateKeysError> {
+		if self.url.starts_with("ws://") || self.url.starts_with("wss://") {
+			return async_std::task::block_on(self.rotate_keys_ws());
+		}
+
+		let request = http::connect(&self.url)
+			.and_then(|client: AuthorClient<Hash, Hash>| client.rotate_keys());
+		let mut runtime = Runtime::new()
+			.map_err(|e| RotateKeysError::Connection(e.to_string()))?;
+		Ok(runtime.block_on(request)?)
+	}
+
+	/// [`RpcClient::rotate_keys`] over a websocket connection.
+	async fn rotate_keys_ws(&self) -> Result<Bytes, RotateKeysError> {
+		let mut client = WsClient::connect(&self.url).await?;
+		let keys = client.call("author_rotateKeys", Vec::new()).await?;
+		serde_json::from_value(keys).map_err(|e| {
+			RotateKeysError::InvalidResponse(format!("expected hex-encoded bytes, {}", e))
+		})
+	}
+}
+
+/// Client of a signer daemon, which holds the secret keys and signs on request.
+pub struct RemoteSigner { url: String, timeout: Duration }
+
+impl RemoteSigner {
+	pub fn new(url: String, timeout: Duration) -> Self { Self { url, timeout } }
+
+	/// Request the signature of `message` by the `scheme` key `public`.
+	///
+	/// Returns the signature as returned by the signer, which is expected to be hex-encoded.
+	pub fn sign(
+		&self,
+		public: &[u8],
+		scheme: &str,
+		message: &[u8],
+	) -> Result<String, RemoteSignerError> {
+		let mut params = serde_json::Map::new();
+		params.insert("publicKey".into(), format!("0x{}", hex::encode(public)).into());
+		params.insert("scheme".into(), scheme.into());
+		params.insert("message".into(), format!("0x{}", hex::encode(message)).into());
+
+		let request = http::connect(&self.url)
+			.and_then(|client: RawClient| client.call_method(SIGNER_SIGN_METHOD, Params::Map(params)));
+		let mut runtime = Runtime::new()
+			.map_err(|e| RemoteSignerError::Connection(e.to_string()))?;
+		let timeout = self.timeout;
+		let response = runtime.block_on(Timeout::new(request, timeout)).map_err(|e| {
+			if e.is_elapsed() {
+				return RemoteSignerError::Timeout(timeout);
+			}
+			match e.into_inner() {
+				Some(RpcError::JsonRpcError(e)) => RemoteSignerError::Rpc(e.message),
+				Some(RpcError::Timeout) => RemoteSignerError::Timeout(timeout),
+				Some(e @ RpcError::ParseError(..)) => RemoteSignerError::InvalidResponse(e.to_string()),
+				Some(e @ RpcError::Other(_)) => RemoteSignerError::Connection(e.to_string()),
+				None => RemoteSignerError::Connection("timer failure".into()),
+			}
+		})?;
+
+		match response {
+			Value::String(signature) => Ok(signature),
+			other => Err(RemoteSignerError::InvalidResponse(format!("expected a string, got {}", other))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{sr25519, Pair};
+
+	/// The RPC methods of a mock node, answering `author_insertKey` with `insert` and
+	/// `author_hasKey` with `has_key`.
+	fn mock_node_io<F>(insert: F, has_key: bool) -> jsonrpc_core::IoHandler
+	where
+		F: Fn(Vec<String>) -> jsonrpc_core::Result<serde_json::Value> + Send + Sync + 'static,
+	{
+		let mut io = jsonrpc_core::IoHandler::new();
+		io.add_method("author_insertKey", move |params: jsonrpc_core::Params| {
+			insert(params.parse()?)
+		});
+		io.add_method("author_hasKey", move |params: jsonrpc_core::Params| {
+			let (public, key_type): (String, String) = params.parse()?;
+			assert_eq!(key_type, "gran");
+			assert_eq!(public, format!("0x{}", hex::encode(alice_public())));
+			Ok(has_key.into())
+		});
+		io
+	}
+
+	/// Start a mock node serving [`mock_node_io`] over HTTP.
+	fn mock_node<F>(insert: F, has_key: bool) -> (jsonrpc_http_server::Server, RpcClient)
+	where
+		F: Fn(Vec<String>) -> jsonrpc_core::Result<serde_json::Value> + Send + Sync + 'static,
+	{
+		let server = jsonrpc_http_server::ServerBuilder::new(mock_node_io(insert, has_key))
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+		let client = RpcClient::new(format!("http://{}", server.address()));
+		(server, client)
+	}
+
+	/// Start a mock node serving [`mock_node_io`] over a websocket.
+	fn mock_ws_node<F>(insert: F, has_key: bool) -> (jsonrpc_ws_server::Server, RpcClient)
+	where
+		F: Fn(Vec<String>) -> jsonrpc_core::Result<serde_json::Value> + Send + Sync + 'static,
+	{
+		let server = jsonrpc_ws_server::ServerBuilder::new(mock_node_io(insert, has_key))
+			.start(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+		let client = RpcClient::new(format!("ws://{}", server.addr()));
+		(server, client)
+	}
+
+	fn alice_public() -> sr25519::Public {
+		sr25519::Pair::from_string("//Alice", None).unwrap().public()
+	}
+
+	fn insert_alice(client: &RpcClient, verify: bool) -> Result<(), InsertKeyError> {
+		let public = sp_core::Bytes(alice_public().0.to_vec());
+		client.insert_key("gran".into(), "//Alice".into(), public, verify)
+	}
+
+	#[test]
+	fn inserted_key_is_checked_with_has_key() {
+		let (_server, client) = mock_node(|params| {
+			assert_eq!(params[..2], ["gran".to_string(), "//Alice".to_string()]);
+			Ok(serde_json::Value::Null)
+		}, true);
+		assert!(insert_alice(&client, true).is_ok());
+
+		let (_server, client) = mock_node(|_| Ok(serde_json::Value::Null), false);
+		assert!(matches!(insert_alice(&client, true), Err(InsertKeyError::NotPresent(_))));
+		assert!(insert_alice(&client, false).is_ok());
+	}
+
+	#[test]
+	fn insert_key_failures_are_distinguished() {
+		let (_server, client) = mock_node(
+			|_| Err(jsonrpc_core::Error::new(jsonrpc_core::ErrorCode::MethodNotFound)),
+			true,
+		);
+		let error = insert_alice(&client, true).err().unwrap();
+		assert!(matches!(error, InsertKeyError::Rpc(_)));
+		assert!(!error.to_string().contains("//Alice"));
+
+		let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		let client = RpcClient::new(format!("http://{}", address));
+		assert!(matches!(insert_alice(&client, true), Err(InsertKeyError::Connection(_))));
+	}
+
+	#[test]
+	fn key_is_inserted_over_a_websocket() {
+		let (_server, client) = mock_ws_node(|params| {
+			assert_eq!(params[..2], ["gran".to_string(), "//Alice".to_string()]);
+			assert_eq!(params[2], format!("0x{}", hex::encode(alice_public())));
+			Ok(serde_json::Value::Null)
+		}, true);
+		assert!(insert_alice(&client, true).is_ok());
+
+		let (_server, client) = mock_ws_node(|_| Ok(serde_json::Value::Null), false);
+		assert!(matches!(insert_alice(&client, true), Err(InsertKeyError::NotPresent(_))));
+		assert!(insert_alice(&client, false).is_ok());
+
+		let (_server, client) = mock_ws_node(
+			|_| Err(jsonrpc_core::Error::invalid_params("Unsafe RPC calls are not allowed")),
+			true,
 		);
+		let error = insert_alice(&client, true).err().unwrap().to_string();
+		assert_eq!(error, "Node returned an error: Unsafe RPC calls are not allowed");
+
+		let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		let client = RpcClient::new(format!("ws://{}", address));
+		assert!(matches!(insert_alice(&client, true), Err(InsertKeyError::Connection(_))));
+	}
+
+	/// The RPC methods of a mock node, answering `author_rotateKeys` with `result`.
+	fn rotate_keys_io(result: jsonrpc_core::Result<serde_json::Value>) -> jsonrpc_core::IoHandler {
+		let mut io = jsonrpc_core::IoHandler::new();
+		io.add_method("author_rotateKeys", move |_| result.clone());
+		io
+	}
+
+	/// Rotate the keys of mock nodes serving [`rotate_keys_io`] over HTTP and a websocket.
+	fn rotate_keys(
+		result: jsonrpc_core::Result<serde_json::Value>,
+	) -> Vec<Result<sp_core::Bytes, RotateKeysError>> {
+		let http = jsonrpc_http_server::ServerBuilder::new(rotate_keys_io(result.clone()))
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+		let ws = jsonrpc_ws_server::ServerBuilder::new(rotate_keys_io(result))
+			.start(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+		vec![
+			RpcClient::new(format!("http://{}", http.address())).rotate_keys(),
+			RpcClient::new(format!("ws://{}", ws.addr())).rotate_keys(),
+		]
+	}
+
+	#[test]
+	fn session_keys_are_rotated() {
+		let keys = (0..128).collect::<Vec<u8>>();
+		for rotated in rotate_keys(Ok(format!("0x{}", hex::encode(&keys)).into())) {
+			assert_eq!(rotated.unwrap().0, keys);
+		}
+
+		let unsafe_rpc = jsonrpc_core::Error::method_not_found();
+		for rotated in rotate_keys(Err(unsafe_rpc)) {
+			let error = rotated.err().unwrap();
+			assert!(matches!(error, RotateKeysError::UnsafeRpc));
+			assert!(error.to_string().contains("--rpc-methods Unsafe"));
+		}
+		for rotated in rotate_keys(Ok(true.into())) {
+			assert!(matches!(rotated, Err(RotateKeysError::InvalidResponse(_))));
+		}
 	}
 }
@@ -0,0 +1,1213 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Signing and verifying messages and files.
+
+use crate::{
+	account::{parse_account_id, parse_public_key},
+	crypto::{
+		Crypto, CryptoScheme, Ecdsa, Ed25519, PublicOf, PublicT, SignatureOf, SignatureT, Sr25519,
+	},
+	error::{static_err, Error, UriStage},
+	format::format_public_key,
+	hash::{self, HashAlgo},
+	input::{
+		decode_base64, decode_hex, encode_base64, get_uri, network_name, read_hash_algo,
+		read_message, read_message_encoding, read_message_from, read_message_utf8,
+		read_required_parameter, read_uri, MessageEncoding,
+	},
+	json::{
+		BatchEntryOutput, BatchSummaryOutput, BatchVerificationOutput, JsonOutput,
+		SignatureFileOutput, SignatureOutput, TestSignatureOutput, TestVectorOutput,
+		TestVectorsOutput, VerificationOutput,
+	},
+	key::{read_key_type, read_keystore_pair},
+	output::{write_atomically, Output, OutputType, Secrets},
+	rpc, with_crypto_scheme,
+};
+use clap::ArgMatches;
+use codec::{Decode, Encode};
+use node_primitives::{AccountId, Signature};
+use rayon::prelude::*;
+use sp_core::{
+	crypto::{ExposeSecret, Ss58AddressFormat, Ss58Codec},
+	hexdisplay::HexDisplay,
+	Pair,
+};
+use sp_runtime::traits::IdentifyAccount;
+use std::{
+	convert::{TryFrom, TryInto}, fs, io::{self, Write}, path::{Path, PathBuf}, time::Duration,
+};
+
+/// Print or check the signatures of the test vectors, the `key test-vectors` command.
+pub fn run_test_vectors<C: Crypto>(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let count = read_required_parameter::<u64>(matches, "count")?;
+	let seed = match matches.value_of("seed") {
+		Some(seed) => decode_hex(seed.trim_start_matches("0x"))?,
+		None => TEST_VECTOR_SEED.to_vec(),
+	};
+	if seed.len() != 32 {
+		return static_err("The seed has to be 32 bytes");
+	}
+	let vectors = test_vectors::<C>(&seed, count, maybe_network)?;
+	let output = if output.is_json() { output } else { OutputType::Json };
+	output.write_json(&mut io::stdout(), &vectors.to_json())?;
+	Ok(())
+}
+
+/// Sign a message, the `sign` command.
+pub fn run_sign<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let address = matches.value_of("address").map(parse_public_key::<C>).transpose()?;
+	let pair = match (matches.value_of("keystore-path"), matches.value_of("remote-signer")) {
+		(Some(path), _) => {
+			let key_type = read_key_type(matches)?;
+			let public = address.as_ref().expect("required by keystore-path; qed");
+			Some(read_keystore_pair::<C>(Path::new(path), key_type, public, password)?)
+		},
+		(None, Some(_)) => None,
+		(None, None) => {
+			let suri = get_uri("suri", matches)?;
+			Some(read_pair::<C>(Some(suri.expose_secret()), password)?)
+		},
+	};
+	let mut out = Output::from_matches(matches)?;
+	let encoding = read_message_encoding(matches)?;
+	let format = read_signature_format(matches)?;
+
+	let hash = read_hash_algo(matches)?;
+	let mut message = read_message(encoding, hash)?;
+	if matches.is_present("wrap-bytes") {
+		message = wrap_bytes(&message);
+	}
+	let (signature, public) = match (pair, matches.value_of("remote-signer")) {
+		(Some(pair), _) => (do_sign::<C>(&pair, &message, format), pair.public()),
+		(None, Some(url)) => {
+			let public = address.expect("required by remote-signer; qed");
+			let signer = rpc::RemoteSigner::new(url.to_string(), REMOTE_SIGNER_TIMEOUT);
+			let signature = remote_sign::<C>(&signer, &public, &message)?;
+			(format_signature::<C>(signature, format), public)
+		},
+		(None, None) => unreachable!("a pair is read unless a remote signer is given; qed"),
+	};
+
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let json = SignatureOutput {
+				signature,
+				signature_format: format.as_str().into(),
+				hash: hash.map(|hash| hash.as_str().into()),
+				public_key: format_public_key(public),
+			};
+			output.write_json(&mut out, &json.to_json())?;
+		},
+		_ => {
+			writeln!(out, "{}", signature)?;
+			if let Some(hash) = hash {
+				eprintln!(
+					"Note: signed the {} digest of the message; verify it with --hash {0}.",
+					hash.as_str(),
+				);
+			}
+		},
+	}
+	out.commit()?;
+	Ok(())
+}
+
+/// The value of `verify --scheme` detecting the scheme of the signature.
+const AUTO_SCHEME: &str = "auto";
+
+/// Verify a signature, the `verify` command.
+///
+/// A scheme given by `--scheme` replaces `C`.
+pub fn run_verify<C: Crypto>(matches: &ArgMatches, output: OutputType) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let detect_scheme = match matches.value_of("scheme") {
+		Some(AUTO_SCHEME) => {
+			let conflicting = ["auto-wrap-bytes", "print-message", "batch"]
+				.iter()
+				.find(|arg| matches.is_present(arg));
+			if let Some(arg) = conflicting {
+				return Err(Error::Formatted(format!(
+					"--scheme {} can not be used with --{}",
+					AUTO_SCHEME,
+					arg,
+				)));
+			}
+			true
+		},
+		Some(scheme) => {
+			let scheme = CryptoScheme::from_str_ci(scheme)?;
+			if scheme.name() != C::NAME {
+				return with_crypto_scheme!(scheme, run_verify::<_>(matches, output));
+			}
+			false
+		},
+		None => false,
+	};
+	let out = Output::from_matches(matches)?;
+	if let Some(path) = matches.value_of("batch") {
+		return verify_batch(out, matches, Path::new(path), C::NAME, output);
+	}
+	let uri = read_signer(verify_uri(matches))?;
+	if public_key_is_ambiguous::<C>(&uri) {
+		eprintln!(
+			"Note: `{}` consists of hex digits only, but is not of the length of a public key; \
+			it is read as an SS58 address.",
+			uri,
+		);
+	}
+	let encoding = read_message_encoding(matches)?;
+
+	// The message that is printed instead of the result with `--print-message`.
+	let (message, text) = if matches.is_present("print-message") {
+		let text = read_message_utf8(encoding, false)?;
+		(text.clone().into_bytes(), Some(text))
+	} else {
+		(read_message(encoding, read_hash_algo(matches)?)?, None)
+	};
+	if detect_scheme {
+		let message = if matches.is_present("wrap-bytes") { wrap_bytes(&message) } else { message };
+		return verify_detecting_scheme(out, matches, &uri, &message, output);
+	}
+	let is_valid_signature = if matches.is_present("auto-wrap-bytes") {
+		match do_verify_any_wrapping::<C>(matches, &uri, message)? {
+			Some(MessageWrapping::None) => {
+				eprintln!("Note: the signature matches the message as given.");
+				true
+			},
+			Some(MessageWrapping::Bytes) => {
+				eprintln!("Note: the signature matches the message wrapped in `<Bytes>`.");
+				true
+			},
+			None => false,
+		}
+	} else if matches.is_present("wrap-bytes") {
+		do_verify::<C>(matches, &uri, wrap_bytes(&message))?
+	} else {
+		do_verify::<C>(matches, &uri, message)?
+	};
+	match text {
+		Some(text) if is_valid_signature => {
+			let mut out = out;
+			write!(out, "{}", text)?;
+			out.commit()?;
+		},
+		Some(_) => return Err(Error::InvalidSignature),
+		None => print_verification::<C>(out, matches, &uri, is_valid_signature, output)?,
+	}
+	Ok(())
+}
+
+/// Write a detached signature of a file, the `sign-file` command.
+pub fn run_sign_file<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let input = Path::new(matches.value_of("file").expect("file is required; qed"));
+	let suri = get_uri("suri", matches)?;
+	let pair = read_pair::<C>(Some(suri.expose_secret()), password)?;
+	let output_path = matches.value_of("signature-file").map(Path::new);
+	let hash = read_hash_algo(matches)?;
+	let format = read_signature_format(matches)?;
+	let written = sign_file::<C>(&pair, input, output_path, hash, format)?;
+	match output {
+		OutputType::Json | OutputType::JsonCompact => {
+			let json = SignatureFileOutput {
+				file: input.display().to_string(),
+				signature_file: written.display().to_string(),
+				signature_format: format.as_str().into(),
+				hash: hash.map(|hash| hash.as_str().into()),
+				public_key: format_public_key(pair.public()),
+			};
+			output.write_json(&mut io::stdout(), &json.to_json())?;
+		},
+		_ => println!("{}", written.display()),
+	}
+	Ok(())
+}
+
+/// The encoding of a signature on the command line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignatureFormat {
+	/// The raw signature bytes, hex-encoded.
+	Hex,
+	/// The raw signature bytes, base64-encoded.
+	Base64,
+	/// The SCALE-encoded `MultiSignature` wrapping the signature, hex-encoded.
+	MultiSignatureHex,
+}
+
+impl SignatureFormat {
+	fn as_str(&self) -> &'static str {
+		match self {
+			SignatureFormat::Hex => "hex",
+			SignatureFormat::Base64 => "base64",
+			SignatureFormat::MultiSignatureHex => "multisig-hex",
+		}
+	}
+}
+
+impl<'a> TryFrom<&'a str> for SignatureFormat {
+	type Error = ();
+
+	fn try_from(s: &'a str) -> Result<SignatureFormat, ()> {
+		match s {
+			"hex" => Ok(SignatureFormat::Hex),
+			"base64" => Ok(SignatureFormat::Base64),
+			"multisig-hex" => Ok(SignatureFormat::MultiSignatureHex),
+			_ => Err(()),
+		}
+	}
+}
+
+/// The default seed of `key test-vectors`.
+const TEST_VECTOR_SEED: [u8; 32] = [
+	0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+	0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+];
+
+/// The messages each key of `key test-vectors` signs.
+const TEST_VECTOR_MESSAGES: [&[u8]; 4] = [
+	b"",
+	b"substrate",
+	b"The quick brown fox jumps over the lazy dog",
+	&[0x00, 0x01, 0x7f, 0x80, 0xfe, 0xff],
+];
+
+/// The test vectors of `count` keys of `C` derived from `seed`, with the signature of each of the
+/// [`TEST_VECTOR_MESSAGES`].
+///
+/// The output only depends on the arguments. Each signature is verified before it is included.
+fn test_vectors<C: Crypto>(
+	seed: &[u8],
+	count: u64,
+	network: Option<Ss58AddressFormat>,
+) -> Result<TestVectorsOutput, Error>
+where
+	PublicOf<C>: PublicT,
+{
+	let hex = |bytes: &[u8]| format!("0x{}", hex::encode(bytes));
+	let vectors = (0..count)
+		.map(|i| {
+			let suri = format!("{}//{}", hex(seed), i);
+			let pair = C::pair_from_suri(&suri, None)?;
+			let account = C::account_from_uri(&suri, None, network, Secrets::Show)
+				.ok_or(Error::InvalidUri { stage: UriStage::Format })?;
+			let signatures = TEST_VECTOR_MESSAGES
+				.iter()
+				.map(|message| {
+					let signature = C::sign_deterministic(&pair, message);
+					if !<C::Pair as Pair>::verify(&signature, message, &pair.public()) {
+						return Err(Error::Formatted(format!(
+							"The signature of {} by {} does not verify",
+							hex(message),
+							suri,
+						)));
+					}
+					Ok(TestSignatureOutput {
+						message: hex(message),
+						signature: hex(signature.as_ref()),
+					})
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok(TestVectorOutput {
+				suri,
+				seed: account.secret_seed,
+				public_key: account.public_key,
+				account_id: account.account_id,
+				ss58_address: account.ss58_address,
+				signatures,
+			})
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+	let signatures = if C::RANDOMIZED_SIGNATURES {
+		"Randomized: the nonces are made without randomness so that the output is reproducible, \
+		but other implementations sign differently. Check the signatures by verifying them."
+	} else {
+		"Deterministic: other implementations make the same signatures."
+	};
+	Ok(TestVectorsOutput {
+		scheme: C::NAME.into(),
+		seed: hex(seed),
+		network_id: network_name(network.unwrap_or_default()),
+		signatures: signatures.into(),
+		vectors,
+	})
+}
+
+/// How long to wait for a remote signer to respond.
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Have `signer` sign `message` with the key `public`, and check the returned signature.
+fn remote_sign<C: Crypto>(
+	signer: &rpc::RemoteSigner,
+	public: &PublicOf<C>,
+	message: &[u8],
+) -> Result<SignatureOf<C>, Error>
+where
+	SignatureOf<C>: SignatureT,
+{
+	let signature = signer.sign(public.as_ref(), C::NAME, message)?;
+	let signature = signature.trim_start_matches("0x");
+	let signature = decode_signature::<C>(signature, SignatureFormat::Hex)
+		.map_err(|e| rpc::RemoteSignerError::InvalidResponse(e.to_string()))?;
+	if <<C as Crypto>::Pair as Pair>::verify(&signature, message, public) {
+		Ok(signature)
+	} else {
+		Err(rpc::RemoteSignerError::SignatureMismatch.into())
+	}
+}
+
+pub fn do_sign<C: Crypto>(pair: &C::Pair, message: &[u8], format: SignatureFormat) -> String
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let signature = pair.sign(message);
+	format_signature::<C>(signature, format)
+}
+
+/// Sign the content of the file `input`, or its `hash` digest, and write the signature in
+/// `format` to `output`, or to `input` with `.sig` appended. Return the path written to.
+///
+/// The signature file is replaced atomically, so it is never left half-written.
+///
+/// No scheme signs streamed messages, so without `hash` the file is read into memory, and
+/// rejected if it is larger than [`MAX_MESSAGE_SIZE`](crate::input::MAX_MESSAGE_SIZE). With
+/// `hash` it is streamed.
+fn sign_file<C: Crypto>(
+	pair: &C::Pair,
+	input: &Path,
+	output: Option<&Path>,
+	hash: Option<HashAlgo>,
+	format: SignatureFormat,
+) -> Result<PathBuf, Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let file = fs::File::open(input)?;
+	let message = match hash {
+		Some(hash) => hash::hash_message(file, MessageEncoding::Raw, hash)?.to_vec(),
+		None => read_message_from(file, MessageEncoding::Raw)?,
+	};
+	let signature = do_sign::<C>(pair, &message, format);
+
+	let output = output.map(Path::to_path_buf).unwrap_or_else(|| {
+		let mut output = input.as_os_str().to_owned();
+		output.push(".sig");
+		output.into()
+	});
+	// A signature is public, and replaces the one of an earlier signing of the file.
+	write_atomically(&output, format!("{}\n", signature).as_bytes(), false, true)?;
+	Ok(output)
+}
+
+pub fn do_verify<C: Crypto>(
+	matches: &ArgMatches,
+	uri: &str,
+	message: Vec<u8>,
+) -> Result<bool, Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let signature = read_signature::<C>(matches)?;
+	let pubkey = match read_public_key::<C>(Some(uri)) {
+		Ok(pubkey) => pubkey,
+		Err(e) => match parse_account_id(uri) {
+			Ok(account_id) => return verify_account_id::<C>(matches, &signature, &message, &account_id),
+			Err(_) => return Err(e),
+		},
+	};
+	Ok(<<C as Crypto>::Pair as Pair>::verify(&signature, &message, &pubkey))
+}
+
+/// Verify the signature against an account id that is not the public key itself.
+///
+/// This requires the public key to be recovered from the signature, which is then checked to be
+/// the one of the account.
+fn verify_account_id<C: Crypto>(
+	matches: &ArgMatches,
+	signature: &SignatureOf<C>,
+	message: &[u8],
+	account_id: &AccountId,
+) -> Result<bool, Error>
+where
+	PublicOf<C>: PublicT,
+{
+	if !matches.is_present("recover") {
+		return Err(Error::Formatted(format!(
+			"{} is an account id, which for {} is the blake2-256 hash of the public key, so the \
+			signature can not be verified against it alone. Give the public key instead, or pass \
+			--recover to recover the public key from the signature.",
+			account_id,
+			C::NAME,
+		)));
+	}
+	let public = C::recover_public(signature, message).ok_or_else(|| Error::Formatted(format!(
+		"The public key can not be recovered from {} signatures",
+		C::NAME,
+	)))?;
+	Ok(public.into_runtime().into_account() == *account_id)
+}
+
+/// Print whether the signature is valid for the key `uri` of scheme `C` to `out`, unless `--quiet`
+/// is given.
+///
+/// Fails with [`Error::InvalidSignature`] if it is not, once `out` is committed.
+fn print_verification<C: Crypto>(
+	mut out: Output,
+	matches: &ArgMatches,
+	uri: &str,
+	is_valid_signature: bool,
+	output: OutputType,
+) -> Result<(), Error>
+where
+	PublicOf<C>: PublicT,
+{
+	// If `uri` is an account id rather than a public key, the public key is not known.
+	let (public, account_id) = match read_public_key::<C>(Some(uri)) {
+		Ok(public) => (Some(format_public_key(public.clone())), public.into_runtime().into_account()),
+		Err(e) => (None, parse_account_id(uri).map_err(|_| e)?),
+	};
+	if !matches.is_present("quiet") {
+		let result = if is_valid_signature { "verifies correctly" } else { "is invalid" };
+		match (output, public) {
+			(OutputType::Json, public) | (OutputType::JsonCompact, public) => {
+				let json = VerificationOutput {
+					valid: is_valid_signature,
+					scheme: Some(C::NAME.into()),
+					public_key: public,
+					ss58_address: Some(account_id.to_ss58check()),
+				};
+				output.write_json(&mut out, &json.to_json())?;
+			},
+			(_, Some(public)) => writeln!(
+				out,
+				"Signature {} for {} public key {} (read as {}).",
+				result,
+				C::NAME,
+				public,
+				public_key_interpretation::<C>(uri),
+			)?,
+			(_, None) => writeln!(
+				out,
+				"Signature {} for {} account {} (public key recovered from the signature).",
+				result,
+				C::NAME,
+				account_id.to_ss58check(),
+			)?,
+		}
+	}
+	out.commit()?;
+	if is_valid_signature {
+		Ok(())
+	} else {
+		Err(Error::InvalidSignature)
+	}
+}
+
+/// Verify the signature with each scheme it may be of, and print the first one it is valid for to
+/// `out`.
+///
+/// The schemes are picked by the length of the signature and of the public key: 65-byte
+/// signatures and 33-byte public keys are ecdsa ones, the others ed25519 or sr25519 ones. Fails if
+/// neither fits any scheme, or the signature can not be checked with one that fits.
+fn verify_detecting_scheme(
+	mut out: Output,
+	matches: &ArgMatches,
+	uri: &str,
+	message: &[u8],
+	output: OutputType,
+) -> Result<(), Error> {
+	/// Check that the signature and the public key or account id have the lengths of `C`.
+	fn fits<C: Crypto>(matches: &ArgMatches, uri: &str) -> Result<(), Error>
+	where
+		SignatureOf<C>: SignatureT,
+		PublicOf<C>: PublicT,
+	{
+		read_signature::<C>(matches)?;
+		if let Err(e) = read_public_key::<C>(Some(uri)) {
+			parse_account_id(uri).map_err(|_| e)?;
+		}
+		Ok(())
+	}
+
+	fn detected<C: Crypto>(
+		out: Output,
+		matches: &ArgMatches,
+		uri: &str,
+		output: OutputType,
+	) -> Result<(), Error>
+	where
+		PublicOf<C>: PublicT,
+	{
+		eprintln!("Note: detected the {} scheme. The detection is best-effort.", C::NAME);
+		print_verification::<C>(out, matches, uri, true, output)
+	}
+
+	let fitting = [
+		(Ed25519::NAME, fits::<Ed25519>(matches, uri)),
+		(Sr25519::NAME, fits::<Sr25519>(matches, uri)),
+		(Ecdsa::NAME, fits::<Ecdsa>(matches, uri)),
+	];
+	let schemes = fitting.iter()
+		.filter(|(_, fits)| fits.is_ok())
+		.map(|(name, _)| *name)
+		.collect::<Vec<_>>();
+	if schemes.is_empty() {
+		let reasons = fitting.iter()
+			.filter_map(|(name, fits)| fits.as_ref().err().map(|e| format!("{}: {}", name, e)))
+			.collect::<Vec<_>>()
+			.join("; ");
+		return Err(Error::Formatted(format!(
+			"The signature and public key fit none of the schemes ({})",
+			reasons,
+		)));
+	}
+
+	let [ed25519, sr25519, ecdsa] = &fitting;
+	if ed25519.1.is_ok() && do_verify::<Ed25519>(matches, uri, message.to_vec())? {
+		return detected::<Ed25519>(out, matches, uri, output);
+	}
+	if sr25519.1.is_ok() && do_verify::<Sr25519>(matches, uri, message.to_vec())? {
+		return detected::<Sr25519>(out, matches, uri, output);
+	}
+	if ecdsa.1.is_ok() && do_verify::<Ecdsa>(matches, uri, message.to_vec())? {
+		return detected::<Ecdsa>(out, matches, uri, output);
+	}
+
+	if !matches.is_present("quiet") {
+		match output {
+			OutputType::Json | OutputType::JsonCompact => {
+				let json = VerificationOutput { valid: false, ..Default::default() };
+				output.write_json(&mut out, &json.to_json())?;
+			},
+			_ => {
+				let names = match schemes.split_last() {
+					Some((last, [])) => last.to_string(),
+					Some((last, rest)) => format!("each of {} and {}", rest.join(", "), last),
+					None => unreachable!("Returned above if there are no schemes; qed"),
+				};
+				writeln!(out, "Signature is invalid for {}.", names)?;
+			},
+		}
+	}
+	out.commit()?;
+	Err(Error::InvalidSignature)
+}
+
+/// Verify each entry of the `verify --batch` manifest at `path` in parallel and print the report
+/// to `out`.
+///
+/// Entries without a scheme are verified with `default_scheme`. Malformed entries are reported
+/// like invalid ones, but with the reason.
+fn verify_batch(
+	mut out: Output,
+	matches: &ArgMatches,
+	path: &Path,
+	default_scheme: &str,
+	output: OutputType,
+) -> Result<(), Error> {
+	let manifest: serde_json::Value = serde_json::from_slice(&fs::read(path)?)
+		.map_err(|e| Error::Formatted(format!("Invalid batch manifest ({})", e)))?;
+	let entries = manifest.as_array()
+		.ok_or(Error::Static("Invalid batch manifest; expecting an array of entries"))?;
+	let format = read_signature_format(matches)?;
+	let encoding = read_message_encoding(matches)?;
+	let should_wrap = matches.is_present("wrap-bytes");
+
+	let results = entries
+		.par_iter()
+		.enumerate()
+		.map(|(index, entry)| {
+			match verify_batch_entry(entry, default_scheme, format, encoding, should_wrap) {
+				Ok(valid) => BatchEntryOutput { index, valid, error: None },
+				Err(e) => BatchEntryOutput { index, valid: false, error: Some(e.to_string()) },
+			}
+		})
+		.collect::<Vec<_>>();
+
+	let valid = results.iter().filter(|result| result.valid).count();
+	let malformed = results.iter().filter(|result| result.error.is_some()).count();
+	let report = BatchVerificationOutput {
+		entries: results,
+		summary: BatchSummaryOutput {
+			total: entries.len(),
+			valid,
+			invalid: entries.len() - valid - malformed,
+			malformed,
+		},
+	};
+	if !matches.is_present("quiet") {
+		output.write_json(&mut out, &report.to_json())?;
+	}
+	out.commit()?;
+
+	if valid == entries.len() || matches.is_present("allow-failures") {
+		Ok(())
+	} else {
+		Err(Error::InvalidSignature)
+	}
+}
+
+/// Verify a single entry of a `verify --batch` manifest.
+fn verify_batch_entry(
+	entry: &serde_json::Value,
+	default_scheme: &str,
+	format: SignatureFormat,
+	encoding: MessageEncoding,
+	should_wrap: bool,
+) -> Result<bool, Error> {
+	fn verify<C: Crypto>(
+		signature: &str,
+		address: &str,
+		message: &[u8],
+		format: SignatureFormat,
+	) -> Result<bool, Error>
+	where
+		SignatureOf<C>: SignatureT,
+		PublicOf<C>: PublicT,
+	{
+		let signature = decode_signature::<C>(signature, format)?;
+		let public = read_public_key::<C>(Some(address))?;
+		Ok(<<C as Crypto>::Pair as Pair>::verify(&signature, message, &public))
+	}
+
+	let field = |name| entry.get(name).and_then(serde_json::Value::as_str);
+	let missing = |name| Error::Formatted(format!("Missing or invalid `{}`; expecting a string", name));
+	let message = field("message").ok_or_else(|| missing("message"))?;
+	let signature = field("signature").ok_or_else(|| missing("signature"))?;
+	let address = field("address").ok_or_else(|| missing("address"))?;
+	let scheme = match entry.get("scheme") {
+		None | Some(serde_json::Value::Null) => default_scheme,
+		Some(_) => field("scheme").ok_or_else(|| missing("scheme"))?,
+	};
+
+	let message = match encoding {
+		MessageEncoding::Hex => decode_hex(message.trim_start_matches("0x"))?,
+		_ => encoding.decode(message.as_bytes())?,
+	};
+	let message = if should_wrap { wrap_bytes(&message) } else { message };
+
+	let scheme = CryptoScheme::from_str_ci(scheme)?;
+	with_crypto_scheme!(scheme, verify::<_>(signature, address, &message, format))
+}
+
+/// The form of the message a signature was found to be valid for.
+#[derive(Debug, PartialEq)]
+pub enum MessageWrapping {
+	/// The message as given.
+	None,
+	/// The message wrapped in `<Bytes>`...`</Bytes>`.
+	Bytes,
+}
+
+/// Verify the signature against the message as given, and failing that against the message
+/// wrapped in `<Bytes>`...`</Bytes>`.
+///
+/// Returns the form of the message that matched, if any.
+pub fn do_verify_any_wrapping<C: Crypto>(
+	matches: &ArgMatches,
+	uri: &str,
+	message: Vec<u8>,
+) -> Result<Option<MessageWrapping>, Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let wrapped = wrap_bytes(&message);
+	if do_verify::<C>(matches, uri, message)? {
+		Ok(Some(MessageWrapping::None))
+	} else if do_verify::<C>(matches, uri, wrapped)? {
+		Ok(Some(MessageWrapping::Bytes))
+	} else {
+		Ok(None)
+	}
+}
+
+const BYTES_WRAPPER_PREFIX: &[u8] = b"<Bytes>";
+const BYTES_WRAPPER_POSTFIX: &[u8] = b"</Bytes>";
+
+/// Wrap the message in `<Bytes>`...`</Bytes>`, as polkadot-js does before signing raw data.
+///
+/// Like polkadot-js, an already wrapped message is returned unchanged.
+pub fn wrap_bytes(message: &[u8]) -> Vec<u8> {
+	if message.starts_with(BYTES_WRAPPER_PREFIX) && message.ends_with(BYTES_WRAPPER_POSTFIX) {
+		return message.to_vec();
+	}
+	[BYTES_WRAPPER_PREFIX, message, BYTES_WRAPPER_POSTFIX].concat()
+}
+
+pub fn read_signature_format(matches: &ArgMatches) -> Result<SignatureFormat, Error> {
+	match matches.value_of("signature-format").map(TryInto::try_into) {
+		Some(Err(_)) => Err(Error::Static("Invalid signature format. See --help for available formats.")),
+		Some(Ok(format)) => Ok(format),
+		None => Ok(SignatureFormat::Hex),
+	}
+}
+
+fn read_signature<C: Crypto>(matches: &ArgMatches) -> Result<SignatureOf<C>, Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let format = read_signature_format(matches)?;
+	if let Some(path) = matches.value_of("sig-file") {
+		return read_signature_file::<C>(Path::new(path), format);
+	}
+	let sig_data = matches
+		.value_of("sig")
+		.expect("signature parameter is required unless sig-file is given; qed");
+	decode_signature::<C>(sig_data, format)
+}
+
+/// The URI given to `verify`, which takes the place of the signature if `--sig-file` is given.
+fn verify_uri<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+	if matches.is_present("sig-file") {
+		matches.value_of("sig")
+	} else {
+		matches.value_of("uri")
+	}
+}
+
+/// Read the signature from the file at `path`.
+///
+/// The file contains either the raw signature or its text encoding given by `format`, where
+/// surrounding whitespace and, for the hex formats, a `0x` prefix are ignored.
+fn read_signature_file<C: Crypto>(path: &Path, format: SignatureFormat) -> Result<SignatureOf<C>, Error>
+where
+	SignatureOf<C>: SignatureT,
+{
+	let sig_data = fs::read(path)?;
+	let mut signature = SignatureOf::<C>::default();
+	if sig_data.len() == signature.as_ref().len() {
+		signature.as_mut().copy_from_slice(&sig_data);
+		return Ok(signature);
+	}
+
+	let sig_text = std::str::from_utf8(&sig_data).map_err(|_| Error::InvalidLength {
+		what: "signature file",
+		read: sig_data.len(),
+		expected: signature.as_ref().len(),
+		scheme: C::NAME,
+	})?.trim();
+	let sig_text = match format {
+		SignatureFormat::Hex | SignatureFormat::MultiSignatureHex => sig_text.trim_start_matches("0x"),
+		SignatureFormat::Base64 => sig_text,
+	};
+	decode_signature::<C>(sig_text, format)
+}
+
+/// Decodes a signature given in the `format` produced by [`format_signature`].
+fn decode_signature<C: Crypto>(sig_data: &str, format: SignatureFormat) -> Result<SignatureOf<C>, Error>
+where
+	SignatureOf<C>: SignatureT,
+{
+	let sig_data = match format {
+		SignatureFormat::Hex => decode_hex(sig_data)?,
+		SignatureFormat::Base64 => decode_base64(sig_data)?,
+		SignatureFormat::MultiSignatureHex => {
+			let multi_signature = Signature::decode(&mut &decode_hex(sig_data)?[..])
+				.map_err(|e| Error::Formatted(format!("Invalid MultiSignature ({})", e)))?;
+			return SignatureOf::<C>::from_runtime(multi_signature)
+				.ok_or(Error::Static("MultiSignature is not of the selected cryptography"));
+		},
+	};
+	let mut signature = <<C as Crypto>::Pair as Pair>::Signature::default();
+	if sig_data.len() != signature.as_ref().len() {
+		return Err(Error::InvalidLength {
+			what: "signature",
+			read: sig_data.len(),
+			expected: signature.as_ref().len(),
+			scheme: C::NAME,
+		});
+	}
+	signature.as_mut().copy_from_slice(&sig_data);
+	Ok(signature)
+}
+
+fn read_public_key<C: Crypto>(matched_uri: Option<&str>) -> Result<PublicOf<C>, Error>
+where
+	PublicOf<C>: PublicT,
+{
+	let uri = matched_uri.expect("parameter is required; thus it can't be None; qed");
+	let data = uri.strip_prefix("0x").unwrap_or(uri);
+	// Hex of the length of a public key takes precedence, as does the `0x` prefix, which can not be
+	// part of an SS58 address. Other hex digits are read as an SS58 address if they are one.
+	if let Ok(pubkey_vec) = hex::decode(data) {
+		let mut public = PublicOf::<C>::default();
+		if pubkey_vec.len() == public.as_ref().len() {
+			public.as_mut().copy_from_slice(&pubkey_vec);
+			return Ok(public);
+		}
+		if data.len() != uri.len() || !public_key_is_ambiguous::<C>(uri) {
+			return Err(Error::Formatted(format!(
+				"Invalid hex length for public key; should be {} bytes",
+				public.as_ref().len(),
+			)));
+		}
+	}
+	<C as Crypto>::Public::from_string_with_version(uri)
+		.map(|(public, _)| public)
+		.map_err(|_| Error::Static("Invalid URI; expecting either a secret URI or a public URI."))
+}
+
+/// Returns `true` if `uri` is hex-encoded public key data.
+fn is_hex_public_key<C: Crypto>(uri: &str) -> bool
+where
+	PublicOf<C>: PublicT,
+{
+	hex::decode(uri.trim_start_matches("0x"))
+		.map(|data| data.len() == PublicOf::<C>::default().as_ref().len())
+		.unwrap_or(false)
+}
+
+/// Describe how [`read_public_key`] reads the public key `uri`.
+fn public_key_interpretation<C: Crypto>(uri: &str) -> &'static str
+where
+	PublicOf<C>: PublicT,
+{
+	if is_hex_public_key::<C>(uri) {
+		"hex-encoded public key"
+	} else {
+		"SS58 public URI"
+	}
+}
+
+/// Returns `true` if `uri` consists of hex digits only, but is read as an SS58 address by
+/// [`read_public_key`] as it is not of the length of a public key.
+fn public_key_is_ambiguous<C: Crypto>(uri: &str) -> bool
+where
+	PublicOf<C>: PublicT,
+{
+	hex::decode(uri).is_ok()
+		&& !is_hex_public_key::<C>(uri)
+		&& PublicOf::<C>::from_string_with_version(uri).is_ok()
+}
+
+/// Read the signer of `verify`, reading it from the file at `<path>` if it is given as `@<path>`.
+fn read_signer(uri: Option<&str>) -> Result<String, Error> {
+	match uri {
+		Some(uri) if uri.starts_with('@') => {
+			let path = Path::new(&uri[1..]);
+			if !path.is_file() {
+				return Err(Error::Formatted(format!(
+					"`{}` is not a regular file; can not read the public key from it",
+					path.display(),
+				)));
+			}
+			Ok(fs::read_to_string(path)?.trim().into())
+		},
+		// A public key is no secret.
+		uri => read_uri(uri).map(|uri| uri.expose_secret().clone()),
+	}
+}
+
+pub fn read_pair<C: Crypto>(
+	matched_suri: Option<&str>,
+	password: Option<&str>,
+) -> Result<<C as Crypto>::Pair, Error> where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let suri = matched_suri.ok_or(Error::Static("parameter is required; thus it can't be None; qed"))?;
+	C::pair_from_suri(suri, password)
+}
+
+fn format_signature<C: Crypto>(signature: SignatureOf<C>, format: SignatureFormat) -> String where
+	SignatureOf<C>: SignatureT,
+{
+	match format {
+		SignatureFormat::Hex => format!("{}", HexDisplay::from(&signature.as_ref())),
+		SignatureFormat::Base64 => encode_base64(signature.as_ref()),
+		SignatureFormat::MultiSignatureHex =>
+			format!("{}", HexDisplay::from(&signature.into_runtime().encode())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{ed25519, sr25519, Public};
+
+	fn test_read_signature_file<CryptoType: Crypto>()
+	where
+		SignatureOf<CryptoType>: SignatureT,
+		PublicOf<CryptoType>: PublicT,
+	{
+		let signature = CryptoType::pair_from_suri("//Alice", None).unwrap().sign(b"message");
+		let hex_signature = hex::encode(&signature);
+		let read = |content: &[u8]| {
+			let mut file = tempfile::NamedTempFile::new().unwrap();
+			file.write_all(content).unwrap();
+			read_signature_file::<CryptoType>(file.path(), SignatureFormat::Hex)
+		};
+
+		assert_eq!(read(signature.as_ref()).unwrap().as_ref(), signature.as_ref());
+		assert_eq!(read(hex_signature.as_bytes()).unwrap().as_ref(), signature.as_ref());
+		assert_eq!(read(format!("0x{}\n", hex_signature).as_bytes()).unwrap().as_ref(), signature.as_ref());
+
+		let error = read(&signature.as_ref()[1..]).err().unwrap().to_string();
+		assert!(error.contains(&format!("expected {} bytes for {}", signature.as_ref().len(), CryptoType::NAME)));
+		let error = read(hex_signature[2..].as_bytes()).err().unwrap().to_string();
+		assert!(error.contains(&format!("expected {} bytes for {}", signature.as_ref().len(), CryptoType::NAME)));
+	}
+
+	#[test]
+	fn read_signature_file_works_for_ed25519() {
+		test_read_signature_file::<Ed25519>();
+	}
+
+	#[test]
+	fn read_signature_file_works_for_sr25519() {
+		test_read_signature_file::<Sr25519>();
+	}
+
+	#[test]
+	fn read_signature_file_works_for_ecdsa() {
+		test_read_signature_file::<Ecdsa>();
+	}
+
+	#[test]
+	fn multi_signature_of_other_cryptography_is_rejected() {
+		let signature = ed25519::Pair::from_string("//Alice", None).unwrap().sign(b"message");
+		let encoded = format_signature::<Ed25519>(signature, SignatureFormat::MultiSignatureHex);
+
+		assert!(decode_signature::<Ed25519>(&encoded, SignatureFormat::MultiSignatureHex).is_ok());
+		assert!(decode_signature::<Sr25519>(&encoded, SignatureFormat::MultiSignatureHex).is_err());
+	}
+
+	#[test]
+	fn wrap_bytes_works() {
+		assert_eq!(wrap_bytes(b"hello"), b"<Bytes>hello</Bytes>".to_vec());
+		assert_eq!(wrap_bytes(b""), b"<Bytes></Bytes>".to_vec());
+		assert_eq!(wrap_bytes(b"<Bytes>hello</Bytes>"), b"<Bytes>hello</Bytes>".to_vec());
+	}
+
+	#[test]
+	fn sign_file_writes_a_detached_signature() {
+		let dir = tempfile::tempdir().unwrap();
+		let input = dir.path().join("release.tar.gz");
+		fs::write(&input, b"release").unwrap();
+		let pair = Sr25519::pair_from_suri("//Alice", None).unwrap();
+
+		let written = sign_file::<Sr25519>(&pair, &input, None, None, SignatureFormat::Hex)
+			.unwrap();
+		assert_eq!(written, dir.path().join("release.tar.gz.sig"));
+		let signature = read_signature_file::<Sr25519>(&written, SignatureFormat::Hex).unwrap();
+		assert!(sr25519::Pair::verify(&signature, b"release", &pair.public()));
+		// Signing again replaces the signature.
+		sign_file::<Sr25519>(&pair, &input, None, None, SignatureFormat::Base64).unwrap();
+		let signature = read_signature_file::<Sr25519>(&written, SignatureFormat::Base64).unwrap();
+		assert!(sr25519::Pair::verify(&signature, b"release", &pair.public()));
+
+		let output = dir.path().join("detached");
+		let hash = Some(HashAlgo::Sha2_256);
+		let format = SignatureFormat::Base64;
+		let written = sign_file::<Sr25519>(&pair, &input, Some(&output), hash, format).unwrap();
+		assert_eq!(written, output);
+		let signature = read_signature_file::<Sr25519>(&written, SignatureFormat::Base64).unwrap();
+		let digest = sp_core::hashing::sha2_256(b"release");
+		assert!(sr25519::Pair::verify(&signature, &digest, &pair.public()));
+
+		let missing = dir.path().join("missing");
+		assert!(sign_file::<Sr25519>(&pair, &missing, None, None, SignatureFormat::Hex).is_err());
+	}
+
+	fn test_signer_forms<CryptoType: Crypto>()
+	where
+		PublicOf<CryptoType>: PublicT,
+	{
+		let public = CryptoType::pair_from_suri("//Alice", None).unwrap().public();
+		let hex_public = hex::encode(public.as_ref());
+		let forms = vec![
+			format!("0x{}", hex_public),
+			hex_public,
+			public.to_ss58check(),
+			public.to_ss58check_with_version(Ss58AddressFormat::KusamaAccount),
+		];
+
+		for form in &forms {
+			let read = read_public_key::<CryptoType>(Some(&read_signer(Some(form)).unwrap())).unwrap();
+			assert_eq!(read.as_ref(), public.as_ref());
+
+			let mut file = tempfile::NamedTempFile::new().unwrap();
+			writeln!(file, "{}", form).unwrap();
+			let signer = format!("@{}", file.path().display());
+			assert_eq!(read_signer(Some(&signer)).unwrap(), *form);
+			let read = read_public_key::<CryptoType>(Some(&read_signer(Some(&signer)).unwrap())).unwrap();
+			assert_eq!(read.as_ref(), public.as_ref());
+		}
+
+		assert!(forms.iter().all(|form| !public_key_is_ambiguous::<CryptoType>(form)));
+		assert!(read_signer(Some("@/does/not/exist")).is_err());
+	}
+
+	#[test]
+	fn signer_forms_work_for_ed25519() {
+		test_signer_forms::<Ed25519>();
+	}
+
+	#[test]
+	fn signer_forms_work_for_sr25519() {
+		test_signer_forms::<Sr25519>();
+	}
+
+	#[test]
+	fn signer_forms_work_for_ecdsa() {
+		test_signer_forms::<Ecdsa>();
+	}
+
+	#[test]
+	fn hex_of_other_length_is_rejected_unless_ss58() {
+		let data = hex::encode([0x11; 31]);
+		assert!(!public_key_is_ambiguous::<Sr25519>(&data));
+		let error = read_public_key::<Sr25519>(Some(&data)).err().unwrap().to_string();
+		assert!(error.contains("should be 32 bytes"));
+	}
+
+	fn check_test_vectors<C: Crypto>()
+	where
+		PublicOf<C>: PublicT,
+		SignatureOf<C>: SignatureT,
+	{
+		let vectors = test_vectors::<C>(&TEST_VECTOR_SEED, 3, None).unwrap().to_json();
+		let again = test_vectors::<C>(&TEST_VECTOR_SEED, 3, None).unwrap().to_json();
+		assert_eq!(vectors.to_string(), again.to_string());
+		assert_eq!(vectors["scheme"], C::NAME);
+
+		let vectors = vectors["vectors"].as_array().unwrap();
+		assert_eq!(vectors.len(), 3);
+		let unhex = |value: &serde_json::Value| decode_hex(&value.as_str().unwrap()[2..]).unwrap();
+		for (i, vector) in vectors.iter().enumerate() {
+			let suri = vector["suri"].as_str().unwrap();
+			assert_eq!(suri, format!("0x{}//{}", hex::encode(TEST_VECTOR_SEED), i));
+			let public = PublicOf::<C>::from_slice(&unhex(&vector["publicKey"]));
+			assert_eq!(public.as_ref(), C::pair_from_suri(suri, None).unwrap().public().as_ref());
+			let signatures = vector["signatures"].as_array().unwrap();
+			assert_eq!(signatures.len(), TEST_VECTOR_MESSAGES.len());
+			for (signature, message) in signatures.iter().zip(&TEST_VECTOR_MESSAGES) {
+				assert_eq!(unhex(&signature["message"]), *message);
+				let mut bytes = SignatureOf::<C>::default();
+				bytes.as_mut().copy_from_slice(&unhex(&signature["signature"]));
+				assert!(<C::Pair as Pair>::verify(&bytes, message, &public));
+			}
+		}
+		let other_seed = test_vectors::<C>(&[1; 32], 1, None).unwrap().to_json();
+		assert_ne!(other_seed["vectors"][0]["publicKey"], vectors[0]["publicKey"]);
+	}
+
+	#[test]
+	fn test_vectors_are_reproducible_and_verify() {
+		check_test_vectors::<Ed25519>();
+		check_test_vectors::<Sr25519>();
+		check_test_vectors::<Ecdsa>();
+	}
+
+	/// Start a mock remote signer, answering sign requests with `respond`.
+	fn mock_signer<F>(respond: F) -> (jsonrpc_http_server::Server, rpc::RemoteSigner)
+	where
+		F: Fn(serde_json::Map<String, serde_json::Value>) -> jsonrpc_core::Result<serde_json::Value>
+			+ Send + Sync + 'static,
+	{
+		let mut io = jsonrpc_core::IoHandler::new();
+		io.add_method(rpc::SIGNER_SIGN_METHOD, move |params: jsonrpc_core::Params| respond(params.parse()?));
+		let server = jsonrpc_http_server::ServerBuilder::new(io)
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap();
+		let signer = rpc::RemoteSigner::new(
+			format!("http://{}", server.address()),
+			std::time::Duration::from_millis(500),
+		);
+		(server, signer)
+	}
+
+	/// Sign the hex-encoded message of a sign request with `pair`.
+	fn sign_request(pair: &sr25519::Pair, params: &serde_json::Map<String, serde_json::Value>) -> String {
+		let message = params["message"].as_str().unwrap().trim_start_matches("0x");
+		format!("0x{}", hex::encode(pair.sign(&hex::decode(message).unwrap())))
+	}
+
+	#[test]
+	fn remote_signer_signature_is_verified() {
+		let alice = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let expected_public = format!("0x{}", hex::encode(alice.public()));
+		let pair = alice.clone();
+		let (_server, signer) = mock_signer(move |params| {
+			assert_eq!(params["publicKey"], expected_public.as_str());
+			assert_eq!(params["scheme"], "sr25519");
+			Ok(sign_request(&pair, &params).into())
+		});
+
+		let signature = remote_sign::<Sr25519>(&signer, &alice.public(), b"Blah Blah\n").unwrap();
+		assert!(sr25519::Pair::verify(&signature, b"Blah Blah\n", &alice.public()));
+	}
+
+	#[test]
+	fn remote_signer_failures_are_distinguished() {
+		let alice = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let sign = |signer: &rpc::RemoteSigner| remote_sign::<Sr25519>(signer, &alice.public(), b"message");
+
+		let bob = sr25519::Pair::from_string("//Bob", None).unwrap();
+		let (_server, signer) = mock_signer(move |params| Ok(sign_request(&bob, &params).into()));
+		assert!(matches!(sign(&signer), Err(Error::RemoteSigner(rpc::RemoteSignerError::SignatureMismatch))));
+
+		let (_server, signer) = mock_signer(|_| Err(jsonrpc_core::Error::invalid_params("unknown key")));
+		assert!(matches!(sign(&signer), Err(Error::RemoteSigner(rpc::RemoteSignerError::Rpc(_)))));
+
+		let (_server, signer) = mock_signer(|_| Ok(42.into()));
+		assert!(matches!(sign(&signer), Err(Error::RemoteSigner(rpc::RemoteSignerError::InvalidResponse(_)))));
+
+		let (_server, signer) = mock_signer(|_| {
+			std::thread::sleep(std::time::Duration::from_secs(2));
+			Ok("0x".into())
+		});
+		assert!(matches!(sign(&signer), Err(Error::RemoteSigner(rpc::RemoteSignerError::Timeout(_)))));
+
+		let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		let signer = rpc::RemoteSigner::new(format!("http://{}", address), std::time::Duration::from_secs(5));
+		assert!(matches!(sign(&signer), Err(Error::RemoteSigner(rpc::RemoteSignerError::Connection(_)))));
+	}
+}
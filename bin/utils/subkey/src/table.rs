@@ -0,0 +1,148 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rendering of results with many rows as a table, for `--output table`.
+
+use clap::ArgMatches;
+use itertools::Itertools;
+use std::io::{self, Write};
+
+/// The spaces between two aligned columns.
+const COLUMN_GAP: &str = "  ";
+/// The ANSI escape sequences the header of an aligned table is highlighted with.
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// How a table is rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableStyle {
+	/// Whether the first row names the columns.
+	pub header: bool,
+	/// Whether the columns are padded to a common width and the header is highlighted.
+	/// Otherwise each row is printed plainly, with its values separated by tabs.
+	pub aligned: bool,
+}
+
+impl TableStyle {
+	/// The style selected by `--no-header` and `--no-color` in `matches`.
+	///
+	/// Tables are only aligned on a terminal, unless `--no-color` is given or `NO_COLOR` is set.
+	pub fn from_matches(matches: &ArgMatches) -> Self {
+		let no_color = matches.is_present("no-color") || std::env::var_os("NO_COLOR").is_some();
+		TableStyle {
+			header: !matches.is_present("no-header"),
+			aligned: !no_color && atty::is(atty::Stream::Stdout),
+		}
+	}
+}
+
+/// Write `rows` to `out` as a table with `columns`.
+///
+/// Each column is as wide as its widest value, so long values like SS58 addresses are never cut.
+pub(super) fn write_table<W: Write>(
+	out: &mut W,
+	columns: &[&str],
+	rows: &[Vec<String>],
+	style: TableStyle,
+) -> io::Result<()> {
+	if !style.aligned {
+		if style.header {
+			writeln!(out, "{}", columns.join("\t"))?;
+		}
+		for row in rows {
+			writeln!(out, "{}", row.join("\t"))?;
+		}
+		return Ok(());
+	}
+
+	let mut widths = columns.iter().map(|column| column.chars().count()).collect::<Vec<_>>();
+	for row in rows {
+		for (width, value) in widths.iter_mut().zip(row) {
+			*width = (*width).max(value.chars().count());
+		}
+	}
+	let line = |values: &mut dyn Iterator<Item = &str>| {
+		values
+			.zip(&widths)
+			.map(|(value, width)| format!("{:<width$}", value, width = width))
+			.join(COLUMN_GAP)
+			.trim_end()
+			.to_string()
+	};
+	if style.header {
+		writeln!(out, "{}{}{}", BOLD, line(&mut columns.iter().copied()), RESET)?;
+	}
+	for row in rows {
+		writeln!(out, "{}", line(&mut row.iter().map(String::as_str)))?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const COLUMNS: &[&str] = &["Path", "SS58 address"];
+
+	fn render(style: TableStyle) -> String {
+		let rows = vec![
+			vec!["//Alice".to_string(), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".into()],
+			vec![
+				"//Alice//stash".to_string(),
+				"5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY".into(),
+			],
+		];
+		let mut out = Vec::new();
+		write_table(&mut out, COLUMNS, &rows, style).unwrap();
+		String::from_utf8(out).unwrap()
+	}
+
+	#[test]
+	fn aligned_table_pads_each_column() {
+		assert_eq!(
+			render(TableStyle { header: true, aligned: true }),
+			"\x1b[1mPath            SS58 address\x1b[0m\n\
+			//Alice         5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY\n\
+			//Alice//stash  5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY\n",
+		);
+		let without_header = render(TableStyle { header: false, aligned: true });
+		assert!(without_header.starts_with("//Alice         5Grwva"));
+		assert_eq!(without_header.lines().count(), 2);
+	}
+
+	#[test]
+	fn plain_table_separates_values_with_tabs() {
+		assert_eq!(
+			render(TableStyle { header: true, aligned: false }),
+			"Path\tSS58 address\n\
+			//Alice\t5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY\n\
+			//Alice//stash\t5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY\n",
+		);
+		let without_header = render(TableStyle { header: false, aligned: false });
+		assert!(without_header.starts_with("//Alice\t"));
+	}
+
+	#[test]
+	fn values_wider_than_the_header_widen_the_column() {
+		let rows = vec![vec!["a".repeat(100), "b".into()]];
+		let mut out = Vec::new();
+		write_table(&mut out, &["A", "B"], &rows, TableStyle { header: false, aligned: true })
+			.unwrap();
+		assert_eq!(String::from_utf8(out).unwrap(), format!("{}  b\n", "a".repeat(100)));
+	}
+}
@@ -0,0 +1,362 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Authoring and signing transactions for the node runtime.
+
+use crate::{
+	account::read_account_id,
+	crypto::{Crypto, PublicOf, PublicT, SignatureOf, SignatureT},
+	error::Error,
+	input::{confirm, decode_hex, read_optional_parameter, read_required_parameter, read_uri},
+	sign::read_pair,
+};
+use clap::ArgMatches;
+use codec::{Decode, Encode};
+use hex_literal::hex;
+use node_primitives::{AccountId, Balance, BlockNumber, Hash, Index};
+use node_runtime::{BalancesCall, Call, Runtime, SignedPayload, UncheckedExtrinsic, VERSION};
+use serde_json::json;
+use sp_core::{crypto::ExposeSecret, hexdisplay::HexDisplay, Pair, H256};
+use sp_runtime::{generic::Era, traits::IdentifyAccount};
+use std::io::stdin;
+
+/// Author and sign a transfer, the `transfer` command.
+pub fn run_transfer<C: Crypto>(matches: &ArgMatches, password: Option<&str>) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let signer = read_pair::<C>(matches.value_of("from"), password)?;
+	let index = read_required_parameter::<Index>(matches, "index")?;
+	let genesis_hash = read_genesis_hash(matches)?;
+
+	let to: AccountId = read_account_id(matches.value_of("to"))?;
+	let amount = read_required_parameter::<Balance>(matches, "amount")?;
+	let function = Call::Balances(BalancesCall::transfer(to.into(), amount));
+
+	let params = ExtrinsicParams::new(index, genesis_hash);
+	let extrinsic = create_extrinsic::<C>(function, signer, params);
+
+	print_extrinsic(extrinsic);
+	Ok(())
+}
+
+/// Sign a transaction, the `sign-transaction` command.
+pub fn run_sign_transaction<C: Crypto>(
+	matches: &ArgMatches,
+	password: Option<&str>,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let password = matches.value_of("password").or(password);
+	let suri = read_uri(matches.value_of("suri"))?;
+	let signer = read_pair::<C>(Some(suri.expose_secret()), password)?;
+	let call = matches.value_of("call").expect("call is required; qed");
+	let function = decode_call(call)?;
+	let params = read_extrinsic_params(matches)?;
+
+	let review = matches.is_present("confirm");
+	if matches.is_present("show-payload") || review {
+		eprintln!("{}", serde_json::to_string_pretty(&payload_breakdown(&function, &params))
+			.expect("Json pretty print failed"));
+	}
+	if review && !matches.is_present("yes") {
+		confirm("Sign this payload? [y/N] ", "Signing was not confirmed", |prompt| {
+			eprint!("{}", prompt);
+			let mut answer = String::new();
+			stdin().read_line(&mut answer).map(|_| answer)
+		})?;
+	}
+	let extrinsic = create_extrinsic::<C>(function, signer, params);
+
+	print_extrinsic(extrinsic);
+	Ok(())
+}
+
+pub fn read_genesis_hash(matches: &ArgMatches) -> Result<H256, Error> {
+	let genesis_hash: Hash = match matches.value_of("genesis").unwrap_or("alex") {
+		"elm" => hex!["10c08714a10c7da78f40a60f6f732cf0dba97acfb5e2035445b032386157d5c3"].into(),
+		"alex" => hex!["dcd1346701ca8396496e52aa2785b1748deb6db09551b72159dcb3e08991025b"].into(),
+		h => {
+			let data = decode_hex(h)?;
+			if data.len() != H256::len_bytes() {
+				return Err(Error::Formatted(format!(
+					"Invalid genesis hash or unrecognized chain identifier `{}`",
+					h,
+				)));
+			}
+			H256::from_slice(&data)
+		},
+	};
+	eprintln!(
+		"Using a genesis hash of {}",
+		HexDisplay::from(&genesis_hash.as_ref())
+	);
+	Ok(genesis_hash)
+}
+
+/// The values of the signed extensions of a transaction that are up to its author.
+#[derive(Clone, Copy)]
+pub struct ExtrinsicParams {
+	nonce: Index,
+	tip: Balance,
+	genesis_hash: H256,
+	spec_version: u32,
+	transaction_version: u32,
+	/// The mortal era and the hash of the block it starts at; `None` for an immortal transaction.
+	era: Option<(Era, H256)>,
+}
+
+impl ExtrinsicParams {
+	/// The parameters of an immortal transaction without tip for the runtime `subkey` is built with.
+	fn new(nonce: Index, genesis_hash: H256) -> Self {
+		ExtrinsicParams {
+			nonce,
+			tip: 0,
+			genesis_hash,
+			spec_version: VERSION.spec_version,
+			transaction_version: VERSION.transaction_version,
+			era: None,
+		}
+	}
+}
+
+pub fn read_extrinsic_params(matches: &ArgMatches) -> Result<ExtrinsicParams, Error> {
+	let nonce = read_required_parameter::<Index>(matches, "nonce")?;
+	let mut params = ExtrinsicParams::new(nonce, read_genesis_hash(matches)?);
+	if let Some(tip) = read_optional_parameter(matches, "tip")? {
+		params.tip = tip;
+	}
+	if let Some(spec_version) = read_optional_parameter(matches, "spec-version")? {
+		params.spec_version = spec_version;
+	}
+	if let Some(transaction_version) = read_optional_parameter(matches, "transaction-version")? {
+		params.transaction_version = transaction_version;
+	}
+	if let Some(period) = read_optional_parameter::<u64>(matches, "era")? {
+		let number = read_required_parameter::<BlockNumber>(matches, "block-number")?;
+		let hash = matches.value_of("prior-block-hash").expect("era requires prior-block-hash; qed");
+		params.era = Some((mortal_era(period, number)?, read_block_hash(hash)?));
+	}
+	Ok(params)
+}
+
+/// The mortal era of `period` blocks starting at block `number`.
+///
+/// Fails if the era can not start at `number`, as the runtime checks the signature against the
+/// hash of the block the era actually starts at.
+fn mortal_era(period: u64, number: BlockNumber) -> Result<Era, Error> {
+	let era = Era::mortal(period, number.into());
+	let birth = era.birth(number.into());
+	if birth != u64::from(number) {
+		return Err(Error::Formatted(format!(
+			"A mortal era of {} blocks can not start at block {}, only at block {}; give the number \
+			and hash of that block instead",
+			period,
+			number,
+			birth,
+		)));
+	}
+	Ok(era)
+}
+
+fn read_block_hash(hash: &str) -> Result<H256, Error> {
+	let data = decode_hex(hash.trim_start_matches("0x"))?;
+	if data.len() != H256::len_bytes() {
+		return Err(Error::Formatted(format!("Invalid block hash `{}`; should be 32 bytes", hash)));
+	}
+	Ok(H256::from_slice(&data))
+}
+
+/// Decode the hex-encoded `call`, which must not be followed by any other data.
+pub fn decode_call(call: &str) -> Result<Call, Error> {
+	let data = decode_hex(call.trim_start_matches("0x"))?;
+	let mut input = &data[..];
+	let function = Call::decode(&mut input).map_err(|e| Error::Formatted(format!("Invalid call ({})", e)))?;
+	if !input.is_empty() {
+		return Err(Error::Formatted(format!("Invalid call; {} bytes are left over", input.len())));
+	}
+	Ok(function)
+}
+
+/// Create the signed extrinsic of `function`.
+pub fn create_extrinsic<C: Crypto>(
+	function: Call,
+	signer: C::Pair,
+	params: ExtrinsicParams,
+) -> UncheckedExtrinsic where
+	PublicOf<C>: PublicT,
+	SignatureOf<C>: SignatureT,
+{
+	sign_payload::<C>(create_signed_payload(function, &params), signer)
+}
+
+/// Create the payload `function` is signed with.
+fn create_signed_payload(function: Call, params: &ExtrinsicParams) -> SignedPayload {
+	let (era, era_hash) = params.era.unwrap_or((Era::Immortal, params.genesis_hash));
+	let extra = |i: Index, f: Balance| {
+		(
+			frame_system::CheckSpecVersion::<Runtime>::new(),
+			frame_system::CheckTxVersion::<Runtime>::new(),
+			frame_system::CheckGenesis::<Runtime>::new(),
+			frame_system::CheckEra::<Runtime>::from(era),
+			frame_system::CheckNonce::<Runtime>::from(i),
+			frame_system::CheckWeight::<Runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(f),
+		)
+	};
+	SignedPayload::from_raw(
+		function,
+		extra(params.nonce, params.tip),
+		(
+			params.spec_version,
+			params.transaction_version,
+			params.genesis_hash,
+			era_hash,
+			(),
+			(),
+			(),
+		),
+	)
+}
+
+/// Sign `raw_payload` and create the extrinsic from it.
+///
+/// Like the runtime, this signs the blake2-256 hash of signed payloads longer than 256 bytes.
+fn sign_payload<C: Crypto>(raw_payload: SignedPayload, signer: C::Pair) -> UncheckedExtrinsic where
+	PublicOf<C>: PublicT,
+	SignatureOf<C>: SignatureT,
+{
+	let signature = raw_payload.using_encoded(|payload| signer.sign(payload)).into_runtime();
+	let signer = signer.public().into_runtime();
+	let (function, extra, _) = raw_payload.deconstruct();
+
+	UncheckedExtrinsic::new_signed(
+		function,
+		signer.into_account().into(),
+		signature,
+		extra,
+	)
+}
+
+/// Describe the payload `function` is signed with for review.
+///
+/// `payload` is the SCALE encoding of the call, the signed extensions and their additional data,
+/// while `signedBytes` is what is actually signed, which is the blake2-256 hash of `payload` if it
+/// is longer than 256 bytes.
+fn payload_breakdown(function: &Call, params: &ExtrinsicParams) -> serde_json::Value {
+	let hex = |data: &[u8]| format!("0x{}", HexDisplay::from(&data));
+	let payload = create_signed_payload(function.clone(), params);
+	let signed_bytes = payload.using_encoded(|signed| signed.to_vec());
+	// `SignedPayload` is only encoded hashed, so the full payload is encoded from its parts.
+	let full_payload = payload.deconstruct().encode();
+	let (era, era_hash) = params.era.unwrap_or((Era::Immortal, params.genesis_hash));
+	let era = match era {
+		Era::Immortal => json!("immortal"),
+		Era::Mortal(period, phase) => json!({ "period": period, "phase": phase }),
+	};
+	json!({
+		"payload": hex(&full_payload),
+		"signedBytes": hex(&signed_bytes),
+		"call": hex(&function.encode()),
+		"era": era,
+		"nonce": params.nonce,
+		"tip": params.tip.to_string(),
+		"specVersion": params.spec_version,
+		"transactionVersion": params.transaction_version,
+		"genesisHash": hex(params.genesis_hash.as_bytes()),
+		"blockHash": hex(era_hash.as_bytes()),
+	})
+}
+
+fn print_extrinsic(extrinsic: UncheckedExtrinsic) {
+	println!("0x{}", HexDisplay::from(&extrinsic.encode()));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crypto::{AccountPublic, Ed25519};
+	use sp_core::blake2_256;
+	use sp_runtime::traits::Verify;
+
+	#[test]
+	fn shown_payload_is_signed() {
+		let pair = Ed25519::pair_from_suri("//Alice", None).unwrap();
+		let account = AccountPublic::from(pair.public()).into_account();
+		let short_call = Call::System(frame_system::Call::remark(vec![1; 10]));
+		let long_call = Call::System(frame_system::Call::remark(vec![1; 300]));
+		let mut params = ExtrinsicParams::new(7, H256::repeat_byte(0x22));
+		params.tip = 5;
+		params.era = Some((Era::mortal(64, 128), H256::repeat_byte(0x33)));
+
+		for call in &[short_call, long_call] {
+			let breakdown = payload_breakdown(call, &params);
+			let field = |name: &str| hex::decode(&breakdown[name].as_str().unwrap()[2..]).unwrap();
+			let payload = field("payload");
+			let signed_bytes = field("signedBytes");
+			if payload.len() > 256 {
+				assert_eq!(signed_bytes, blake2_256(&payload).to_vec());
+			} else {
+				assert_eq!(signed_bytes, payload);
+			}
+			assert_eq!(field("call"), call.encode());
+			assert_eq!(breakdown["nonce"], 7);
+			assert_eq!(breakdown["tip"], "5");
+			assert_eq!(breakdown["era"], json!({ "period": 64, "phase": 0 }));
+			assert_eq!(field("blockHash"), vec![0x33; 32]);
+
+			let extrinsic = create_extrinsic::<Ed25519>(call.clone(), pair.clone(), params);
+			let (_, signature, _) = extrinsic.signature.unwrap();
+			assert!(signature.verify(&signed_bytes[..], &account));
+		}
+	}
+
+	#[test]
+	fn sign_transaction_rejects_invalid_input() {
+		assert!(mortal_era(64, 128).is_ok());
+		let error = mortal_era(1 << 16, 100).err().unwrap().to_string();
+		assert!(error.contains("only at block 96"));
+
+		let call = Call::System(frame_system::Call::remark(vec![1, 2, 3])).encode();
+		assert!(decode_call(&hex::encode(&call)).is_ok());
+		assert!(decode_call(&format!("0x{}", hex::encode(&call))).is_ok());
+		assert!(decode_call(&format!("{}00", hex::encode(&call))).is_err());
+		assert!(read_block_hash("0x1234").is_err());
+	}
+
+	#[test]
+	fn should_work() {
+		let s = "0123456789012345678901234567890123456789012345678901234567890123";
+
+		let d1: Hash = hex::decode(s)
+			.ok()
+			.and_then(|x| Decode::decode(&mut &x[..]).ok())
+			.unwrap();
+
+		let d2: Hash = {
+			let mut gh: [u8; 32] = Default::default();
+			gh.copy_from_slice(hex::decode(s).unwrap().as_ref());
+			Hash::from(gh)
+		};
+
+		assert_eq!(d1, d2);
+	}
+}
@@ -16,9 +16,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use super::{PublicOf, PublicT, Crypto};
-use sp_core::Pair;
+use crate::{
+	crypto::{Crypto, PublicOf, PublicT, SignatureOf, SignatureT},
+	error::Error,
+	format::format_seed,
+	input::read_required_parameter,
+	output::{Output, OutputType, Secrets},
+};
+use clap::ArgMatches;
+use sp_core::{crypto::{Ss58AddressFormat, Ss58Codec}, Pair};
+use sp_runtime::traits::IdentifyAccount;
 use rand::{rngs::OsRng, RngCore};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 fn good_waypoint(done: u64) -> u64 {
 	match done {
@@ -65,9 +74,9 @@ fn calculate_score(_desired: &str, key: &str) -> usize {
 }
 
 /// Validate whether the char is allowed to be used in base58.
-/// num 0, lower l, upper I and O are not allowed.
+/// Only ASCII letters and digits are, except for num 0, lower l, upper I and O.
 fn validate_base58(c :char) -> bool {
-	c.is_alphanumeric() && !"0lIO".contains(c)
+	c.is_ascii_alphanumeric() && !"0lIO".contains(c)
 }
 
 pub(super) fn generate_key<C: Crypto>(desired: &str) -> Result<KeyPair<C>, &'static str> where
@@ -79,7 +88,7 @@ pub(super) fn generate_key<C: Crypto>(desired: &str) -> Result<KeyPair<C>, &'sta
 
 	if !desired.chars().all(validate_base58) {
 		return Err("Pattern can only contains valid characters in base58 \
-			(all alphanumeric except for 0, l, I and O)");
+			(all ASCII letters and digits except for 0, l, I and O)");
 	}
 
 	eprintln!("Generating key containing pattern '{}'", desired);
@@ -119,9 +128,124 @@ pub(super) fn generate_key<C: Crypto>(desired: &str) -> Result<KeyPair<C>, &'sta
 	}
 }
 
+/// Generate a key whose SS58 address on `network` starts with `prefix`.
+///
+/// The prefix includes the leading characters that are the same for all addresses of the network,
+/// e.g. `5` for substrate. The search gives up after `max_attempts` keys, or as soon as `cancel`
+/// is set; both errors tell how many keys were tried.
+pub fn generate_vanity<C: Crypto>(
+	prefix: &str,
+	network: Ss58AddressFormat,
+	max_attempts: u64,
+	cancel: &AtomicBool,
+) -> Result<(C::Pair, <C::Pair as Pair>::Seed), Error> where
+		PublicOf<C>: PublicT,
+{
+	if prefix.is_empty() {
+		return Err(Error::Static("Prefix must not be empty"));
+	}
+
+	if !prefix.chars().all(validate_base58) {
+		return Err(Error::Static("Prefix can only contains valid characters in base58 \
+			(all ASCII letters and digits except for 0, l, I and O)"));
+	}
+
+	eprintln!("Generating key with an address starting with '{}'", prefix);
+
+	let mut seed = <C::Pair as Pair>::Seed::default();
+	let mut done = 0;
+
+	while done < max_attempts {
+		if cancel.load(Ordering::Relaxed) {
+			return Err(Error::Formatted(format!("Search stopped after {} attempts", done)));
+		}
+
+		if done % 100000 == 0 {
+			OsRng.fill_bytes(seed.as_mut());
+		} else {
+			next_seed(seed.as_mut());
+		}
+
+		let p = C::Pair::from_seed(&seed);
+		let ss58 = p.public().into_runtime().into_account().to_ss58check_with_version(network);
+		done += 1;
+		if ss58.starts_with(prefix) {
+			eprintln!("Found {} after {} attempts", ss58, done);
+			return Ok((p, seed));
+		}
+
+		if done % good_waypoint(done) == 0 {
+			eprintln!("{} keys searched", done);
+		}
+	}
+
+	Err(Error::Formatted(format!(
+		"No address starting with '{}' found in {} attempts",
+		prefix,
+		done,
+	)))
+}
+
+/// Run `search` with a flag that is set by Ctrl-C, so that it stops the search rather than the
+/// whole program.
+#[cfg(target_family = "unix")]
+pub(super) fn interruptible<T>(search: impl FnOnce(&AtomicBool) -> T) -> T {
+	static CANCEL: AtomicBool = AtomicBool::new(false);
+	// Storing to an atomic is safe to do in a signal handler.
+	let handler = unsafe {
+		signal_hook_registry::register(libc::SIGINT, || CANCEL.store(true, Ordering::Relaxed))
+	};
+	let result = search(&CANCEL);
+	if let Ok(handler) = handler {
+		signal_hook_registry::unregister(handler);
+	}
+	result
+}
+
+/// Run `search` with a flag that is never set.
+#[cfg(not(target_family = "unix"))]
+pub(super) fn interruptible<T>(search: impl FnOnce(&AtomicBool) -> T) -> T {
+	search(&AtomicBool::new(false))
+}
+
+/// Search for an account with a pattern in its address, the `vanity` command.
+pub fn run_vanity<C: Crypto>(
+	matches: &ArgMatches,
+	maybe_network: Option<Ss58AddressFormat>,
+	output: OutputType,
+	secrets: Secrets,
+) -> Result<(), Error>
+where
+	SignatureOf<C>: SignatureT,
+	PublicOf<C>: PublicT,
+{
+	let desired: String = matches
+		.value_of("pattern")
+		.map(str::to_string)
+		.unwrap_or_default();
+	let mut out = Output::from_matches(matches)?;
+	out.set_secret(secrets != Secrets::Hide);
+	let formated_seed = if matches.is_present("prefix") {
+		let max_attempts = match matches.value_of("max-attempts") {
+			Some(_) => read_required_parameter::<u64>(matches, "max-attempts")?,
+			None => u64::max_value(),
+		};
+		let network = maybe_network.unwrap_or_default();
+		let (_, seed) = interruptible(|cancel| {
+			generate_vanity::<C>(&desired, network, max_attempts, cancel)
+		})?;
+		format_seed(&seed)
+	} else {
+		format_seed(&generate_key::<C>(&desired)?.seed)
+	};
+	C::print_from_uri(&mut out, &formated_seed, None, maybe_network, output, secrets)?;
+	out.commit()?;
+	Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-	use super::super::Ed25519;
+	use crate::crypto::Ed25519;
 	use super::*;
 	use sp_core::{crypto::Ss58Codec, Pair};
 	#[cfg(feature = "bench")]
@@ -183,6 +307,8 @@ mod tests {
 		assert!(generate_key::<Ed25519>("I").is_err());
 		assert!(generate_key::<Ed25519>("O").is_err());
 		assert!(generate_key::<Ed25519>("!").is_err());
+		assert!(generate_key::<Ed25519>("é").is_err());
+		assert!(generate_key::<Ed25519>("٣").is_err());
 	}
 
 	#[test]
@@ -191,6 +317,42 @@ mod tests {
 		assert!(generate_key::<Ed25519>("L").is_ok());
 	}
 
+	#[test]
+	fn vanity_address_starts_with_the_prefix() {
+		let cancel = AtomicBool::new(false);
+		let network = Ss58AddressFormat::SubstrateAccount;
+		let (pair, seed) = generate_vanity::<Ed25519>("5G", network, u64::max_value(), &cancel)
+			.unwrap();
+		assert!(pair.public().to_ss58check_with_version(network).starts_with("5G"));
+		assert_eq!(<Ed25519 as Crypto>::Pair::from_seed(&seed).public(), pair.public());
+
+		let network = Ss58AddressFormat::PolkadotAccount;
+		let (pair, _) = generate_vanity::<Ed25519>("1", network, 1, &cancel).unwrap();
+		assert!(pair.public().to_ss58check_with_version(network).starts_with('1'));
+	}
+
+	#[test]
+	fn vanity_search_is_bounded() {
+		let cancel = AtomicBool::new(false);
+		let network = Ss58AddressFormat::SubstrateAccount;
+		// Substrate addresses always start with 5.
+		let error = generate_vanity::<Ed25519>("6", network, 1000, &cancel).err().unwrap();
+		assert_eq!(error.to_string(), "No address starting with '6' found in 1000 attempts");
+
+		cancel.store(true, Ordering::Relaxed);
+		let error = generate_vanity::<Ed25519>("5", network, 1000, &cancel).err().unwrap();
+		assert_eq!(error.to_string(), "Search stopped after 0 attempts");
+	}
+
+	#[test]
+	fn vanity_prefix_is_validated() {
+		let cancel = AtomicBool::new(false);
+		let network = Ss58AddressFormat::SubstrateAccount;
+		for prefix in &["", "50", "5l", "5I", "5O", "5!", "5é"] {
+			assert!(generate_vanity::<Ed25519>(prefix, network, 1, &cancel).is_err());
+		}
+	}
+
 	#[cfg(feature = "bench")]
 	#[bench]
 	fn bench_paranoiac(b: &mut Bencher) {
@@ -0,0 +1,124 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A minimal JSON-RPC client over `ws://` and `wss://` connections to a node.
+
+use async_std::{io::{Read, Write}, net::TcpStream};
+use async_tls::TlsConnector;
+use jsonrpc_core::{Call, Id, MethodCall, Output, Params, Value, Version};
+use soketto::{connection::{Receiver, Sender}, handshake::{Client, ServerResponse}};
+use sp_core::crypto::Zeroize;
+use url::Url;
+
+/// Failure of a call to a node.
+#[derive(Debug, derive_more::Display)]
+pub enum CallError {
+	/// The node could not be reached, or the connection failed.
+	Connection(String),
+	/// The node returned an error for the call.
+	#[display(fmt = "{}", "_0.message")]
+	Rpc(jsonrpc_core::Error),
+	/// The response of the node is not a JSON-RPC response.
+	InvalidResponse(String),
+}
+
+/// A websocket, with or without TLS.
+trait Socket: Read + Write + Unpin {}
+
+impl<T: Read + Write + Unpin> Socket for T {}
+
+/// A websocket connection to a node, on which calls are made one after another.
+pub struct WsClient {
+	sender: Sender<Box<dyn Socket>>,
+	receiver: Receiver<Box<dyn Socket>>,
+	next_id: u64,
+}
+
+impl WsClient {
+	/// Connect to the node at `url`; `wss://` connections are secured with TLS.
+	pub async fn connect(url: &str) -> Result<Self, CallError> {
+		let connection_error = |e: &dyn std::fmt::Display| CallError::Connection(e.to_string());
+		let url = Url::parse(url).map_err(|e| connection_error(&e))?;
+		let tls = match url.scheme() {
+			"ws" => false,
+			"wss" => true,
+			scheme => return Err(CallError::Connection(format!("unsupported scheme `{}`", scheme))),
+		};
+		let host = url.host_str().ok_or_else(|| CallError::Connection("URL without host".into()))?;
+		let port = url.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+
+		let tcp = TcpStream::connect((host, port)).await.map_err(|e| connection_error(&e))?;
+		let socket: Box<dyn Socket> = if tls {
+			let tls = TlsConnector::default().connect(host, tcp).await;
+			Box::new(tls.map_err(|e| connection_error(&e))?)
+		} else {
+			Box::new(tcp)
+		};
+
+		let host_header = match url.port() {
+			Some(port) => format!("{}:{}", host, port),
+			None => host.to_string(),
+		};
+		let resource = match url.query() {
+			Some(query) => format!("{}?{}", url.path(), query),
+			None => url.path().to_string(),
+		};
+		let mut client = Client::new(socket, &host_header, &resource);
+		match client.handshake().await.map_err(|e| connection_error(&e))? {
+			ServerResponse::Accepted { .. } => {}
+			ServerResponse::Redirect { status_code, .. }
+			| ServerResponse::Rejected { status_code } => return Err(CallError::Connection(format!(
+				"websocket handshake refused with HTTP status {}",
+				status_code,
+			))),
+		}
+		let (sender, receiver) = client.into_builder().finish();
+		Ok(WsClient { sender, receiver, next_id: 0 })
+	}
+
+	/// Call `method` with `params`, and return its result.
+	pub async fn call(&mut self, method: &str, params: Vec<Value>) -> Result<Value, CallError> {
+		let connection_error = |e: soketto::connection::Error| CallError::Connection(e.to_string());
+		self.next_id += 1;
+		let id = Id::Num(self.next_id);
+		let mut request = serde_json::to_string(&Call::MethodCall(MethodCall {
+			jsonrpc: Some(Version::V2),
+			method: method.into(),
+			params: Params::Array(params),
+			id: id.clone(),
+		})).expect("calls are always serializable; qed");
+		// The request may hold a secret URI.
+		let sent = self.sender.send_text(&request).await;
+		request.zeroize();
+		sent.map_err(connection_error)?;
+		self.sender.flush().await.map_err(connection_error)?;
+
+		let mut message = Vec::new();
+		self.receiver.receive_data(&mut message).await.map_err(connection_error)?;
+		let output: Output = serde_json::from_slice(&message)
+			.map_err(|e| CallError::InvalidResponse(e.to_string()))?;
+		if *output.id() != id {
+			let error = format!("unexpected response id {:?}", output.id());
+			return Err(CallError::InvalidResponse(error));
+		}
+		match output {
+			Output::Success(success) => Ok(success.result),
+			Output::Failure(failure) => Err(CallError::Rpc(failure.error)),
+		}
+	}
+}
@@ -0,0 +1,129 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use std::{io::Write, process::{Command, Output, Stdio}};
+
+const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+/// Run subkey with `args` and `--error-format json`, passing `message` on STDIN.
+fn subkey(args: &[&str], message: &[u8]) -> Output {
+	let mut child = Command::new(cargo_bin("subkey"))
+		.args(&["--error-format", "json"])
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(message).unwrap();
+	child.wait_with_output().unwrap()
+}
+
+/// Check that `output` failed with `kind` and `exit_code`, and return the error message.
+fn assert_error(output: &Output, kind: &str, exit_code: i32) -> String {
+	assert_eq!(output.status.code(), Some(exit_code));
+	let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+	assert_eq!(stderr.lines().count(), 1, "a single line is expected, got {}", stderr);
+	let json: serde_json::Value = serde_json::from_str(&stderr).unwrap();
+	assert_eq!(json["error"]["kind"], kind);
+	assert_eq!(json["error"]["exitCode"], exit_code);
+	json["error"]["message"].as_str().unwrap().to_string()
+}
+
+fn sign(message: &[u8]) -> String {
+	let output = subkey(&["sign", "//Alice"], message);
+	assert!(output.status.success());
+	String::from_utf8(output.stdout).unwrap().trim().into()
+}
+
+#[test]
+fn invalid_signature_is_verification_failed() {
+	let output = subkey(&["verify", &sign(b"hello"), ALICE], b"other");
+	assert_eq!(assert_error(&output, "verification-failed", 1), "Signature invalid.");
+}
+
+#[test]
+fn invalid_arguments_are_usage() {
+	let message = assert_error(&subkey(&["verify"], b""), "usage", 2);
+	assert!(message.contains("required arguments were not provided"));
+
+	let output = subkey(&["--output", "yaml", "inspect", "//Alice"], b"");
+	assert!(assert_error(&output, "usage", 2).contains("Invalid output name"));
+
+	// An invalid error format is reported as text.
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["--error-format", "yaml", "inspect", "//Alice"])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	assert!(String::from_utf8(output.stderr).unwrap().starts_with("Error: Invalid error format"));
+}
+
+#[test]
+fn missing_file_is_io() {
+	let dir = tempfile::tempdir().unwrap();
+	let missing = dir.path().join("missing");
+	let output = subkey(&["verify", "--sig-file", missing.to_str().unwrap(), ALICE], b"hello");
+	assert_error(&output, "io", 3);
+}
+
+#[test]
+fn malformed_data_is_parse() {
+	let output = subkey(&["verify", "zz", ALICE], b"hello");
+	let message = assert_error(&output, "parse", 4);
+	assert!(message.starts_with("Invalid hex"));
+
+	let output = subkey(&["verify", "00", ALICE], b"hello");
+	assert!(assert_error(&output, "parse", 4).contains("invalid length"));
+}
+
+#[test]
+fn unreachable_node_is_rpc() {
+	let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+	let url = format!("http://{}", address);
+	let output = subkey(&["insert", "//Alice", "gran", &url], b"");
+	let message = assert_error(&output, "rpc", 5);
+	assert!(!message.contains("//Alice"));
+}
+
+#[test]
+fn errors_are_text_by_default() {
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["verify", "zz", ALICE])
+		.stdin(Stdio::null())
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(4));
+	assert!(String::from_utf8(output.stderr).unwrap().starts_with("Error: Invalid hex"));
+}
+
+#[cfg(unix)]
+#[test]
+fn invalid_utf8_arguments_are_usage_errors() {
+	use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["--error-format", "json", "verify"])
+		.arg(OsStr::from_bytes(b"\xff"))
+		.arg(ALICE)
+		.stdin(Stdio::null())
+		.output()
+		.unwrap();
+	assert_error(&output, "usage", 2);
+}
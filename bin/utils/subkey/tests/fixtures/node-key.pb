@@ -0,0 +1,2 @@
+@	
+ yV.T@x詋:敾d
\ No newline at end of file
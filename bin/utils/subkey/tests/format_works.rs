@@ -0,0 +1,98 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use sp_core::{
+	blake2_256, crypto::{AccountId32, Ss58AddressFormat, DEV_PHRASE}, ecdsa, ed25519, sr25519, Pair,
+};
+use std::process::Command;
+use subkey::format::{
+	account_id_bytes, format_account_id, format_entropy, format_public_key, format_seed, inspect_uri,
+};
+
+/// Whether `value` is `0x` followed by lowercase hex.
+fn is_prefixed_lowercase_hex(value: &str) -> bool {
+	value.starts_with("0x")
+		&& value.len() > 2
+		&& value[2..].chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+#[test]
+fn keys_are_prefixed_lowercase_hex() {
+	let (pair, seed) = ed25519::Pair::from_phrase(DEV_PHRASE, None).unwrap();
+	assert!(is_prefixed_lowercase_hex(&format_seed(&seed)));
+	assert!(is_prefixed_lowercase_hex(&format_public_key(pair.public())));
+	assert!(is_prefixed_lowercase_hex(&format_account_id(pair.public())));
+	assert!(is_prefixed_lowercase_hex(&format_entropy(DEV_PHRASE).unwrap()));
+
+	assert_eq!(format_seed(&[0xAB, 0x01]), "0xab01");
+	assert_eq!(format_public_key([0xCD; 2]), "0xcdcd");
+	assert_eq!(format_entropy("not a phrase"), None);
+}
+
+#[test]
+fn account_ids_are_hashed_for_ecdsa_only() {
+	let public = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+	assert_eq!(account_id_bytes(public), AccountId32::from(public.0));
+	let public = ecdsa::Pair::from_string("//Alice", None).unwrap().public();
+	let account_id = account_id_bytes(public.clone());
+	assert_eq!(account_id, AccountId32::from(blake2_256(public.as_ref())));
+	assert_eq!(format_account_id(public), format!("0x{}", hex::encode(account_id)));
+}
+
+#[test]
+fn uris_are_inspected_by_kind() {
+	let phrase = inspect_uri::<sr25519::Pair>(DEV_PHRASE, None, None).unwrap();
+	assert_eq!(phrase.secret_phrase.as_deref(), Some(DEV_PHRASE));
+	assert!(phrase.entropy.is_some() && phrase.secret_seed.is_some());
+	assert!(phrase.secret_key_uri.is_none() && phrase.public_key_uri.is_none());
+
+	let soft = inspect_uri::<sr25519::Pair>("//Alice/soft///password", None, None).unwrap();
+	assert_eq!(soft.inline_password, Some(true));
+	assert_eq!(soft.secret_seed, None);
+
+	let alice = inspect_uri::<sr25519::Pair>("//Alice", None, None).unwrap();
+	let public = inspect_uri::<sr25519::Pair>(&alice.ss58_address, None, None).unwrap();
+	assert_eq!(public.public_key_uri.as_deref(), Some(&alice.ss58_address[..]));
+	assert_eq!(public.public_key, alice.public_key);
+	assert_eq!(public.secret_seed, None);
+
+	let kusama = Some(Ss58AddressFormat::KusamaAccount);
+	let kusama = inspect_uri::<sr25519::Pair>("//Alice", None, kusama).unwrap();
+	assert_eq!(kusama.network, Ss58AddressFormat::KusamaAccount);
+	assert_eq!(kusama.account_id, alice.account_id);
+	assert_ne!(kusama.ss58_address, alice.ss58_address);
+}
+
+#[test]
+fn key_info_is_what_inspect_prints() {
+	for uri in &[DEV_PHRASE, "//Alice", "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"] {
+		let output = Command::new(cargo_bin("subkey"))
+			.args(&["--output", "json", "inspect", uri])
+			.output()
+			.unwrap();
+		assert!(output.status.success());
+		let printed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+		let info = inspect_uri::<sr25519::Pair>(uri, None, None).unwrap();
+		assert_eq!(printed["publicKey"], info.public_key);
+		assert_eq!(printed["accountId"], info.account_id);
+		assert_eq!(printed["ss58Address"], info.ss58_address);
+		assert_eq!(printed["networkId"], String::from(info.network));
+	}
+}
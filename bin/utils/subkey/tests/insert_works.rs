@@ -0,0 +1,58 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use std::{fs, io::Write, path::Path, process::{Command, Stdio}};
+
+const ALICE_PUBLIC: &str = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+/// Insert the key into the keystore at `keystore`, with `uri` as the URI argument and `stdin`.
+fn insert(keystore: &Path, uri: &str, stdin: &[u8]) -> Vec<(String, String)> {
+	let mut child = Command::new(cargo_bin("subkey"))
+		.args(&["insert", uri, "gran", "--keystore-path", keystore.to_str().unwrap()])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(stdin).unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	// The secret is never printed.
+	assert!(output.stdout.is_empty());
+	fs::read_dir(keystore)
+		.unwrap()
+		.map(|entry| {
+			let path = entry.unwrap().path();
+			let name = path.file_name().unwrap().to_str().unwrap().to_string();
+			(name, fs::read_to_string(&path).unwrap())
+		})
+		.collect()
+}
+
+#[test]
+fn insert_reads_the_uri_from_stdin_and_files() {
+	let dir = tempfile::tempdir().unwrap();
+	let uri_file = dir.path().join("uri");
+	fs::write(&uri_file, "//Alice\n").unwrap();
+
+	let name = format!("{}{}", hex::encode(b"gran"), ALICE_PUBLIC);
+	let expected = vec![(name, "\"//Alice\"".to_string())];
+	assert_eq!(insert(&dir.path().join("literal"), "//Alice", b""), expected);
+	assert_eq!(insert(&dir.path().join("piped"), "-", b"//Alice\n"), expected);
+	assert_eq!(insert(&dir.path().join("file"), uri_file.to_str().unwrap(), b""), expected);
+}
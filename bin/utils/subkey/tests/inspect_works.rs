@@ -0,0 +1,596 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use libp2p::identity::{ed25519, PublicKey};
+use std::{io::Write, process::{Command, Output, Stdio}};
+
+const ALICE_PUBLIC: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+const ALICE_SEED: &str = "0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a";
+const ALICE_ADDRESS: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+const PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+fn subkey(args: &[&str]) -> Output {
+	let output = Command::new(cargo_bin("subkey")).args(args).output().unwrap();
+	assert!(output.status.success());
+	output
+}
+
+/// The hex-encoded public key and the peer ID of the node key `secret`, as sc-network derives it.
+fn node_key_public(secret: Vec<u8>) -> (String, String) {
+	let public = ed25519::Keypair::from(ed25519::SecretKey::from_bytes(secret).unwrap()).public();
+	let public_hex = format!("0x{}", hex::encode(public.encode()));
+	(public_hex, PublicKey::Ed25519(public).into_peer_id().to_base58())
+}
+
+#[test]
+fn secret_phrase_is_redacted() {
+	for uri in &[PHRASE, "//Alice"] {
+		let output = subkey(&["--yes", "--show-secret", "inspect", uri]);
+		let stdout = String::from_utf8(output.stdout).unwrap();
+		assert!(stdout.contains(&format!("`{}` is account:", uri)));
+
+		let output = subkey(&["--yes", "--show-secret", "--redact-secret", "inspect", uri]);
+		let redacted = String::from_utf8(output.stdout).unwrap();
+		assert!(!redacted.contains(uri));
+		assert!(redacted.contains("[redacted] is account:"));
+		// The entropy is as secret as the phrase.
+		assert!(!redacted.contains("Entropy"));
+		assert_eq!(stdout.contains("Entropy (hex):"), *uri == PHRASE);
+		let public = |text: &str| text
+			.lines()
+			.skip(1)
+			.filter(|line| !line.contains("Entropy"))
+			.map(String::from)
+			.collect::<Vec<_>>();
+		assert_eq!(public(&redacted), public(&stdout));
+	}
+}
+
+#[test]
+fn secret_phrase_is_omitted_from_json() {
+	for (uri, field) in &[(PHRASE, "secretPhrase"), ("//Alice", "secretKeyUri")] {
+		let output = subkey(&["--output", "json", "--show-secret", "inspect", uri]);
+		let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+		assert_eq!(json[field], *uri);
+
+		let args = ["--output", "json", "--show-secret", "--redact-secret", "inspect", uri];
+		let output = subkey(&args);
+		let redacted: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+		assert!(redacted.get(field).is_none());
+		assert_eq!(json.get("entropy").is_some(), *uri == PHRASE);
+		assert!(redacted.get("entropy").is_none());
+		assert_eq!(redacted["ss58Address"], json["ss58Address"]);
+		assert_eq!(redacted["secretSeed"], json["secretSeed"]);
+	}
+}
+
+fn stdout(args: &[&str]) -> String {
+	String::from_utf8(subkey(args).stdout).unwrap()
+}
+
+fn json(args: &[&str]) -> serde_json::Value {
+	serde_json::from_slice(&subkey(&[&["--output", "json"], args].concat()).stdout).unwrap()
+}
+
+#[test]
+fn inspect_hides_secrets_by_default() {
+	let hidden = format!(
+		"Secret Key URI [redacted] is account:\n  \
+		Inline password:    no\n  \
+		Network ID/version: substrate\n  \
+		Public key (hex):   {public}\n  \
+		Account ID:         {public}\n  \
+		SS58 Address:       {address}\n",
+		public = ALICE_PUBLIC,
+		address = ALICE_ADDRESS,
+	);
+	assert_eq!(stdout(&["inspect", "//Alice"]), hidden);
+	assert_eq!(stdout(&["--hide-secret", "inspect", "//Alice"]), hidden);
+
+	let shown = format!(
+		"Secret Key URI `//Alice` is account:\n  \
+		Inline password:    no\n  \
+		Network ID/version: substrate\n  \
+		Secret seed:        {seed}\n  \
+		Public key (hex):   {public}\n  \
+		Account ID:         {public}\n  \
+		SS58 Address:       {address}\n",
+		seed = ALICE_SEED,
+		public = ALICE_PUBLIC,
+		address = ALICE_ADDRESS,
+	);
+	assert_eq!(stdout(&["--yes", "--show-secret", "inspect", "//Alice"]), shown);
+}
+
+#[test]
+fn inspect_json_omits_secrets_by_default() {
+	let hidden = json(&["inspect", "//Alice"]);
+	assert_eq!(hidden, json(&["--hide-secret", "inspect", "//Alice"]));
+	assert_eq!(hidden, serde_json::json!({
+		"formatVersion": 2,
+		"inlinePassword": false,
+		"networkId": "substrate",
+		"publicKey": ALICE_PUBLIC,
+		"accountId": ALICE_PUBLIC,
+		"ss58Address": ALICE_ADDRESS,
+	}));
+
+	let shown = json(&["--show-secret", "inspect", "//Alice"]);
+	assert_eq!(shown, serde_json::json!({
+		"formatVersion": 2,
+		"secretKeyUri": "//Alice",
+		"inlinePassword": false,
+		"networkId": "substrate",
+		"secretSeed": ALICE_SEED,
+		"publicKey": ALICE_PUBLIC,
+		"accountId": ALICE_PUBLIC,
+		"ss58Address": ALICE_ADDRESS,
+	}));
+}
+
+#[test]
+fn generate_shows_secrets_by_default() {
+	let shown = stdout(&["--yes", "generate"]);
+	assert!(shown.starts_with("Secret phrase `"));
+	assert!(shown.contains("Secret seed:"));
+	let json = json(&["generate"]);
+	assert!(json["secretPhrase"].is_string() && json["secretSeed"].is_string());
+	let phrase = json["secretPhrase"].as_str().unwrap();
+	assert_eq!(json, self::json(&["--show-secret", "inspect", phrase]));
+
+	let hidden = stdout(&["--hide-secret", "generate"]);
+	assert!(hidden.starts_with("Secret phrase [redacted] is account:"));
+	assert!(!hidden.contains("Secret seed:"));
+	let json = self::json(&["--hide-secret", "generate"]);
+	assert!(json.get("secretPhrase").is_none() && json.get("secretSeed").is_none());
+	assert!(json["ss58Address"].is_string());
+}
+
+#[test]
+fn secrets_are_only_printed_to_a_pipe_with_yes() {
+	let commands = [
+		&["generate"][..],
+		&["--show-secret", "inspect", "//Alice"],
+		&["derive", "//Alice", "//0"],
+	];
+	for args in &commands {
+		let output = Command::new(cargo_bin("subkey")).args(*args).output().unwrap();
+		assert_eq!(output.status.code(), Some(2));
+		assert!(output.stdout.is_empty());
+		let stderr = String::from_utf8(output.stderr).unwrap();
+		assert!(stderr.contains("Refusing to print secrets"), "{}", stderr);
+	}
+	// Machine output, hidden secrets and output files are not refused.
+	json(&["--show-secret", "inspect", "//Alice"]);
+	stdout(&["inspect", "//Alice"]);
+	stdout(&["--show-secret", "inspect", "--quiet", "//Alice"]);
+	let dir = tempfile::tempdir().unwrap();
+	let file = dir.path().join("account.txt");
+	stdout(&["generate", "--output-file", file.to_str().unwrap()]);
+}
+
+#[test]
+fn compact_json_has_the_fields_of_pretty_json() {
+	for args in &[&["inspect", "//Alice"][..], &["--show-secret", "inspect", PHRASE][..]] {
+		let compact = stdout(&[&["--output", "json-compact"], *args].concat());
+		assert_eq!(compact.lines().count(), 1);
+		let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+		assert_eq!(compact, json(args));
+	}
+}
+
+#[test]
+fn table_has_a_row_per_account() {
+	let dir = tempfile::tempdir().unwrap();
+	let list = dir.path().join("uris");
+	std::fs::write(&list, format!("//Alice\n{}\n", ALICE_ADDRESS)).unwrap();
+	let list = list.to_str().unwrap();
+
+	// STDOUT is not a terminal, so the rows are plain.
+	let row = format!("substrate\t{}\t{}", ALICE_ADDRESS, ALICE_PUBLIC);
+	assert_eq!(
+		stdout(&["--output", "table", "inspect", "--uri-list", list]),
+		format!("Network\tSS58 address\tPublic key\n{}\n{}\n", row, row),
+	);
+	assert_eq!(
+		stdout(&["--output", "table", "--no-header", "inspect", "//Alice"]),
+		format!("{}\n", row),
+	);
+}
+
+#[test]
+fn show_and_hide_secret_conflict() {
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["--show-secret", "--hide-secret", "inspect", "//Alice"])
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+}
+
+#[test]
+fn quiet_prints_a_single_line() {
+	assert_eq!(stdout(&["inspect", "--quiet", "//Alice"]), format!("{}\n", ALICE_ADDRESS));
+	let public = stdout(&["inspect", "-q", "--public-hex", "//Alice"]);
+	assert_eq!(public, format!("{}\n", ALICE_PUBLIC));
+	assert_eq!(
+		stdout(&["--network", "kusama", "inspect", "-q", "//Alice"]),
+		"HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F\n",
+	);
+	assert_eq!(
+		stdout(&["--ed25519", "inspect", "-q", "--public-hex", "//Alice"]),
+		"0x88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee\n",
+	);
+	// Public URIs work just as well.
+	assert_eq!(stdout(&["inspect", "-q", ALICE_ADDRESS]), format!("{}\n", ALICE_ADDRESS));
+
+	let generated = stdout(&["--network", "kusama", "generate", "--quiet"]);
+	assert_eq!(generated.lines().count(), 1);
+	assert!(generated.ends_with('\n') && !generated.ends_with("\n\n"));
+	assert_eq!(stdout(&["inspect", "-q", generated.trim_end()]), generated);
+}
+
+#[test]
+fn quiet_fails_on_invalid_uris() {
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["inspect", "--quiet", "not a valid uri"])
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+	assert!(output.stdout.is_empty());
+	assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn invalid_uris_exit_with_an_error() {
+	let dir = tempfile::tempdir().unwrap();
+	let list = dir.path().join("uris");
+	std::fs::write(&list, "//Alice
+not a valid uri
+").unwrap();
+	let list = list.to_str().unwrap();
+
+	let commands = [
+		&["inspect", "not a valid uri"][..],
+		&["--output", "json", "inspect", "not a valid uri"],
+		&["--output", "binary", "inspect", "not a valid uri"],
+		&["--output", "table", "inspect", "--uri-list", list],
+	];
+	for args in &commands {
+		let output = Command::new(cargo_bin("subkey")).args(*args).output().unwrap();
+		assert_eq!(output.status.code(), Some(2), "{:?}", args);
+		assert!(output.stdout.is_empty());
+		let stderr = String::from_utf8(output.stderr).unwrap();
+		assert!(stderr.contains("Invalid phrase/URI given"), "{}", stderr);
+	}
+}
+
+#[test]
+fn output_file_replaces_the_output() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("alice.json");
+	let path_arg = path.to_str().unwrap();
+
+	let output = subkey(&["--output", "json", "inspect", "//Alice", "--output-file", path_arg]);
+	assert!(output.stdout.is_empty());
+	let written = std::fs::read(&path).unwrap();
+	assert_eq!(written, subkey(&["--output", "json", "inspect", "//Alice"]).stdout);
+
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["inspect", "--quiet", "//Alice", "--output-file", path_arg])
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+	assert_eq!(std::fs::read(&path).unwrap(), written);
+
+	subkey(&["inspect", "--quiet", "//Alice", "--output-file", path_arg, "--force"]);
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("{}\n", ALICE_ADDRESS));
+	let entries = std::fs::read_dir(dir.path()).unwrap().count();
+	assert_eq!(entries, 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn output_file_of_secrets_is_private() {
+	use std::os::unix::fs::PermissionsExt;
+	let dir = tempfile::tempdir().unwrap();
+	let mode = |name: &str| {
+		let metadata = std::fs::metadata(dir.path().join(name)).unwrap();
+		metadata.permissions().mode() & 0o777
+	};
+	let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+	subkey(&["generate", "--output-file", &path("generated")]);
+	assert_eq!(mode("generated"), 0o600);
+	subkey(&["--hide-secret", "generate", "--output-file", &path("hidden")]);
+	assert_eq!(mode("hidden"), 0o644);
+	subkey(&["inspect", "//Alice", "--output-file", &path("inspected")]);
+	assert_eq!(mode("inspected"), 0o644);
+	subkey(&["--show-secret", "inspect", "//Alice", "--output-file", &path("shown")]);
+	assert_eq!(mode("shown"), 0o600);
+	subkey(&["key", "derive-many", "--suri", "//Alice", "--count", "2", "--output-file", &path("many")]);
+	assert_eq!(mode("many"), 0o600);
+}
+
+#[test]
+fn derive_many_only_replaces_output_file_with_force() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("accounts.csv");
+	let path_arg = path.to_str().unwrap();
+	let args = ["key", "derive-many", "--suri", "//Alice", "--count", "3", "--output-file", path_arg];
+
+	subkey(&args);
+	let written = std::fs::read_to_string(&path).unwrap();
+	assert_eq!(written.lines().count(), 4);
+
+	std::fs::write(&path, "kept").unwrap();
+	let output = Command::new(cargo_bin("subkey")).args(&args).output().unwrap();
+	assert!(!output.status.success());
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), "kept");
+
+	subkey(&[&args[..], &["--force"]].concat());
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+}
+
+#[test]
+fn qr_code_follows_the_account() {
+	let account = stdout(&["inspect", "//Alice"]);
+	let with_qr = stdout(&["inspect", "--qr", "//Alice"]);
+	assert!(with_qr.starts_with(&format!("{}\n", account)));
+	let code = &with_qr[account.len() + 1..];
+	// The address fits in a version 4 code of 33 modules, with a quiet zone of 4 on each side.
+	assert_eq!(code.lines().count(), (33 + 8 + 1) / 2);
+	assert!(code.lines().all(|line| line.chars().count() == 33 + 8));
+
+	let secret = Command::new(cargo_bin("subkey"))
+		.args(&["inspect", "--qr", "--qr-secret", "//Alice"])
+		.output()
+		.unwrap();
+	assert!(secret.status.success());
+	assert!(String::from_utf8(secret.stderr).unwrap().contains("WARNING"));
+
+	let refused = [
+		&["--output", "json", "inspect", "--qr", "//Alice"][..],
+		&["inspect", "--qr", "--qr-secret", ALICE_ADDRESS][..],
+		&["inspect", "--qr", "--qr-genesis-hash", "0x1234", "//Alice"][..],
+		&["inspect", "--qr-secret", "//Alice"][..],
+	];
+	for args in &refused {
+		let output = Command::new(cargo_bin("subkey")).args(*args).output().unwrap();
+		assert!(!output.status.success(), "{:?} succeeded", args);
+	}
+
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("alice.png");
+	let args = ["inspect", "--qr-file", path.to_str().unwrap(), "//Alice"];
+	let output = Command::new(cargo_bin("subkey")).args(&args).output().unwrap();
+	assert_eq!(output.status.success(), cfg!(feature = "qr-png"));
+	assert_eq!(path.exists(), cfg!(feature = "qr-png"));
+}
+
+#[test]
+fn dev_accounts_are_narrowed_by_scheme_and_network() {
+	let all = json(&["key", "dev-accounts"]);
+	assert_eq!(all.as_array().unwrap().len(), 3 * 12);
+	assert_eq!(all[0]["uri"], "//Alice");
+	assert_eq!(all[0]["ss58Address"], ALICE_ADDRESS);
+
+	let ed25519 = json(&["--ed25519", "--network", "kusama", "key", "dev-accounts"]);
+	let ed25519 = ed25519.as_array().unwrap();
+	assert_eq!(ed25519.len(), 12);
+	assert!(ed25519.iter().all(|account| account["scheme"] == "ed25519"));
+	assert!(ed25519.iter().all(|account| account["networkId"] == "kusama"));
+
+	let addresses = stdout(&["--output", "address", "-s", "key", "dev-accounts"]);
+	assert_eq!(addresses.lines().next(), Some(ALICE_ADDRESS));
+	assert_eq!(addresses.lines().count(), 12);
+}
+
+#[test]
+fn node_key_peer_id_is_derived_from_the_secret() {
+	let dir = tempfile::tempdir().unwrap();
+	// The peer ID is the identity multihash of the protobuf-encoded public key, as sc-network and
+	// libp2p derive it.
+	let fixture = dir.path().join("fixture.key");
+	std::fs::write(&fixture, (1..=32).collect::<Vec<u8>>()).unwrap();
+	let output = subkey(&["inspect-node-key", "--quiet", fixture.to_str().unwrap()]);
+	assert_eq!(output.stdout, &b"12D3KooWJ1TsijH7H5F74hfAD5XishQz3sxrmAtVY37GtNd9CqYf\n"[..]);
+
+	let file = dir.path().join("node.key");
+	let file = file.to_str().unwrap();
+	let output = subkey(&["--output", "json", "generate-node-key", "--file", file]);
+	let generated: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert!(generated.get("secret").is_none());
+	let (public, peer_id) = node_key_public(std::fs::read(file).unwrap());
+	assert_eq!(generated["publicKey"], public);
+	assert_eq!(generated["peerId"], peer_id);
+	let output = subkey(&["inspect-node-key", "-q", file]);
+	assert_eq!(output.stdout, format!("{}\n", peer_id).as_bytes());
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		let mode = std::fs::metadata(file).unwrap().permissions().mode();
+		assert_eq!(mode & 0o777, 0o600);
+	}
+
+	// Without --file, the secret is printed instead.
+	let output = subkey(&["--output", "json", "generate-node-key"]);
+	let generated: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	let secret = hex::decode(&generated["secret"].as_str().unwrap()[2..]).unwrap();
+	let (public, peer_id) = node_key_public(secret);
+	assert_eq!(generated["publicKey"], public);
+	assert_eq!(generated["peerId"], peer_id);
+
+	let output = subkey(&["generate-node-key", "--file", file, "--force", "--quiet"]);
+	let peer_id = String::from_utf8(output.stdout).unwrap();
+	assert_eq!(subkey(&["inspect-node-key", "-q", file]).stdout, peer_id.as_bytes());
+}
+
+#[test]
+fn node_key_is_inspected_from_a_file_hex_or_stdin() {
+	let secret = (1..=32).collect::<Vec<u8>>();
+	let peer_id = "12D3KooWJ1TsijH7H5F74hfAD5XishQz3sxrmAtVY37GtNd9CqYf";
+	let public = "0x79b5562e8fe654f94078b112e8a98ba7901f853ae695bed7e0e3910bad049664";
+	let expected = format!(
+		"Peer ID:     {}\nPublic key:  {}\nMultihash:   identity (0x00), 36 bytes\n",
+		peer_id,
+		public,
+	);
+
+	let dir = tempfile::tempdir().unwrap();
+	let file = dir.path().join("node.key");
+	std::fs::write(&file, &secret).unwrap();
+	let output = subkey(&["inspect-node-key", file.to_str().unwrap()]);
+	assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+	let output = subkey(&["--output", "json", "inspect-node-key", "--hex", &hex::encode(&secret)]);
+	let inspected: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(inspected["peerId"], peer_id);
+	assert_eq!(inspected["publicKey"], public);
+	assert_eq!(inspected["multihashCodec"], "identity");
+	assert_eq!(inspected["multihashLength"], 36);
+
+	let mut child = Command::new(cargo_bin("subkey"))
+		.arg("inspect-node-key")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(&secret).unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["inspect-node-key", "--hex", &hex::encode(&secret[..31])])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("Invalid node key of 31 bytes; expected 32 bytes"));
+}
+
+#[test]
+fn account_forms_tell_which_schemes_use_the_public_key() {
+	let output = subkey(&["--output", "json", "account-forms", ALICE_PUBLIC]);
+	let forms: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(forms["accountId"], ALICE_PUBLIC);
+	assert_eq!(forms["ss58Address"], ALICE_ADDRESS);
+	assert_eq!(forms["schemes"][0]["scheme"], "sr25519");
+	assert_eq!(forms["schemes"][0]["publicKey"], ALICE_PUBLIC);
+	assert_eq!(forms["schemes"][1]["accountIdIsPublicKey"], true);
+	assert_eq!(forms["schemes"][2]["scheme"], "ecdsa");
+	assert_eq!(forms["schemes"][2]["accountIdIsPublicKey"], false);
+	assert!(forms["schemes"][2].get("publicKey").is_none());
+
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["account-forms", &ALICE_PUBLIC[..64]])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("Invalid account id of 31 bytes; expected 32 bytes"));
+}
+
+#[test]
+fn node_keys_are_converted_to_and_from_protobuf() {
+	let dir = tempfile::tempdir().unwrap();
+	let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/node-key.pb");
+	let peer_id = "Peer ID:     12D3KooWJ1TsijH7H5F74hfAD5XishQz3sxrmAtVY37GtNd9CqYf\n";
+	let convert = |input: &str, from, to, file: &str| {
+		let output = subkey(&[
+			"key", "convert-node-key", "--input", input, "--from", from, "--to", to,
+			"--output-file", file,
+		]);
+		assert_eq!(String::from_utf8(output.stdout).unwrap(), peer_id);
+	};
+
+	let raw = dir.path().join("node.key");
+	let raw = raw.to_str().unwrap();
+	convert(fixture, "protobuf", "raw", raw);
+	assert_eq!(std::fs::read(raw).unwrap(), (1..=32).collect::<Vec<u8>>());
+
+	let protobuf = dir.path().join("node-key.pb");
+	let protobuf = protobuf.to_str().unwrap();
+	convert(raw, "raw", "protobuf", protobuf);
+	assert_eq!(std::fs::read(protobuf).unwrap(), std::fs::read(fixture).unwrap());
+
+	let hex = dir.path().join("node-key.hex");
+	let hex = hex.to_str().unwrap();
+	convert(protobuf, "protobuf", "hex", hex);
+	let encoded = std::fs::read_to_string(hex).unwrap();
+	assert_eq!(encoded.trim(), hex::encode((1..=32).collect::<Vec<u8>>()));
+	std::fs::remove_file(protobuf).unwrap();
+	convert(hex, "hex", "protobuf", protobuf);
+	assert_eq!(std::fs::read(protobuf).unwrap(), std::fs::read(fixture).unwrap());
+
+	// Only ed25519 keys are node keys.
+	let rsa = dir.path().join("rsa.pb");
+	let mut key = vec![0x08, 0x00, 0x12, 0x80, 0x01];
+	key.extend_from_slice(&[0; 128]);
+	std::fs::write(&rsa, key).unwrap();
+	let output = Command::new(cargo_bin("subkey"))
+		.args(&["--yes", "key", "convert-node-key", "--input", rsa.to_str().unwrap()])
+		.args(&["--from", "protobuf", "--to", "hex"])
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("is an RSA key; Substrate nodes only use ed25519 node keys"));
+}
+
+/// Run subkey in a session of its own, without a controlling terminal, with `stdin` piped in.
+#[cfg(unix)]
+fn subkey_without_tty(args: &[&str], stdin: &[u8]) -> Output {
+	use std::os::unix::process::CommandExt;
+	let mut command = Command::new(cargo_bin("subkey"));
+	command.args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+	// Safe, as `setsid` is async-signal-safe.
+	unsafe {
+		command.pre_exec(|| match libc::setsid() {
+			-1 => Err(std::io::Error::last_os_error()),
+			_ => Ok(()),
+		});
+	}
+	let mut child = command.spawn().unwrap();
+	child.stdin.take().unwrap().write_all(stdin).unwrap();
+	child.wait_with_output().unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn secrets_are_read_from_stdin_without_a_tty() {
+	let output = subkey_without_tty(&["--output", "json", "inspect"], b"//Alice\n");
+	assert!(output.status.success());
+	let inspected: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(inspected["ss58Address"], ALICE_ADDRESS);
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("no terminal to prompt for `URI`"));
+
+	let args = ["--output", "json", "--password-interactive", "inspect", "//Alice"];
+	let output = subkey_without_tty(&args, b"password\n");
+	assert!(output.status.success());
+	let inspected: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_ne!(inspected["ss58Address"], ALICE_ADDRESS);
+
+	let output = subkey_without_tty(&args, b"");
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("--password-interactive needs a terminal, or the password on STDIN"));
+}
@@ -0,0 +1,447 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use serde_json::{json, Value};
+use sp_core::{crypto::{KeyTypeId, Pair, SecretString}, ed25519};
+use std::{fs, io::Write, path::Path, process::{Command, Output, Stdio}};
+
+const ALICE_PUBLIC: &str = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+const ALICE_ADDRESS: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+fn subkey(args: &[&str]) -> Output {
+	Command::new(cargo_bin("subkey")).args(args).output().unwrap()
+}
+
+/// Insert the key of `uri` with `key_type` into the keystore at `keystore`.
+fn insert(keystore: &Path, scheme: &str, uri: &str, key_type: &str) {
+	let keystore = keystore.to_str().unwrap();
+	let output = subkey(&[scheme, "insert", uri, key_type, "--keystore-path", keystore]);
+	assert!(output.status.success());
+}
+
+#[test]
+fn keys_are_listed_with_warnings_for_other_files() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path();
+	insert(keystore, "-e", "//Alice", "gran");
+	insert(keystore, "-s", "//Alice", "babe");
+	insert(keystore, "-k", "//Alice", "acco");
+	fs::write(keystore.join("notes.txt"), "not a key").unwrap();
+	let corrupt = format!("{}{}", hex::encode(b"imon"), ALICE_PUBLIC);
+	fs::write(keystore.join(&corrupt), "//Alice").unwrap();
+
+	let keystore = keystore.to_str().unwrap();
+	let output = subkey(&["--output", "json", "key", "list", "--keystore-path", keystore]);
+	assert!(output.status.success());
+	let keys: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(keys, json!([
+		{
+			"formatVersion": 2,
+			"keyType": "acco",
+			"scheme": "ecdsa",
+			"publicKey": "0x020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1",
+			"ss58Address": "5C7C2Z5sWbytvHpuLTvzKunnnRwQxft1jiqrLD5rhucQ5S9X",
+		},
+		{
+			"formatVersion": 2,
+			"keyType": "babe",
+			"scheme": "sr25519",
+			"publicKey": format!("0x{}", ALICE_PUBLIC),
+			"ss58Address": ALICE_ADDRESS,
+		},
+		{
+			"formatVersion": 2,
+			"keyType": "gran",
+			"scheme": "ed25519",
+			"publicKey": "0x88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee",
+			"ss58Address": "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu",
+		},
+	]));
+
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert_eq!(stderr, format!(
+		"Warning: Skipping `{}`: the file does not hold a secret key URI\n\
+		Warning: Skipping `notes.txt`: the name is not hex-encoded\n",
+		corrupt,
+	));
+	// Secrets are never printed.
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(!stdout.contains("Alice") && !stderr.contains("Alice"));
+}
+
+#[test]
+fn missing_keystore_is_an_error() {
+	let dir = tempfile::tempdir().unwrap();
+	let missing = dir.path().join("missing");
+	let output = subkey(&["key", "list", "--keystore-path", missing.to_str().unwrap()]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(!missing.exists());
+}
+
+#[test]
+fn encrypted_keys_are_listed() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().to_str().unwrap();
+	// Key files are encrypted by default if a password is given.
+	let args = ["--password", "secret", "insert", "//Alice", "gran", "--keystore-path", keystore];
+	let output = subkey(&args);
+	assert!(output.status.success());
+	let file = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+	assert!(!fs::read_to_string(file).unwrap().contains("Alice"));
+
+	let output = subkey(&["--output", "json", "key", "list", "--keystore-path", keystore]);
+	assert!(output.status.success());
+	assert!(output.stderr.is_empty());
+	let keys: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(keys.as_array().unwrap().len(), 1);
+
+	// Encrypting the key file requires a password.
+	let plain = &["insert", "//Alice", "gran", "--keystore-path", keystore];
+	let output = subkey(&[&plain[..], &["--keystore-format", "encrypted"]].concat());
+	assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn generated_keys_are_inserted_into_a_keystore() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().to_str().unwrap();
+	let args = ["--yes", "--password", "secret", "--output", "json", "generate", "--key-type"];
+	let output = subkey(&[&args[..], &["babe", "--keystore-path", keystore]].concat());
+	assert!(output.status.success());
+	let generated: Value = serde_json::from_slice(&output.stdout).unwrap();
+	let public = &generated["publicKey"].as_str().unwrap()[2..];
+	let contents = fs::read(dir.path().join(format!("{}{}", hex::encode(b"babe"), public))).unwrap();
+	assert!(sc_keystore::decode_key_file(&contents, None).is_err());
+	let phrase = sc_keystore::decode_key_file(&contents, Some("secret")).unwrap();
+	assert_eq!(generated["secretPhrase"], phrase);
+
+	let args = ["--yes", "generate", "--key-type", "babe", "--keystore-path", keystore];
+	let output = subkey(&[&args[..], &["--keystore-format", "encrypted"]].concat());
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(key_files(dir.path()).len(), 1);
+}
+
+#[test]
+fn json_files_are_imported_as_exported() {
+	let dir = tempfile::tempdir().unwrap();
+	let file = dir.path().join("alice.json");
+	let file = file.to_str().unwrap();
+	let export = ["--password", "secret", "key", "export-json", "--suri", "//Alice"];
+	let output = subkey(&[&export[..], &["--name", "Alice", "--output-file", file]].concat());
+	assert!(output.status.success());
+	let exported: Value = serde_json::from_str(&fs::read_to_string(file).unwrap()).unwrap();
+	assert_eq!(exported["address"], ALICE_ADDRESS);
+	assert_eq!(exported["encoding"]["content"], json!(["pkcs8", "sr25519"]));
+	assert_eq!(exported["meta"]["name"], "Alice");
+	assert!(!exported.to_string().contains(ALICE_PUBLIC));
+
+	let import = ["--password", "secret", "--output", "json", "key", "import-json", file];
+	let output = subkey(&import);
+	assert!(output.status.success());
+	let imported: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(imported["publicKey"], format!("0x{}", ALICE_PUBLIC));
+	assert_eq!(imported["name"], "Alice");
+	assert_eq!(imported["secretKey"], Value::Null);
+
+	let output = subkey(&["--password", "wrong", "key", "import-json", file]);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(String::from_utf8(output.stderr).unwrap().contains("Invalid password"));
+	// The scheme of the file has to be selected.
+	let output = subkey(&["-e", "--password", "secret", "key", "import-json", file]);
+	assert_eq!(output.status.code(), Some(2));
+	// Exporting requires a password.
+	let output = subkey(&["key", "export-json", "--suri", "//Alice"]);
+	assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn json_files_are_imported_into_a_keystore() {
+	let dir = tempfile::tempdir().unwrap();
+	let file = dir.path().join("alice.json");
+	let file = file.to_str().unwrap();
+	let keystore = dir.path().join("keystore");
+	let keystore = keystore.to_str().unwrap();
+	let export = ["-e", "--password", "secret", "key", "export-json", "--suri", "//Alice"];
+	let output = subkey(&[&export[..], &["--output-file", file]].concat());
+	assert!(output.status.success());
+
+	let import = ["-e", "--password", "secret", "key", "import-json", file];
+	let insert = ["--keystore-path", keystore, "--key-type", "gran"];
+	let output = subkey(&[&import[..], &insert].concat());
+	assert!(output.status.success());
+	assert!(output.stdout.is_empty());
+
+	let output = subkey(&["--output", "json", "key", "list", "--keystore-path", keystore]);
+	let keys: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(keys, json!([{
+		"formatVersion": 2,
+		"keyType": "gran",
+		"scheme": "ed25519",
+		"publicKey": "0x88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee",
+		"ss58Address": "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu",
+	}]));
+	let plain = fs::read_dir(keystore).unwrap().next().unwrap().unwrap().path();
+	let secret_key = fs::read_to_string(plain).unwrap();
+
+	let encrypted = dir.path().join("encrypted");
+	let encrypted = encrypted.to_str().unwrap();
+	let insert = ["--keystore-path", encrypted, "--key-type", "gran", "--keystore-format", "encrypted"];
+	let output = subkey(&[&import[..], &insert].concat());
+	assert!(output.status.success());
+	let file = fs::read_dir(encrypted).unwrap().next().unwrap().unwrap().path();
+	let contents = fs::read_to_string(file).unwrap();
+	assert!(!contents.contains(secret_key.trim_matches('"').trim_start_matches("0x")));
+	assert!(contents.contains("sc-keystore-encrypted"));
+}
+
+#[test]
+fn session_keys_are_decoded_along_the_layout() {
+	let gran = "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee";
+	let session_keys = format!("0x{}{}", gran, ALICE_PUBLIC);
+	let output = subkey(&[
+		"--output", "json", "key", "decode-session-keys", &session_keys,
+		"--layout", "gran,babe:sr25519:32",
+	]);
+	assert!(output.status.success());
+	let decoded: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(decoded["sessionKeys"], session_keys);
+	assert_eq!(decoded["keys"][0]["keyType"], "gran");
+	assert_eq!(decoded["keys"][0]["scheme"], "ed25519");
+	assert_eq!(decoded["keys"][0]["publicKey"], format!("0x{}", gran));
+	assert_eq!(decoded["keys"][1]["keyType"], "babe");
+	assert_eq!(decoded["keys"][1]["ss58Address"], ALICE_ADDRESS);
+
+	// The default layout expects four keys.
+	let output = subkey(&["key", "decode-session-keys", &session_keys]);
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("bytes long, but the layout expects"));
+}
+
+/// The names of the files in the keystore at `keystore`, sorted.
+fn key_files(keystore: &Path) -> Vec<String> {
+	let mut names = fs::read_dir(keystore)
+		.unwrap()
+		.map(|entry| entry.unwrap().file_name().into_string().unwrap())
+		.collect::<Vec<_>>();
+	names.sort();
+	names
+}
+
+#[test]
+fn key_is_removed_by_public_key_or_address() {
+	let dir = tempfile::tempdir().unwrap();
+	insert(dir.path(), "-s", "//Alice", "babe");
+	insert(dir.path(), "-s", "//Bob", "babe");
+	insert(dir.path(), "-s", "//Alice", "imon");
+	let keystore = dir.path().to_str().unwrap();
+	let alice_babe = format!("{}{}", hex::encode(b"babe"), ALICE_PUBLIC);
+
+	// Nothing is removed unless confirmed.
+	let remove = ["key", "remove", "--keystore-path", keystore, "--key-type", "babe"];
+	let output = subkey(&[&remove[..], &["--public", ALICE_PUBLIC]].concat());
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains(&alice_babe) && stderr.contains(ALICE_ADDRESS));
+	assert!(stderr.contains("pass --yes"));
+	assert_eq!(key_files(dir.path()).len(), 3);
+
+	let output = subkey(&[&remove[..], &["--public", ALICE_ADDRESS, "--yes"]].concat());
+	assert!(output.status.success());
+	let files = key_files(dir.path());
+	assert_eq!(files.len(), 2);
+	assert!(!files.contains(&alice_babe));
+	assert!(files.contains(&format!("{}{}", hex::encode(b"imon"), ALICE_PUBLIC)));
+}
+
+#[test]
+fn all_keys_of_a_type_are_removed() {
+	let dir = tempfile::tempdir().unwrap();
+	insert(dir.path(), "-e", "//Alice", "gran");
+	insert(dir.path(), "-e", "//Bob", "gran");
+	insert(dir.path(), "-s", "//Alice", "babe");
+	let keystore = dir.path().to_str().unwrap();
+
+	let remove = ["key", "remove", "--keystore-path", keystore, "-y"];
+	let output = subkey(&[&remove[..], &["--key-type", "gran", "--all"]].concat());
+	assert!(output.status.success());
+	assert_eq!(key_files(dir.path()), vec![format!("{}{}", hex::encode(b"babe"), ALICE_PUBLIC)]);
+
+	// Either a public key or --all is required.
+	let output = subkey(&[&remove[..], &["--key-type", "babe"]].concat());
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(key_files(dir.path()).len(), 1);
+}
+
+#[test]
+fn missing_key_is_not_removed() {
+	let dir = tempfile::tempdir().unwrap();
+	insert(dir.path(), "-s", "//Alice", "babe");
+	let keystore = dir.path().to_str().unwrap();
+
+	let remove = ["key", "remove", "--keystore-path", keystore, "--yes"];
+	let output = subkey(&[&remove[..], &["--key-type", "gran", "--public", ALICE_PUBLIC]].concat());
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains(&format!("No gran key `{}` in keystore", ALICE_PUBLIC)));
+
+	let output = subkey(&[&remove[..], &["--key-type", "gran", "--all"]].concat());
+	assert_eq!(output.status.code(), Some(2));
+	assert!(String::from_utf8(output.stderr).unwrap().contains("No gran key in keystore"));
+	assert_eq!(key_files(dir.path()).len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn shredded_key_leaves_no_readable_secret() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().join("keystore");
+	insert(&keystore, "-s", "//Alice", "babe");
+	// A hard link keeps the contents of the file readable after it is removed.
+	let file = keystore.join(format!("{}{}", hex::encode(b"babe"), ALICE_PUBLIC));
+	let link = dir.path().join("link");
+	fs::hard_link(&file, &link).unwrap();
+	let len = fs::metadata(&file).unwrap().len() as usize;
+
+	let keystore = keystore.to_str().unwrap();
+	let remove = ["key", "remove", "--keystore-path", keystore, "--key-type", "babe", "--all"];
+	let output = subkey(&[&remove[..], &["--shred", "--yes"]].concat());
+	assert!(output.status.success());
+	assert!(!file.exists());
+	assert_eq!(fs::read(&link).unwrap(), vec![0; len]);
+}
+
+/// Insert the ed25519 key of `//Alice` with `password` into an encrypted keystore at `keystore`.
+fn insert_encrypted(keystore: &str, password: &str) {
+	let args = ["-e", "--password", password, "insert", "//Alice", "gran", "--keystore-path"];
+	let output = subkey(&[&args[..], &[keystore, "--keystore-format", "encrypted"]].concat());
+	assert!(output.status.success());
+}
+
+/// Run `key rotate-password` on `keystore` with `args`, answering the prompts with `stdin`.
+fn rotate_password(keystore: &str, args: &[&str], stdin: &[u8]) -> Output {
+	let mut child = Command::new(cargo_bin("subkey"))
+		.args(&["key", "rotate-password", "--keystore-path", keystore])
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(stdin).unwrap();
+	child.wait_with_output().unwrap()
+}
+
+#[test]
+fn key_files_are_rewritten_for_the_new_password() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().join("keystore");
+	let keystore = keystore.to_str().unwrap();
+	insert_encrypted(keystore, "old");
+	let file = fs::read_dir(keystore).unwrap().next().unwrap().unwrap().path();
+	let original = fs::read(&file).unwrap();
+	let public = file.file_name().unwrap().to_str().unwrap()[8..].to_owned();
+
+	let backup = dir.path().join("backup");
+	let args = ["--backup-dir", backup.to_str().unwrap()];
+	let output = rotate_password(keystore, &args, b"old\nnew\nnew\n");
+	assert!(output.status.success());
+	assert_eq!(fs::read(backup.join(file.file_name().unwrap())).unwrap(), original);
+
+	let contents = fs::read(&file).unwrap();
+	assert!(sc_keystore::decode_key_file(&contents, Some("old")).is_err());
+	let suri = sc_keystore::decode_key_file(&contents, Some("new")).unwrap();
+	// The URI of the file makes the same key with the new password.
+	let output = subkey(&["-e", "--password", "new", "--output", "json", "inspect", &suri]);
+	assert!(output.status.success());
+	let printed: Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(printed["publicKey"], format!("0x{}", public));
+}
+
+#[test]
+fn plain_key_files_are_encrypted_for_the_new_password() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().join("keystore");
+	let keystore = keystore.to_str().unwrap();
+	let args = ["-e", "--password", "old", "insert", "//Alice", "gran", "--keystore-path"];
+	let output = subkey(&[&args[..], &[keystore, "--keystore-format", "plain"]].concat());
+	assert!(output.status.success());
+	let file = fs::read_dir(keystore).unwrap().next().unwrap().unwrap().path();
+	let original = fs::read(&file).unwrap();
+
+	// The phrase of the file is only replaced by its seed if the file is backed up.
+	let output = rotate_password(keystore, &[], b"old\nnew\nnew\n");
+	assert!(String::from_utf8(output.stderr).unwrap().contains("--backup-dir"));
+	assert_eq!(fs::read(&file).unwrap(), original);
+	let backup = dir.path().join("backup");
+	let args = ["--backup-dir", backup.to_str().unwrap()];
+	let output = rotate_password(keystore, &args, b"old\nnew\nnew\n");
+	assert!(output.status.success());
+
+	let public = ed25519::Pair::from_string("//Alice", Some("old")).unwrap().public();
+	let key_pair = |password: &str| {
+		let password = SecretString::new(password.into());
+		let store = sc_keystore::Store::open(keystore, Some(password)).unwrap();
+		let pair = store.read().key_pair_by_type::<ed25519::Pair>(&public, KeyTypeId(*b"gran"));
+		pair.map(|pair| pair.public())
+	};
+	assert!(key_pair("old").is_err());
+	assert!(key_pair("other").is_err());
+	assert_eq!(key_pair("new").unwrap(), public);
+}
+
+#[test]
+fn mismatched_new_passwords_are_refused() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().to_str().unwrap();
+	insert_encrypted(keystore, "old");
+	let file = fs::read_dir(keystore).unwrap().next().unwrap().unwrap().path();
+	let original = fs::read(&file).unwrap();
+
+	let output = rotate_password(keystore, &[], b"old\nnew\nwen\n");
+	assert_eq!(output.status.code(), Some(2));
+	assert_eq!(fs::read(&file).unwrap(), original);
+}
+
+#[test]
+fn strict_rotation_rewrites_nothing_if_a_file_can_not_be_read() {
+	let dir = tempfile::tempdir().unwrap();
+	let keystore = dir.path().to_str().unwrap();
+	insert_encrypted(keystore, "old");
+	let args = ["-s", "--password", "other", "insert", "//Bob", "babe", "--keystore-path"];
+	let output = subkey(&[&args[..], &[keystore, "--keystore-format", "encrypted"]].concat());
+	assert!(output.status.success());
+	let originals = key_files(dir.path())
+		.into_iter()
+		.map(|name| fs::read(dir.path().join(&name)).unwrap())
+		.collect::<Vec<_>>();
+
+	let output = rotate_password(keystore, &["--strict"], b"old\nnew\nnew\n");
+	assert_eq!(output.status.code(), Some(2));
+	let contents = key_files(dir.path())
+		.into_iter()
+		.map(|name| fs::read(dir.path().join(&name)).unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(contents, originals);
+
+	// Without --strict, the file of the other password is skipped.
+	let output = rotate_password(keystore, &[], b"old\nnew\nnew\n");
+	assert!(output.status.success());
+	assert!(String::from_utf8(output.stderr).unwrap().contains("Skipping"));
+}
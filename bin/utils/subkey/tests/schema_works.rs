@@ -0,0 +1,159 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use serde_json::Value;
+use std::{io::Write, process::{Command, Stdio}};
+
+const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+fn json(args: &[&str], stdin: &[u8]) -> Value {
+	let mut child = Command::new(cargo_bin("subkey"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(stdin).unwrap();
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success(), "{:?} failed", args);
+	serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn schema(command: &str) -> Value {
+	json(&["key", "schema", command], b"")
+}
+
+/// Check `instance` against the subset of JSON Schema the output schemas use.
+fn validate(schema: &Value, instance: &Value) -> Result<(), String> {
+	if let Some(expected) = schema.get("const") {
+		if expected != instance {
+			return Err(format!("{} is not {}", instance, expected));
+		}
+	}
+	let type_matches = match schema["type"].as_str() {
+		Some("object") => instance.is_object(),
+		Some("string") => instance.is_string(),
+		Some("boolean") => instance.is_boolean(),
+		Some("integer") => instance.is_u64() || instance.is_i64(),
+		Some("array") => instance.is_array(),
+		Some(other) => return Err(format!("unexpected type {} in schema", other)),
+		None => true,
+	};
+	if !type_matches {
+		return Err(format!("{} is not of type {}", instance, schema["type"]));
+	}
+	if let Some(minimum) = schema["minimum"].as_i64() {
+		if instance.as_i64().map_or(true, |value| value < minimum) {
+			return Err(format!("{} is less than {}", instance, minimum));
+		}
+	}
+	for item in instance.as_array().into_iter().flatten() {
+		validate(&schema["items"], item)?;
+	}
+	if let Some(fields) = instance.as_object() {
+		for required in schema["required"].as_array().into_iter().flatten() {
+			if !fields.contains_key(required.as_str().unwrap()) {
+				return Err(format!("missing field {}", required));
+			}
+		}
+		for (name, value) in fields {
+			match schema["properties"].get(name) {
+				Some(field_schema) => validate(field_schema, value)?,
+				None if schema["additionalProperties"] == false => {
+					return Err(format!("unexpected field {}", name));
+				},
+				None => {},
+			}
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn account_outputs_match_their_schema() {
+	let instances = vec![
+		("generate", json(&["--output", "json", "generate"], b"")),
+		("inspect", json(&["--output", "json", "inspect", "//Alice"], b"")),
+		("inspect", json(&["--output", "json", "--show-secret", "inspect", "//Alice///pw"], b"")),
+		("inspect", json(&["--output", "json", "inspect", ALICE], b"")),
+		("inspect", json(&["--output", "json", "inspect", "--vrf", "label", "//Alice"], b"")),
+		("derive", json(&["--output", "json", "derive", ALICE, "/soft"], b"")),
+		("validate-suri", json(&["--output", "json", "validate-suri", "//Alice//0/1"], b"")),
+		("generate-node-key", json(&["--output", "json", "generate-node-key"], b"")),
+		("inspect-node-key", json(&["--output", "json", "inspect-node-key"], &[1; 32])),
+	];
+	for (command, instance) in instances {
+		assert_eq!(instance["formatVersion"], 2);
+		validate(&schema(command), &instance)
+			.unwrap_or_else(|e| panic!("{} output {} is invalid: {}", command, instance, e));
+	}
+}
+
+#[test]
+fn other_key_command_outputs_match_their_schema() {
+	let alice_public = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+	let storage_key = ["key", "storage-key", "--pallet", "System", "--item", "Number"];
+	let instances = vec![
+		("key test-vectors", json(&["key", "test-vectors", "--count", "2"], b"")),
+		("reencode-address", json(&["--output", "json", "reencode-address", ALICE], b"")),
+		("account-forms", json(&["--output", "json", "account-forms", alice_public], b"")),
+		("key dev-accounts", json(&["--output", "json", "key", "dev-accounts"], b"")),
+		("key key-types", json(&["--output", "json", "key", "key-types"], b"")),
+		("key storage-key", json(&[&["--output", "json"][..], &storage_key].concat(), b"")),
+	];
+	for (command, instance) in instances {
+		let versioned = instance.as_array().map_or(&instance, |items| &items[0]);
+		assert_eq!(versioned["formatVersion"], 2);
+		validate(&schema(command), &instance)
+			.unwrap_or_else(|e| panic!("{} output {} is invalid: {}", command, instance, e));
+	}
+}
+
+#[test]
+fn signature_outputs_match_their_schema() {
+	let signed = json(&["--output", "json", "sign", "--hash", "sha-256", "//Alice"], b"hello");
+	validate(&schema("sign"), &signed).unwrap();
+	let signature = signed["signature"].as_str().unwrap();
+
+	let args = ["--output", "json", "verify", "--hash", "sha-256", signature, ALICE];
+	let verified = json(&args, b"hello");
+	assert_eq!(verified["valid"], true);
+	validate(&schema("verify"), &verified).unwrap();
+}
+
+#[test]
+fn schema_rejects_other_outputs() {
+	let schema = schema("inspect");
+	let mut instance = json(&["--output", "json", "inspect", "//Alice"], b"");
+	instance["formatVersion"] = 1.into();
+	assert!(validate(&schema, &instance).is_err());
+	instance["formatVersion"] = 2.into();
+	instance["unknown"] = true.into();
+	assert!(validate(&schema, &instance).is_err());
+	instance.as_object_mut().unwrap().remove("unknown");
+	instance.as_object_mut().unwrap().remove("ss58Address");
+	assert!(validate(&schema, &instance).is_err());
+
+	let all = json(&["key", "schema"], b"");
+	assert_eq!(all["inspect"], schema);
+	assert!(all.as_object().unwrap().len() > 1);
+	let args = ["key", "schema", "transfer"];
+	let output = Command::new(cargo_bin("subkey")).args(&args).output().unwrap();
+	assert!(!output.status.success());
+}
@@ -0,0 +1,285 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use std::{io::Write, process::{Command, Output, Stdio}};
+
+const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+
+/// Run subkey with `args`, passing `message` on STDIN.
+fn subkey(args: &[&str], message: &[u8]) -> Output {
+	let mut child = Command::new(cargo_bin("subkey"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.unwrap();
+	// subkey may exit before reading all of STDIN, for instance on invalid arguments.
+	let _ = child.stdin.take().unwrap().write_all(message);
+	child.wait_with_output().unwrap()
+}
+
+fn sign(message: &[u8]) -> String {
+	let output = subkey(&["sign", "//Alice"], message);
+	assert!(output.status.success());
+	String::from_utf8(output.stdout).unwrap().trim().into()
+}
+
+#[test]
+fn valid_signature_exits_with_zero() {
+	let signature = sign(b"hello");
+
+	let output = subkey(&["verify", &signature, ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(0));
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("verifies correctly for sr25519 public key"));
+	assert!(stdout.contains("(read as SS58 public URI)"));
+
+	let output = subkey(&["--output", "json", "verify", &signature, ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(0));
+	let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(json["valid"], true);
+	assert_eq!(json["scheme"], "sr25519");
+	assert_eq!(json["ss58Address"], ALICE);
+	assert_eq!(
+		json["publicKey"],
+		"0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+	);
+}
+
+#[test]
+fn invalid_signature_exits_with_one() {
+	let signature = sign(b"hello");
+
+	let output = subkey(&["verify", &signature, ALICE], b"other");
+	assert_eq!(output.status.code(), Some(1));
+	assert!(String::from_utf8(output.stdout).unwrap().contains("is invalid"));
+
+	let output = subkey(&["--output", "json", "verify", &signature, ALICE], b"other");
+	assert_eq!(output.status.code(), Some(1));
+	let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(json["valid"], false);
+}
+
+#[test]
+fn malformed_signature_exits_with_four() {
+	let output = subkey(&["verify", "0xzz", ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(4));
+
+	let output = subkey(&["verify", "00", ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(4));
+	assert!(String::from_utf8(output.stderr).unwrap().contains("invalid length"));
+
+	let output = subkey(&["verify"], b"hello");
+	assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn quiet_prints_nothing() {
+	let signature = sign(b"hello");
+
+	for (message, code) in &[(&b"hello"[..], 0), (&b"other"[..], 1)] {
+		let output = subkey(&["--output", "json", "verify", "--quiet", &signature, ALICE], message);
+		assert_eq!(output.status.code(), Some(*code));
+		assert!(output.stdout.is_empty());
+	}
+}
+
+#[test]
+fn verified_message_is_printed() {
+	let signature = sign("héllo".as_bytes());
+
+	let output = subkey(&["verify", "--print-message", &signature, ALICE], "héllo".as_bytes());
+	assert_eq!(output.status.code(), Some(0));
+	assert_eq!(output.stdout, "héllo".as_bytes());
+
+	let output = subkey(&["verify", "--print-message", &signature, ALICE], b"other");
+	assert_eq!(output.status.code(), Some(1));
+	assert!(output.stdout.is_empty());
+
+	let signature = sign(&[0xff]);
+	let output = subkey(&["verify", "--print-message", &signature, ALICE], &[0xff]);
+	assert_eq!(output.status.code(), Some(4));
+	assert!(output.stdout.is_empty());
+	assert!(String::from_utf8(output.stderr).unwrap().contains("Message is not valid UTF-8"));
+}
+
+#[test]
+fn signature_is_read_from_file() {
+	let mut file = tempfile::NamedTempFile::new().unwrap();
+	writeln!(file, "0x{}", sign(b"hello")).unwrap();
+	let path = file.path().to_str().unwrap();
+
+	let output = subkey(&["verify", "--sig-file", path, ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(0));
+	let output = subkey(&["verify", "--sig-file", path, ALICE], b"other");
+	assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn scheme_is_detected() {
+	for scheme in &["--ed25519", "--sr25519", "--secp256k1"] {
+		let output = subkey(&[scheme, "sign", "//Alice"], b"hello");
+		let signature = String::from_utf8(output.stdout).unwrap().trim().to_string();
+		let output = subkey(&[scheme, "inspect", "//Alice"], b"");
+		let inspect = String::from_utf8(output.stdout).unwrap();
+		let public = inspect
+			.lines()
+			.find(|line| line.trim_start().starts_with("Public key (hex):"))
+			.and_then(|line| line.split_whitespace().last())
+			.unwrap()
+			.to_string();
+
+		let output = subkey(&["verify", "--scheme", "auto", &signature, &public], b"hello");
+		assert_eq!(output.status.code(), Some(0), "{} should be detected", scheme);
+		let name = match *scheme {
+			"--ed25519" => "ed25519",
+			"--sr25519" => "sr25519",
+			_ => "ecdsa",
+		};
+		let stderr = String::from_utf8(output.stderr).unwrap();
+		assert!(stderr.contains(&format!("detected the {} scheme", name)));
+		assert!(stderr.contains("best-effort"));
+		assert!(String::from_utf8(output.stdout).unwrap().contains(&format!("for {} public key", name)));
+	}
+}
+
+#[test]
+fn scheme_option_replaces_the_selected_scheme() {
+	let output = subkey(&["--ed25519", "sign", "//Alice"], b"hello");
+	let signature = String::from_utf8(output.stdout).unwrap().trim().to_string();
+	let public = "0x88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee";
+
+	let output = subkey(&["verify", "--scheme", "ed25519", &signature, public], b"hello");
+	assert_eq!(output.status.code(), Some(0));
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("verifies correctly for ed25519 public key"));
+	let args = ["--ed25519", "verify", "--scheme", "sr25519", &signature, public];
+	let output = subkey(&args, b"hello");
+	assert_eq!(output.status.code(), Some(1));
+
+	let output = subkey(&["verify", "--scheme", "rsa", &signature, public], b"hello");
+	assert_eq!(output.status.code(), Some(2));
+	let output = subkey(&["verify", "--scheme", "auto", "--batch", "batch.json"], b"");
+	assert_eq!(output.status.code(), Some(2));
+	assert!(String::from_utf8(output.stderr).unwrap().contains("can not be used with --batch"));
+}
+
+#[test]
+fn ecdsa_signs_the_digest_like_any_message() {
+	let message = b"Blah Blah\n";
+	let output = subkey(&["--secp256k1", "sign", "--hash", "sha-256", "//Alice"], message);
+	assert!(output.status.success());
+	let signature = String::from_utf8(output.stdout).unwrap().trim().to_string();
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("signed the sha-256 digest of the message"));
+
+	let public = "0x020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1";
+	let args = ["--secp256k1", "verify", "--hash", "sha-256", &signature, public];
+	assert_eq!(subkey(&args, message).status.code(), Some(0));
+	let args = ["--secp256k1", "verify", &signature, public];
+	assert_eq!(subkey(&args, message).status.code(), Some(1));
+
+	// ecdsa hashes the digest with blake2-256 once more, as it does with any message, so the
+	// signature is the one of the digest itself.
+	let digest = b"2df2a94c537e0b45369b1adf839c4d9bfdbcd9333380b8adfcef41c64fbd5d39";
+	let args = ["--secp256k1", "verify", "--encoding", "hex", &signature, public];
+	assert_eq!(subkey(&args, digest).status.code(), Some(0));
+}
+
+#[test]
+fn wrong_key_fails_for_every_scheme() {
+	let signature = sign(b"hello");
+	let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+
+	let output = subkey(&["verify", "--scheme", "auto", &signature, bob], b"hello");
+	assert_eq!(output.status.code(), Some(1));
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("invalid for each of ed25519 and sr25519"));
+}
+
+#[test]
+fn malformed_signature_is_an_error() {
+	let output = subkey(&["verify", "--scheme", "auto", "0x1234", ALICE], b"hello");
+	assert_eq!(output.status.code(), Some(2));
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("fit none of the schemes"));
+}
+
+#[test]
+fn batch_reports_each_entry() {
+	let output = subkey(&["--ed25519", "sign", "//Alice"], b"ed25519 message");
+	let ed25519_signature = String::from_utf8(output.stdout).unwrap().trim().to_string();
+	let output = subkey(&["--ed25519", "--output", "json", "inspect", "//Alice"], b"");
+	let ed25519_alice: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+	let manifest = serde_json::json!([
+		{ "message": "hello", "signature": sign(b"hello"), "address": ALICE },
+		{ "message": "other", "signature": sign(b"hello"), "address": ALICE },
+		{
+			"message": "ed25519 message",
+			"signature": ed25519_signature,
+			"address": ed25519_alice["ss58Address"],
+			"scheme": "ed25519",
+		},
+		{ "message": "hello", "address": ALICE },
+		{ "message": "hello", "signature": sign(b"hello"), "address": ALICE, "scheme": "rsa" },
+	]);
+	let mut file = tempfile::NamedTempFile::new().unwrap();
+	write!(file, "{}", manifest).unwrap();
+	let path = file.path().to_str().unwrap();
+
+	let output = subkey(&["verify", "--batch", path], b"");
+	assert_eq!(output.status.code(), Some(1));
+	let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	let valid = report["entries"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|entry| entry["valid"].as_bool().unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(valid, vec![true, false, true, false, false]);
+	assert_eq!(report["entries"][3]["index"], 3);
+	assert!(report["entries"][3]["error"].as_str().unwrap().contains("`signature`"));
+	assert!(report["entries"][4]["error"].as_str().unwrap().contains("Unknown scheme `rsa`"));
+	assert!(report["entries"][1].get("error").is_none());
+	assert_eq!(
+		report["summary"],
+		serde_json::json!({ "total": 5, "valid": 2, "invalid": 1, "malformed": 2 }),
+	);
+
+	let output = subkey(&["verify", "--batch", path, "--allow-failures"], b"");
+	assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn batch_of_valid_entries_exits_with_zero() {
+	let manifest = serde_json::json!([
+		{ "message": hex::encode(b"hello"), "signature": sign(b"hello"), "address": ALICE },
+	]);
+	let mut file = tempfile::NamedTempFile::new().unwrap();
+	write!(file, "{}", manifest).unwrap();
+
+	let output = subkey(&["verify", "--hex", "--batch", file.path().to_str().unwrap()], b"");
+	assert_eq!(output.status.code(), Some(0));
+
+	write!(file, "not json").unwrap();
+	let output = subkey(&["verify", "--batch", file.path().to_str().unwrap()], b"");
+	assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,219 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Substrate. If not, see <http://www.gnu.org/licenses/>.
+
+//! The contents of key files.
+//!
+//! A key file holds the secret URI of its key, either as a JSON string, or encrypted with the
+//! keystore password as a JSON object. Both are read, whatever format new key files are written in.
+
+use crate::{Error, Result};
+use rand::{rngs::OsRng, RngCore};
+use scrypt::ScryptParams;
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::Zeroize;
+use subtle::ConstantTimeEq;
+use xsalsa20poly1305::{
+	aead::{Aead, NewAead, generic_array::GenericArray},
+	Nonce, XSalsa20Poly1305,
+};
+
+/// The `format` of encrypted key files, which tells them from other JSON objects.
+const ENCRYPTED_FORMAT: &str = "sc-keystore-encrypted";
+/// The version of the encrypted key files that are written and read.
+const ENCRYPTED_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// The length of the encryption key, and of the check value derived along with it.
+const KEY_LEN: usize = 32;
+/// The most memory scrypt may use to read a key file, 1 GiB, so that a key file can not make the
+/// keystore run out of memory.
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+/// The largest scrypt `log_n` of key files.
+const MAX_SCRYPT_LOG_N: u8 = 20;
+/// The largest scrypt `p` of key files, so that a key file can not take minutes to read.
+const MAX_SCRYPT_P: u32 = 16;
+
+/// The format of the key files written by a [`Store`](crate::Store).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFileFormat {
+	/// The secret URI as a JSON string.
+	Plain,
+	/// The secret URI encrypted with XSalsa20-Poly1305, with a key derived from the keystore
+	/// password by scrypt.
+	Encrypted {
+		/// The binary logarithm of the scrypt cost parameter N.
+		log_n: u8,
+		/// The scrypt block size.
+		r: u32,
+		/// The scrypt parallelization.
+		p: u32,
+	},
+}
+
+impl Default for KeyFileFormat {
+	fn default() -> Self {
+		KeyFileFormat::Plain
+	}
+}
+
+impl KeyFileFormat {
+	/// The encrypted format, with the scrypt parameters of polkadot-js: N = 2^15, r = 8, p = 1.
+	pub fn encrypted() -> Self {
+		KeyFileFormat::Encrypted { log_n: 15, r: 8, p: 1 }
+	}
+
+	/// Check that key files can be written in this format with `password`.
+	pub(crate) fn check(&self, password: Option<&str>) -> Result<()> {
+		match self {
+			KeyFileFormat::Plain => Ok(()),
+			KeyFileFormat::Encrypted { .. } if password.is_none() =>
+				Err(Error::InvalidKeyFileFormat("encrypted key files require a password")),
+			KeyFileFormat::Encrypted { log_n, r, p } => scrypt_params(*log_n, *r, *p).map(drop),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeyFile {
+	Plain(String),
+	Encrypted(EncryptedKeyFile),
+}
+
+/// An encrypted key file. The binary fields are hex-encoded.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct EncryptedKeyFile {
+	format: String,
+	version: u32,
+	log_n: u8,
+	r: u32,
+	p: u32,
+	salt: String,
+	nonce: String,
+	/// Derived from the password along with the encryption key, so that a wrong password is told
+	/// from a corrupt file.
+	check: String,
+	ciphertext: String,
+}
+
+/// The contents of a key file holding `suri`, in `format`.
+///
+/// Fails with [`Error::InvalidKeyFileFormat`] if the file can not be written in `format` with
+/// `password`.
+pub fn encode(suri: &str, format: KeyFileFormat, password: Option<&str>) -> Result<Vec<u8>> {
+	format.check(password)?;
+	let (log_n, r, p, password) = match (format, password) {
+		(KeyFileFormat::Encrypted { log_n, r, p }, Some(password)) => (log_n, r, p, password),
+		_ => return serde_json::to_vec(&KeyFile::Plain(suri.into())).map_err(Into::into),
+	};
+
+	let mut salt = [0u8; SALT_LEN];
+	let mut nonce = [0u8; NONCE_LEN];
+	OsRng.fill_bytes(&mut salt);
+	OsRng.fill_bytes(&mut nonce);
+	let mut keys = derive_keys(password, &salt, log_n, r, p)?;
+	let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&keys[..KEY_LEN]));
+	let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), suri.as_bytes())
+		.expect("secret URIs are far shorter than the limit of XSalsa20-Poly1305; qed");
+	let check = hex::encode(&keys[KEY_LEN..]);
+	keys.zeroize();
+
+	let file = EncryptedKeyFile {
+		format: ENCRYPTED_FORMAT.into(),
+		version: ENCRYPTED_VERSION,
+		log_n,
+		r,
+		p,
+		salt: hex::encode(salt),
+		nonce: hex::encode(nonce),
+		check,
+		ciphertext: hex::encode(ciphertext),
+	};
+	serde_json::to_vec(&KeyFile::Encrypted(file)).map_err(Into::into)
+}
+
+/// The secret URI of a key file with `contents`, decrypted with `password` if it is encrypted.
+///
+/// Fails with [`Error::InvalidPassword`] if the password is wrong, and with
+/// [`Error::CorruptKeyFile`] if the file was changed.
+pub fn decode(contents: &[u8], password: Option<&str>) -> Result<String> {
+	let file = match serde_json::from_slice(contents)? {
+		KeyFile::Plain(suri) => return Ok(suri),
+		KeyFile::Encrypted(file) => file,
+	};
+	if file.format != ENCRYPTED_FORMAT || file.version != ENCRYPTED_VERSION {
+		return Err(Error::CorruptKeyFile);
+	}
+	let password = password.ok_or(Error::InvalidPassword)?;
+	let decode_hex = |field: &str, len: Option<usize>| match hex::decode(field) {
+		Ok(bytes) if len.map_or(true, |len| bytes.len() == len) => Ok(bytes),
+		_ => Err(Error::CorruptKeyFile),
+	};
+	let salt = decode_hex(&file.salt, Some(SALT_LEN))?;
+	let nonce = decode_hex(&file.nonce, Some(NONCE_LEN))?;
+	let check = decode_hex(&file.check, Some(KEY_LEN))?;
+	let ciphertext = decode_hex(&file.ciphertext, None)?;
+
+	let mut keys = derive_keys(password, &salt, file.log_n, file.r, file.p)?;
+	let password_matches = bool::from(keys[KEY_LEN..].ct_eq(&check));
+	let cipher = XSalsa20Poly1305::new(GenericArray::from_slice(&keys[..KEY_LEN]));
+	keys.zeroize();
+	if !password_matches {
+		return Err(Error::InvalidPassword);
+	}
+	let suri = cipher.decrypt(Nonce::from_slice(&nonce), &ciphertext[..])
+		.map_err(|_| Error::CorruptKeyFile)?;
+	String::from_utf8(suri).map_err(|_| Error::CorruptKeyFile)
+}
+
+/// Whether `contents` are those of a key file, of either format.
+///
+/// Encrypted key files are not decrypted, so this does not tell whether they are intact.
+pub fn is_key_file(contents: &[u8]) -> bool {
+	match serde_json::from_slice(contents) {
+		Ok(KeyFile::Plain(mut suri)) => {
+			suri.zeroize();
+			true
+		},
+		Ok(KeyFile::Encrypted(file)) => file.format == ENCRYPTED_FORMAT,
+		Err(_) => false,
+	}
+}
+
+/// Derive the encryption key and the check value from `password`.
+///
+/// The scrypt parameters are checked against the limits of key files before anything is derived.
+fn derive_keys(password: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<Vec<u8>> {
+	let params = scrypt_params(log_n, r, p)?;
+	let mut keys = vec![0; 2 * KEY_LEN];
+	scrypt::scrypt(password.as_bytes(), salt, &params, &mut keys)
+		.expect("the length of the keys is a valid output length of scrypt; qed");
+	Ok(keys)
+}
+
+/// The scrypt parameters `log_n`, `r` and `p`, if they are valid and within the limits of key
+/// files.
+fn scrypt_params(log_n: u8, r: u32, p: u32) -> Result<ScryptParams> {
+	// scrypt uses 128 * r * N bytes of memory.
+	if log_n > MAX_SCRYPT_LOG_N || p > MAX_SCRYPT_P
+		|| (128 * u64::from(r)) << log_n > MAX_SCRYPT_MEMORY
+	{
+		return Err(Error::InvalidKeyFileFormat("the scrypt parameters exceed the limits"));
+	}
+	ScryptParams::new(log_n, r, p)
+		.map_err(|_| Error::InvalidKeyFileFormat("invalid scrypt parameters"))
+}
@@ -28,6 +28,12 @@ use sp_core::{
 use sp_application_crypto::{AppKey, AppPublic, AppPair, ed25519, sr25519, ecdsa};
 use parking_lot::RwLock;
 
+mod key_file;
+
+pub use key_file::{
+	decode as decode_key_file, encode as encode_key_file, is_key_file, KeyFileFormat,
+};
+
 /// Keystore pointer
 pub type KeyStorePtr = Arc<RwLock<Store>>;
 
@@ -56,6 +62,12 @@ pub enum Error {
 	/// Keystore unavailable
 	#[display(fmt="Keystore unavailable")]
 	Unavailable,
+	/// Key file changed or unreadable
+	#[display(fmt="Corrupt key file")]
+	CorruptKeyFile,
+	/// Key files can not be written in the format
+	#[display(fmt="Invalid key file format: {}", "_0")]
+	InvalidKeyFileFormat(&'static str),
 }
 
 /// Keystore Result
@@ -66,9 +78,11 @@ impl From<Error> for TraitError {
 		match error {
 			Error::KeyNotSupported(id) => TraitError::KeyNotSupported(id),
 			Error::PairNotFound(e) => TraitError::PairNotFound(e),
-			Error::InvalidSeed | Error::InvalidPhrase | Error::InvalidPassword => {
+			Error::InvalidSeed | Error::InvalidPhrase | Error::InvalidPassword
+				| Error::InvalidKeyFileFormat(_) => {
 				TraitError::ValidationError(error.to_string())
 			},
+			Error::CorruptKeyFile => TraitError::Other(error.to_string()),
 			Error::Unavailable => TraitError::Unavailable,
 			Error::Io(e) => TraitError::Other(e.to_string()),
 			Error::Json(e) => TraitError::Other(e.to_string()),
@@ -96,17 +110,28 @@ pub struct Store {
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
 	password: Option<SecretString>,
+	key_file_format: KeyFileFormat,
 }
 
 impl Store {
 	/// Open the store at the given path.
 	///
-	/// Optionally takes a password that will be used to encrypt/decrypt the keys.
+	/// Optionally takes a password that will be used to encrypt/decrypt the keys. Key files are
+	/// written [encrypted](KeyFileFormat::encrypted) if a password is given, and plain otherwise.
 	pub fn open<T: Into<PathBuf>>(path: T, password: Option<SecretString>) -> Result<KeyStorePtr> {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
 
-		let instance = Self { path: Some(path), additional: HashMap::new(), password };
+		let key_file_format = match password {
+			Some(_) => KeyFileFormat::encrypted(),
+			None => KeyFileFormat::Plain,
+		};
+		let instance = Self {
+			path: Some(path),
+			additional: HashMap::new(),
+			password,
+			key_file_format,
+		};
 		Ok(Arc::new(RwLock::new(instance)))
 	}
 
@@ -115,10 +140,21 @@ impl Store {
 		Arc::new(RwLock::new(Self {
 			path: None,
 			additional: HashMap::new(),
-			password: None
+			password: None,
+			key_file_format: KeyFileFormat::default(),
 		}))
 	}
 
+	/// Set the format of the key files written from now on.
+	///
+	/// Key files of any format are read. Encrypted key files are encrypted with the password of
+	/// the store, so they require one.
+	pub fn set_key_file_format(&mut self, format: KeyFileFormat) -> Result<()> {
+		format.check(self.password())?;
+		self.key_file_format = format;
+		Ok(())
+	}
+
 	/// Get the key phrase for the given public key and key type from the in-memory store.
 	fn get_additional_pair(
 		&self,
@@ -142,13 +178,20 @@ impl Store {
 	/// Places it into the file system store.
 	fn insert_unknown(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
 		if let Some(path) = self.key_file_path(public, key_type) {
-			let mut file = File::create(path).map_err(Error::Io)?;
-			serde_json::to_writer(&file, &suri).map_err(Error::Json)?;
-			file.flush().map_err(Error::Io)?;
+			self.write_key_file(path, suri)?;
 		}
 		Ok(())
 	}
 
+	/// Write the key file at `path`, in the key file format of the store.
+	fn write_key_file(&self, path: PathBuf, suri: &str) -> Result<()> {
+		let contents = key_file::encode(suri, self.key_file_format, self.password())?;
+		let mut file = File::create(path).map_err(Error::Io)?;
+		file.write_all(&contents).map_err(Error::Io)?;
+		file.flush().map_err(Error::Io)?;
+		Ok(())
+	}
+
 	/// Insert a new key.
 	///
 	/// Places it into the file system store.
@@ -175,9 +218,7 @@ impl Store {
 	pub fn generate_by_type<Pair: PairT>(&self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password());
 		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			let mut file = File::create(path)?;
-			serde_json::to_writer(&file, &phrase)?;
-			file.flush()?;
+			self.write_key_file(path, &phrase)?;
 		}
 		Ok(pair)
 	}
@@ -216,9 +257,9 @@ impl Store {
 		}
 
 		let path = self.key_file_path(public, key_type).ok_or_else(|| Error::Unavailable)?;
-		let file = File::open(path)?;
+		let contents = fs::read(path)?;
 
-		serde_json::from_reader(&file).map_err(Into::into)
+		key_file::decode(&contents, self.password())
 	}
 
 	/// Get a key pair for the given public key and key type.
@@ -577,6 +618,136 @@ mod tests {
 		assert_eq!(key_pair.public(), store_key_pair.public());
 	}
 
+	/// An encrypted format that is cheap to derive keys for.
+	const ENCRYPTED: KeyFileFormat = KeyFileFormat::Encrypted { log_n: 10, r: 8, p: 1 };
+
+	fn open_encrypted(path: &std::path::Path, password: &str) -> KeyStorePtr {
+		let store = Store::open(path, Some(FromStr::from_str(password).unwrap())).unwrap();
+		store.write().set_key_file_format(ENCRYPTED).unwrap();
+		store
+	}
+
+	#[test]
+	fn encrypted_key_files_are_read_with_the_password() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = open_encrypted(temp_dir.path(), "password");
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let generated: ed25519::AppPair = store.write().generate().unwrap();
+
+		// Neither secret is stored in plain text.
+		for entry in fs::read_dir(temp_dir.path()).unwrap() {
+			let contents = fs::read(entry.unwrap().path()).unwrap();
+			assert!(is_key_file(&contents));
+			let contents = String::from_utf8(contents).unwrap();
+			assert!(contents.starts_with('{') && !contents.contains("Alice"));
+		}
+
+		let store = Store::open(temp_dir.path(), Some(FromStr::from_str("password").unwrap()))
+			.unwrap();
+		let read = store.read().key_pair_by_type::<sr25519::Pair>(&pair.public(), SR25519).unwrap();
+		assert_eq!(read.public(), pair.public());
+		let read = store.read().key_pair::<ed25519::AppPair>(&generated.public()).unwrap();
+		assert_eq!(read.public(), generated.public());
+
+		for password in &[None, Some(FromStr::from_str("wrong").unwrap())] {
+			let store = Store::open(temp_dir.path(), password.clone()).unwrap();
+			assert!(matches!(
+				store.read().key_pair_by_type::<sr25519::Pair>(&pair.public(), SR25519),
+				Err(Error::InvalidPassword)
+			));
+		}
+	}
+
+	#[test]
+	fn key_files_are_encrypted_if_the_store_has_a_password() {
+		let temp_dir = TempDir::new().unwrap();
+		let password = Some(FromStr::from_str("password").unwrap());
+		let store = Store::open(temp_dir.path(), password).unwrap();
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let path = store.read().key_file_path(pair.public().as_slice(), SR25519).unwrap();
+		let file: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+		assert_eq!(file["logN"], 15);
+
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Bob").unwrap();
+		let path = store.read().key_file_path(pair.public().as_slice(), SR25519).unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "\"//Bob\"");
+	}
+
+	#[test]
+	fn plain_key_files_are_still_read() {
+		let temp_dir = TempDir::new().unwrap();
+		let password = Some(FromStr::from_str("password").unwrap());
+		let store = Store::open(temp_dir.path(), password).unwrap();
+		store.write().set_key_file_format(KeyFileFormat::Plain).unwrap();
+		let plain = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		store.write().set_key_file_format(ENCRYPTED).unwrap();
+		let encrypted = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Bob").unwrap();
+
+		let files = fs::read_dir(temp_dir.path()).unwrap().map(|entry| {
+			fs::read_to_string(entry.unwrap().path()).unwrap()
+		}).collect::<HashSet<_>>();
+		assert!(files.contains("\"//Alice\""));
+		for pair in &[plain, encrypted] {
+			let read = store.read().key_pair_by_type::<sr25519::Pair>(&pair.public(), SR25519);
+			assert_eq!(read.unwrap().public(), pair.public());
+		}
+	}
+
+	#[test]
+	fn tampered_key_files_are_refused() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = open_encrypted(temp_dir.path(), "password");
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let path = store.read().key_file_path(pair.public().as_slice(), SR25519).unwrap();
+
+		let mut file: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+		let ciphertext = file["ciphertext"].as_str().unwrap();
+		let flipped = if ciphertext.starts_with('0') { "1" } else { "0" };
+		file["ciphertext"] = format!("{}{}", flipped, &ciphertext[1..]).into();
+		fs::write(&path, file.to_string()).unwrap();
+
+		assert!(matches!(
+			store.read().key_pair_by_type::<sr25519::Pair>(&pair.public(), SR25519),
+			Err(Error::CorruptKeyFile)
+		));
+	}
+
+	#[test]
+	fn key_files_with_excessive_scrypt_parameters_are_refused() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = open_encrypted(temp_dir.path(), "password");
+		let pair = store.write().insert_by_type::<sr25519::Pair>(SR25519, "//Alice").unwrap();
+		let path = store.read().key_file_path(pair.public().as_slice(), SR25519).unwrap();
+
+		let file: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+		for (field, value) in &[("logN", 40), ("r", 1 << 20), ("p", 1 << 20)] {
+			let mut file = file.clone();
+			file[field] = (*value).into();
+			fs::write(&path, file.to_string()).unwrap();
+			assert!(matches!(
+				store.read().key_pair_by_type::<sr25519::Pair>(&pair.public(), SR25519),
+				Err(Error::InvalidKeyFileFormat(_))
+			));
+		}
+		let excessive = KeyFileFormat::Encrypted { log_n: 21, r: 8, p: 1 };
+		assert!(matches!(
+			store.write().set_key_file_format(excessive),
+			Err(Error::InvalidKeyFileFormat(_))
+		));
+	}
+
+	#[test]
+	fn encrypted_key_files_require_a_password() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = Store::open(temp_dir.path(), None).unwrap();
+		assert!(matches!(
+			store.write().set_key_file_format(ENCRYPTED),
+			Err(Error::InvalidKeyFileFormat(_))
+		));
+		assert!(!is_key_file(b"{\"format\":\"other\"}"));
+	}
+
 	#[test]
 	fn store_ignores_files_with_invalid_name() {
 		let temp_dir = TempDir::new().unwrap();